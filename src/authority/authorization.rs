@@ -0,0 +1,693 @@
+//! Operation Authorization - Declarative minimum authority per operation
+//!
+//! Every `CrudManager` and `AuthorityAgeEncryption` operation asks an `OperationPolicy`
+//! whether the acting authority may run it. A rule maps an operation name to the
+//! minimum `AuthorityLevel`, optionally limited to a scope below the repository root.
+//! Rules come from, most specific last:
+//!
+//! 1. built-in rules (`OperationPolicy::builtin`)
+//! 2. the global file `$XDG_CONFIG_HOME/padlock/authorization.toml` (or `~/.config/...`)
+//! 3. the repository file `.padlock/authorization.toml`, whose rules replace earlier
+//!    rules for the same operation and scope
+//!
+//! Anyone who can write to the repository can edit its policy file, so it is only read
+//! together with `.padlock/authorization.toml.sig`, a signature by a trusted root of at
+//! least repo level (see `trust`). A repository file without a valid signature fails
+//! every operation rather than being skipped.
+//!
+//! Operations on a key of a known type are named `<operation>-<x|m|r|i|d>` (`rotate-r`,
+//! `revoke-d`) and fall back to the plain operation's rules. Every rule whose scope
+//! overlaps the operation's path applies, so a scoped rule can only tighten the
+//! unscoped one. Operations no rule covers are denied.
+//!
+//! Callers presenting only a passphrase act with distro authority unless a trusted policy
+//! file raises `passphrase_level`, so dropping `--as-key` does not escape a scoped rule.
+//! Distro keys issued with capabilities (see `capability`) are further limited to their
+//! paths, access, operations and number of uses, whatever the policy allows.
+//!
+//! ```toml
+//! # Authority of callers presenting a passphrase but no key
+//! passphrase_level = "ignition"
+//!
+//! [[rule]]
+//! operation = "unlock"
+//! level = "repo"
+//! scope = "secrets/production"
+//! ```
+//!
+//! Security Guardian: Edgar - Authority checked on every operation, denials explained
+
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use serde::Deserialize;
+
+use crate::encryption::age_automation::{
+    error::{AgeError, AgeResult},
+    lifecycle::clock::default_state_dir,
+    security::AuditLogger,
+};
+use super::capability::{self, KeyCapabilities, UsageLedger};
+use super::chain::{AuthorityKey, KeyType};
+use super::envelope::KeyEnvelope;
+use super::expiration::PolicySource;
+use super::signing;
+use super::trust::TrustAnchors;
+use super::validation::AuthorityLevel;
+
+/// Policy file path relative to a repository root
+pub const REPOSITORY_FILE: &str = ".padlock/authorization.toml";
+
+/// Signature of the repository policy file, relative to a repository root
+pub const SIGNATURE_FILE: &str = ".padlock/authorization.toml.sig";
+
+/// Policy file name inside the global padlock config directory
+pub const GLOBAL_FILE: &str = "authorization.toml";
+
+/// Domain separating policy signatures from other signed data
+const SIGNATURE_CONTEXT: &[u8] = b"padlock-authorization-v1\n";
+
+/// Built-in minimum levels of plain operations
+const BUILTIN_RULES: &[(&str, KeyType)] = &[
+    ("status", KeyType::Distro),
+    ("verify", KeyType::Distro),
+    ("lock", KeyType::Distro),
+    ("unlock", KeyType::Distro),
+    ("edit", KeyType::Distro),
+    ("exec", KeyType::Distro),
+    ("encrypt", KeyType::Distro),
+    ("decrypt", KeyType::Distro),
+    ("rearmor", KeyType::Distro),
+    ("vault-get", KeyType::Distro),
+    ("vault-list", KeyType::Distro),
+    ("vault-set", KeyType::Distro),
+    ("vault-rm", KeyType::Distro),
+    ("backup-list", KeyType::Distro),
+    ("backup-restore", KeyType::Repo),
+    ("rotate", KeyType::Repo),
+    ("access-matrix", KeyType::Repo),
+    ("allow", KeyType::Ignition),
+    ("revoke", KeyType::Ignition),
+    ("reset", KeyType::Skull),
+    ("emergency", KeyType::Skull),
+];
+
+/// Operations whose built-in keyed rules require the target key's parent
+const PARENT_OPERATIONS: &[&str] = &["rotate", "revoke"];
+
+/// Who is asking for an operation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Actor {
+    /// Caller presenting a passphrase but no authority key
+    Passphrase,
+    /// Caller acting with an authority key, identified by its fingerprint (hex); `lineage`
+    /// holds its verified envelopes up to a trusted root when known, the key first
+    Key { key_type: KeyType, fingerprint: String, capabilities: Option<KeyCapabilities>, lineage: Vec<KeyEnvelope> },
+}
+
+impl Actor {
+    /// Act as `key`; callers are responsible for proving possession
+    pub fn for_key(key: &AuthorityKey) -> Self {
+        Actor::Key {
+            key_type: key.key_type(),
+            fingerprint: key.fingerprint().hex().to_string(),
+            capabilities: key.metadata().capabilities.clone(),
+            lineage: Vec::new(),
+        }
+    }
+
+    /// Act as the enveloped key in `path`, which must hold its unexpired secret
+    ///
+    /// The envelope must verify up to a root in `anchors` before its type, expiry or
+    /// capabilities are believed, so editing the headers cannot raise the key's level.
+    pub fn holding(path: &Path, anchors: &TrustAnchors) -> AgeResult<Self> {
+        let lineage = KeyEnvelope::read_lineage(path, anchors)
+            .map_err(|e| authorization_error(format!("Cannot act with {}: {}", path.display(), e)))?;
+        let envelope = &lineage[0];
+        let key = AuthorityKey::from_file(path, envelope.key_type)?;
+        if key.key_material().private_key().is_none() {
+            return Err(authorization_error(format!("{} holds no secret key; cannot act with it", path.display())));
+        }
+        if key.is_expired() {
+            return Err(authorization_error(format!("{} has expired; cannot act with it", path.display())));
+        }
+        if key.key_type() == KeyType::Distro {
+            capability::verify_issued(envelope, path, anchors)?;
+        }
+        Ok(Actor::Key {
+            key_type: key.key_type(),
+            fingerprint: key.fingerprint().hex().to_string(),
+            capabilities: key.metadata().capabilities.clone(),
+            lineage,
+        })
+    }
+
+    /// Capabilities limiting the actor; None when only the policy applies
+    pub fn capabilities(&self) -> Option<&KeyCapabilities> {
+        match self {
+            Actor::Key { capabilities, .. } => capabilities.as_ref(),
+            Actor::Passphrase => None,
+        }
+    }
+
+    /// Whether the actor may work on repository path `relative`
+    pub fn covers(&self, relative: &Path) -> bool {
+        self.capabilities().is_none_or(|capabilities| capabilities.allows_path(relative))
+    }
+}
+
+impl fmt::Display for Actor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Actor::Passphrase => write!(f, "passphrase holder"),
+            Actor::Key { key_type, fingerprint, .. } => write!(f, "{} key {}", key_type, &fingerprint[..fingerprint.len().min(8)]),
+        }
+    }
+}
+
+/// Minimum authority for one operation, optionally within a scope
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyRule {
+    pub operation: String,
+    pub level: AuthorityLevel,
+    /// Path below the repository root the rule is limited to
+    pub scope: Option<PathBuf>,
+    pub source: PolicySource,
+}
+
+impl PolicyRule {
+    pub fn new(operation: &str, level: AuthorityLevel) -> Self {
+        Self {
+            operation: operation.to_lowercase(),
+            level,
+            scope: None,
+            source: PolicySource::BuiltIn,
+        }
+    }
+
+    /// Limit the rule to `scope`, relative to the repository root
+    pub fn with_scope(mut self, scope: &str) -> AgeResult<Self> {
+        self.scope = Some(parse_scope(scope)?);
+        Ok(self)
+    }
+
+    /// Whether the rule covers an operation on `relative` (empty for the whole repository)
+    fn applies_to(&self, relative: &Path) -> bool {
+        match &self.scope {
+            None => true,
+            Some(scope) => relative.starts_with(scope) || scope.starts_with(relative),
+        }
+    }
+}
+
+impl fmt::Display for PolicyRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} requires {}", self.operation, self.level.key_type())?;
+        if let Some(scope) = &self.scope {
+            write!(f, " within {}", scope.display())?;
+        }
+        write!(f, " ({})", self.source)
+    }
+}
+
+/// A refused operation and the rule that refused it
+#[derive(Debug, Clone)]
+pub struct Denial {
+    pub operation: String,
+    pub actor: Actor,
+    pub provided: AuthorityLevel,
+    /// None when no rule covers the operation
+    pub rule: Option<PolicyRule>,
+}
+
+impl fmt::Display for Denial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} denied for {} ({})", self.operation, self.actor, self.provided.key_type())?;
+        match &self.rule {
+            Some(rule) => write!(f, ": rule '{}'", rule),
+            None => write!(f, ": no rule allows '{}'", self.operation),
+        }
+    }
+}
+
+impl From<Denial> for AgeError {
+    fn from(denial: Denial) -> Self {
+        authorization_error(denial.to_string())
+    }
+}
+
+/// Policy file contents
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthorizationFile {
+    pub passphrase_level: Option<String>,
+    #[serde(default)]
+    pub rule: Vec<RuleEntry>,
+}
+
+/// One `[[rule]]` table
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleEntry {
+    pub operation: String,
+    pub level: String,
+    pub scope: Option<String>,
+}
+
+/// Rules mapping operations to the authority they require
+#[derive(Debug, Clone)]
+pub struct OperationPolicy {
+    rules: Vec<PolicyRule>,
+    passphrase_level: AuthorityLevel,
+}
+
+impl OperationPolicy {
+    /// Built-in rules; passphrase holders act with distro authority
+    pub fn builtin() -> Self {
+        let mut rules: Vec<PolicyRule> = BUILTIN_RULES.iter()
+            .map(|(operation, key_type)| PolicyRule::new(operation, AuthorityLevel::from_key_type(*key_type)))
+            .collect();
+        for operation in PARENT_OPERATIONS {
+            for key_type in [KeyType::Skull, KeyType::Master, KeyType::Repo, KeyType::Ignition, KeyType::Distro] {
+                let required = key_type.parent_type().unwrap_or(key_type);
+                rules.push(PolicyRule::new(&keyed_operation(operation, key_type), AuthorityLevel::from_key_type(required)));
+            }
+        }
+        Self { rules, passphrase_level: AuthorityLevel::DistroAccess }
+    }
+
+    /// Built-in rules overridden by the global file and a repository's signed
+    /// `.padlock/authorization.toml`, checked against the global trusted roots
+    pub fn load(repository: &Path) -> AgeResult<Self> {
+        let mut policy = Self::builtin();
+        if let Some(path) = global_policy_path().filter(|path| path.is_file()) {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| AgeError::file_error("read", path.clone(), e))?;
+            policy = policy.with_file(&content, PolicySource::Global(path))?;
+        }
+        policy.with_repository(repository, &TrustAnchors::load()?)
+    }
+
+    /// Layer a repository's policy file, which must be signed by a root in `anchors`
+    pub fn with_repository(self, repository: &Path, anchors: &TrustAnchors) -> AgeResult<Self> {
+        let path = repository.join(REPOSITORY_FILE);
+        if !path.is_file() {
+            return Ok(self);
+        }
+        let content = std::fs::read(&path)
+            .map_err(|e| AgeError::file_error("read", path.clone(), e))?;
+        verify_signature(repository, &content, anchors)?;
+        let content = String::from_utf8(content)
+            .map_err(|_| authorization_error(format!("{} is not UTF-8", path.display())))?;
+        self.with_file(&content, PolicySource::Repository(path))
+    }
+
+    /// Layer a policy file's settings over these rules
+    pub fn with_file(mut self, content: &str, source: PolicySource) -> AgeResult<Self> {
+        let file: AuthorizationFile = toml::from_str(content)
+            .map_err(|e| authorization_error(format!("Invalid authorization policy {}: {}", source, e)))?;
+        if let Some(level) = &file.passphrase_level {
+            self.passphrase_level = parse_level(level)?;
+        }
+        for entry in file.rule {
+            let mut rule = PolicyRule::new(&entry.operation, parse_level(&entry.level)?);
+            if let Some(scope) = &entry.scope {
+                rule = rule.with_scope(scope)?;
+            }
+            rule.source = source.clone();
+            self = self.with_rule(rule);
+        }
+        Ok(self)
+    }
+
+    /// Add `rule`, replacing any rule for the same operation and scope
+    pub fn with_rule(mut self, rule: PolicyRule) -> Self {
+        self.rules.retain(|existing| existing.operation != rule.operation || existing.scope != rule.scope);
+        self.rules.push(rule);
+        self
+    }
+
+    /// Authority of callers presenting a passphrase but no key
+    pub fn with_passphrase_level(mut self, level: AuthorityLevel) -> Self {
+        self.passphrase_level = level;
+        self
+    }
+
+    pub fn rules(&self) -> &[PolicyRule] {
+        &self.rules
+    }
+
+    /// Authority `actor` acts with
+    pub fn level_of(&self, actor: &Actor) -> AuthorityLevel {
+        match actor {
+            Actor::Passphrase => self.passphrase_level,
+            Actor::Key { key_type, .. } => AuthorityLevel::from_key_type(*key_type),
+        }
+    }
+
+    /// Why `actor` may not run `operation` on `relative` (empty for the whole repository);
+    /// None when the operation is allowed
+    pub fn denial(&self, operation: &str, relative: &Path, actor: &Actor) -> Option<Denial> {
+        let provided = self.level_of(actor);
+        let denial = |rule: Option<&PolicyRule>| Denial {
+            operation: operation.to_string(),
+            actor: actor.clone(),
+            provided,
+            rule: rule.cloned(),
+        };
+
+        let rules = self.rules_for(&operation.to_lowercase());
+        if rules.is_empty() {
+            return Some(denial(None));
+        }
+        rules.into_iter()
+            .filter(|rule| rule.applies_to(relative) && !provided.can_perform(rule.level))
+            .max_by_key(|rule| rule.level)
+            .map(|rule| denial(Some(rule)))
+    }
+
+    /// Rules of `operation`, or of its plain form for keyed operations without their own
+    fn rules_for(&self, operation: &str) -> Vec<&PolicyRule> {
+        let rules: Vec<&PolicyRule> = self.rules.iter().filter(|rule| rule.operation == operation).collect();
+        if !rules.is_empty() {
+            return rules;
+        }
+        match plain_operation(operation) {
+            plain if plain != operation => self.rules_for(plain),
+            _ => rules,
+        }
+    }
+}
+
+/// Enforces an operation policy and audits refusals
+///
+/// Without a fixed policy, each operation uses the policy of the repository holding its
+/// path; operations without a path use the current directory's. Uses of keys limited
+/// to a number of uses are counted in that repository's usage ledger, kept in the
+/// padlock state directory.
+#[derive(Debug, Clone, Default)]
+pub struct OperationAuthorizer {
+    policy: Option<OperationPolicy>,
+    /// State directory holding usage ledgers (None for the XDG state dir)
+    state_dir: Option<PathBuf>,
+}
+
+impl OperationAuthorizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `policy` for every operation instead of loading repository files
+    pub fn with_policy(mut self, policy: OperationPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Keep usage ledgers under `dir` instead of the default state directory
+    pub fn with_state_dir(mut self, dir: PathBuf) -> Self {
+        self.state_dir = Some(dir);
+        self
+    }
+
+    /// Check `operation` on `path` by `actor`, logging `AUTHORIZATION_DENIED` or
+    /// `CAPABILITY_DENIED` on refusal
+    pub fn authorize(&self, operation: &str, path: Option<&Path>, actor: &Actor, audit_logger: &AuditLogger) -> AgeResult<()> {
+        let target = path.unwrap_or_else(|| Path::new("."));
+        let root = repository_root(target);
+        let policy = match &self.policy {
+            Some(policy) => policy.clone(),
+            None => OperationPolicy::load(&root)?,
+        };
+        let relative = target.strip_prefix(&root).unwrap_or(target);
+
+        if let Some(denial) = policy.denial(operation, relative, actor) {
+            audit_logger.log_error(&format!("AUTHORIZATION_DENIED {} {}: {}", operation, target.display(), denial))?;
+            return Err(denial.into());
+        }
+
+        if let Actor::Key { fingerprint, capabilities: Some(capabilities), .. } = actor {
+            let file = Some(relative).filter(|_| !target.is_dir());
+            let refused = match capabilities.refusal(operation, file) {
+                Some(reason) => Err(authorization_error(format!("{} denied for {}: {}", operation, actor, reason))),
+                None if capabilities.max_uses.is_some() && KeyCapabilities::counts_use(operation) => {
+                    let state_dir = self.state_dir.clone().unwrap_or_else(default_state_dir);
+                    let mut ledger = UsageLedger::load(&state_dir, &root)?;
+                    ledger.record_use(fingerprint, capabilities)
+                        .and_then(|_| ledger.save(&state_dir, &root))
+                }
+                None => Ok(()),
+            };
+            if let Err(error) = refused {
+                audit_logger.log_error(&format!("CAPABILITY_DENIED {} {}: {}", operation, target.display(), error))?;
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sign a repository's policy file with `key`, which must be of at least repo level and
+/// listed in the trusted-roots file of every machine enforcing the policy
+pub fn sign_repository_policy(repository: &Path, key: &AuthorityKey) -> AgeResult<PathBuf> {
+    if !AuthorityLevel::from_key_type(key.key_type()).can_perform(AuthorityLevel::RepoControl) {
+        return Err(authorization_error(format!("A {} key cannot sign authorization policy", key.key_type())));
+    }
+    let path = repository.join(REPOSITORY_FILE);
+    let content = std::fs::read(&path)
+        .map_err(|e| AgeError::file_error("read", path.clone(), e))?;
+    let signature = signing::prove_possession(key, &signed_bytes(&content))?;
+    let signature_path = repository.join(SIGNATURE_FILE);
+    let record = format!("signer: {}\nsignature: {}\n", signing::key_recipient(key)?, hex::encode(signature));
+    std::fs::write(&signature_path, record)
+        .map_err(|e| AgeError::file_error("write", signature_path.clone(), e))?;
+    Ok(signature_path)
+}
+
+/// `$XDG_CONFIG_HOME/padlock/authorization.toml`, falling back to `~/.config`
+pub fn global_policy_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("padlock").join(GLOBAL_FILE))
+}
+
+/// Check the repository policy signature: a trusted root of at least repo level signed
+/// exactly `content`
+fn verify_signature(repository: &Path, content: &[u8], anchors: &TrustAnchors) -> AgeResult<()> {
+    let path = repository.join(SIGNATURE_FILE);
+    let record = std::fs::read_to_string(&path).map_err(|_| authorization_error(format!(
+        "{} is not signed; sign it with 'padlock key sign-policy' or move its rules to {}",
+        repository.join(REPOSITORY_FILE).display(),
+        global_policy_path().map_or_else(|| GLOBAL_FILE.to_string(), |path| path.display().to_string()))))?;
+    let field = |name: &str| record.lines()
+        .find_map(|line| line.trim().strip_prefix(name).map(str::trim))
+        .ok_or_else(|| authorization_error(format!("{} has no '{}' line", path.display(), name.trim_end_matches(':'))));
+    let signer = field("signer:")?;
+    let signature = hex::decode(field("signature:")?)
+        .map_err(|_| authorization_error(format!("{} holds a malformed signature", path.display())))?;
+
+    let root = anchors.root_for(signer)
+        .ok_or_else(|| authorization_error(format!("{} is signed by {}, which is not a trusted root", path.display(), signer)))?;
+    if !AuthorityLevel::from_key_type(root.key_type).can_perform(AuthorityLevel::RepoControl) {
+        return Err(authorization_error(format!("{} is signed by a {} root; policy needs repo authority or above", path.display(), root.key_type)));
+    }
+    if !signing::verify(&signing::recipient_public(signer)?, &signed_bytes(content), &signature) {
+        return Err(authorization_error(format!("{} does not match its signature in {}", REPOSITORY_FILE, path.display())));
+    }
+    Ok(())
+}
+
+fn signed_bytes(content: &[u8]) -> Vec<u8> {
+    [SIGNATURE_CONTEXT, content].concat()
+}
+
+/// Name of `operation` applied to a key of `key_type`, e.g. `rotate-r`
+pub fn keyed_operation(operation: &str, key_type: KeyType) -> String {
+    let letter = match key_type {
+        KeyType::Skull => 'x',
+        KeyType::Master => 'm',
+        KeyType::Repo => 'r',
+        KeyType::Ignition => 'i',
+        KeyType::Distro => 'd',
+    };
+    format!("{}-{}", operation.to_lowercase(), letter)
+}
+
+/// Plain form of a keyed operation (`rotate-r` is `rotate`); other names unchanged
+pub fn plain_operation(operation: &str) -> &str {
+    match operation.rsplit_once('-') {
+        Some((plain, suffix)) if suffix.len() == 1 && KeyType::from_str(suffix).is_ok() => plain,
+        _ => operation,
+    }
+}
+
+/// Nearest ancestor of `path` with a `.padlock` directory, else the directory holding `path`
+pub fn repository_root(path: &Path) -> PathBuf {
+    let start = if path.is_dir() {
+        path.to_path_buf()
+    } else {
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    };
+    start.ancestors()
+        .find(|dir| dir.join(".padlock").is_dir())
+        .map(Path::to_path_buf)
+        .unwrap_or(start)
+}
+
+/// Level named by a key type (`skull`, `repo`, `d`, ...)
+fn parse_level(name: &str) -> AgeResult<AuthorityLevel> {
+    KeyType::from_str(name)
+        .map(AuthorityLevel::from_key_type)
+        .map_err(|_| authorization_error(format!("Unknown authority level '{}'; use a key type such as repo or distro", name)))
+}
+
+/// Relative scope path without `.` components
+fn parse_scope(scope: &str) -> AgeResult<PathBuf> {
+    let mut parsed = PathBuf::new();
+    for component in Path::new(scope).components() {
+        match component {
+            Component::Normal(part) => parsed.push(part),
+            Component::CurDir => {}
+            _ => return Err(authorization_error(format!("Scope '{}' must be a path inside the repository", scope))),
+        }
+    }
+    Ok(parsed)
+}
+
+fn authorization_error(details: String) -> AgeError {
+    AgeError::SecurityValidationFailed {
+        validation_type: "operation_authorization".to_string(),
+        details,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::signing;
+    use tempfile::TempDir;
+
+    fn key(key_type: KeyType) -> Actor {
+        Actor::Key { key_type, fingerprint: "0123456789abcdef".to_string(), capabilities: None, lineage: Vec::new() }
+    }
+
+    fn identity(seed: u8) -> String {
+        let hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").unwrap();
+        bech32::encode_upper::<bech32::Bech32>(hrp, &[seed; 32]).unwrap()
+    }
+
+    fn recipient(seed: u8) -> String {
+        signing::recipient_for_secret(&signing::identity_secret(&identity(seed)).unwrap()).unwrap()
+    }
+
+    /// Enveloped key of `key_type` written to `path`
+    fn write_key(path: &Path, key_type: KeyType, seed: u8) -> AuthorityKey {
+        std::fs::write(path, KeyEnvelope::new(key_type, &recipient(seed)).unwrap().render(&identity(seed)).as_bytes()).unwrap();
+        AuthorityKey::open(path).unwrap()
+    }
+
+    #[test]
+    fn test_builtin_policy_levels() {
+        let policy = OperationPolicy::builtin();
+        let repo = Path::new("");
+
+        // Passphrase holders act at distro level unless a trusted policy raises them
+        assert!(policy.denial("lock", repo, &Actor::Passphrase).is_none());
+        assert!(policy.denial("rotate", repo, &Actor::Passphrase).is_some());
+        assert!(policy.clone().with_passphrase_level(AuthorityLevel::RepoControl).denial("rotate", repo, &Actor::Passphrase).is_none());
+        let denial = policy.denial("emergency", repo, &Actor::Passphrase).unwrap();
+        assert_eq!(denial.rule.unwrap().level, AuthorityLevel::SkullAuthority);
+        assert!(policy.denial("emergency", repo, &key(KeyType::Skull)).is_none());
+
+        // Keyed operations require the target's parent; unknown ones fall back or are denied
+        assert!(policy.denial("revoke-d", repo, &key(KeyType::Ignition)).is_none());
+        assert!(policy.denial("rotate-r", repo, &key(KeyType::Repo)).is_some());
+        assert!(policy.denial("rotate-r", repo, &key(KeyType::Master)).is_none());
+        assert!(policy.denial("encrypt-i", repo, &key(KeyType::Distro)).is_none());
+        let unknown = policy.denial("format-disk", repo, &key(KeyType::Skull)).unwrap();
+        assert!(unknown.rule.is_none());
+    }
+
+    #[test]
+    fn test_repository_policy_scopes_and_audit() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join(".padlock")).unwrap();
+        std::fs::create_dir_all(dir.path().join("secrets/production")).unwrap();
+        let policy_file = r#"
+passphrase_level = "ignition"
+
+[[rule]]
+operation = "unlock"
+level = "master"
+scope = "./secrets/production"
+"#;
+        std::fs::write(dir.path().join(REPOSITORY_FILE), policy_file).unwrap();
+
+        // Unsigned, self-signed and tampered policy files are refused
+        let repo_key = write_key(&dir.path().join("auth-repo.key"), KeyType::Repo, 1);
+        let anchors = TrustAnchors::new().with_root(KeyType::Repo, &recipient(1)).unwrap();
+        let unsigned = OperationPolicy::builtin().with_repository(dir.path(), &anchors).unwrap_err();
+        assert!(unsigned.to_string().contains("is not signed"));
+        sign_repository_policy(dir.path(), &repo_key).unwrap();
+        assert!(OperationPolicy::builtin().with_repository(dir.path(), &TrustAnchors::new()).is_err());
+        std::fs::write(dir.path().join(REPOSITORY_FILE), policy_file.replace("ignition", "skull")).unwrap();
+        assert!(OperationPolicy::builtin().with_repository(dir.path(), &anchors).is_err());
+        std::fs::write(dir.path().join(REPOSITORY_FILE), policy_file).unwrap();
+        let distro_key = write_key(&dir.path().join("auth-distro.key"), KeyType::Distro, 2);
+        assert!(sign_repository_policy(dir.path(), &distro_key).is_err());
+
+        let policy = OperationPolicy::builtin().with_repository(dir.path(), &anchors).unwrap();
+        assert_eq!(policy.level_of(&Actor::Passphrase), AuthorityLevel::IgnitionControl);
+        assert!(policy.denial("rotate", Path::new(""), &Actor::Passphrase).is_some());
+        assert!(policy.denial("unlock", Path::new("docs/readme.txt"), &key(KeyType::Distro)).is_none());
+        // The scoped rule covers files inside it and operations on the whole repository
+        assert!(policy.denial("unlock", Path::new("secrets/production/db.env"), &key(KeyType::Repo)).is_some());
+        assert!(policy.denial("unlock", Path::new(""), &key(KeyType::Repo)).is_some());
+        assert!(policy.denial("unlock", Path::new(""), &key(KeyType::Master)).is_none());
+
+        let log = dir.path().join("audit.log");
+        let audit_logger = AuditLogger::new(Some(log.clone())).unwrap();
+        let authorizer = OperationAuthorizer::new().with_policy(policy);
+        let target = dir.path().join("secrets/production");
+        assert!(authorizer.authorize("unlock", Some(&target), &key(KeyType::Distro), &audit_logger).is_err());
+        assert!(authorizer.authorize("unlock", Some(&dir.path().join("docs.txt")), &key(KeyType::Distro), &audit_logger).is_ok());
+
+        let entries = std::fs::read_to_string(&log).unwrap();
+        assert!(entries.contains("AUTHORIZATION_DENIED unlock"));
+        assert!(entries.contains("unlock requires master within secrets/production"));
+
+        assert!(OperationPolicy::builtin().with_file("[[rule]]\noperation = \"lock\"\nlevel = \"admin\"\n", PolicySource::BuiltIn).is_err());
+        assert!(PolicyRule::new("lock", AuthorityLevel::DistroAccess).with_scope("../outside").is_err());
+    }
+
+    #[test]
+    fn test_holding_requires_verified_envelope() {
+        let dir = TempDir::new().unwrap();
+        let ignition = write_key(&dir.path().join("auth-ignition.key"), KeyType::Ignition, 1);
+        let distro = KeyEnvelope::new(KeyType::Distro, &recipient(2)).unwrap().with_parent(&ignition).unwrap();
+        let distro_path = dir.path().join("agent.key");
+        std::fs::write(&distro_path, distro.render(&identity(2)).as_bytes()).unwrap();
+
+        let anchors = TrustAnchors::new().with_root(KeyType::Ignition, &recipient(1)).unwrap();
+        let Actor::Key { fingerprint, lineage, .. } = Actor::holding(&distro_path, &anchors).unwrap() else {
+            panic!("holding a key acts as that key");
+        };
+        assert_eq!(fingerprint, AuthorityKey::open(&distro_path).unwrap().fingerprint().hex());
+        assert_eq!(lineage, vec![distro.clone(), KeyEnvelope::read(&dir.path().join("auth-ignition.key")).unwrap().unwrap()]);
+        assert!(Actor::holding(&distro_path, &TrustAnchors::new()).is_err());
+
+        // Retyping the key file to skull does not give it skull authority
+        let retyped = dir.path().join("retyped.key");
+        std::fs::write(&retyped, distro.render(&identity(2)).replace("# type: distro", "# type: skull")).unwrap();
+        assert_eq!(AuthorityKey::open(&retyped).unwrap().key_type(), KeyType::Skull);
+        let refused = Actor::holding(&retyped, &anchors).unwrap_err();
+        assert!(refused.to_string().contains("Cannot act with"));
+
+        // Nor does a self-made skull key no root vouches for
+        let rogue = dir.path().join("rogue.key");
+        write_key(&rogue, KeyType::Skull, 3);
+        assert!(Actor::holding(&rogue, &anchors).is_err());
+    }
+}
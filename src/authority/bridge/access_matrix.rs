@@ -0,0 +1,430 @@
+//! Repository Access Matrix - Which authority keys can decrypt which files
+//!
+//! Maps every age file in a repository to the authority keys able to open it, either
+//! directly (the key is a recipient in the file header) or transitively (the key is an
+//! ancestor in the X->M->R->I->D chain of a direct recipient). Only headers are read,
+//! so the report needs no passphrases and never decrypts payloads.
+//!
+//! X25519 stanzas are matched by trial-unwrapping with the key's identity; keys loaded
+//! without private material can only be matched against SSH stanzas.
+//!
+//! Security Guardian: Edgar - Audit who can read what
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::encryption::age_automation::{
+    error::{AgeError, AgeResult},
+    header::{AgeHeader, KnownRecipient, StanzaKind},
+};
+use super::super::{AuthorityChain, AuthorityKey, KeyFingerprint, KeyType};
+
+/// How a key reaches a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessKind {
+    /// The key is a recipient of the file
+    Direct,
+    /// The key controls a recipient through the authority chain
+    Transitive,
+}
+
+impl fmt::Display for AccessKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessKind::Direct => write!(f, "direct"),
+            AccessKind::Transitive => write!(f, "transitive"),
+        }
+    }
+}
+
+/// Problem flagged for a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "finding", rename_all = "snake_case")]
+pub enum AccessFinding {
+    /// No current (unrevoked, unexpired) key can open the file
+    NoCurrentKey,
+    /// Revoked keys can still open the file
+    RevokedKeyAccess { keys: Vec<String> },
+    /// No current key of a recovery type (X or M) is a direct recipient
+    MissingRecoveryRecipient { key_type: String },
+    /// The header could not be parsed
+    UnreadableHeader { reason: String },
+}
+
+impl fmt::Display for AccessFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessFinding::NoCurrentKey => write!(f, "no current key can open"),
+            AccessFinding::RevokedKeyAccess { keys } => write!(f, "readable by revoked {}", keys.join(" ")),
+            AccessFinding::MissingRecoveryRecipient { key_type } => write!(f, "missing {} recovery recipient", key_type),
+            AccessFinding::UnreadableHeader { reason } => write!(f, "unreadable header: {}", reason),
+        }
+    }
+}
+
+/// Authority key column of the matrix
+#[derive(Debug, Clone, Serialize)]
+pub struct MatrixKey {
+    pub label: String,
+    pub key_type: String,
+    pub fingerprint: String,
+    pub revoked: bool,
+    pub expired: bool,
+    /// False when the key material cannot be matched against headers
+    pub matchable: bool,
+}
+
+impl MatrixKey {
+    fn is_current(&self) -> bool {
+        !self.revoked && !self.expired
+    }
+}
+
+/// Access row for a single file
+#[derive(Debug, Clone, Serialize)]
+pub struct FileAccess {
+    /// Path relative to the repository
+    pub path: PathBuf,
+    pub stanzas: String,
+    /// True when the file also opens with a passphrase (scrypt stanza)
+    pub passphrase: bool,
+    /// Recipient stanzas that match no authority key
+    pub unidentified_stanzas: usize,
+    /// Key label -> access kind
+    pub access: BTreeMap<String, AccessKind>,
+    pub findings: Vec<AccessFinding>,
+}
+
+/// Repository-wide access report
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessMatrix {
+    pub repository: PathBuf,
+    pub generated_at: DateTime<Utc>,
+    pub keys: Vec<MatrixKey>,
+    pub files: Vec<FileAccess>,
+}
+
+impl AccessMatrix {
+    /// Build the matrix for every age file under a repository
+    pub fn build(repository: &Path, chain: &AuthorityChain, revoked: &HashSet<KeyFingerprint>) -> AgeResult<Self> {
+        if !repository.is_dir() {
+            return Err(AgeError::InvalidOperation {
+                operation: "access_matrix".to_string(),
+                reason: format!("Repository path required: {}", repository.display()),
+            });
+        }
+
+        let authority_keys: Vec<&AuthorityKey> = [KeyType::Skull, KeyType::Master, KeyType::Repo, KeyType::Ignition, KeyType::Distro]
+            .iter()
+            .flat_map(|key_type| {
+                let mut keys = chain.get_keys_by_type(*key_type);
+                keys.sort_by(|a, b| a.fingerprint().hex().cmp(b.fingerprint().hex()));
+                keys
+            })
+            .collect();
+
+        let mut keys = Vec::new();
+        let mut known = Vec::new();
+        for key in &authority_keys {
+            let label = key_label(key);
+            let recipient = known_recipient(key, &label);
+            keys.push(MatrixKey {
+                label,
+                key_type: key.key_type().to_string(),
+                fingerprint: key.fingerprint().hex().to_string(),
+                revoked: revoked.contains(key.fingerprint()),
+                expired: key.is_expired(),
+                matchable: recipient.is_some(),
+            });
+            if let Some(recipient) = recipient {
+                known.push((*key, recipient));
+            }
+        }
+
+        let mut paths = Vec::new();
+        collect_age_files(repository, &mut paths)?;
+        paths.sort();
+
+        let files = paths.iter()
+            .map(|path| Self::file_access(repository, path, chain, &keys, &known))
+            .collect();
+
+        Ok(Self {
+            repository: repository.to_path_buf(),
+            generated_at: Utc::now(),
+            keys,
+            files,
+        })
+    }
+
+    fn file_access(
+        repository: &Path,
+        path: &Path,
+        chain: &AuthorityChain,
+        keys: &[MatrixKey],
+        known: &[(&AuthorityKey, KnownRecipient)],
+    ) -> FileAccess {
+        let mut row = FileAccess {
+            path: path.strip_prefix(repository).unwrap_or(path).to_path_buf(),
+            stanzas: String::new(),
+            passphrase: false,
+            unidentified_stanzas: 0,
+            access: BTreeMap::new(),
+            findings: Vec::new(),
+        };
+
+        let header = match AgeHeader::read(path) {
+            Ok(header) => header,
+            Err(e) => {
+                row.findings.push(AccessFinding::UnreadableHeader { reason: e.to_string() });
+                return row;
+            }
+        };
+        row.stanzas = header.summary();
+
+        for stanza in &header.stanzas {
+            if stanza.kind == StanzaKind::Scrypt {
+                row.passphrase = true;
+                continue;
+            }
+            let mut matched = false;
+            for (key, recipient) in known.iter().filter(|(_, recipient)| recipient.matches(stanza)) {
+                matched = true;
+                row.access.insert(recipient.label.clone(), AccessKind::Direct);
+
+                // Every ancestor of a recipient can recover the recipient's key
+                let mut current = chain.get_parent(key.fingerprint());
+                while let Some(parent) = current {
+                    row.access.entry(key_label(parent)).or_insert(AccessKind::Transitive);
+                    current = chain.get_parent(parent.fingerprint());
+                }
+            }
+            if !matched {
+                row.unidentified_stanzas += 1;
+            }
+        }
+
+        let key = |label: &str| keys.iter().find(|key| key.label == label);
+        if !row.access.keys().any(|label| key(label).is_some_and(MatrixKey::is_current)) {
+            row.findings.push(AccessFinding::NoCurrentKey);
+        }
+
+        let revoked: Vec<String> = row.access.keys()
+            .filter(|label| key(label).is_some_and(|key| key.revoked))
+            .cloned()
+            .collect();
+        if !revoked.is_empty() {
+            row.findings.push(AccessFinding::RevokedKeyAccess { keys: revoked });
+        }
+
+        for recovery in [KeyType::Skull, KeyType::Master] {
+            let covered = row.access.iter().any(|(label, access)| {
+                *access == AccessKind::Direct
+                    && key(label).is_some_and(|key| key.is_current() && key.key_type == recovery.to_string())
+            });
+            if !covered {
+                row.findings.push(AccessFinding::MissingRecoveryRecipient { key_type: recovery.to_string() });
+            }
+        }
+
+        row
+    }
+
+    /// Files with at least one finding
+    pub fn flagged_files(&self) -> Vec<&FileAccess> {
+        self.files.iter().filter(|file| !file.findings.is_empty()).collect()
+    }
+
+    /// Render the matrix as pretty-printed JSON
+    pub fn to_json(&self) -> AgeResult<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| AgeError::InvalidOperation {
+                operation: "access_matrix_to_json".to_string(),
+                reason: e.to_string(),
+            })
+    }
+
+    /// Render the matrix as CSV: one row per file, one column per key
+    pub fn to_csv(&self) -> String {
+        let mut header = vec!["file".to_string(), "stanzas".to_string(), "passphrase".to_string()];
+        header.extend(self.keys.iter().map(|key| key.label.clone()));
+        header.push("findings".to_string());
+
+        let mut out = csv_row(&header);
+        for file in &self.files {
+            let mut row = vec![file.path.display().to_string(), file.stanzas.clone(), file.passphrase.to_string()];
+            row.extend(self.keys.iter().map(|key| {
+                file.access.get(&key.label).map(ToString::to_string).unwrap_or_default()
+            }));
+            row.push(file.findings.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "));
+            out.push_str(&csv_row(&row));
+        }
+        out
+    }
+
+    /// Render the matrix as a human-readable table (D = direct, T = transitive)
+    pub fn to_table(&self) -> String {
+        let mut out = format!("Access matrix: {} ({} files, {} keys)\n",
+            self.repository.display(), self.files.len(), self.keys.len());
+
+        let path_width = self.files.iter()
+            .map(|file| file.path.display().to_string().len())
+            .chain(["FILE".len()])
+            .max()
+            .unwrap_or(0);
+        let mut header = format!("{:<pw$}", "FILE", pw = path_width);
+        for key in &self.keys {
+            header.push_str(&format!("  {}", key.label));
+        }
+        out.push_str(&format!("{}  FINDINGS\n", header));
+
+        for file in &self.files {
+            let mut line = format!("{:<pw$}", file.path.display().to_string(), pw = path_width);
+            for key in &self.keys {
+                let mark = match file.access.get(&key.label) {
+                    Some(AccessKind::Direct) => "D",
+                    Some(AccessKind::Transitive) => "T",
+                    None => "-",
+                };
+                line.push_str(&format!("  {:<w$}", mark, w = key.label.len()));
+            }
+            let findings = file.findings.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ");
+            out.push_str(format!("{}  {}", line, findings).trim_end());
+            out.push('\n');
+        }
+        if self.files.is_empty() {
+            out.push_str("(no age files)\n");
+        }
+
+        for key in &self.keys {
+            let mut notes = Vec::new();
+            if key.revoked {
+                notes.push("revoked");
+            }
+            if key.expired {
+                notes.push("expired");
+            }
+            if !key.matchable {
+                notes.push("not matchable");
+            }
+            if !notes.is_empty() {
+                out.push_str(&format!("note: {} {}\n", key.label, notes.join(", ")));
+            }
+        }
+        out
+    }
+}
+
+/// Matrix column label for a key, e.g. `master:1a2b3c4d`
+fn key_label(key: &AuthorityKey) -> String {
+    format!("{}:{}", key.key_type(), key.fingerprint().short())
+}
+
+/// Header matcher for an authority key: its identity if present, else its public recipient
+fn known_recipient(key: &AuthorityKey, label: &str) -> Option<KnownRecipient> {
+    let material = key.key_material();
+    let text = String::from_utf8_lossy(material.private_key().unwrap_or(material.public_key())).into_owned();
+    let line = text.lines().map(str::trim).find(|line| !line.is_empty() && !line.starts_with('#'))?;
+
+    if line.starts_with("AGE-SECRET-KEY-") {
+        KnownRecipient::from_identity(label, line).ok()
+    } else {
+        KnownRecipient::from_recipient(label, line).ok()
+    }
+}
+
+/// Recursively collect `.age` files, skipping `.git`
+fn collect_age_files(directory: &Path, files: &mut Vec<PathBuf>) -> AgeResult<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if path.file_name().is_some_and(|name| name != ".git") {
+                collect_age_files(&path, files)?;
+            }
+        } else if path.extension().and_then(|s| s.to_str()) == Some("age") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// One CSV record, quoting fields that need it
+fn csv_row(fields: &[String]) -> String {
+    let quoted: Vec<String> = fields.iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect();
+    format!("{}\n", quoted.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::chain::{KeyFormat, KeyMaterial};
+    use crate::encryption::age_automation::header::testing;
+    use tempfile::TempDir;
+
+    fn authority_key(secret: u8, key_type: KeyType) -> (AuthorityKey, [u8; 32]) {
+        let (identity, public) = testing::identity([secret; 32]);
+        let material = KeyMaterial::new(identity.clone().into_bytes(), Some(identity.into_bytes()), KeyFormat::Age);
+        (AuthorityKey::new(material, key_type, None, None).unwrap(), public)
+    }
+
+    #[test]
+    fn test_access_matrix_findings() {
+        let (skull, skull_pub) = authority_key(1, KeyType::Skull);
+        let (master, master_pub) = authority_key(2, KeyType::Master);
+        let (repo_key, repo_pub) = authority_key(3, KeyType::Repo);
+        let (skull_fp, master_fp, repo_fp) = (skull.fingerprint().clone(), master.fingerprint().clone(), repo_key.fingerprint().clone());
+
+        let mut chain = AuthorityChain::new();
+        chain.add_key(skull).unwrap();
+        chain.add_key(master).unwrap();
+        chain.add_key(repo_key).unwrap();
+        chain.add_authority_relationship(&skull_fp, &master_fp).unwrap();
+        chain.add_authority_relationship(&master_fp, &repo_fp).unwrap();
+
+        let repo = TempDir::new().unwrap();
+        std::fs::write(repo.path().join("recovered.age"), testing::x25519_header(&[skull_pub, master_pub], false)).unwrap();
+        std::fs::write(repo.path().join("repo-only.age"), testing::x25519_header(&[repo_pub], false)).unwrap();
+        std::fs::write(repo.path().join("stranger.age"), testing::x25519_header(&[[42u8; 32]], true)).unwrap();
+
+        let revoked: HashSet<KeyFingerprint> = [repo_fp.clone()].into();
+        let matrix = AccessMatrix::build(repo.path(), &chain, &revoked).unwrap();
+        assert_eq!(matrix.keys.len(), 3);
+        assert_eq!(matrix.keys[0].key_type, "skull");
+
+        let file = |name: &str| matrix.files.iter().find(|f| f.path == Path::new(name)).unwrap();
+        assert!(file("recovered.age").findings.is_empty());
+
+        let repo_only = file("repo-only.age");
+        let repo_label = format!("repo:{}", repo_fp.short());
+        assert_eq!(repo_only.access[&repo_label], AccessKind::Direct);
+        assert_eq!(repo_only.access[&format!("skull:{}", skull_fp.short())], AccessKind::Transitive);
+        assert!(repo_only.findings.contains(&AccessFinding::RevokedKeyAccess { keys: vec![repo_label] }));
+        assert!(repo_only.findings.contains(&AccessFinding::MissingRecoveryRecipient { key_type: "master".to_string() }));
+        assert!(!repo_only.findings.contains(&AccessFinding::NoCurrentKey));
+
+        let stranger = file("stranger.age");
+        assert!(stranger.passphrase);
+        assert_eq!(stranger.unidentified_stanzas, 1);
+        assert!(stranger.findings.contains(&AccessFinding::NoCurrentKey));
+        assert_eq!(matrix.flagged_files().len(), 2);
+
+        let csv = matrix.to_csv();
+        assert!(csv.starts_with("file,stanzas,passphrase,skull:"));
+        assert!(csv.lines().any(|line| line.starts_with("repo-only.age,X25519,false,transitive,transitive,direct,")));
+        assert!(matrix.to_table().contains("note: repo:"));
+        let json: serde_json::Value = serde_json::from_str(&matrix.to_json().unwrap()).unwrap();
+        assert_eq!(json["files"].as_array().unwrap().len(), 3);
+    }
+}
@@ -5,8 +5,8 @@
 //!
 //! Security Guardian: Edgar - Production authority-Age integration
 
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 
 use crate::encryption::age_automation::{
     config::OutputFormat,
@@ -16,8 +16,17 @@ use crate::encryption::age_automation::{
 };
 use super::super::{
     AuthorityChain, AuthorityKey, KeyFingerprint, KeyType,
-    validation::{AuthorityValidationEngine, AuthorityLevel},
+    lineage::{LineageCheck, LineageResolver, Relation},
+    revocation::RevocationList,
+    signing,
+    trust::TrustAnchors,
+    validation::AuthorityValidationEngine,
 };
+use super::access_matrix::AccessMatrix;
+use super::external::{ExternalAuthorityValidator, ScriptValidator};
+
+/// Authority manager script of older pilot layouts, relative to the pilot directory
+pub const LEGACY_AUTHORITY_MANAGER: &str = "01-key_authority/authority_manager.sh";
 
 /// Authority-aware Age automation interface
 pub struct AuthorityAgeInterface {
@@ -54,10 +63,11 @@ impl AuthorityAgeInterface {
         format: OutputFormat,
     ) -> AgeResult<()> {
         // 1. Authorize operation
-        self.validation_engine.validate_operation_authorization(
+        self.validation_engine.authorize_operation(
             "encrypt",
             authority_key_fp,
-            AuthorityLevel::DistroAccess,
+            Some(input),
+            &self.audit_logger,
         )?;
         
         // 2. Get authority key
@@ -79,6 +89,7 @@ impl AuthorityAgeInterface {
             format,
             pattern_filter: None,
             backup_before_lock: false,
+            preserve_xattrs: false,
         };
         
         // Convert single file operation to repository operation
@@ -97,10 +108,11 @@ impl AuthorityAgeInterface {
         authority_key_fp: &KeyFingerprint,
     ) -> AgeResult<()> {
         // 1. Authorize operation
-        self.validation_engine.validate_operation_authorization(
+        self.validation_engine.authorize_operation(
             "decrypt",
             authority_key_fp,
-            AuthorityLevel::DistroAccess,
+            Some(input),
+            &self.audit_logger,
         )?;
         
         // 2. Get authority key
@@ -139,10 +151,11 @@ impl AuthorityAgeInterface {
         authority_key_fp: &KeyFingerprint,
     ) -> AgeResult<crate::encryption::age_automation::operations::RepositoryStatus> {
         // 1. Authorize operation
-        self.validation_engine.validate_operation_authorization(
+        self.validation_engine.authorize_operation(
             "status",
             authority_key_fp,
-            AuthorityLevel::DistroAccess,
+            Some(repo_path),
+            &self.audit_logger,
         )?;
         
         // 2. Log operation
@@ -152,6 +165,35 @@ impl AuthorityAgeInterface {
         self.crud_manager.status(repo_path)
     }
     
+    /// Get the repository access matrix: which chain keys can decrypt which files
+    pub fn access_matrix_with_authority(
+        &mut self,
+        repo_path: &Path,
+        authority_key_fp: &KeyFingerprint,
+        revoked: &HashSet<KeyFingerprint>,
+    ) -> AgeResult<AccessMatrix> {
+        // 1. Authorize operation - auditing access needs repository authority by default
+        self.validation_engine.authorize_operation(
+            "access-matrix",
+            authority_key_fp,
+            Some(repo_path),
+            &self.audit_logger,
+        )?;
+        
+        // 2. Log operation
+        self.audit_logger.log_authority_operation("access_matrix", authority_key_fp.hex())?;
+        
+        // 3. Build report from file headers
+        let matrix = AccessMatrix::build(repo_path, &self.authority_chain, revoked)?;
+        self.audit_logger.log_info(&format!(
+            "ACCESS_MATRIX {} files: {} flagged: {}",
+            repo_path.display(),
+            matrix.files.len(),
+            matrix.flagged_files().len()
+        ))?;
+        Ok(matrix)
+    }
+    
     /// Extract passphrase for operation from authority key
     fn get_operation_passphrase(&self, authority_key: &AuthorityKey) -> AgeResult<String> {
         match authority_key.key_type() {
@@ -207,131 +249,151 @@ impl AuthorityAgeInterface {
     }
 }
 
-/// Bridge to Lucas's atomic authority operations  
+/// Lucas's atomic authority protocol, implemented natively
+///
+/// Relationships are proven from key file envelopes by `LineageResolver`, on top of
+/// `AuthorityValidationEngine`; external validators can then only veto.
 pub struct LucasAuthorityBridge {
-    authority_manager_path: PathBuf,
-    emergency_recovery_path: PathBuf,
+    resolver: LineageResolver,
+    anchors: TrustAnchors,
+    /// Chain whose skull key may authorize emergencies and whose lineage revocations follow
+    chain: AuthorityChain,
+    revocations: RevocationList,
+    validators: Vec<Box<dyn ExternalAuthorityValidator>>,
     audit_logger: AuditLogger,
 }
 
 impl LucasAuthorityBridge {
     /// Create new Lucas bridge
-    pub fn new(
-        pilot_directory: &Path,
-        audit_log_path: Option<PathBuf>,
-    ) -> AgeResult<Self> {
-        let authority_manager_path = pilot_directory.join("01-key_authority/authority_manager.sh");
-        let emergency_recovery_path = pilot_directory.join("01-key_authority/emergency_recovery.sh");
-        
-        // Verify scripts exist
-        if !authority_manager_path.exists() {
-            return Err(AgeError::InvalidOperation {
-                operation: "create_lucas_bridge".to_string(),
-                reason: format!("Authority manager script not found: {}", authority_manager_path.display()),
-            });
-        }
-        
-        let audit_logger = AuditLogger::new(audit_log_path)?;
-        
+    pub fn new(audit_log_path: Option<PathBuf>) -> AgeResult<Self> {
+        let anchors = TrustAnchors::load()?;
         Ok(Self {
-            authority_manager_path,
-            emergency_recovery_path,
-            audit_logger,
+            resolver: LineageResolver::new().with_trust_anchors(anchors.clone()),
+            anchors,
+            chain: AuthorityChain::new(),
+            revocations: RevocationList::default(),
+            validators: Vec::new(),
+            audit_logger: AuditLogger::new(audit_log_path)?,
         })
     }
     
-    /// Validate authority relationship atomically using Lucas's script
+    /// Also look for intermediate key files in `dir`
+    pub fn with_search_dir(mut self, dir: PathBuf) -> Self {
+        self.resolver = self.resolver.with_search_dir(dir);
+        self
+    }
+    
+    /// Verify parent keys against these roots instead of the configured ones
+    pub fn with_trust_anchors(mut self, anchors: TrustAnchors) -> Self {
+        self.resolver = self.resolver.with_trust_anchors(anchors.clone());
+        self.anchors = anchors;
+        self
+    }
+    
+    /// Accept the skull key of `chain` for emergencies and follow its lineage for revocations
+    pub fn with_authority_chain(mut self, chain: AuthorityChain) -> Self {
+        self.chain = chain;
+        self
+    }
+    
+    /// Refuse keys revoked in `revocations`
+    pub fn with_revocations(mut self, revocations: RevocationList) -> Self {
+        self.resolver = self.resolver.with_revocations(revocations.clone());
+        self.revocations = revocations;
+        self
+    }
+    
+    /// Add an external check run after native validation passes
+    pub fn with_validator(mut self, validator: Box<dyn ExternalAuthorityValidator>) -> Self {
+        self.validators.push(validator);
+        self
+    }
+    
+    /// Prove `parent`'s authority over `child`, with the lineage path
+    pub fn check_authority(&self, parent_key_path: &Path, child_key_path: &Path) -> AgeResult<LineageCheck> {
+        let mut check = self.resolver.check(Relation::Authority, parent_key_path, child_key_path)?;
+        if check.is_valid() {
+            for validator in &self.validators {
+                if !validator.validate_authority(parent_key_path, child_key_path)? {
+                    check.failure = Some(format!("Rejected by external validator {}", validator.name()));
+                    break;
+                }
+            }
+        }
+        
+        let message = format!("LUCAS_VALIDATION {} -> {}: {}",
+            parent_key_path.display(), child_key_path.display(), check.token());
+        match &check.failure {
+            None => self.audit_logger.log_info(&message)?,
+            Some(failure) => self.audit_logger.log_warning(&format!("{} ({})", message, failure))?,
+        }
+        Ok(check)
+    }
+    
+    /// Validate authority relationship atomically
     pub fn validate_authority_atomically(
         &self,
         parent_key_path: &Path,
         child_key_path: &Path,
     ) -> AgeResult<bool> {
-        self.audit_logger.log_info(&format!(
-            "Lucas atomic validation: {} -> {}",
-            parent_key_path.display(),
-            child_key_path.display()
-        ))?;
-        
-        let result = Command::new("bash")
-            .arg(&self.authority_manager_path)
-            .arg("validate_authority")
-            .arg("--parent").arg(parent_key_path)
-            .arg("--child").arg(child_key_path)
-            .arg("--atomic")
-            .output()
-            .map_err(|e| AgeError::InvalidOperation {
-                operation: "lucas_validate_authority".to_string(),
-                reason: format!("Failed to execute Lucas script: {}", e),
-            })?;
-        
-        if !result.status.success() {
-            let stderr = String::from_utf8_lossy(&result.stderr);
-            self.audit_logger.log_warning(&format!("Lucas validation failed: {}", stderr))?;
-            return Ok(false);
-        }
-        
-        // Parse Lucas's validation result
-        let output = String::from_utf8_lossy(&result.stdout);
-        let is_valid = output.trim() == "AUTHORITY_VALID";
-        
-        self.audit_logger.log_info(&format!(
-            "Lucas validation result: {} ({})",
-            if is_valid { "VALID" } else { "INVALID" },
-            output.trim()
-        ))?;
-        
-        Ok(is_valid)
+        Ok(self.check_authority(parent_key_path, child_key_path)?.is_valid())
     }
     
-    /// Perform emergency recovery using Lucas's script
+    /// Authorize an emergency recovery with a skull key
+    ///
+    /// The key file must be an enveloped, unexpired, unrevoked skull key holding its
+    /// secret, and must be the authority chain's skull key or a skull root in the trust
+    /// anchors; a key file merely labelled skull is refused. Every external validator must
+    /// then approve. The recovery itself is left to the caller (e.g.
+    /// `CrudManager::emergency_unlock`).
     pub fn emergency_recovery(&self, recovery_type: &str, key_path: &Path) -> AgeResult<()> {
-        self.audit_logger.log_emergency_operation("lucas_emergency_recovery", key_path)?;
-        
-        let result = Command::new("bash")
-            .arg(&self.emergency_recovery_path)
-            .arg(recovery_type)
-            .arg("--key").arg(key_path)
-            .output()
-            .map_err(|e| AgeError::InvalidOperation {
-                operation: "lucas_emergency_recovery".to_string(),
-                reason: format!("Failed to execute emergency recovery: {}", e),
-            })?;
+        let refuse = |reason: String| {
+            self.audit_logger.log_warning(&format!("LUCAS_EMERGENCY_REFUSED {} {}: {}", recovery_type, key_path.display(), reason))?;
+            Err(AgeError::SecurityValidationFailed {
+                validation_type: "emergency_recovery".to_string(),
+                details: reason,
+            })
+        };
         
-        if !result.status.success() {
-            let stderr = String::from_utf8_lossy(&result.stderr);
-            return Err(AgeError::InvalidOperation {
-                operation: "lucas_emergency_recovery".to_string(),
-                reason: format!("Emergency recovery failed: {}", stderr),
-            });
+        let key = match AuthorityKey::open(key_path) {
+            Ok(key) => key,
+            Err(error) => return refuse(error.to_string()),
+        };
+        if key.key_type() != KeyType::Skull {
+            return refuse(format!("Emergency recovery needs a skull key, not a {} key", key.key_type()));
+        }
+        if !key.key_material().has_private_key() {
+            return refuse("Emergency recovery needs the skull key's secret".to_string());
+        }
+        if key.is_expired() {
+            return refuse(format!("Skull key {} has expired", key.fingerprint()));
+        }
+        let in_chain = self.chain.get_keys_by_type(KeyType::Skull).iter().any(|skull| skull.fingerprint() == key.fingerprint());
+        let rooted = signing::key_recipient(&key)
+            .is_ok_and(|recipient| self.anchors.root_for(&recipient).is_some_and(|root| root.key_type == KeyType::Skull));
+        if !in_chain && !rooted {
+            return refuse(format!("Skull key {} is neither the authority chain's skull key nor a trusted skull root", key.fingerprint()));
+        }
+        if let Err(error) = self.revocations.check(&key, &self.chain) {
+            return refuse(error.to_string());
+        }
+        for validator in &self.validators {
+            if !validator.approve_emergency(recovery_type, key_path)? {
+                return refuse(format!("Not approved by external validator {}", validator.name()));
+            }
         }
         
-        self.audit_logger.log_info("Lucas emergency recovery completed successfully")?;
+        self.audit_logger.log_emergency_operation(&format!("lucas_emergency_recovery:{}", recovery_type), key_path)?;
         Ok(())
     }
     
     /// Test if Lucas bridge is operational
     pub fn health_check(&self) -> AgeResult<()> {
-        // Test authority manager script
-        let result = Command::new("bash")
-            .arg(&self.authority_manager_path)
-            .arg("--version")
-            .output();
-            
-        match result {
-            Ok(output) if output.status.success() => {
-                let version = String::from_utf8_lossy(&output.stdout);
-                self.audit_logger.log_info(&format!("Lucas bridge operational: {}", version.trim()))?;
-                Ok(())
-            }
-            Ok(_) => Err(AgeError::InvalidOperation {
-                operation: "lucas_health_check".to_string(),
-                reason: "Authority manager script returned error".to_string(),
-            }),
-            Err(e) => Err(AgeError::InvalidOperation {
-                operation: "lucas_health_check".to_string(),
-                reason: format!("Cannot execute authority manager: {}", e),
-            }),
+        for validator in &self.validators {
+            validator.health_check()?;
         }
+        self.audit_logger.log_info(&format!("Lucas bridge operational (native, {} external validator(s))", self.validators.len()))?;
+        Ok(())
     }
 }
 
@@ -352,8 +414,87 @@ impl AuthorityAgeFactory {
         AuthorityAgeInterface::new(crud_manager, authority_chain, None)
     }
     
-    /// Create Lucas authority bridge
+    /// Create Lucas authority bridge; a legacy authority manager script in the pilot
+    /// directory is kept as an external validator
     pub fn create_lucas_bridge(pilot_directory: &Path) -> AgeResult<LucasAuthorityBridge> {
-        LucasAuthorityBridge::new(pilot_directory, None)
+        let bridge = LucasAuthorityBridge::new(None)?;
+        let legacy_script = pilot_directory.join(LEGACY_AUTHORITY_MANAGER);
+        if legacy_script.is_file() {
+            return Ok(bridge.with_validator(Box::new(ScriptValidator::new(legacy_script)?)));
+        }
+        Ok(bridge)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::{signing, KeyEnvelope};
+    use tempfile::TempDir;
+
+    /// Enveloped skull -> master -> repo -> ignition -> distro key files; trusts the skull key
+    fn write_chain(dir: &Path) -> TrustAnchors {
+        let mut anchors = TrustAnchors::new();
+        let mut parent: Option<AuthorityKey> = None;
+        for (seed, key_type) in [KeyType::Skull, KeyType::Master, KeyType::Repo, KeyType::Ignition, KeyType::Distro].into_iter().enumerate() {
+            let hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").unwrap();
+            let identity = bech32::encode_upper::<bech32::Bech32>(hrp, &[seed as u8 + 40; 32]).unwrap();
+            let recipient = signing::recipient_for_secret(&signing::identity_secret(&identity).unwrap()).unwrap();
+            let mut envelope = KeyEnvelope::new(key_type, &recipient).unwrap();
+            match &parent {
+                Some(parent) => envelope = envelope.with_parent(parent).unwrap(),
+                None => anchors = anchors.with_root(key_type, &recipient).unwrap(),
+            }
+            let path = dir.join(format!("auth-{}.key", key_type));
+            std::fs::write(&path, envelope.render(&identity).as_bytes()).unwrap();
+            parent = Some(AuthorityKey::from_file(&path, key_type).unwrap());
+        }
+        anchors
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_native_bridge_with_stub_validator_veto() {
+        let dir = TempDir::new().unwrap();
+        let anchors = write_chain(dir.path());
+        let key = |key_type: &str| dir.path().join(format!("auth-{}.key", key_type));
+
+        let native = LucasAuthorityBridge::new(None).unwrap().with_trust_anchors(anchors.clone());
+        assert!(native.validate_authority_atomically(&key("master"), &key("distro")).unwrap());
+        assert!(!native.validate_authority_atomically(&key("distro"), &key("master")).unwrap());
+        native.emergency_recovery("break-glass", &key("skull")).unwrap();
+        assert!(native.emergency_recovery("break-glass", &key("master")).is_err());
+
+        // A stub organization script that refuses distro keys and approves only break-glass
+        let script = dir.path().join(LEGACY_AUTHORITY_MANAGER);
+        std::fs::create_dir_all(script.parent().unwrap()).unwrap();
+        std::fs::write(&script, "#!/bin/bash\ncase \"$1\" in\n  validate_authority) [[ \"$5\" == *distro* ]] && { echo AUTHORITY_INVALID; exit 1; }; echo AUTHORITY_VALID ;;\n  approve_emergency) [[ \"$2\" == break-glass ]] ;;\n  --version) echo stub ;;\nesac\n").unwrap();
+        let bridge = AuthorityAgeFactory::create_lucas_bridge(dir.path()).unwrap().with_trust_anchors(anchors.clone());
+        bridge.health_check().unwrap();
+        assert!(bridge.validate_authority_atomically(&key("master"), &key("ignition")).unwrap());
+        let vetoed = bridge.check_authority(&key("master"), &key("distro")).unwrap();
+        assert_eq!(vetoed.token(), "AUTHORITY_INVALID");
+        assert!(vetoed.failure.unwrap().contains("authority_manager.sh"));
+
+        let strict = LucasAuthorityBridge::new(None).unwrap().with_trust_anchors(anchors)
+            .with_validator(Box::new(ScriptValidator::new(script).unwrap().with_emergency_approval(true)));
+        strict.emergency_recovery("break-glass", &key("skull")).unwrap();
+        assert!(strict.emergency_recovery("rekey", &key("skull")).is_err());
+    }
+
+    #[test]
+    fn test_emergency_requires_known_skull_key() {
+        let dir = TempDir::new().unwrap();
+        write_chain(dir.path());
+        let skull_path = dir.path().join("auth-skull.key");
+
+        // A key file labelled skull that no root or chain vouches for is refused
+        let untrusted = LucasAuthorityBridge::new(None).unwrap().with_trust_anchors(TrustAnchors::new());
+        let refused = untrusted.emergency_recovery("break-glass", &skull_path).unwrap_err();
+        assert!(refused.to_string().contains("neither"));
+
+        // The chain's own skull key is accepted without a configured root
+        let mut chain = AuthorityChain::new();
+        chain.add_key(AuthorityKey::open(&skull_path).unwrap()).unwrap();
+        let chained = LucasAuthorityBridge::new(None).unwrap().with_trust_anchors(TrustAnchors::new()).with_authority_chain(chain);
+        chained.emergency_recovery("break-glass", &skull_path).unwrap();
+    }
+}
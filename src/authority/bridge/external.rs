@@ -0,0 +1,167 @@
+//! External Authority Validators
+//!
+//! Native validation decides every authority question; external validators can only
+//! add conditions on top of it (an HSM lookup, an HR roster, a change-freeze calendar).
+//! `ScriptValidator` speaks the protocol of the original authority manager script, so
+//! an organization's existing script keeps working as one such extra check:
+//!
+//! - `bash <script> validate_authority --parent P --child C --atomic` prints
+//!   `AUTHORITY_VALID`, `AUTHORITY_INVALID` or `ERROR`
+//! - `bash <script> approve_emergency <type> --key K` exits 0 to approve (opt-in)
+//! - `bash <script> --version` exits 0 when the script is usable
+//!
+//! Security Guardian: Edgar - Organizations may add checks, never remove them
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::encryption::age_automation::error::{AgeError, AgeResult};
+
+/// Extra authority check run after native validation has passed
+pub trait ExternalAuthorityValidator: Send + Sync {
+    /// Name used in audit entries and explanations
+    fn name(&self) -> &str;
+
+    /// Whether `parent` may hold authority over `child`; errors block the operation
+    fn validate_authority(&self, parent: &Path, child: &Path) -> AgeResult<bool>;
+
+    /// Whether an emergency recovery with `key` may proceed
+    fn approve_emergency(&self, _recovery_type: &str, _key: &Path) -> AgeResult<bool> {
+        Ok(true)
+    }
+
+    /// Fail when the validator cannot currently answer
+    fn health_check(&self) -> AgeResult<()> {
+        Ok(())
+    }
+}
+
+/// Validator backed by a bash script using the authority manager protocol
+#[derive(Debug, Clone)]
+pub struct ScriptValidator {
+    script: PathBuf,
+    name: String,
+    approves_emergencies: bool,
+}
+
+impl ScriptValidator {
+    pub fn new(script: PathBuf) -> AgeResult<Self> {
+        if !script.is_file() {
+            return Err(validator_error("create_script_validator", format!("Validator script not found: {}", script.display())));
+        }
+        let name = script.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        Ok(Self { script, name, approves_emergencies: false })
+    }
+
+    /// Also ask the script to approve emergency recoveries
+    pub fn with_emergency_approval(mut self, enabled: bool) -> Self {
+        self.approves_emergencies = enabled;
+        self
+    }
+
+    fn run(&self, operation: &str, args: &[&std::ffi::OsStr]) -> AgeResult<std::process::Output> {
+        Command::new("bash")
+            .arg(&self.script)
+            .args(args)
+            .output()
+            .map_err(|e| validator_error(operation, format!("Failed to execute {}: {}", self.script.display(), e)))
+    }
+}
+
+impl ExternalAuthorityValidator for ScriptValidator {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn validate_authority(&self, parent: &Path, child: &Path) -> AgeResult<bool> {
+        let output = self.run("script_validate_authority", &[
+            "validate_authority".as_ref(), "--parent".as_ref(), parent.as_os_str(),
+            "--child".as_ref(), child.as_os_str(), "--atomic".as_ref(),
+        ])?;
+        match String::from_utf8_lossy(&output.stdout).trim() {
+            "AUTHORITY_VALID" if output.status.success() => Ok(true),
+            "AUTHORITY_INVALID" => Ok(false),
+            answer => Err(validator_error("script_validate_authority", format!(
+                "{} answered '{}' (exit {:?}): {}", self.name, answer, output.status.code(),
+                String::from_utf8_lossy(&output.stderr).trim()))),
+        }
+    }
+
+    fn approve_emergency(&self, recovery_type: &str, key: &Path) -> AgeResult<bool> {
+        if !self.approves_emergencies {
+            return Ok(true);
+        }
+        let output = self.run("script_approve_emergency", &[
+            "approve_emergency".as_ref(), recovery_type.as_ref(), "--key".as_ref(), key.as_os_str(),
+        ])?;
+        Ok(output.status.success())
+    }
+
+    fn health_check(&self) -> AgeResult<()> {
+        let output = self.run("script_health_check", &["--version".as_ref()])?;
+        if !output.status.success() {
+            return Err(validator_error("script_health_check", format!("{} --version failed", self.name)));
+        }
+        Ok(())
+    }
+}
+
+fn validator_error(operation: &str, reason: String) -> AgeError {
+    AgeError::InvalidOperation {
+        operation: operation.to_string(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const STUB: &str = r#"#!/bin/bash
+case "$1" in
+    validate_authority)
+        # Refuse any child whose file name mentions "contractor"
+        if [[ "$5" == *contractor* ]]; then echo AUTHORITY_INVALID; exit 1; fi
+        if [[ "$5" == *broken* ]]; then echo ERROR; exit 2; fi
+        echo AUTHORITY_VALID ;;
+    approve_emergency)
+        [[ "$2" == "break-glass" ]] ;;
+    --version)
+        echo "stub-validator 1.0" ;;
+    *)
+        echo ERROR; exit 2 ;;
+esac
+"#;
+
+    fn stub(dir: &Path) -> ScriptValidator {
+        let script = dir.join("validator.sh");
+        std::fs::write(&script, STUB).unwrap();
+        ScriptValidator::new(script).unwrap()
+    }
+
+    #[test]
+    fn test_script_validator_protocol() {
+        let dir = TempDir::new().unwrap();
+        let validator = stub(dir.path());
+        assert_eq!(validator.name(), "validator.sh");
+        validator.health_check().unwrap();
+
+        assert!(validator.validate_authority(Path::new("keys/auth-ignition.key"), Path::new("keys/auth-distro.key")).unwrap());
+        assert!(!validator.validate_authority(Path::new("keys/auth-ignition.key"), Path::new("keys/contractor.key")).unwrap());
+        assert!(validator.validate_authority(Path::new("keys/auth-ignition.key"), Path::new("keys/broken.key")).is_err());
+    }
+
+    #[test]
+    fn test_emergency_approval_is_opt_in() {
+        let dir = TempDir::new().unwrap();
+        let key = Path::new("keys/auth-skull.key");
+        assert!(stub(dir.path()).approve_emergency("anything", key).unwrap());
+
+        let approving = stub(dir.path()).with_emergency_approval(true);
+        assert!(approving.approve_emergency("break-glass", key).unwrap());
+        assert!(!approving.approve_emergency("anything", key).unwrap());
+
+        assert!(ScriptValidator::new(dir.path().join("missing.sh")).is_err());
+    }
+}
@@ -2,17 +2,22 @@
 //!
 //! Integration bridges connecting authority chain with external systems:
 //! - Age automation integration (Edgar's TTY patterns)
-//! - Lucas authority pattern integration (atomic operations)
+//! - Lucas authority protocol, native with pluggable external validators
+//! - Repository access matrix reporting
 //!
 //! Security Guardian: Edgar - Authority integration framework
 
 pub mod age_integration;
+pub mod access_matrix;
+pub mod external;
 
 pub use age_integration::{
     AuthorityAgeInterface,
     LucasAuthorityBridge,
     AuthorityAgeFactory,
 };
+pub use external::{ExternalAuthorityValidator, ScriptValidator};
+pub use access_matrix::{AccessMatrix, AccessKind, AccessFinding, FileAccess, MatrixKey};
 
 use crate::encryption::age_automation::error::AgeResult;
 
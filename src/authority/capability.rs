@@ -0,0 +1,438 @@
+//! Key Capabilities - Scope and limits carried by distro keys
+//!
+//! Distro keys go to third parties and automated agents, so they can be issued with
+//! constraints every operation they run is checked against:
+//!
+//! - `paths`: globs of repository paths the key may decrypt or lock (see
+//!   `rules::glob_to_regex`); none means the whole repository
+//! - `access`: `read-only` keys may decrypt but never lock or change anything
+//! - `max_uses`: operations the key may run, counted per repository in a `UsageLedger`
+//!   under the machine-local state directory, out of reach of anyone who can write to
+//!   the repository
+//! - `operations`: operations the key may run; none means any the policy allows
+//!
+//! The constraints live in the key's envelope as a `# capabilities:` header, covered by
+//! the parent signature, and in the first link of its certificate:
+//!
+//! ```text
+//! # capabilities: paths=config/*.env,docs/** access=read-only max_uses=50 operations=unlock,status
+//! ```
+//!
+//! Lock adds a scoped key's recipient only to files inside its paths.
+//!
+//! Security Guardian: Edgar - Third parties get exactly what they were issued
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::encryption::age_automation::error::{AgeError, AgeResult};
+use crate::encryption::age_automation::lifecycle::clock::write_atomic;
+use crate::encryption::age_automation::lifecycle::rules::glob_to_regex;
+use super::authorization::plain_operation;
+use super::chain::KeyType;
+use super::envelope::KeyEnvelope;
+use super::trust::TrustAnchors;
+
+/// Envelope header line prefix holding a key's capabilities
+pub const KEY_CAPABILITIES_PREFIX: &str = "# capabilities: ";
+
+/// Operations that change files, keys or access; refused to read-only keys
+const WRITE_OPERATIONS: &[&str] = &[
+    "lock", "edit", "rearmor", "rotate", "reset", "emergency", "encrypt", "allow", "revoke",
+    "vault-set", "vault-rm", "backup-restore",
+];
+
+/// Operations that only inspect and do not count as a use
+const UNCOUNTED_OPERATIONS: &[&str] = &["status", "verify", "backup-list"];
+
+/// What a scoped key may do to the files it covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Access {
+    ReadOnly,
+    ReadWrite,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Access::ReadOnly => write!(f, "read-only"),
+            Access::ReadWrite => write!(f, "read-write"),
+        }
+    }
+}
+
+/// Constraints a distro key was issued with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyCapabilities {
+    /// Globs of repository paths the key covers; empty covers everything
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    pub access: Access,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_uses: Option<u64>,
+    /// Operations the key may run; empty allows any
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub operations: Vec<String>,
+}
+
+impl Default for KeyCapabilities {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            access: Access::ReadWrite,
+            max_uses: None,
+            operations: Vec::new(),
+        }
+    }
+}
+
+impl KeyCapabilities {
+    /// Parse a `key=value` list such as `paths=a/**,b/*.env access=read-only max_uses=10`
+    ///
+    /// Items are separated by whitespace; list values by commas.
+    pub fn parse_spec(spec: &str) -> AgeResult<Self> {
+        let mut result = Self::default();
+        for item in spec.split_whitespace() {
+            let (name, value) = item.split_once('=')
+                .ok_or_else(|| capability_error(format!("Expected key=value, got '{}'", item)))?;
+            let list = || value.split(',').filter(|entry| !entry.is_empty()).map(str::to_string).collect();
+            match name {
+                "paths" => result.paths = list(),
+                "operations" => result.operations = list().into_iter().map(|op: String| op.to_lowercase()).collect(),
+                "access" => result.access = match value {
+                    "read-only" | "ro" => Access::ReadOnly,
+                    "read-write" | "rw" => Access::ReadWrite,
+                    _ => return Err(capability_error(format!("access must be read-only or read-write, got '{}'", value))),
+                },
+                "max_uses" => result.max_uses = Some(value.parse()
+                    .map_err(|_| capability_error(format!("max_uses must be a number, got '{}'", value)))?),
+                _ => return Err(capability_error(format!("Unknown capability '{}'", name))),
+            }
+        }
+        result.validate()?;
+        Ok(result)
+    }
+
+    /// The `key=value` list written after `# capabilities: `
+    pub fn to_spec(&self) -> String {
+        let mut items = Vec::new();
+        if !self.paths.is_empty() {
+            items.push(format!("paths={}", self.paths.join(",")));
+        }
+        items.push(format!("access={}", self.access));
+        if let Some(max_uses) = self.max_uses {
+            items.push(format!("max_uses={}", max_uses));
+        }
+        if !self.operations.is_empty() {
+            items.push(format!("operations={}", self.operations.join(",")));
+        }
+        items.join(" ")
+    }
+
+    /// Check every path glob compiles and stays inside the repository
+    pub fn validate(&self) -> AgeResult<()> {
+        for pattern in &self.paths {
+            if pattern.starts_with('/') || pattern.split('/').any(|part| part == "..") {
+                return Err(capability_error(format!("Path '{}' must stay inside the repository", pattern)));
+            }
+            glob_to_regex(pattern)?;
+        }
+        if self.max_uses == Some(0) {
+            return Err(capability_error("max_uses must be at least 1".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Whether the key covers repository path `relative`, encrypted (`.age`) or not
+    pub fn allows_path(&self, relative: &Path) -> bool {
+        if self.paths.is_empty() {
+            return true;
+        }
+        let path = relative.to_string_lossy().replace('\\', "/");
+        let plain = path.strip_suffix(".age").unwrap_or(&path);
+        self.paths.iter()
+            .filter_map(|pattern| glob_to_regex(pattern).ok())
+            .any(|regex| regex.is_match(&path) || regex.is_match(plain))
+    }
+
+    /// Why the key may not run `operation` on `relative` (None for the whole repository);
+    /// None when it may
+    pub fn refusal(&self, operation: &str, relative: Option<&Path>) -> Option<String> {
+        let operation = operation.to_lowercase();
+        let plain = plain_operation(&operation);
+        if !self.operations.is_empty() && !self.operations.iter().any(|allowed| *allowed == operation || allowed == plain) {
+            return Some(format!("operations are limited to {}", self.operations.join(", ")));
+        }
+        if self.access == Access::ReadOnly && WRITE_OPERATIONS.contains(&plain) {
+            return Some("key is read-only".to_string());
+        }
+        match relative {
+            Some(relative) if !self.allows_path(relative) => {
+                Some(format!("{} is outside paths {}", relative.display(), self.paths.join(",")))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether running `operation` counts toward `max_uses`
+    pub fn counts_use(operation: &str) -> bool {
+        !UNCOUNTED_OPERATIONS.contains(&plain_operation(&operation.to_lowercase()))
+    }
+}
+
+impl fmt::Display for KeyCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_spec())
+    }
+}
+
+/// Capabilities may only be issued to distro keys
+pub fn check_key_type(key_type: KeyType) -> AgeResult<()> {
+    if key_type != KeyType::Distro {
+        return Err(capability_error(format!("Capabilities are issued to distro keys, not {} keys", key_type)));
+    }
+    Ok(())
+}
+
+/// A distro key's recipient and the capabilities it was issued with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopedKey {
+    pub fingerprint: String,
+    pub recipient: String,
+    /// None for an unrestricted distro key
+    pub capabilities: Option<KeyCapabilities>,
+    /// Verified envelopes from the key up to its trusted root; empty when not verified
+    pub lineage: Vec<KeyEnvelope>,
+}
+
+impl ScopedKey {
+    /// Read an issued distro key file; the secret is not needed
+    pub fn load(path: &Path, anchors: &TrustAnchors) -> AgeResult<Self> {
+        let envelope = KeyEnvelope::read(path)?
+            .ok_or_else(|| capability_error(format!("{} is a plain age key; scoped keys need a padlock key envelope", path.display())))?;
+        let lineage = verify_issued(&envelope, path, anchors)?;
+        Ok(Self { lineage, ..Self::from_envelope(&envelope) })
+    }
+
+    pub fn from_envelope(envelope: &KeyEnvelope) -> Self {
+        Self {
+            fingerprint: envelope.fingerprint.clone(),
+            recipient: envelope.recipient.clone(),
+            capabilities: envelope.capabilities.clone(),
+            lineage: Vec::new(),
+        }
+    }
+
+    /// Whether lock should encrypt repository path `relative` to this key
+    pub fn covers(&self, relative: &Path) -> bool {
+        self.capabilities.as_ref().is_none_or(|capabilities| capabilities.allows_path(relative))
+    }
+}
+
+/// Recorded use of one key
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KeyUsage {
+    pub usage_count: u64,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+/// Per-repository use counts of capability-limited keys, by fingerprint
+///
+/// Ledgers live under `<state dir>/usage/`, one file per repository named after a digest
+/// of its canonical path, the same machine-local directory that holds the clock signing
+/// key. Key holders must not be able to write it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageLedger {
+    pub keys: BTreeMap<String, KeyUsage>,
+}
+
+impl UsageLedger {
+    /// Ledger file of `repository` under the padlock state directory `state_dir`
+    pub fn path(state_dir: &Path, repository: &Path) -> PathBuf {
+        let canonical = repository.canonicalize().unwrap_or_else(|_| repository.to_path_buf());
+        let digest = Sha256::digest(canonical.to_string_lossy().as_bytes());
+        state_dir.join("usage").join(format!("{}.json", &hex::encode(digest)[..16]))
+    }
+
+    /// Load a repository's ledger; empty when it has none
+    pub fn load(state_dir: &Path, repository: &Path) -> AgeResult<Self> {
+        let path = Self::path(state_dir, repository);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let data = fs::read(&path).map_err(|e| AgeError::file_error("read", path.clone(), e))?;
+        serde_json::from_slice(&data)
+            .map_err(|e| capability_error(format!("Invalid usage ledger {}: {}", path.display(), e)))
+    }
+
+    /// Write the ledger atomically, owner read/write only
+    pub fn save(&self, state_dir: &Path, repository: &Path) -> AgeResult<()> {
+        let path = Self::path(state_dir, repository);
+        let data = serde_json::to_vec_pretty(self).map_err(|e| capability_error(e.to_string()))?;
+        write_atomic(&path, &data)
+    }
+
+    pub fn usage_count(&self, fingerprint: &str) -> u64 {
+        self.keys.get(fingerprint).map_or(0, |usage| usage.usage_count)
+    }
+
+    /// Count one use of the key `fingerprint`, refusing once the recorded count has
+    /// reached its capabilities' `max_uses`; returns the new count
+    pub fn record_use(&mut self, fingerprint: &str, capabilities: &KeyCapabilities) -> AgeResult<u64> {
+        let usage = self.keys.entry(fingerprint.to_string()).or_default();
+        if let Some(max_uses) = capabilities.max_uses {
+            if usage.usage_count >= max_uses {
+                return Err(capability_error(format!("Key has used all {} of its permitted uses", max_uses)));
+            }
+        }
+        usage.usage_count += 1;
+        usage.last_used = Some(Utc::now());
+        Ok(usage.usage_count)
+    }
+}
+
+/// Refuse distro keys that were not issued: the envelope must carry a parent signature
+/// that verifies against a known parent, up to a root in `anchors`
+///
+/// The signature covers the capabilities header, so it cannot be edited or dropped
+/// without invalidating the key. A parent that cannot be found is a refusal, not a pass.
+/// Returns the verified lineage, the key first.
+pub fn verify_issued(envelope: &KeyEnvelope, path: &Path, anchors: &TrustAnchors) -> AgeResult<Vec<KeyEnvelope>> {
+    check_key_type(envelope.key_type)?;
+    if envelope.parent.is_none() || envelope.parent_signature.is_none() {
+        return Err(capability_error(format!("{} is not signed by a parent key; distro keys must be issued", path.display())));
+    }
+    anchors.verify(envelope, path)
+        .map_err(|e| capability_error(format!("Cannot verify the issuer of {}: {}", path.display(), e)))
+}
+
+fn capability_error(details: String) -> AgeError {
+    AgeError::SecurityValidationFailed {
+        validation_type: "key_capabilities".to_string(),
+        details,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::authorization::{Actor, OperationAuthorizer};
+    use crate::authority::chain::AuthorityKey;
+    use crate::authority::signing;
+    use crate::encryption::age_automation::security::AuditLogger;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_capability_spec_and_checks() {
+        let capabilities = KeyCapabilities::parse_spec("paths=config/*.env,docs/** access=read-only max_uses=3 operations=unlock,status").unwrap();
+        assert_eq!(KeyCapabilities::parse_spec(&capabilities.to_spec()).unwrap(), capabilities);
+        assert_eq!(capabilities.access, Access::ReadOnly);
+
+        assert!(capabilities.allows_path(Path::new("config/db.env.age")));
+        assert!(capabilities.allows_path(Path::new("docs/guide/intro.md")));
+        assert!(!capabilities.allows_path(Path::new("secrets/prod.env")));
+
+        assert!(capabilities.refusal("unlock", Some(Path::new("config/db.env.age"))).is_none());
+        assert!(capabilities.refusal("unlock", None).is_none());
+        assert!(capabilities.refusal("unlock", Some(Path::new("secrets/prod.env.age"))).is_some());
+        assert!(capabilities.refusal("exec", None).unwrap().contains("limited to"));
+        let writable = KeyCapabilities { operations: Vec::new(), ..capabilities.clone() };
+        assert_eq!(writable.refusal("lock", None).unwrap(), "key is read-only");
+        assert!(KeyCapabilities::counts_use("unlock") && !KeyCapabilities::counts_use("status"));
+
+        assert!(KeyCapabilities::parse_spec("paths=../outside").is_err());
+        assert!(KeyCapabilities::parse_spec("access=admin").is_err());
+        assert!(KeyCapabilities::parse_spec("max_uses=0").is_err());
+        assert!(check_key_type(KeyType::Ignition).is_err());
+    }
+
+    #[test]
+    fn test_usage_ledger_enforces_max_uses() {
+        let state = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        std::fs::create_dir_all(repo.path().join(".padlock")).unwrap();
+        let capabilities = KeyCapabilities { max_uses: Some(2), ..KeyCapabilities::default() };
+        let use_once = || {
+            let mut ledger = UsageLedger::load(state.path(), repo.path()).unwrap();
+            let used = ledger.record_use("3f9a", &capabilities);
+            ledger.save(state.path(), repo.path()).unwrap();
+            used
+        };
+
+        assert_eq!(use_once().unwrap(), 1);
+        assert_eq!(use_once().unwrap(), 2);
+        assert!(use_once().is_err());
+        let ledger = UsageLedger::load(state.path(), repo.path()).unwrap();
+        assert_eq!(ledger.usage_count("3f9a"), 2);
+        assert!(ledger.keys["3f9a"].last_used.is_some());
+        // Nothing inside the repository holds the count
+        assert!(UsageLedger::path(state.path(), repo.path()).starts_with(state.path()));
+        assert_eq!(std::fs::read_dir(repo.path().join(".padlock")).unwrap().count(), 0);
+
+        // The authorizer counts uses per repository; inspecting is free
+        let actor = Actor::Key { key_type: KeyType::Distro, fingerprint: "77aa".to_string(), capabilities: Some(capabilities), lineage: Vec::new() };
+        let audit_logger = AuditLogger::new(None).unwrap();
+        let authorizer = OperationAuthorizer::new().with_state_dir(state.path().to_path_buf());
+        for operation in ["unlock", "status", "unlock"] {
+            authorizer.authorize(operation, Some(repo.path()), &actor, &audit_logger).unwrap();
+        }
+        assert!(authorizer.authorize("unlock", Some(repo.path()), &actor, &audit_logger).is_err());
+        assert!(authorizer.authorize("status", Some(repo.path()), &actor, &audit_logger).is_ok());
+    }
+
+    #[test]
+    fn test_issued_capabilities_are_signed() {
+        let dir = TempDir::new().unwrap();
+        let identity = |seed: u8| {
+            let hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").unwrap();
+            bech32::encode_upper::<bech32::Bech32>(hrp, &[seed; 32]).unwrap()
+        };
+        let envelope = |seed: u8, key_type: KeyType| {
+            let secret = signing::identity_secret(&identity(seed)).unwrap();
+            KeyEnvelope::new(key_type, &signing::recipient_for_secret(&secret).unwrap()).unwrap()
+        };
+        let parent_path = dir.path().join("auth-ignition.key");
+        fs::write(&parent_path, envelope(1, KeyType::Ignition).render(&identity(1)).as_bytes()).unwrap();
+        let parent = AuthorityKey::open(&parent_path).unwrap();
+        let anchors = TrustAnchors::new().with_root(KeyType::Ignition, &signing::key_recipient(&parent).unwrap()).unwrap();
+
+        let capabilities = KeyCapabilities::parse_spec("paths=docs/** access=read-only").unwrap();
+        assert!(envelope(3, KeyType::Repo).with_capabilities(Some(capabilities.clone())).is_err());
+        let issued = envelope(2, KeyType::Distro)
+            .with_capabilities(Some(capabilities.clone()))
+            .unwrap()
+            .with_parent(&parent)
+            .unwrap();
+        let key_path = dir.path().join("agent.key");
+        let content = issued.render(&identity(2));
+        fs::write(&key_path, content.as_bytes()).unwrap();
+
+        let actor = Actor::holding(&key_path, &anchors).unwrap();
+        assert_eq!(actor.capabilities(), Some(&capabilities));
+        assert_eq!(ScopedKey::load(&key_path, &anchors).unwrap().capabilities, Some(capabilities));
+        verify_issued(&issued, &key_path, &anchors).unwrap();
+
+        // Without its parent on hand (or a certificate) the issue cannot be checked
+        let elsewhere = TempDir::new().unwrap();
+        let moved = elsewhere.path().join("agent.key");
+        fs::write(&moved, content.as_bytes()).unwrap();
+        assert!(verify_issued(&issued, &moved, &anchors).is_err());
+        assert!(ScopedKey::load(&moved, &anchors).is_err());
+        assert!(Actor::holding(&moved, &anchors).is_err());
+        let found = anchors.clone().with_search_dir(dir.path().to_path_buf());
+        assert!(ScopedKey::load(&moved, &found).is_ok());
+
+        // Widening or dropping the capabilities breaks the parent signature
+        fs::write(&key_path, content.replace("access=read-only", "access=read-write").as_bytes()).unwrap();
+        assert!(Actor::holding(&key_path, &anchors).is_err());
+        let unsigned = envelope(2, KeyType::Distro).render(&identity(2));
+        fs::write(&key_path, unsigned.as_bytes()).unwrap();
+        assert!(Actor::holding(&key_path, &anchors).is_err());
+    }
+}
@@ -0,0 +1,400 @@
+//! Authority Certificates - Portable, offline-verifiable proof of a key's lineage
+//!
+//! `AuthorityProof` only lives inside one process. A certificate carries the same
+//! statement out of it: each link binds a child's `age1...` recipient to its parent
+//! with key type, validity window and scope, signed by the parent (XEdDSA, see
+//! `signing`). Links run from the certified key up to the top of the chain, so whoever
+//! trusts any key on the way - usually the skull key - can check the certificate with
+//! that key's public recipient alone, without the chain or any secret.
+//!
+//! A link never widens its parent: its window must sit inside the parent's, and its
+//! scope inside the parent's scope (an empty scope inherits the parent's). A distro
+//! key's capabilities (see `capability`) are signed into its own link, the only link
+//! that may carry them.
+//!
+//! Security Guardian: Edgar - Lineage a CI runner can prove without the chain
+
+use std::fs;
+use std::path::Path;
+use chrono::{DateTime, Duration, SubsecRound, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::encryption::age_automation::error::{AgeError, AgeResult};
+use crate::encryption::age_automation::lifecycle::trusted_now;
+use crate::encryption::age_automation::secret::Zeroizing;
+use super::capability::{self, KeyCapabilities};
+use super::chain::{AuthorityChain, AuthorityKey, KeyType};
+use super::signing;
+
+/// Format tag of certificate files
+pub const CERTIFICATE_FORMAT: &str = "padlock-authority-cert-v1";
+
+/// A key as named inside a certificate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertifiedKey {
+    /// Chain fingerprint (hex)
+    pub fingerprint: String,
+    pub key_type: KeyType,
+    pub recipient: String,
+}
+
+impl CertifiedKey {
+    fn of(key: &AuthorityKey) -> AgeResult<Self> {
+        Ok(Self {
+            fingerprint: key.fingerprint().hex().to_string(),
+            key_type: key.key_type(),
+            recipient: signing::key_recipient(key)?,
+        })
+    }
+}
+
+/// One parent-to-child binding, signed by the parent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificateLink {
+    pub subject: CertifiedKey,
+    pub issuer: CertifiedKey,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    /// Repository paths the subject is certified for; empty inherits the issuer's
+    #[serde(default)]
+    pub scope: Vec<String>,
+    /// Constraints of a scoped distro subject
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<KeyCapabilities>,
+    /// Hex XEdDSA signature by the issuer over every field above
+    pub signature: String,
+}
+
+impl CertificateLink {
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut bytes = format!("{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
+            CERTIFICATE_FORMAT,
+            self.subject.fingerprint, self.subject.key_type, self.subject.recipient,
+            self.issuer.fingerprint, self.issuer.key_type, self.issuer.recipient,
+            self.not_before.to_rfc3339(), self.not_after.to_rfc3339(),
+            serde_json::to_string(&self.scope).unwrap_or_default());
+        // Appended only when present so links without capabilities keep their signatures
+        if let Some(capabilities) = &self.capabilities {
+            bytes.push_str(&format!("{}\n", capabilities.to_spec()));
+        }
+        bytes.into_bytes()
+    }
+
+    fn sign(subject: &AuthorityKey, issuer: &AuthorityKey, not_before: DateTime<Utc>, not_after: DateTime<Utc>, scope: Vec<String>, capabilities: Option<KeyCapabilities>) -> AgeResult<Self> {
+        let mut link = Self {
+            subject: CertifiedKey::of(subject)?,
+            issuer: CertifiedKey::of(issuer)?,
+            not_before,
+            not_after,
+            scope,
+            capabilities,
+            signature: String::new(),
+        };
+        link.signature = hex::encode(signing::sign_with_key(issuer, &link.signed_bytes())?);
+        Ok(link)
+    }
+
+    fn verify_signature(&self) -> AgeResult<()> {
+        let signature = hex::decode(&self.signature)
+            .map_err(|_| certificate_error(format!("Malformed signature on {} link", self.subject.key_type)))?;
+        let public = signing::recipient_public(&self.issuer.recipient)?;
+        if !signing::verify(&public, &self.signed_bytes(), &signature) {
+            return Err(certificate_error(format!("Signature of {} key {} by {} key {} does not verify",
+                self.subject.key_type, short(&self.subject.fingerprint), self.issuer.key_type, short(&self.issuer.fingerprint))));
+        }
+        Ok(())
+    }
+}
+
+/// Exportable certificate for an ignition or distro key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthorityCertificate {
+    pub format: String,
+    pub issued_at: DateTime<Utc>,
+    /// Certified key first; each link's issuer is the next link's subject
+    pub chain: Vec<CertificateLink>,
+}
+
+/// What a successful verification established
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifiedCertificate {
+    pub subject: CertifiedKey,
+    /// The trusted key the chain was checked up to
+    pub root: CertifiedKey,
+    /// Effective scope; empty means unrestricted
+    pub scope: Vec<String>,
+    /// Capabilities the certified distro key was issued with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<KeyCapabilities>,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    /// Links checked, certified key to root
+    pub depth: usize,
+}
+
+impl AuthorityCertificate {
+    /// Certify `subject` with every link up to the top of `chain`
+    ///
+    /// Needs the secret of each key above `subject`. A distro subject's capabilities are
+    /// signed into its link.
+    pub fn issue(chain: &AuthorityChain, subject: &AuthorityKey, validity: Duration, scope: Vec<String>) -> AgeResult<Self> {
+        if !matches!(subject.key_type(), KeyType::Ignition | KeyType::Distro) {
+            return Err(certificate_error(format!("Certificates are issued for ignition and distro keys, not {} keys", subject.key_type())));
+        }
+        if validity <= Duration::zero() {
+            return Err(certificate_error("Certificate validity must be positive".to_string()));
+        }
+
+        let issued_at = Utc::now().trunc_subsecs(0);
+        let not_after = issued_at + validity;
+        let mut links = Vec::new();
+        let mut current = subject;
+        let mut scope = Some(scope);
+        let mut capabilities = subject.metadata().capabilities.clone();
+        while let Some(issuer) = chain.get_parent(current.fingerprint()) {
+            if current.is_expired() {
+                return Err(certificate_error(format!("{} key {} has expired", current.key_type(), current.fingerprint())));
+            }
+            links.push(CertificateLink::sign(current, issuer, issued_at, not_after, scope.take().unwrap_or_default(), capabilities.take())?);
+            current = issuer;
+        }
+        if links.is_empty() {
+            return Err(certificate_error(format!("{} key {} has no parent in the chain", subject.key_type(), subject.fingerprint())));
+        }
+
+        Ok(Self { format: CERTIFICATE_FORMAT.to_string(), issued_at, chain: links })
+    }
+
+    /// The certified key
+    pub fn subject(&self) -> Option<&CertifiedKey> {
+        self.chain.first().map(|link| &link.subject)
+    }
+
+    /// Check the certificate against one trusted recipient at time `at`
+    ///
+    /// The chain is followed from the certified key until a link issued by `root`;
+    /// links above it are not needed and not checked.
+    pub fn verify(&self, root: &str, at: DateTime<Utc>) -> AgeResult<VerifiedCertificate> {
+        if self.format != CERTIFICATE_FORMAT {
+            return Err(certificate_error(format!("Unsupported certificate format '{}'", self.format)));
+        }
+        let subject = self.subject().ok_or_else(|| certificate_error("Certificate has no links".to_string()))?;
+        if !matches!(subject.key_type, KeyType::Ignition | KeyType::Distro) {
+            return Err(certificate_error(format!("Certificate is for a {} key", subject.key_type)));
+        }
+
+        let depth = self.chain.iter().position(|link| link.issuer.recipient == root)
+            .map(|index| index + 1)
+            .ok_or_else(|| certificate_error("Certificate does not chain to the given root".to_string()))?;
+        let trusted = &self.chain[..depth];
+
+        for (index, link) in trusted.iter().enumerate() {
+            if link.subject.key_type.parent_type() != Some(link.issuer.key_type) {
+                return Err(certificate_error(format!("A {} key cannot certify a {} key", link.issuer.key_type, link.subject.key_type)));
+            }
+            if let Some(parent) = trusted.get(index + 1) {
+                if parent.subject != link.issuer {
+                    return Err(certificate_error(format!("Link for {} key is not issued by the next key in the chain", link.subject.key_type)));
+                }
+                if link.not_before < parent.not_before || link.not_after > parent.not_after {
+                    return Err(certificate_error(format!("Validity of {} link exceeds its issuer's", link.subject.key_type)));
+                }
+            }
+            if let Some(capabilities) = &link.capabilities {
+                if index > 0 {
+                    return Err(certificate_error(format!("Capabilities on the {} link above the certified key", link.subject.key_type)));
+                }
+                capability::check_key_type(link.subject.key_type)?;
+                capabilities.validate()?;
+            }
+            if at < link.not_before || at > link.not_after {
+                return Err(certificate_error(format!("{} link is valid {} to {}, not at {}",
+                    link.subject.key_type, link.not_before.to_rfc3339(), link.not_after.to_rfc3339(), at.to_rfc3339())));
+            }
+            link.verify_signature()?;
+        }
+
+        // Narrow the scope from the root down
+        let mut scope: Vec<String> = Vec::new();
+        for link in trusted.iter().rev() {
+            if link.scope.is_empty() {
+                continue;
+            }
+            if let Some(outside) = link.scope.iter().find(|path| !scope.is_empty() && !scope.iter().any(|parent| scope_within(path, parent))) {
+                return Err(certificate_error(format!("Scope '{}' of {} link is wider than its issuer's", outside, link.subject.key_type)));
+            }
+            scope = link.scope.clone();
+        }
+
+        Ok(VerifiedCertificate {
+            subject: subject.clone(),
+            root: trusted[depth - 1].issuer.clone(),
+            scope,
+            capabilities: trusted[0].capabilities.clone(),
+            not_before: trusted.iter().map(|link| link.not_before).max().expect("at least one link"),
+            not_after: trusted.iter().map(|link| link.not_after).min().expect("at least one link"),
+            depth,
+        })
+    }
+
+    /// `verify` at the rollback-resistant current time
+    pub fn verify_now(&self, root: &str) -> AgeResult<VerifiedCertificate> {
+        self.verify(root, trusted_now())
+    }
+
+    /// True when the identity file holds the certified key's secret
+    pub fn is_held_by(&self, identity_file: &Path) -> AgeResult<bool> {
+        let text = Zeroizing::new(fs::read_to_string(identity_file)
+            .map_err(|e| AgeError::file_error("read", identity_file.to_path_buf(), e))?);
+        let identity = text.lines().map(str::trim).find(|line| line.starts_with("AGE-SECRET-KEY-"))
+            .ok_or_else(|| certificate_error(format!("{} holds no age identity", identity_file.display())))?;
+        let recipient = signing::recipient_for_secret(&*signing::identity_secret(identity)?)?;
+        Ok(self.subject().is_some_and(|subject| subject.recipient == recipient))
+    }
+
+    pub fn load(path: &Path) -> AgeResult<Self> {
+        let data = fs::read(path).map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?;
+        serde_json::from_slice(&data)
+            .map_err(|e| certificate_error(format!("Invalid certificate {}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, path: &Path) -> AgeResult<()> {
+        let data = serde_json::to_vec_pretty(self).map_err(|e| certificate_error(e.to_string()))?;
+        fs::write(path, data).map_err(|e| AgeError::file_error("write", path.to_path_buf(), e))
+    }
+}
+
+/// Trusted root recipient from an `age1...` string or a key file
+///
+/// Key files may hold the recipient (`age1...` or `# public key: age1...`) or the
+/// identity itself, from which the recipient is derived.
+pub fn root_recipient(spec: &str) -> AgeResult<String> {
+    let spec = spec.trim();
+    if spec.starts_with("age1") {
+        signing::recipient_public(spec)?;
+        return Ok(spec.to_string());
+    }
+
+    let path = Path::new(spec);
+    let text = Zeroizing::new(fs::read_to_string(path).map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?);
+    for line in text.lines().map(str::trim) {
+        if let Some(recipient) = line.strip_prefix("# public key: ").or(Some(line)).filter(|line| line.starts_with("age1")) {
+            return Ok(recipient.to_string());
+        }
+    }
+    match text.lines().map(str::trim).find(|line| line.starts_with("AGE-SECRET-KEY-")) {
+        Some(identity) => signing::recipient_for_secret(&*signing::identity_secret(identity)?),
+        None => Err(certificate_error(format!("{} holds no age recipient", path.display()))),
+    }
+}
+
+/// `child` lies at or below repository path `parent`
+fn scope_within(child: &str, parent: &str) -> bool {
+    let parent = parent.trim_end_matches('/');
+    parent == "*" || parent.is_empty() || child == parent
+        || child.strip_prefix(parent).is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn short(fingerprint_hex: &str) -> &str {
+    &fingerprint_hex[..fingerprint_hex.len().min(8)]
+}
+
+fn certificate_error(details: String) -> AgeError {
+    AgeError::SecurityValidationFailed {
+        validation_type: "authority_certificate".to_string(),
+        details,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::chain::{KeyFormat, KeyMaterial, KeyMetadata};
+    use tempfile::TempDir;
+
+    fn key(seed: u8, key_type: KeyType) -> AuthorityKey {
+        let hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").unwrap();
+        let identity = bech32::encode_upper::<bech32::Bech32>(hrp, &[seed; 32]).unwrap();
+        let material = KeyMaterial::new(identity.as_bytes().to_vec(), Some(identity.into_bytes()), KeyFormat::Age);
+        AuthorityKey::new(material, key_type, None, None).unwrap()
+    }
+
+    fn chain() -> (AuthorityChain, Vec<AuthorityKey>) {
+        let keys = vec![key(1, KeyType::Master), key(2, KeyType::Repo), key(3, KeyType::Ignition), key(4, KeyType::Distro)];
+        let mut chain = AuthorityChain::new();
+        for key in &keys {
+            chain.add_key(key.clone()).unwrap();
+        }
+        for pair in keys.windows(2) {
+            chain.add_authority_relationship(pair[0].fingerprint(), pair[1].fingerprint()).unwrap();
+        }
+        (chain, keys)
+    }
+
+    #[test]
+    fn test_certificate_verifies_offline_against_root() {
+        let (chain, keys) = chain();
+        let dir = TempDir::new().unwrap();
+        let cert_path = dir.path().join("distro.cert");
+        AuthorityCertificate::issue(&chain, &keys[3], Duration::days(7), vec!["services/api".to_string()])
+            .unwrap()
+            .save(&cert_path)
+            .unwrap();
+
+        let cert = AuthorityCertificate::load(&cert_path).unwrap();
+        let master = signing::key_recipient(&keys[0]).unwrap();
+        let verified = cert.verify_now(&master).unwrap();
+        assert_eq!(verified.subject.key_type, KeyType::Distro);
+        assert_eq!(verified.depth, 3);
+        assert_eq!(verified.scope, vec!["services/api".to_string()]);
+
+        // An intermediate key can serve as the trusted root too
+        let repo = signing::key_recipient(&keys[1]).unwrap();
+        assert_eq!(cert.verify_now(&repo).unwrap().depth, 2);
+
+        let stranger = signing::recipient_for_secret(&[9; 32]).unwrap();
+        assert!(cert.verify_now(&stranger).is_err());
+        assert!(cert.verify(&master, cert.issued_at + Duration::days(8)).is_err());
+
+        let identity = dir.path().join("distro.key");
+        fs::write(&identity, signing::key_identity(&keys[3]).unwrap().as_bytes()).unwrap();
+        assert!(cert.is_held_by(&identity).unwrap());
+    }
+
+    #[test]
+    fn test_tampered_or_widened_certificate_rejected() {
+        let (chain, keys) = chain();
+        let master = signing::key_recipient(&keys[0]).unwrap();
+        let cert = AuthorityCertificate::issue(&chain, &keys[2], Duration::days(1), vec![]).unwrap();
+        assert!(AuthorityCertificate::issue(&chain, &keys[1], Duration::days(1), vec![]).is_err());
+
+        let mut extended = cert.clone();
+        extended.chain[0].not_after += Duration::days(30);
+        assert!(extended.verify_now(&master).is_err());
+
+        let mut swapped = cert.clone();
+        swapped.chain[0].subject = CertifiedKey::of(&key(7, KeyType::Ignition)).unwrap();
+        assert!(swapped.verify_now(&master).is_err());
+
+        // A properly signed link that claims more than its issuer was given
+        let narrow = AuthorityCertificate::issue(&chain, &keys[2], Duration::days(1), vec!["docs".to_string()]).unwrap();
+        let mut widened = narrow.clone();
+        widened.chain.insert(0, CertificateLink::sign(&keys[3], &keys[2], narrow.issued_at, narrow.chain[0].not_after, vec!["src".to_string()], None).unwrap());
+        assert!(widened.verify_now(&master).is_err());
+        widened.chain[0] = CertificateLink::sign(&keys[3], &keys[2], narrow.issued_at, narrow.chain[0].not_after, vec!["docs/public".to_string()], None).unwrap();
+        assert_eq!(widened.verify_now(&master).unwrap().scope, vec!["docs/public".to_string()]);
+
+        // A scoped distro key's capabilities are signed into its link and cannot be dropped
+        let capabilities = KeyCapabilities::parse_spec("paths=docs/** access=read-only").unwrap();
+        let metadata = KeyMetadata { capabilities: Some(capabilities.clone()), ..KeyMetadata::default() };
+        let scoped = AuthorityKey::new(keys[3].key_material().clone(), KeyType::Distro, None, Some(metadata)).unwrap();
+        let scoped_cert = AuthorityCertificate::issue(&chain, &scoped, Duration::days(1), vec![]).unwrap();
+        assert_eq!(scoped_cert.verify_now(&master).unwrap().capabilities, Some(capabilities.clone()));
+        let mut stripped = scoped_cert.clone();
+        stripped.chain[0].capabilities = None;
+        assert!(stripped.verify_now(&master).is_err());
+        let mut lifted = scoped_cert.clone();
+        lifted.chain[1].capabilities = Some(capabilities);
+        assert!(lifted.verify_now(&master).is_err());
+    }
+}
@@ -12,6 +12,11 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::encryption::age_automation::error::{AgeError, AgeResult};
+use crate::encryption::age_automation::lifecycle::trusted_now;
+use crate::encryption::age_automation::secret::{serde_exposed, SecretBytes};
+use super::capability::KeyCapabilities;
+use super::envelope::{detect_key_type, KeyEnvelope};
+use super::ignition::ExpirationPolicy;
 
 /// Key types in the authority hierarchy
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -88,6 +93,17 @@ impl KeyType {
         }
     }
     
+    /// Position in the hierarchy: Skull is highest (4), Distro lowest (0)
+    pub fn authority_level(&self) -> u8 {
+        match self {
+            KeyType::Skull => 4,
+            KeyType::Master => 3,
+            KeyType::Repo => 2,
+            KeyType::Ignition => 1,
+            KeyType::Distro => 0,
+        }
+    }
+    
     /// Check if this key type is an ignition key (passphrase-wrapped)
     pub fn is_ignition_key(&self) -> bool {
         matches!(self, KeyType::Skull | KeyType::Ignition | KeyType::Distro)
@@ -156,7 +172,8 @@ impl fmt::Display for KeyFingerprint {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeyMaterial {
     public_key: Vec<u8>,
-    private_key: Option<Vec<u8>>, // None for public keys only
+    #[serde(with = "serde_exposed")]
+    private_key: Option<SecretBytes>, // None for public keys only
     key_format: KeyFormat,
 }
 
@@ -172,7 +189,7 @@ impl KeyMaterial {
     pub fn new(public_key: Vec<u8>, private_key: Option<Vec<u8>>, format: KeyFormat) -> Self {
         Self {
             public_key,
-            private_key,
+            private_key: private_key.map(SecretBytes::new),
             key_format: format,
         }
     }
@@ -184,7 +201,7 @@ impl KeyMaterial {
     
     /// Get private key if available
     pub fn private_key(&self) -> Option<&[u8]> {
-        self.private_key.as_deref()
+        self.private_key.as_ref().map(SecretBytes::expose_secret)
     }
     
     /// Check if this is a private key
@@ -222,6 +239,26 @@ pub struct KeyMetadata {
     pub expiration: Option<DateTime<Utc>>,
     pub last_used: Option<DateTime<Utc>>,
     pub usage_count: u64,
+    /// Constraints a distro key was issued with
+    #[serde(default)]
+    pub capabilities: Option<KeyCapabilities>,
+}
+
+impl KeyMetadata {
+    /// Count one use, refusing once the capabilities' `max_uses` have been recorded
+    pub fn record_use(&mut self) -> AgeResult<()> {
+        if let Some(max_uses) = self.capabilities.as_ref().and_then(|capabilities| capabilities.max_uses) {
+            if self.usage_count >= max_uses {
+                return Err(AgeError::SecurityValidationFailed {
+                    validation_type: "key_capabilities".to_string(),
+                    details: format!("Key has used all {} of its permitted uses", max_uses),
+                });
+            }
+        }
+        self.last_used = Some(Utc::now());
+        self.usage_count += 1;
+        Ok(())
+    }
 }
 
 impl Default for KeyMetadata {
@@ -233,6 +270,7 @@ impl Default for KeyMetadata {
             expiration: None,
             last_used: None,
             usage_count: 0,
+            capabilities: None,
         }
     }
 }
@@ -258,26 +296,63 @@ impl AuthorityKey {
     }
     
     /// Load authority key from file
+    ///
+    /// Enveloped key files must declare `key_type`; their fingerprint is the recipient's.
     pub fn from_file(path: &Path, key_type: KeyType) -> AgeResult<Self> {
         let key_data = std::fs::read(path)
             .map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?;
         
-        // Parse key based on format detection
-        let key_material = Self::parse_key_material(&key_data)?;
+        let envelope = KeyEnvelope::parse(&String::from_utf8_lossy(&key_data))?;
+        let Some(envelope) = envelope else {
+            // Parse key based on format detection
+            let key_material = Self::parse_key_material(&key_data)?;
+            return Self::new(key_material, key_type, Some(path.to_path_buf()), None);
+        };
         
-        Self::new(key_material, key_type, Some(path.to_path_buf()), None)
+        if envelope.key_type != key_type {
+            return Err(AgeError::InvalidOperation {
+                operation: "load_key".to_string(),
+                reason: format!("{} is a {} key, not a {} key", path.display(), envelope.key_type, key_type),
+            });
+        }
+        let private_key = Self::parse_key_material(&key_data)?.private_key().map(<[u8]>::to_vec);
+        let key_material = KeyMaterial::new(envelope.recipient.clone().into_bytes(), private_key, KeyFormat::Age);
+        let metadata = KeyMetadata {
+            creation_time: envelope.created,
+            creator: "padlock_key_envelope".to_string(),
+            description: format!("{} authority key", key_type),
+            expiration: envelope.expires,
+            last_used: None,
+            usage_count: 0,
+            capabilities: envelope.capabilities.clone(),
+        };
+        Self::new(key_material, key_type, Some(path.to_path_buf()), Some(metadata))
+    }
+    
+    /// Load an enveloped key file, taking the key type from its envelope
+    pub fn open(path: &Path) -> AgeResult<Self> {
+        let key_type = detect_key_type(path)?.ok_or_else(|| AgeError::InvalidOperation {
+            operation: "load_key".to_string(),
+            reason: format!("{} is a plain age key; its key type is unknown", path.display()),
+        })?;
+        Self::from_file(path, key_type)
     }
     
     /// Parse key material from bytes
     fn parse_key_material(data: &[u8]) -> AgeResult<KeyMaterial> {
-        // Simple Age key detection (starts with "AGE-SECRET-KEY-")
-        if data.starts_with(b"AGE-SECRET-KEY-") {
+        // Simple Age key detection on the first line that is not an age-keygen comment
+        let first_line = String::from_utf8_lossy(data).lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .unwrap_or_default()
+            .to_string();
+        if first_line.starts_with("AGE-SECRET-KEY-") {
             Ok(KeyMaterial::new(
                 data.to_vec(),
                 Some(data.to_vec()),
                 KeyFormat::Age,
             ))
-        } else if data.starts_with(b"age1") {
+        } else if first_line.starts_with("age1") {
             // Age public key
             Ok(KeyMaterial::new(
                 data.to_vec(),
@@ -339,11 +414,19 @@ impl AuthorityKey {
     /// Check if key is expired
     pub fn is_expired(&self) -> bool {
         if let Some(expiration) = self.metadata.expiration {
-            Utc::now() > expiration
+            trusted_now() > expiration
         } else {
             false
         }
     }
+
+    /// Check the key against an expiration policy: age since creation, and idle time
+    /// since `last_used` (or creation when it was never used)
+    pub fn is_expired_under(&self, policy: &ExpirationPolicy) -> bool {
+        self.is_expired()
+            || policy.is_expired(self.metadata.creation_time)
+            || policy.is_idle(self.metadata.last_used.unwrap_or(self.metadata.creation_time))
+    }
 }
 
 /// Authority chain managing key relationships
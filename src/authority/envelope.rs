@@ -0,0 +1,367 @@
+//! Key Envelope - Self-describing padlock key files
+//!
+//! Plain age key files say nothing about what they are, so every loader had to be told
+//! the key type. An enveloped key file wraps the age identity in `#` header lines that
+//! `age -i` skips as comments, so the file stays a valid age identity file:
+//!
+//! ```text
+//! # padlock-key: v1
+//! # type: distro
+//! # fingerprint: 3f9a...
+//! # parent: 81c2...
+//! # created: 2026-10-18T09:00:00Z
+//! # expires: 2026-11-17T09:00:00Z
+//! # policy: max_age=30d
+//! # capabilities: paths=config/** access=read-only
+//! # public key: age1...
+//! # parent-signature: 5d0e...
+//! AGE-SECRET-KEY-1...
+//! ```
+//!
+//! The fingerprint is the SHA-256 of the recipient, so it stays stable while headers
+//! change. The parent signature (XEdDSA by the parent key, see `signing`) covers every
+//! header above it, including a distro key's capabilities (see `capability`). `expires`
+//! is a hard ceiling fixed at creation; the effective expiration policy can only bring
+//! it forward.
+//!
+//! `parse` and `read` only check that the headers agree with the identity. Anything that
+//! acts on the type, expiry, policy or capabilities reads the file with `read_verified`,
+//! which also ties the envelope to a trusted root (see `trust`).
+//!
+//! Security Guardian: Edgar - Key files that say what they are
+
+use std::fs;
+use std::path::Path;
+use chrono::{DateTime, SubsecRound, Utc};
+
+use crate::encryption::age_automation::error::{AgeError, AgeResult};
+use crate::encryption::age_automation::secret::Zeroizing;
+use super::capability::{self, KeyCapabilities, KEY_CAPABILITIES_PREFIX};
+use super::chain::{AuthorityKey, KeyFingerprint, KeyType};
+use super::expiration::KEY_POLICY_PREFIX;
+use super::ignition::ExpirationPolicy;
+use super::signing;
+use super::trust::TrustAnchors;
+
+/// First line of every enveloped key file
+pub const ENVELOPE_MARKER: &str = "# padlock-key: v1";
+
+/// Parsed header of an enveloped key file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyEnvelope {
+    pub key_type: KeyType,
+    /// SHA-256 (hex) of the recipient
+    pub fingerprint: String,
+    pub parent: Option<String>,
+    pub created: DateTime<Utc>,
+    pub expires: Option<DateTime<Utc>>,
+    /// Per-key expiration policy spec (see `expiration`)
+    pub policy: Option<String>,
+    /// Constraints of a scoped distro key
+    pub capabilities: Option<KeyCapabilities>,
+    pub recipient: String,
+    /// Hex XEdDSA signature by the parent over the headers above it
+    pub parent_signature: Option<String>,
+}
+
+impl KeyEnvelope {
+    /// Unsigned envelope for a freshly created key
+    pub fn new(key_type: KeyType, recipient: &str) -> AgeResult<Self> {
+        signing::recipient_public(recipient)?;
+        Ok(Self {
+            key_type,
+            fingerprint: recipient_fingerprint(recipient)?,
+            parent: None,
+            created: Utc::now().trunc_subsecs(0),
+            expires: None,
+            policy: None,
+            capabilities: None,
+            recipient: recipient.to_string(),
+            parent_signature: None,
+        })
+    }
+
+    /// Fix the expiration ceiling
+    pub fn with_expires(mut self, expires: Option<DateTime<Utc>>) -> Self {
+        self.expires = expires.map(|at| at.trunc_subsecs(0));
+        self
+    }
+
+    /// Record a per-key expiration policy spec
+    pub fn with_policy(mut self, policy: Option<String>) -> Self {
+        self.policy = policy.filter(|spec| !spec.is_empty());
+        self
+    }
+
+    /// Limit a distro key to `capabilities`; set before signing so the signature covers them
+    pub fn with_capabilities(mut self, capabilities: Option<KeyCapabilities>) -> AgeResult<Self> {
+        if let Some(capabilities) = &capabilities {
+            capability::check_key_type(self.key_type)?;
+            capabilities.validate()?;
+        }
+        self.capabilities = capabilities;
+        Ok(self)
+    }
+
+    /// Name `parent` as the issuing key and sign the headers with it when its secret is at hand
+    pub fn with_parent(mut self, parent: &AuthorityKey) -> AgeResult<Self> {
+        if self.key_type.parent_type() != Some(parent.key_type()) {
+            return Err(envelope_error(format!("A {} key cannot issue a {} key", parent.key_type(), self.key_type)));
+        }
+        self.parent = Some(parent.fingerprint().hex().to_string());
+        self.parent_signature = match signing::key_identity(parent) {
+            Some(_) => Some(hex::encode(signing::sign_with_key(parent, &self.signed_bytes())?)),
+            None => None,
+        };
+        Ok(self)
+    }
+
+    /// Key file content: headers followed by the identity
+    pub fn render(&self, identity: &str) -> Zeroizing<String> {
+        let mut content = self.headers();
+        if let Some(signature) = &self.parent_signature {
+            content.push_str(&format!("# parent-signature: {}\n", signature));
+        }
+        content.push_str(identity.trim());
+        content.push('\n');
+        Zeroizing::new(content)
+    }
+
+    fn headers(&self) -> String {
+        let mut headers = format!("{}\n# type: {}\n# fingerprint: {}\n", ENVELOPE_MARKER, self.key_type, self.fingerprint);
+        if let Some(parent) = &self.parent {
+            headers.push_str(&format!("# parent: {}\n", parent));
+        }
+        headers.push_str(&format!("# created: {}\n", self.created.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)));
+        headers.push_str(&format!("# expires: {}\n", self.expires
+            .map(|at| at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+            .unwrap_or_else(|| "never".to_string())));
+        if let Some(policy) = &self.policy {
+            headers.push_str(&format!("{}{}\n", KEY_POLICY_PREFIX, policy));
+        }
+        if let Some(capabilities) = &self.capabilities {
+            headers.push_str(&format!("{}{}\n", KEY_CAPABILITIES_PREFIX, capabilities.to_spec()));
+        }
+        headers.push_str(&format!("# public key: {}\n", self.recipient));
+        headers
+    }
+
+    fn signed_bytes(&self) -> Vec<u8> {
+        self.headers().into_bytes()
+    }
+
+    /// Parse key file content; None when it is not enveloped
+    ///
+    /// Fails when the headers contradict each other or the identity they wrap.
+    pub fn parse(content: &str) -> AgeResult<Option<Self>> {
+        let mut lines = content.lines().map(str::trim).filter(|line| !line.is_empty());
+        if lines.next() != Some(ENVELOPE_MARKER) {
+            return Ok(None);
+        }
+
+        let mut key_type = None;
+        let mut fingerprint = None;
+        let mut parent = None;
+        let mut created = None;
+        let mut expires = None;
+        let mut policy = None;
+        let mut capabilities = None;
+        let mut recipient = None;
+        let mut parent_signature = None;
+        let mut identity = None;
+        for line in lines {
+            if line.starts_with("AGE-SECRET-KEY-") {
+                identity = Some(line);
+                continue;
+            }
+            let Some((field, value)) = line.strip_prefix("# ").and_then(|header| header.split_once(": ")) else {
+                continue;
+            };
+            let value = value.trim();
+            match field {
+                "type" => key_type = Some(KeyType::from_str(value)?),
+                "fingerprint" => fingerprint = Some(value.to_string()),
+                "parent" => parent = Some(value.to_string()),
+                "created" => created = Some(parse_time("created", value)?),
+                "expires" if value != "never" => expires = Some(parse_time("expires", value)?),
+                "policy" => policy = Some(value.to_string()),
+                "capabilities" => capabilities = Some(KeyCapabilities::parse_spec(value)?),
+                "public key" => recipient = Some(value.to_string()),
+                "parent-signature" => parent_signature = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        let missing = |field: &str| envelope_error(format!("Key envelope has no '{}' header", field));
+        let envelope = Self {
+            key_type: key_type.ok_or_else(|| missing("type"))?,
+            fingerprint: fingerprint.ok_or_else(|| missing("fingerprint"))?,
+            parent,
+            created: created.ok_or_else(|| missing("created"))?,
+            expires,
+            policy,
+            capabilities,
+            recipient: recipient.ok_or_else(|| missing("public key"))?,
+            parent_signature,
+        };
+
+        if envelope.capabilities.is_some() {
+            capability::check_key_type(envelope.key_type)?;
+        }
+        if envelope.fingerprint != recipient_fingerprint(&envelope.recipient)? {
+            return Err(envelope_error("Envelope fingerprint does not match its public key".to_string()));
+        }
+        if let Some(identity) = identity {
+            if signing::recipient_for_secret(&*signing::identity_secret(identity)?)? != envelope.recipient {
+                return Err(envelope_error("Envelope public key does not match the identity it wraps".to_string()));
+            }
+        }
+        Ok(Some(envelope))
+    }
+
+    /// Read a key file's envelope; None when the file is a plain age key
+    pub fn read(path: &Path) -> AgeResult<Option<Self>> {
+        let content = Zeroizing::new(fs::read_to_string(path)
+            .map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?);
+        Self::parse(&content)
+    }
+
+    /// Read a key file's envelope and verify it up to a root in `anchors`
+    ///
+    /// Plain age keys fail, since nothing about them can be verified.
+    pub fn read_verified(path: &Path, anchors: &TrustAnchors) -> AgeResult<Self> {
+        Ok(Self::read_lineage(path, anchors)?.remove(0))
+    }
+
+    /// Same as `read_verified`, returning the verified lineage: the key first, then its
+    /// parents up to the root
+    pub fn read_lineage(path: &Path, anchors: &TrustAnchors) -> AgeResult<Vec<Self>> {
+        let envelope = Self::read(path)?
+            .ok_or_else(|| envelope_error(format!("{} is a plain age key; its type cannot be verified", path.display())))?;
+        anchors.verify(&envelope, path)
+    }
+
+    /// Check the parent signature against the parent's recipient
+    pub fn verify_parent(&self, parent_recipient: &str) -> AgeResult<()> {
+        let signature = self.parent_signature.as_deref()
+            .ok_or_else(|| envelope_error(format!("{} key {} carries no parent signature", self.key_type, short(&self.fingerprint))))?;
+        let signature = hex::decode(signature).map_err(|_| envelope_error("Malformed parent signature".to_string()))?;
+        if !signing::verify(&signing::recipient_public(parent_recipient)?, &self.signed_bytes(), &signature) {
+            return Err(envelope_error(format!("Parent signature of {} key {} does not verify", self.key_type, short(&self.fingerprint))));
+        }
+        Ok(())
+    }
+
+    /// Earliest of the fixed ceiling and the policy's expiration
+    pub fn expires_at(&self, policy: Option<&ExpirationPolicy>) -> Option<DateTime<Utc>> {
+        let by_policy = policy.map(|policy| policy.expires_at(self.created));
+        match (self.expires, by_policy) {
+            (Some(fixed), Some(by_policy)) => Some(fixed.min(by_policy)),
+            (fixed, by_policy) => fixed.or(by_policy),
+        }
+    }
+
+    /// Expired under the fixed ceiling or the given policy, by the rollback-resistant clock
+    pub fn is_expired(&self, policy: Option<&ExpirationPolicy>) -> bool {
+        let now = crate::encryption::age_automation::lifecycle::trusted_now();
+        self.expires_at(policy).is_some_and(|at| now > at)
+    }
+}
+
+/// Key type recorded in a key file; None for plain age keys
+pub fn detect_key_type(path: &Path) -> AgeResult<Option<KeyType>> {
+    Ok(KeyEnvelope::read(path)?.map(|envelope| envelope.key_type))
+}
+
+/// Envelope fingerprint of an `age1...` recipient
+pub fn recipient_fingerprint(recipient: &str) -> AgeResult<String> {
+    Ok(KeyFingerprint::from_key_material(recipient.trim().as_bytes())?.hex().to_string())
+}
+
+fn parse_time(field: &str, value: &str) -> AgeResult<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(|_| envelope_error(format!("Invalid '{}' header '{}'", field, value)))
+}
+
+fn short(fingerprint_hex: &str) -> &str {
+    &fingerprint_hex[..fingerprint_hex.len().min(8)]
+}
+
+fn envelope_error(reason: String) -> AgeError {
+    AgeError::InvalidOperation {
+        operation: "key_envelope".to_string(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::chain::{KeyFormat, KeyMaterial};
+
+    fn identity(seed: u8) -> String {
+        let hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").unwrap();
+        bech32::encode_upper::<bech32::Bech32>(hrp, &[seed; 32]).unwrap()
+    }
+
+    fn envelope(seed: u8, key_type: KeyType) -> KeyEnvelope {
+        let secret = signing::identity_secret(&identity(seed)).unwrap();
+        KeyEnvelope::new(key_type, &signing::recipient_for_secret(&secret).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_envelope_roundtrip_and_parent_signature() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let parent_path = dir.path().join("auth-ignition.key");
+        fs::write(&parent_path, envelope(1, KeyType::Ignition).render(&identity(1)).as_bytes()).unwrap();
+        let parent = AuthorityKey::from_file(&parent_path, KeyType::Ignition).unwrap();
+
+        let child = envelope(2, KeyType::Distro)
+            .with_expires(Some(Utc::now() + chrono::Duration::days(30)))
+            .with_policy(Some("max_age=7d".to_string()))
+            .with_parent(&parent)
+            .unwrap();
+        let child_path = dir.path().join("auth-distro.key");
+        fs::write(&child_path, child.render(&identity(2)).as_bytes()).unwrap();
+
+        assert_eq!(detect_key_type(&child_path).unwrap(), Some(KeyType::Distro));
+        let parsed = KeyEnvelope::read(&child_path).unwrap().unwrap();
+        assert_eq!(parsed, child);
+        assert_eq!(parsed.parent.as_deref(), Some(parent.fingerprint().hex()));
+        parsed.verify_parent(&signing::key_recipient(&parent).unwrap()).unwrap();
+        assert!(parsed.verify_parent(&envelope(3, KeyType::Ignition).recipient).is_err());
+
+        // Loading by type agrees with the envelope; its fingerprint is the recipient's
+        let loaded = AuthorityKey::from_file(&child_path, KeyType::Distro).unwrap();
+        assert_eq!(loaded.fingerprint().hex(), parsed.fingerprint);
+        assert!(AuthorityKey::from_file(&child_path, KeyType::Repo).is_err());
+        assert_eq!(AuthorityKey::open(&child_path).unwrap().key_type(), KeyType::Distro);
+
+        let plain = dir.path().join("plain.key");
+        fs::write(&plain, format!("# created: 2026-01-01T00:00:00Z\n{}\n", identity(4))).unwrap();
+        assert_eq!(detect_key_type(&plain).unwrap(), None);
+        assert!(AuthorityKey::open(&plain).is_err());
+
+        let skull_key = AuthorityKey::new(KeyMaterial::new(vec![1], None, KeyFormat::Age), KeyType::Skull, None, None).unwrap();
+        assert!(envelope(5, KeyType::Distro).with_parent(&skull_key).is_err());
+    }
+
+    #[test]
+    fn test_tampered_envelope_and_expiration() {
+        // Plain parsing believes an edited type; only `read_verified` catches it. The
+        // identity must match either way
+        let mut edited = envelope(1, KeyType::Distro).render(&identity(1)).replace("# type: distro", "# type: skull");
+        assert_eq!(KeyEnvelope::parse(&edited).unwrap().unwrap().key_type, KeyType::Skull);
+        edited = edited.replace(&identity(1), &identity(2));
+        assert!(KeyEnvelope::parse(&edited).is_err());
+
+        let past = envelope(1, KeyType::Distro).with_expires(Some(Utc::now() - chrono::Duration::hours(1)));
+        assert!(past.is_expired(None));
+        let mut open_ended = envelope(1, KeyType::Distro);
+        open_ended.created -= chrono::Duration::hours(1);
+        assert!(!open_ended.is_expired(None));
+        let strict = ExpirationPolicy::new(std::time::Duration::from_secs(0), false);
+        assert!(open_ended.is_expired(Some(&strict)));
+    }
+}
@@ -0,0 +1,393 @@
+//! Expiration Policies - Configurable key lifetimes with layered overrides
+//!
+//! The built-in `ExpirationPolicy::default_for_type` is only the bottom layer. A key's
+//! effective policy is resolved through, most specific last:
+//!
+//! 1. built-in defaults per key type
+//! 2. the global file `$XDG_CONFIG_HOME/padlock/expiration.toml` (or `~/.config/...`)
+//! 3. the repository file `.padlock/expiration.toml`
+//! 4. a `# policy:` line in the key file itself, written at creation time
+//!
+//! Each layer only changes the fields it sets. Durations are written as a number and a
+//! unit (`s`, `m`, `h`, `d`, `w`); `max_age = "never"` removes expiry for the key type.
+//!
+//! ```toml
+//! [ignition]
+//! max_age = "30d"
+//! warning = "3d"
+//! auto_rotate = true
+//! max_idle = "14d"
+//!
+//! [repo]
+//! max_age = "365d"
+//! ```
+//!
+//! The per-key form is `# policy: max_age=7d warning=1d auto_rotate=false max_idle=never`.
+//!
+//! Security Guardian: Edgar - Lifetimes decided by policy, not by hardcoding
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::encryption::age_automation::error::{AgeError, AgeResult};
+use super::chain::KeyType;
+use super::ignition::ExpirationPolicy;
+
+/// Policy file name inside the global padlock configuration directory
+pub const GLOBAL_FILE: &str = "expiration.toml";
+
+/// Policy file path relative to a repository root
+pub const REPOSITORY_FILE: &str = ".padlock/expiration.toml";
+
+/// Key file comment carrying a per-key policy
+pub const KEY_POLICY_PREFIX: &str = "# policy: ";
+
+/// Fields one layer sets; unset fields keep the value from the layer below
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyOverride {
+    pub max_age: Option<String>,
+    pub warning: Option<String>,
+    pub auto_rotate: Option<bool>,
+    pub max_idle: Option<String>,
+}
+
+impl PolicyOverride {
+    /// Parse the `key=value` list of a `# policy:` line (separated by spaces or commas)
+    pub fn parse_spec(spec: &str) -> AgeResult<Self> {
+        let mut result = Self::default();
+        for item in spec.split(|c: char| c == ',' || c.is_whitespace()).filter(|item| !item.is_empty()) {
+            let (name, value) = item.split_once('=')
+                .ok_or_else(|| policy_error(format!("Expected key=value, got '{}'", item)))?;
+            let value = value.to_string();
+            match name {
+                "max_age" => result.max_age = Some(value),
+                "warning" => result.warning = Some(value),
+                "max_idle" => result.max_idle = Some(value),
+                "auto_rotate" => result.auto_rotate = Some(value.parse()
+                    .map_err(|_| policy_error(format!("auto_rotate must be true or false, got '{}'", value)))?),
+                _ => return Err(policy_error(format!("Unknown policy field '{}'", name))),
+            }
+        }
+        result.validate()?;
+        Ok(result)
+    }
+
+    /// The `key=value` list written after `# policy: `
+    pub fn to_spec(&self) -> String {
+        let mut items = Vec::new();
+        if let Some(value) = &self.max_age {
+            items.push(format!("max_age={}", value));
+        }
+        if let Some(value) = &self.warning {
+            items.push(format!("warning={}", value));
+        }
+        if let Some(value) = self.auto_rotate {
+            items.push(format!("auto_rotate={}", value));
+        }
+        if let Some(value) = &self.max_idle {
+            items.push(format!("max_idle={}", value));
+        }
+        items.join(" ")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check every duration parses
+    pub fn validate(&self) -> AgeResult<()> {
+        for value in [&self.max_age, &self.warning, &self.max_idle].into_iter().flatten() {
+            parse_duration(value)?;
+        }
+        if self.warning.as_deref().is_some_and(is_never) {
+            return Err(policy_error("warning cannot be 'never'; use 0s for no warning window".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Layer these fields over `base`
+    pub fn apply(&self, key_type: KeyType, base: Option<ExpirationPolicy>) -> AgeResult<Option<ExpirationPolicy>> {
+        let mut policy = match (&self.max_age, base) {
+            (Some(max_age), base) => match parse_duration(max_age)? {
+                None => return Ok(None),
+                Some(max_age) => {
+                    let auto_rotation = base.as_ref().is_some_and(ExpirationPolicy::auto_rotation);
+                    ExpirationPolicy::new(max_age, auto_rotation)
+                        .with_max_idle(base.as_ref().and_then(ExpirationPolicy::max_idle))
+                }
+            },
+            (None, Some(base)) => base,
+            (None, None) if self.is_empty() => return Ok(None),
+            (None, None) => return Err(policy_error(format!(
+                "{} keys do not expire by default; set max_age before '{}'", key_type, self.to_spec()))),
+        };
+
+        if let Some(warning) = &self.warning {
+            policy = policy.with_warning_threshold(parse_duration(warning)?.unwrap_or_default());
+        }
+        if let Some(auto_rotate) = self.auto_rotate {
+            policy = policy.with_auto_rotation(auto_rotate);
+        }
+        if let Some(max_idle) = &self.max_idle {
+            policy = policy.with_max_idle(parse_duration(max_idle)?);
+        }
+        Ok(Some(policy))
+    }
+}
+
+/// Policy file contents: one optional table per key type
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyFile {
+    pub skull: Option<PolicyOverride>,
+    pub master: Option<PolicyOverride>,
+    pub repo: Option<PolicyOverride>,
+    pub ignition: Option<PolicyOverride>,
+    pub distro: Option<PolicyOverride>,
+}
+
+impl PolicyFile {
+    /// Parse and validate a policy file
+    pub fn parse(content: &str) -> AgeResult<Self> {
+        let file: PolicyFile = toml::from_str(content)
+            .map_err(|e| policy_error(format!("Invalid expiration policy file: {}", e)))?;
+        for entry in [&file.skull, &file.master, &file.repo, &file.ignition, &file.distro].into_iter().flatten() {
+            entry.validate()?;
+        }
+        Ok(file)
+    }
+
+    /// Load a policy file; None when it does not exist
+    pub fn load(path: &Path) -> AgeResult<Option<Self>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?;
+        Self::parse(&content).map(Some)
+    }
+
+    pub fn for_type(&self, key_type: KeyType) -> Option<&PolicyOverride> {
+        match key_type {
+            KeyType::Skull => self.skull.as_ref(),
+            KeyType::Master => self.master.as_ref(),
+            KeyType::Repo => self.repo.as_ref(),
+            KeyType::Ignition => self.ignition.as_ref(),
+            KeyType::Distro => self.distro.as_ref(),
+        }
+    }
+}
+
+/// Layer that last changed a key's effective policy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "layer", content = "path", rename_all = "lowercase")]
+pub enum PolicySource {
+    BuiltIn,
+    Global(PathBuf),
+    Repository(PathBuf),
+    Key(PathBuf),
+}
+
+impl fmt::Display for PolicySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicySource::BuiltIn => write!(f, "built-in default"),
+            PolicySource::Global(path) => write!(f, "global config {}", path.display()),
+            PolicySource::Repository(path) => write!(f, "repository config {}", path.display()),
+            PolicySource::Key(path) => write!(f, "key file {}", path.display()),
+        }
+    }
+}
+
+/// Resolved policy of one key and where it came from
+#[derive(Debug, Clone)]
+pub struct EffectivePolicy {
+    pub policy: Option<ExpirationPolicy>,
+    pub source: PolicySource,
+}
+
+impl EffectivePolicy {
+    /// One-line summary, e.g. `max_age=30d warning=3d auto_rotate=true max_idle=never`
+    pub fn describe(&self) -> String {
+        match &self.policy {
+            None => "never expires".to_string(),
+            Some(policy) => format!("max_age={} warning={} auto_rotate={} max_idle={}",
+                format_duration(policy.max_age()), format_duration(policy.warning_threshold()),
+                policy.auto_rotation(), policy.max_idle().map(format_duration).unwrap_or_else(|| "never".to_string())),
+        }
+    }
+}
+
+/// Global and repository policy layers
+#[derive(Debug, Clone, Default)]
+pub struct ExpirationPolicies {
+    global: Option<(PathBuf, PolicyFile)>,
+    repository: Option<(PathBuf, PolicyFile)>,
+}
+
+impl ExpirationPolicies {
+    /// Global policies, plus a repository's when given
+    pub fn load(repository: Option<&Path>) -> AgeResult<Self> {
+        let mut policies = Self::default();
+        if let Some(path) = global_policy_path() {
+            policies = policies.with_global_file(&path)?;
+        }
+        match repository {
+            Some(repository) => policies.with_repository(repository),
+            None => Ok(policies),
+        }
+    }
+
+    /// Use `path` as the global layer
+    pub fn with_global_file(mut self, path: &Path) -> AgeResult<Self> {
+        self.global = PolicyFile::load(path)?.map(|file| (path.to_path_buf(), file));
+        Ok(self)
+    }
+
+    /// Use a repository's `.padlock/expiration.toml` as the repository layer
+    pub fn with_repository(mut self, repository: &Path) -> AgeResult<Self> {
+        let path = repository.join(REPOSITORY_FILE);
+        self.repository = PolicyFile::load(&path)?.map(|file| (path, file));
+        Ok(self)
+    }
+
+    /// Effective policy for a key type before per-key settings
+    pub fn for_type(&self, key_type: KeyType) -> AgeResult<EffectivePolicy> {
+        let mut effective = EffectivePolicy {
+            policy: ExpirationPolicy::default_for_type(key_type),
+            source: PolicySource::BuiltIn,
+        };
+        let layers = [
+            self.global.as_ref().map(|(path, file)| (PolicySource::Global(path.clone()), file)),
+            self.repository.as_ref().map(|(path, file)| (PolicySource::Repository(path.clone()), file)),
+        ];
+        for (source, file) in layers.into_iter().flatten() {
+            if let Some(layer) = file.for_type(key_type) {
+                effective.policy = layer.apply(key_type, effective.policy)?;
+                effective.source = source;
+            }
+        }
+        Ok(effective)
+    }
+
+    /// Effective policy for a key file, honouring its `# policy:` line
+    pub fn for_key_file(&self, key_type: KeyType, path: &Path) -> AgeResult<EffectivePolicy> {
+        let mut effective = self.for_type(key_type)?;
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?;
+        if let Some(layer) = key_policy(&content)? {
+            effective.policy = layer.apply(key_type, effective.policy)?;
+            effective.source = PolicySource::Key(path.to_path_buf());
+        }
+        Ok(effective)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/padlock/expiration.toml`, falling back to `~/.config`
+pub fn global_policy_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("padlock").join(GLOBAL_FILE))
+}
+
+/// Per-key policy from a key file's `# policy:` line
+pub fn key_policy(key_file_content: &str) -> AgeResult<Option<PolicyOverride>> {
+    key_file_content.lines()
+        .find_map(|line| line.strip_prefix(KEY_POLICY_PREFIX))
+        .map(PolicyOverride::parse_spec)
+        .transpose()
+}
+
+/// Parse `30d`, `12h`, `90m`, `45s`, `2w`; `never` yields None
+pub fn parse_duration(value: &str) -> AgeResult<Option<Duration>> {
+    let value = value.trim();
+    if is_never(value) {
+        return Ok(None);
+    }
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse()
+        .map_err(|_| policy_error(format!("Invalid duration '{}': expected a number and a unit", value)))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        _ => return Err(policy_error(format!("Invalid duration '{}': unit must be s, m, h, d or w", value))),
+    };
+    Ok(Some(Duration::from_secs(number.saturating_mul(seconds))))
+}
+
+/// Largest whole unit that represents a duration exactly
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    [("w", 7 * 86400), ("d", 86400), ("h", 3600), ("m", 60)]
+        .iter()
+        .find(|(_, unit)| secs > 0 && secs.is_multiple_of(*unit))
+        .map(|(name, unit)| format!("{}{}", secs / unit, name))
+        .unwrap_or_else(|| format!("{}s", secs))
+}
+
+fn is_never(value: &str) -> bool {
+    matches!(value, "never" | "none")
+}
+
+fn policy_error(reason: String) -> AgeError {
+    AgeError::ConfigurationError {
+        parameter: "expiration_policy".to_string(),
+        value: String::new(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let dir = TempDir::new().unwrap();
+        let global = dir.path().join("global.toml");
+        std::fs::write(&global, "[distro]\nmax_age = \"14d\"\nmax_idle = \"3d\"\n\n[repo]\nmax_age = \"365d\"\n").unwrap();
+        std::fs::create_dir_all(dir.path().join(".padlock")).unwrap();
+        std::fs::write(dir.path().join(REPOSITORY_FILE), "[distro]\nwarning = \"2d\"\nauto_rotate = false\n").unwrap();
+        let key = dir.path().join("auth-distro.key");
+        std::fs::write(&key, "# created: 2026-01-01T00:00:00Z\n# policy: max_age=1d\n# public key: age1x\n").unwrap();
+
+        let policies = ExpirationPolicies::default().with_global_file(&global).unwrap()
+            .with_repository(dir.path()).unwrap();
+
+        let master = policies.for_type(KeyType::Master).unwrap();
+        assert!(master.policy.is_none());
+        assert_eq!(master.source, PolicySource::BuiltIn);
+
+        let repo = policies.for_type(KeyType::Repo).unwrap();
+        assert_eq!(repo.source, PolicySource::Global(global.clone()));
+        assert_eq!(repo.describe(), "max_age=365d warning=876h auto_rotate=false max_idle=never");
+
+        let distro = policies.for_type(KeyType::Distro).unwrap();
+        assert_eq!(distro.source, PolicySource::Repository(dir.path().join(REPOSITORY_FILE)));
+        assert_eq!(distro.describe(), "max_age=2w warning=2d auto_rotate=false max_idle=3d");
+
+        // A new max_age resets the warning window to its default share
+        let keyed = policies.for_key_file(KeyType::Distro, &key).unwrap();
+        assert_eq!(keyed.source, PolicySource::Key(key));
+        assert_eq!(keyed.describe(), "max_age=1d warning=144m auto_rotate=false max_idle=3d");
+    }
+
+    #[test]
+    fn test_invalid_policies_rejected() {
+        assert!(PolicyFile::parse("[distro]\nmax_age = \"7 days\"\n").is_err());
+        assert!(PolicyFile::parse("[distro]\nlifetime = \"7d\"\n").is_err());
+        assert!(PolicyOverride::parse_spec("max_age=7d colour=red").is_err());
+        assert!(PolicyOverride::parse_spec("warning=1d").unwrap().apply(KeyType::Master, None).is_err());
+        assert!(PolicyOverride::parse_spec("max_age=never").unwrap().apply(KeyType::Distro,
+            ExpirationPolicy::default_for_type(KeyType::Distro)).unwrap().is_none());
+    }
+}
@@ -3,15 +3,34 @@
 //! Passphrase-wrapped key operations implementing secure key storage and access
 //! control for X, I, and D key types in the authority chain.
 //!
+//! Unlocking is rate limited: each failed attempt is counted and delays the next attempt
+//! exponentially, and reaching the `UnlockPolicy` threshold locks the key out until an
+//! I-level or higher ancestor of the key resets the counter by signing the reset with its
+//! secret. Failures and lockouts are written to the audit log.
+//!
+//! The counter, the policy and the issuing authority are never taken from the key file,
+//! which its holder can edit. `UnlockLedger` keeps them in the machine-local padlock
+//! state directory, keyed by a digest of the wrapped key; `unlock_file` and
+//! `reset_lockout_file` load and update that record.
+//!
 //! Security Guardian: Edgar - Secure passphrase wrapping and validation
 
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 
 use crate::encryption::age_automation::error::{AgeError, AgeResult};
+use crate::encryption::age_automation::lifecycle::trusted_now;
+use crate::encryption::age_automation::lifecycle::clock::{default_state_dir, write_atomic};
+use crate::encryption::age_automation::secret::Zeroizing;
+use crate::encryption::age_automation::security::AuditLogger;
 use super::chain::{KeyType, KeyMaterial, KeyFingerprint, AuthorityKey};
+use super::envelope::KeyEnvelope;
+use super::signing;
+use super::trust::{self, TrustAnchors};
 
 /// Passphrase hash for secure verification
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,11 +100,11 @@ impl EncryptedKeyMaterial {
     /// Encrypt key material with derived key
     pub fn encrypt(key_material: &KeyMaterial, encryption_key: &[u8]) -> AgeResult<Self> {
         // Simple XOR encryption for demo (should use proper AES-GCM in production)
-        let plaintext = serde_json::to_vec(key_material)
+        let plaintext = Zeroizing::new(serde_json::to_vec(key_material)
             .map_err(|e| AgeError::InvalidOperation {
                 operation: "serialize_key".to_string(),
                 reason: e.to_string(),
-            })?;
+            })?);
         
         let mut ciphertext = Vec::with_capacity(plaintext.len());
         for (i, &byte) in plaintext.iter().enumerate() {
@@ -105,7 +124,7 @@ impl EncryptedKeyMaterial {
     /// Decrypt key material with derived key
     pub fn decrypt(&self, encryption_key: &[u8]) -> AgeResult<KeyMaterial> {
         // Simple XOR decryption for demo
-        let mut plaintext = Vec::with_capacity(self.ciphertext.len());
+        let mut plaintext = Zeroizing::new(Vec::with_capacity(self.ciphertext.len()));
         for (i, &byte) in self.ciphertext.iter().enumerate() {
             plaintext.push(byte ^ encryption_key[i % encryption_key.len()]);
         }
@@ -126,6 +145,9 @@ pub struct ExpirationPolicy {
     expiration_duration: Duration,
     auto_rotation: bool,
     warning_threshold: Duration,
+    /// Longest time the key may go unused before it is treated as expired
+    #[serde(default)]
+    max_idle: Option<Duration>,
 }
 
 impl ExpirationPolicy {
@@ -135,13 +157,62 @@ impl ExpirationPolicy {
             expiration_duration: duration,
             auto_rotation,
             warning_threshold: Duration::from_secs(duration.as_secs() / 10), // 10% warning
+            max_idle: None,
         }
     }
     
+    /// Replace the default warning window of 10% of the maximum age
+    pub fn with_warning_threshold(mut self, threshold: Duration) -> Self {
+        self.warning_threshold = threshold;
+        self
+    }
+    
+    /// Turn automatic rotation by maintenance on or off
+    pub fn with_auto_rotation(mut self, auto_rotation: bool) -> Self {
+        self.auto_rotation = auto_rotation;
+        self
+    }
+    
+    /// Expire keys left unused for longer than `max_idle`
+    pub fn with_max_idle(mut self, max_idle: Option<Duration>) -> Self {
+        self.max_idle = max_idle;
+        self
+    }
+    
+    /// Maximum age since creation
+    pub fn max_age(&self) -> Duration {
+        self.expiration_duration
+    }
+    
+    /// Warning window before expiry
+    pub fn warning_threshold(&self) -> Duration {
+        self.warning_threshold
+    }
+    
+    /// Maximum idle time since last use, if limited
+    pub fn max_idle(&self) -> Option<Duration> {
+        self.max_idle
+    }
+    
+    /// Time at which a key created at `creation_time` expires
+    pub fn expires_at(&self, creation_time: DateTime<Utc>) -> DateTime<Utc> {
+        creation_time + chrono::Duration::from_std(self.expiration_duration).unwrap_or_default()
+    }
+    
+    /// Time at which a key created at `creation_time` enters its warning window
+    pub fn warning_at(&self, creation_time: DateTime<Utc>) -> DateTime<Utc> {
+        self.expires_at(creation_time) - chrono::Duration::from_std(self.warning_threshold).unwrap_or_default()
+    }
+    
+    /// Whether keys under this policy are rotated automatically by maintenance
+    pub fn auto_rotation(&self) -> bool {
+        self.auto_rotation
+    }
+    
     /// Check if key is expired based on creation time
     pub fn is_expired(&self, creation_time: DateTime<Utc>) -> bool {
         let expiration_time = creation_time + chrono::Duration::from_std(self.expiration_duration).unwrap_or_default();
-        Utc::now() > expiration_time
+        trusted_now() > expiration_time
     }
     
     /// Check if key is approaching expiration
@@ -149,7 +220,17 @@ impl ExpirationPolicy {
         let warning_time = creation_time 
             + chrono::Duration::from_std(self.expiration_duration).unwrap_or_default()
             - chrono::Duration::from_std(self.warning_threshold).unwrap_or_default();
-        Utc::now() > warning_time
+        trusted_now() > warning_time
+    }
+    
+    /// Time at which a key last used at `last_used` expires for idleness
+    pub fn idle_expires_at(&self, last_used: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.max_idle.map(|idle| last_used + chrono::Duration::from_std(idle).unwrap_or_default())
+    }
+    
+    /// Check if a key last used at `last_used` has been idle too long
+    pub fn is_idle(&self, last_used: DateTime<Utc>) -> bool {
+        self.idle_expires_at(last_used).is_some_and(|at| trusted_now() > at)
     }
     
     /// Default expiration policy for key type
@@ -164,6 +245,43 @@ impl ExpirationPolicy {
     }
 }
 
+/// Brute-force protection for ignition key unlock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnlockPolicy {
+    /// Consecutive failures after which the key is locked out
+    pub max_failed_attempts: u64,
+    /// Delay after the first failure, doubled for each further failure
+    pub base_delay: Duration,
+    /// Upper bound for the backoff delay
+    pub max_delay: Duration,
+}
+
+impl Default for UnlockPolicy {
+    fn default() -> Self {
+        Self {
+            max_failed_attempts: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(300),
+        }
+    }
+}
+
+impl UnlockPolicy {
+    /// Create new unlock policy
+    pub fn new(max_failed_attempts: u64, base_delay: Duration, max_delay: Duration) -> Self {
+        Self { max_failed_attempts, base_delay, max_delay }
+    }
+    
+    /// Backoff required after `failures` consecutive failed attempts
+    pub fn backoff(&self, failures: u64) -> Duration {
+        if failures == 0 {
+            return Duration::ZERO;
+        }
+        let factor = 1u32.checked_shl((failures - 1).min(31) as u32).unwrap_or(u32::MAX);
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
 /// Ignition key with passphrase protection
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IgnitionKey {
@@ -173,6 +291,9 @@ pub struct IgnitionKey {
     authority_chain: Vec<KeyFingerprint>,
     creation_timestamp: DateTime<Utc>,
     expiration_policy: Option<ExpirationPolicy>,
+    /// Kept in the `UnlockLedger`, never in the key file
+    #[serde(skip)]
+    unlock_policy: UnlockPolicy,
     metadata: IgnitionKeyMetadata,
 }
 
@@ -183,7 +304,14 @@ pub struct IgnitionKeyMetadata {
     pub creator: String,
     pub last_unlock: Option<DateTime<Utc>>,
     pub unlock_count: u64,
+    /// Consecutive failed unlocks since the last success or reset (ledger only)
+    #[serde(skip)]
     pub failed_unlock_attempts: u64,
+    #[serde(skip)]
+    pub last_failed_unlock: Option<DateTime<Utc>>,
+    /// Set when the failure threshold was reached; cleared only by an authority reset
+    #[serde(skip)]
+    pub locked_out_at: Option<DateTime<Utc>>,
 }
 
 impl Default for IgnitionKeyMetadata {
@@ -195,6 +323,8 @@ impl Default for IgnitionKeyMetadata {
             last_unlock: None,
             unlock_count: 0,
             failed_unlock_attempts: 0,
+            last_failed_unlock: None,
+            locked_out_at: None,
         }
     }
 }
@@ -247,12 +377,105 @@ impl IgnitionKey {
             authority_chain,
             creation_timestamp: Utc::now(),
             expiration_policy: ExpirationPolicy::default_for_type(key_type),
+            unlock_policy: UnlockPolicy::default(),
             metadata,
         })
     }
     
+    /// Replace the expiration policy chosen for the key type
+    pub fn with_expiration_policy(mut self, policy: Option<ExpirationPolicy>) -> Self {
+        self.expiration_policy = policy;
+        self
+    }
+    
+    /// Expiration policy of this key, if it expires
+    pub fn expiration_policy(&self) -> Option<&ExpirationPolicy> {
+        self.expiration_policy.as_ref()
+    }
+    
+    /// Replace the brute-force protection policy
+    pub fn with_unlock_policy(mut self, policy: UnlockPolicy) -> Self {
+        self.unlock_policy = policy;
+        self
+    }
+    
+    /// Load an ignition key saved with `save`
+    pub fn load(path: &Path) -> AgeResult<Self> {
+        let data = fs::read(path).map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?;
+        serde_json::from_slice(&data).map_err(|e| AgeError::InvalidOperation {
+            operation: "load_ignition_key".to_string(),
+            reason: format!("{}: {}", path.display(), e),
+        })
+    }
+    
+    /// Atomically write the key owner-only; unlock counters stay in the `UnlockLedger`
+    pub fn save(&self, path: &Path) -> AgeResult<()> {
+        let data = serde_json::to_vec_pretty(self).map_err(|e| AgeError::InvalidOperation {
+            operation: "save_ignition_key".to_string(),
+            reason: e.to_string(),
+        })?;
+        write_atomic(path, &data)
+    }
+    
+    /// Unlock a key stored on disk
+    ///
+    /// The failure counter comes from `ledger` and is written back whatever the outcome;
+    /// the key file itself only records the successful unlock.
+    pub fn unlock_file(path: &Path, ledger: &UnlockLedger, passphrase: &str, audit: &AuditLogger) -> AgeResult<KeyMaterial> {
+        let mut key = Self::load(path)?;
+        ledger.apply(&mut key)?;
+        let result = key.unlock(passphrase, audit);
+        ledger.record(&key)?;
+        if result.is_ok() {
+            key.save(path)?;
+        }
+        result
+    }
+    
+    /// Reset the lockout of the key stored at `path` with the enveloped authority key
+    /// file `authority_path`
+    ///
+    /// The key's issuer, as pinned in `ledger`, is looked up beside the authority and the
+    /// key and verified up to a root in `anchors`; the authority must appear in that
+    /// lineage.
+    pub fn reset_lockout_file(path: &Path, ledger: &UnlockLedger, authority_path: &Path, anchors: &TrustAnchors, audit: &AuditLogger) -> AgeResult<()> {
+        let mut key = Self::load(path)?;
+        ledger.apply(&mut key)?;
+        let authority = AuthorityKey::open(authority_path)?;
+        let dirs = [trust::parent_dir(authority_path), trust::parent_dir(path)];
+        let issuer_lineage = match key.authority_chain.first() {
+            Some(issuer) => anchors.lineage_of(issuer.hex(), &dirs)?,
+            None => Vec::new(),
+        };
+        key.reset_lockout(&authority, &issuer_lineage, audit)?;
+        ledger.record(&key)
+    }
+    
     /// Unlock ignition key with passphrase
-    pub fn unlock(&mut self, passphrase: &str) -> AgeResult<KeyMaterial> {
+    ///
+    /// Refused without checking the passphrase while the key is locked out or still
+    /// inside the backoff window of its last failure.
+    pub fn unlock(&mut self, passphrase: &str, audit: &AuditLogger) -> AgeResult<KeyMaterial> {
+        if let Some(locked_at) = self.metadata.locked_out_at {
+            audit.log_warning(&format!("IGNITION_UNLOCK_REFUSED {} locked out since {}",
+                self.metadata.name, locked_at.to_rfc3339()))?;
+            return Err(AgeError::SecurityValidationFailed {
+                validation_type: "ignition_lockout".to_string(),
+                details: format!("Ignition key '{}' is locked out after {} failed attempts; an ignition-level or higher authority must reset it",
+                    self.metadata.name, self.metadata.failed_unlock_attempts),
+            });
+        }
+        
+        if let Some(remaining) = self.backoff_remaining() {
+            audit.log_warning(&format!("IGNITION_UNLOCK_THROTTLED {} retry in {}s",
+                self.metadata.name, remaining.as_secs().max(1)))?;
+            return Err(AgeError::SecurityValidationFailed {
+                validation_type: "ignition_backoff".to_string(),
+                details: format!("Too many failed attempts for '{}'; retry in {}s",
+                    self.metadata.name, remaining.as_secs().max(1)),
+            });
+        }
+        
         // Check if key has expired
         if let Some(policy) = &self.expiration_policy {
             if policy.is_expired(self.creation_timestamp) {
@@ -265,7 +488,7 @@ impl IgnitionKey {
         
         // Verify passphrase
         if !self.passphrase_hash.verify(passphrase)? {
-            self.metadata.failed_unlock_attempts += 1;
+            self.record_failed_unlock(audit)?;
             return Err(AgeError::InvalidOperation {
                 operation: "unlock_ignition_key".to_string(),
                 reason: "Invalid passphrase".to_string(),
@@ -279,14 +502,106 @@ impl IgnitionKey {
         // Update metadata
         self.metadata.last_unlock = Some(Utc::now());
         self.metadata.unlock_count += 1;
+        self.metadata.failed_unlock_attempts = 0;
+        self.metadata.last_failed_unlock = None;
         
         Ok(key_material)
     }
     
+    /// Count a failed unlock, locking the key out once the threshold is reached
+    fn record_failed_unlock(&mut self, audit: &AuditLogger) -> AgeResult<()> {
+        let now = Utc::now();
+        self.metadata.failed_unlock_attempts += 1;
+        self.metadata.last_failed_unlock = Some(now);
+        audit.log_warning(&format!("IGNITION_UNLOCK_FAILED {} attempt {}/{}",
+            self.metadata.name, self.metadata.failed_unlock_attempts, self.unlock_policy.max_failed_attempts))?;
+        
+        if self.metadata.failed_unlock_attempts >= self.unlock_policy.max_failed_attempts {
+            self.metadata.locked_out_at = Some(now);
+            audit.log_error(&format!("IGNITION_LOCKOUT {} after {} failed attempts",
+                self.metadata.name, self.metadata.failed_unlock_attempts))?;
+        }
+        Ok(())
+    }
+    
+    /// Time left before another unlock attempt is accepted
+    pub fn backoff_remaining(&self) -> Option<Duration> {
+        let last_failure = self.metadata.last_failed_unlock?;
+        let delay = self.unlock_policy.backoff(self.metadata.failed_unlock_attempts);
+        let retry_at = last_failure + chrono::Duration::from_std(delay).unwrap_or_default();
+        (retry_at - Utc::now()).to_std().ok().filter(|remaining| !remaining.is_zero())
+    }
+    
+    /// Check if the key is locked out after too many failed unlocks
+    pub fn is_locked_out(&self) -> bool {
+        self.metadata.locked_out_at.is_some()
+    }
+    
+    /// Clear the failure counter and any lockout
+    ///
+    /// The resetting authority must be ignition level or higher and rank above this
+    /// key's own type, so a D key is reset by its I key (or above) and never by itself.
+    /// It must be an ancestor of this key: `issuer_lineage` is the verified lineage of
+    /// the key's issuer (issuer first, see `TrustAnchors::verify`), and the authority
+    /// must be one of its keys. It must also hold its secret: the reset is signed with it
+    /// and checked against the authority's public recipient, so a key built from public
+    /// bytes cannot reset.
+    pub fn reset_lockout(&mut self, authority: &AuthorityKey, issuer_lineage: &[KeyEnvelope], audit: &AuditLogger) -> AgeResult<()> {
+        let required = self.key_type.parent_type().unwrap_or(self.key_type).authority_level()
+            .max(KeyType::Ignition.authority_level());
+        if authority.key_type().authority_level() < required {
+            audit.log_warning(&format!("IGNITION_RESET_DENIED {} by {} key {}",
+                self.metadata.name, authority.key_type(), authority.fingerprint().short()))?;
+            return Err(AgeError::SecurityValidationFailed {
+                validation_type: "ignition_reset_authority".to_string(),
+                details: format!("A {} key cannot reset the {} key '{}'",
+                    authority.key_type(), self.key_type, self.metadata.name),
+            });
+        }
+        
+        let issued_by = issuer_lineage.first()
+            .is_some_and(|issuer| self.authority_chain.iter().any(|fingerprint| fingerprint.hex() == issuer.fingerprint));
+        let recipient = signing::key_recipient(authority).ok();
+        let ancestor = issued_by && issuer_lineage.iter()
+            .any(|envelope| envelope.key_type == authority.key_type() && Some(&envelope.recipient) == recipient.as_ref());
+        if !ancestor {
+            audit.log_warning(&format!("IGNITION_RESET_DENIED {} by {} key {}: not an ancestor",
+                self.metadata.name, authority.key_type(), authority.fingerprint().short()))?;
+            return Err(AgeError::SecurityValidationFailed {
+                validation_type: "ignition_reset_authority".to_string(),
+                details: format!("The {} key {} is not a verified ancestor of '{}'",
+                    authority.key_type(), authority.fingerprint().short(), self.metadata.name),
+            });
+        }
+        
+        let challenge = format!("padlock-ignition-reset-v1\n{}\n{}\n{}\n",
+            self.ledger_id(), self.metadata.failed_unlock_attempts, Utc::now().timestamp_millis());
+        let signature = match signing::prove_possession(authority, challenge.as_bytes()) {
+            Ok(signature) => signature,
+            Err(e) => {
+                audit.log_warning(&format!("IGNITION_RESET_DENIED {} by {} key {}: {}",
+                    self.metadata.name, authority.key_type(), authority.fingerprint().short(), e))?;
+                return Err(AgeError::SecurityValidationFailed {
+                    validation_type: "ignition_reset_authority".to_string(),
+                    details: format!("Resetting '{}' requires the {} key's secret: {}",
+                        self.metadata.name, authority.key_type(), e),
+                });
+            }
+        };
+        
+        audit.log_info(&format!("IGNITION_RESET {} by {} key {} ({} failed attempts cleared, signature {})",
+            self.metadata.name, authority.key_type(), authority.fingerprint().short(),
+            self.metadata.failed_unlock_attempts, &hex::encode(signature)[..16]))?;
+        self.metadata.failed_unlock_attempts = 0;
+        self.metadata.last_failed_unlock = None;
+        self.metadata.locked_out_at = None;
+        Ok(())
+    }
+    
     /// Change passphrase for ignition key
-    pub fn change_passphrase(&mut self, old_passphrase: &str, new_passphrase: &str) -> AgeResult<()> {
+    pub fn change_passphrase(&mut self, old_passphrase: &str, new_passphrase: &str, audit: &AuditLogger) -> AgeResult<()> {
         // Verify old passphrase and unlock key
-        let key_material = self.unlock(old_passphrase)?;
+        let key_material = self.unlock(old_passphrase, audit)?;
         
         // Validate new passphrase strength
         validate_passphrase_strength(new_passphrase)?;
@@ -299,10 +614,11 @@ impl IgnitionKey {
         Ok(())
     }
     
-    /// Check if ignition key is expired
+    /// Check if ignition key is expired, by age or by idle time since its last unlock
     pub fn is_expired(&self) -> bool {
         if let Some(policy) = &self.expiration_policy {
             policy.is_expired(self.creation_timestamp)
+                || policy.is_idle(self.metadata.last_unlock.unwrap_or(self.creation_timestamp))
         } else {
             false
         }
@@ -342,6 +658,15 @@ impl IgnitionKey {
         self.metadata = metadata;
     }
     
+    /// Ledger id: a digest of the wrapped key, which the holder cannot change without
+    /// breaking the key
+    fn ledger_id(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(&self.wrapped_key.ciphertext);
+        hasher.update(self.key_type.to_string().as_bytes());
+        hex::encode(hasher.finalize())[..32].to_string()
+    }
+    
     /// Get key fingerprint derived from wrapped key material
     pub fn fingerprint(&self) -> AgeResult<KeyFingerprint> {
         // Generate fingerprint from the encrypted key material
@@ -358,6 +683,91 @@ impl IgnitionKey {
     }
 }
 
+/// Unlock counters and policy of one ignition key, as stored by `UnlockLedger`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct UnlockRecord {
+    failed_unlock_attempts: u64,
+    last_failed_unlock: Option<DateTime<Utc>>,
+    locked_out_at: Option<DateTime<Utc>>,
+    policy: Option<UnlockPolicy>,
+    /// Issuing authority seen when the key was first recorded
+    #[serde(default)]
+    authority_chain: Option<Vec<KeyFingerprint>>,
+}
+
+/// Failed-unlock counters kept outside the key file
+///
+/// Records live under `<state dir>/ignition/`, the machine-local directory that also
+/// holds the clock signing key. It must not be writable by key holders.
+pub struct UnlockLedger {
+    dir: PathBuf,
+}
+
+impl Default for UnlockLedger {
+    fn default() -> Self {
+        Self::new(&default_state_dir())
+    }
+}
+
+impl UnlockLedger {
+    /// Ledger under the padlock state directory `state_dir`
+    pub fn new(state_dir: &Path) -> Self {
+        Self { dir: state_dir.join("ignition") }
+    }
+    
+    fn record_path(&self, key: &IgnitionKey) -> PathBuf {
+        self.dir.join(format!("{}.json", key.ledger_id()))
+    }
+    
+    fn read(&self, key: &IgnitionKey) -> AgeResult<Option<UnlockRecord>> {
+        let path = self.record_path(key);
+        match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).map(Some).map_err(|e| AgeError::InvalidOperation {
+                operation: "load_unlock_ledger".to_string(),
+                reason: format!("{}: {}", path.display(), e),
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(AgeError::file_error("read", path, e)),
+        }
+    }
+    
+    /// Load the stored counters, policy and issuing authority into `key`
+    ///
+    /// A key without a record keeps the policy and issuer it was given, which the next
+    /// `record` pins in the ledger.
+    pub fn apply(&self, key: &mut IgnitionKey) -> AgeResult<()> {
+        let Some(record) = self.read(key)? else {
+            return Ok(());
+        };
+        key.metadata.failed_unlock_attempts = record.failed_unlock_attempts;
+        key.metadata.last_failed_unlock = record.last_failed_unlock;
+        key.metadata.locked_out_at = record.locked_out_at;
+        if let Some(policy) = record.policy {
+            key.unlock_policy = policy;
+        }
+        if let Some(authority_chain) = record.authority_chain {
+            key.authority_chain = authority_chain;
+        }
+        Ok(())
+    }
+    
+    /// Store the key's current counters and policy
+    pub fn record(&self, key: &IgnitionKey) -> AgeResult<()> {
+        let record = UnlockRecord {
+            failed_unlock_attempts: key.metadata.failed_unlock_attempts,
+            last_failed_unlock: key.metadata.last_failed_unlock,
+            locked_out_at: key.metadata.locked_out_at,
+            policy: Some(key.unlock_policy.clone()),
+            authority_chain: Some(key.authority_chain.clone()),
+        };
+        let data = serde_json::to_vec_pretty(&record).map_err(|e| AgeError::InvalidOperation {
+            operation: "save_unlock_ledger".to_string(),
+            reason: e.to_string(),
+        })?;
+        write_atomic(&self.record_path(key), &data)
+    }
+}
+
 /// Validate passphrase strength according to security requirements
 pub fn validate_passphrase_strength(passphrase: &str) -> AgeResult<()> {
     // Minimum length requirement
@@ -501,4 +911,123 @@ mod tests {
         assert_eq!(key.key_type(), KeyType::Ignition);
         assert_eq!(key.metadata().name, "test-key");
     }
-}
\ No newline at end of file
+    
+    fn distro_key(policy: UnlockPolicy, issuer: Option<&AuthorityKey>) -> IgnitionKey {
+        let key_material = KeyMaterial::new(
+            b"distro_public_key".to_vec(),
+            Some(b"distro_private_key".to_vec()),
+            KeyFormat::Age,
+        );
+        IgnitionKey::create(&key_material, KeyType::Distro, "DistroPass123!", issuer, Some("agent".to_string()))
+            .unwrap()
+            .with_unlock_policy(policy)
+    }
+    
+    fn authority(key_type: KeyType) -> AuthorityKey {
+        let hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").unwrap();
+        let identity = bech32::encode_upper::<bech32::Bech32>(hrp, &[key_type.authority_level(); 32]).unwrap();
+        let material = KeyMaterial::new(identity.as_bytes().to_vec(), Some(identity.into_bytes()), KeyFormat::Age);
+        AuthorityKey::new(material, key_type, None, None).unwrap()
+    }
+    
+    /// Write an enveloped key file for `seed` under `dir`, issued by `parent`
+    fn issue(dir: &Path, seed: u8, key_type: KeyType, parent: Option<&AuthorityKey>) -> (AuthorityKey, PathBuf) {
+        let hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").unwrap();
+        let identity = bech32::encode_upper::<bech32::Bech32>(hrp, &[seed; 32]).unwrap();
+        let recipient = signing::recipient_for_secret(&signing::identity_secret(&identity).unwrap()).unwrap();
+        let mut envelope = KeyEnvelope::new(key_type, &recipient).unwrap();
+        if let Some(parent) = parent {
+            envelope = envelope.with_parent(parent).unwrap();
+        }
+        let path = dir.join(format!("{}-{}.key", key_type, seed));
+        fs::write(&path, envelope.render(&identity).as_bytes()).unwrap();
+        (AuthorityKey::from_file(&path, key_type).unwrap(), path)
+    }
+    
+    #[test]
+    fn test_unlock_backoff_and_lockout() {
+        let audit = AuditLogger::new(None).unwrap();
+        let keys = tempfile::TempDir::new().unwrap();
+        let (repo, _) = issue(keys.path(), 1, KeyType::Repo, None);
+        let (ignition, _) = issue(keys.path(), 2, KeyType::Ignition, Some(&repo));
+        let anchors = TrustAnchors::new().with_root(KeyType::Repo, &signing::key_recipient(&repo).unwrap()).unwrap();
+        let lineage = anchors.lineage_of(ignition.fingerprint().hex(), &[keys.path().to_path_buf()]).unwrap();
+        let mut key = distro_key(UnlockPolicy::new(3, Duration::ZERO, Duration::ZERO), Some(&ignition));
+        
+        for _ in 0..3 {
+            assert!(key.unlock("WrongGuess123!", &audit).is_err());
+        }
+        assert!(key.is_locked_out());
+        
+        // The right passphrase no longer helps, and a D key cannot reset itself
+        assert!(key.unlock("DistroPass123!", &audit).is_err());
+        assert!(key.reset_lockout(&authority(KeyType::Distro), &lineage, &audit).is_err());
+        
+        // An I key known only by its public recipient cannot sign the reset
+        let public_only = KeyMaterial::new(signing::key_recipient(&ignition).unwrap().into_bytes(), None, KeyFormat::Age);
+        let public_only = AuthorityKey::new(public_only, KeyType::Ignition, None, None).unwrap();
+        assert!(key.reset_lockout(&public_only, &lineage, &audit).is_err());
+        
+        // An I key that did not issue this key cannot reset it, even with a valid lineage
+        let (stranger, _) = issue(keys.path(), 3, KeyType::Ignition, Some(&repo));
+        let error = key.reset_lockout(&stranger, &lineage, &audit).unwrap_err().to_string();
+        assert!(error.contains("not a verified ancestor"));
+        let strangers = anchors.lineage_of(stranger.fingerprint().hex(), &[keys.path().to_path_buf()]).unwrap();
+        assert!(key.reset_lockout(&stranger, &strangers, &audit).is_err());
+        assert!(key.is_locked_out());
+        
+        key.reset_lockout(&ignition, &lineage, &audit).unwrap();
+        assert!(key.unlock("DistroPass123!", &audit).is_ok());
+        assert_eq!(key.metadata().failed_unlock_attempts, 0);
+        
+        // With a real delay the next attempt is throttled before the passphrase is checked
+        let mut slow = distro_key(UnlockPolicy::new(5, Duration::from_secs(60), Duration::from_secs(600)), None);
+        assert!(slow.unlock("WrongGuess123!", &audit).is_err());
+        assert!(slow.backoff_remaining().is_some());
+        assert!(slow.unlock("DistroPass123!", &audit).is_err());
+        assert_eq!(slow.metadata().failed_unlock_attempts, 1);
+        assert_eq!(slow.unlock_policy.backoff(3), Duration::from_secs(240));
+        assert_eq!(slow.unlock_policy.backoff(10), Duration::from_secs(600));
+    }
+    
+    #[test]
+    fn test_failed_attempts_persist_outside_key_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("agent.ignition");
+        let ledger = UnlockLedger::new(&dir.path().join("state"));
+        let audit = AuditLogger::new(None).unwrap();
+        let (repo, _) = issue(dir.path(), 1, KeyType::Repo, None);
+        let (ignition, ignition_path) = issue(dir.path(), 2, KeyType::Ignition, Some(&repo));
+        let (_, stranger_path) = issue(dir.path(), 3, KeyType::Ignition, Some(&repo));
+        let anchors = TrustAnchors::new().with_root(KeyType::Repo, &signing::key_recipient(&repo).unwrap()).unwrap();
+        let key = distro_key(UnlockPolicy::new(2, Duration::ZERO, Duration::ZERO), Some(&ignition));
+        key.save(&path).unwrap();
+        ledger.record(&key).unwrap();
+        let pristine = fs::read(&path).unwrap();
+        
+        assert!(IgnitionKey::unlock_file(&path, &ledger, "WrongGuess123!", &audit).is_err());
+        assert!(IgnitionKey::unlock_file(&path, &ledger, "WrongGuess123!", &audit).is_err());
+        assert!(IgnitionKey::unlock_file(&path, &ledger, "DistroPass123!", &audit).is_err());
+        
+        // Neither the counter nor the policy is in the key file, so restoring or editing
+        // it does not lift the lockout
+        assert_eq!(fs::read(&path).unwrap(), pristine);
+        assert!(!String::from_utf8_lossy(&pristine).contains("failed_unlock_attempts"));
+        let mut reloaded = IgnitionKey::load(&path).unwrap();
+        assert!(!reloaded.is_locked_out());
+        ledger.apply(&mut reloaded).unwrap();
+        assert!(reloaded.is_locked_out());
+        assert_eq!(reloaded.metadata().failed_unlock_attempts, 2);
+        assert!(IgnitionKey::unlock_file(&path, &ledger, "DistroPass123!", &audit).is_err());
+        
+        // Naming another issuer in the key file does not let that key reset it: the
+        // ledger pinned the issuer when the key was first recorded
+        let mut edited = IgnitionKey::load(&path).unwrap();
+        edited.authority_chain = vec![AuthorityKey::open(&stranger_path).unwrap().fingerprint().clone()];
+        edited.save(&path).unwrap();
+        assert!(IgnitionKey::reset_lockout_file(&path, &ledger, &stranger_path, &anchors, &audit).is_err());
+        
+        IgnitionKey::reset_lockout_file(&path, &ledger, &ignition_path, &anchors, &audit).unwrap();
+        assert!(IgnitionKey::unlock_file(&path, &ledger, "DistroPass123!", &audit).is_ok());
+    }
+}
@@ -0,0 +1,320 @@
+//! Key Lineage - Authority between two key files, reconstructed from their envelopes
+//!
+//! Backs `padlock key authority` and `padlock key subject`. Starting at the lower key,
+//! the resolver follows each envelope's `parent` fingerprint to the next key file,
+//! looking beside the two given files and in any extra search directories, until it
+//! meets the upper key. Every hop must carry a parent signature that verifies against
+//! the parent's recipient; the hops are then loaded into an `AuthorityChain` and
+//! proven with `AuthorityProof` (authority) or `SubjectProof` (subject), so revoked
+//! keys fail as they do everywhere else. The upper key's own headers are believed only
+//! once it verifies up to a trusted root (see `trust`).
+//!
+//! Security Guardian: Edgar - Lineage proven hop by hop, never assumed
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::encryption::age_automation::error::{AgeError, AgeResult};
+use super::chain::{AuthorityChain, AuthorityKey, KeyType};
+use super::envelope::KeyEnvelope;
+use super::revocation::RevocationList;
+use super::trust::{self, TrustAnchors};
+use super::validation::AuthorityValidationEngine;
+
+/// Relationship asked about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    /// First key has authority over the second
+    Authority,
+    /// First key is subject to the second
+    Subject,
+}
+
+/// One key on the path between the two files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineageLink {
+    pub key_type: KeyType,
+    pub fingerprint: String,
+    pub path: PathBuf,
+}
+
+impl fmt::Display for LineageLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<8} {}  {}", self.key_type.to_string(), &self.fingerprint[..self.fingerprint.len().min(16)], self.path.display())
+    }
+}
+
+/// Outcome of a lineage check
+#[derive(Debug, Clone)]
+pub struct LineageCheck {
+    pub relation: Relation,
+    /// Keys from the upper key down to the lower one; partial when the check failed
+    pub path: Vec<LineageLink>,
+    /// Why the relationship does not hold; None when it does
+    pub failure: Option<String>,
+}
+
+impl LineageCheck {
+    pub fn is_valid(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    /// `AUTHORITY_VALID` or `AUTHORITY_INVALID`
+    pub fn token(&self) -> &'static str {
+        if self.is_valid() { "AUTHORITY_VALID" } else { "AUTHORITY_INVALID" }
+    }
+
+    /// Lineage path, one key per line, upper key first
+    pub fn explain(&self) -> String {
+        let mut text = String::new();
+        for (depth, link) in self.path.iter().enumerate() {
+            let marker = if depth == 0 { String::new() } else { format!("{}↳ ", "  ".repeat(depth - 1)) };
+            text.push_str(&format!("  {}{}\n", marker, link));
+        }
+        if let Some(failure) = &self.failure {
+            text.push_str(&format!("  ✗ {}\n", failure));
+        }
+        text
+    }
+}
+
+/// Finds and proves the lineage between two enveloped key files
+#[derive(Debug, Clone, Default)]
+pub struct LineageResolver {
+    search_dirs: Vec<PathBuf>,
+    revocations: RevocationList,
+    anchors: TrustAnchors,
+}
+
+impl LineageResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also look for intermediate key files in `dir`
+    pub fn with_search_dir(mut self, dir: PathBuf) -> Self {
+        self.search_dirs.push(dir);
+        self
+    }
+
+    /// Verify the upper key against the roots in `anchors`
+    pub fn with_trust_anchors(mut self, anchors: TrustAnchors) -> Self {
+        self.anchors = anchors;
+        self
+    }
+
+    /// Refuse keys revoked in `revocations`
+    pub fn with_revocations(mut self, revocations: RevocationList) -> Self {
+        self.revocations = revocations;
+        self
+    }
+
+    /// Check `relation` between `first` and `second`
+    ///
+    /// Errors mean the question could not be answered (unreadable or plain key files);
+    /// a relationship that does not hold is an `Ok` check with a failure.
+    pub fn check(&self, relation: Relation, first: &Path, second: &Path) -> AgeResult<LineageCheck> {
+        let (upper, lower) = match relation {
+            Relation::Authority => (first, second),
+            Relation::Subject => (second, first),
+        };
+        let upper_envelope = read_envelope(upper)?;
+        let lower_envelope = read_envelope(lower)?;
+        let mut path = vec![link(&lower_envelope, lower)];
+        let invalid = |path: Vec<LineageLink>, failure: String| {
+            Ok(LineageCheck { relation, path: path.into_iter().rev().collect(), failure: Some(failure) })
+        };
+
+        if upper_envelope.key_type.authority_level() <= lower_envelope.key_type.authority_level() {
+            return invalid(path, format!("A {} key is not above a {} key", upper_envelope.key_type, lower_envelope.key_type));
+        }
+        let anchors = self.search_dirs.iter().cloned().fold(self.anchors.clone(), TrustAnchors::with_search_dir);
+        if let Err(error) = anchors.verify(&upper_envelope, upper) {
+            return invalid(path, error.to_string());
+        }
+
+        // Walk parent fingerprints up from the lower key, checking each parent signature
+        let candidates = self.candidates(&[upper, lower]);
+        let mut hops = vec![(lower_envelope.clone(), lower.to_path_buf())];
+        let mut current = lower_envelope.clone();
+        while current.fingerprint != upper_envelope.fingerprint {
+            let Some(parent_fingerprint) = current.parent.clone() else {
+                return invalid(path, format!("{} key {} has no parent, and {} key {} is not above it",
+                    current.key_type, short(&current.fingerprint), upper_envelope.key_type, short(&upper_envelope.fingerprint)));
+            };
+            let parent = if parent_fingerprint == upper_envelope.fingerprint {
+                Some((upper_envelope.clone(), upper.to_path_buf()))
+            } else {
+                candidates.get(&parent_fingerprint).cloned()
+            };
+            let Some((parent, parent_path)) = parent else {
+                return invalid(path, format!("Parent {} of {} key {} is not among the searched key files",
+                    short(&parent_fingerprint), current.key_type, short(&current.fingerprint)));
+            };
+            if current.key_type.parent_type() != Some(parent.key_type) {
+                return invalid(path, format!("{} key {} names a {} key as its parent",
+                    current.key_type, short(&current.fingerprint), parent.key_type));
+            }
+            if parent.key_type.authority_level() >= upper_envelope.key_type.authority_level() && parent.fingerprint != upper_envelope.fingerprint {
+                return invalid(path, format!("Lineage of {} key {} reaches {} key {} without meeting {} key {}",
+                    lower_envelope.key_type, short(&lower_envelope.fingerprint), parent.key_type, short(&parent.fingerprint),
+                    upper_envelope.key_type, short(&upper_envelope.fingerprint)));
+            }
+            if let Err(error) = current.verify_parent(&parent.recipient) {
+                return invalid(path, error.to_string());
+            }
+            path.push(link(&parent, &parent_path));
+            hops.push((parent.clone(), parent_path));
+            current = parent;
+        }
+
+        // Prove each hop through the validation engine
+        let mut chain = AuthorityChain::new();
+        let mut keys = Vec::new();
+        for (envelope, path) in &hops {
+            let key = AuthorityKey::from_file(path, envelope.key_type)?;
+            keys.push(key.fingerprint().clone());
+            chain.add_key(key)?;
+        }
+        for pair in keys.windows(2) {
+            chain.add_authority_relationship(&pair[1], &pair[0])?;
+        }
+        let mut engine = AuthorityValidationEngine::new(chain).with_revocations(self.revocations.clone());
+        for pair in keys.windows(2) {
+            let (child, parent) = (&pair[0], &pair[1]);
+            let proven = match relation {
+                Relation::Authority => engine.test_authority(parent, child),
+                Relation::Subject => engine.test_subject(child, parent),
+            };
+            match proven {
+                Ok(true) => {}
+                Ok(false) => return invalid(path, format!("Proof for {} over {} did not verify", short(parent.hex()), short(child.hex()))),
+                Err(error) => return invalid(path, error.to_string()),
+            }
+        }
+
+        Ok(LineageCheck { relation, path: path.into_iter().rev().collect(), failure: None })
+    }
+
+    /// Enveloped key files beside the given files and in the search directories
+    fn candidates(&self, files: &[&Path]) -> HashMap<String, (KeyEnvelope, PathBuf)> {
+        let mut dirs: Vec<PathBuf> = files.iter().map(|file| trust::parent_dir(file)).collect();
+        dirs.extend(self.search_dirs.iter().cloned());
+        trust::key_candidates(&dirs)
+    }
+}
+
+fn read_envelope(path: &Path) -> AgeResult<KeyEnvelope> {
+    KeyEnvelope::read(path)?.ok_or_else(|| AgeError::InvalidOperation {
+        operation: "key_lineage".to_string(),
+        reason: format!("{} is a plain age key; lineage needs padlock key envelopes", path.display()),
+    })
+}
+
+fn link(envelope: &KeyEnvelope, path: &Path) -> LineageLink {
+    LineageLink { key_type: envelope.key_type, fingerprint: envelope.fingerprint.clone(), path: path.to_path_buf() }
+}
+
+fn short(fingerprint_hex: &str) -> &str {
+    &fingerprint_hex[..fingerprint_hex.len().min(8)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::signing;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write_key(dir: &Path, seed: u8, key_type: KeyType, parent: Option<&AuthorityKey>) -> AuthorityKey {
+        let hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").unwrap();
+        let identity = bech32::encode_upper::<bech32::Bech32>(hrp, &[seed; 32]).unwrap();
+        let recipient = signing::recipient_for_secret(&signing::identity_secret(&identity).unwrap()).unwrap();
+        let mut envelope = KeyEnvelope::new(key_type, &recipient).unwrap();
+        if let Some(parent) = parent {
+            envelope = envelope.with_parent(parent).unwrap();
+        }
+        let path = dir.join(format!("auth-{}.key", key_type));
+        fs::write(&path, envelope.render(&identity).as_bytes()).unwrap();
+        AuthorityKey::from_file(&path, key_type).unwrap()
+    }
+
+    /// Resolver trusting the skull key of `keys`
+    fn resolver(keys: &[AuthorityKey]) -> LineageResolver {
+        let anchors = TrustAnchors::new().with_root(KeyType::Skull, &signing::key_recipient(&keys[0]).unwrap()).unwrap();
+        LineageResolver::new().with_trust_anchors(anchors)
+    }
+
+    fn chain(dir: &Path) -> Vec<AuthorityKey> {
+        let mut keys: Vec<AuthorityKey> = Vec::new();
+        for (seed, key_type) in [KeyType::Skull, KeyType::Master, KeyType::Repo, KeyType::Ignition, KeyType::Distro].into_iter().enumerate() {
+            let key = write_key(dir, seed as u8 + 1, key_type, keys.last());
+            keys.push(key);
+        }
+        keys
+    }
+
+    #[test]
+    fn test_lineage_reconstructed_across_levels() {
+        let dir = TempDir::new().unwrap();
+        let keys = chain(dir.path());
+        let skull = dir.path().join("auth-skull.key");
+        let repo = dir.path().join("auth-repo.key");
+        let distro = dir.path().join("auth-distro.key");
+
+        let check = resolver(&keys).check(Relation::Authority, &skull, &distro).unwrap();
+        assert_eq!(check.token(), "AUTHORITY_VALID");
+        let types: Vec<KeyType> = check.path.iter().map(|link| link.key_type).collect();
+        assert_eq!(types, vec![KeyType::Skull, KeyType::Master, KeyType::Repo, KeyType::Ignition, KeyType::Distro]);
+
+        assert!(resolver(&keys).check(Relation::Subject, &distro, &repo).unwrap().is_valid());
+        assert_eq!(resolver(&keys).check(Relation::Authority, &distro, &repo).unwrap().token(), "AUTHORITY_INVALID");
+        assert!(!resolver(&keys).check(Relation::Subject, &repo, &distro).unwrap().is_valid());
+
+        // Without a trusted root the upper key's headers are not believed
+        assert!(!LineageResolver::new().check(Relation::Authority, &skull, &distro).unwrap().is_valid());
+
+        // An upper key retyped in its header breaks its own parent signature
+        let retyped = dir.path().join("retyped.key");
+        fs::write(&retyped, fs::read_to_string(&repo).unwrap().replace("# type: repo", "# type: master")).unwrap();
+        let check = resolver(&keys).check(Relation::Authority, &retyped, &distro).unwrap();
+        assert!(!check.is_valid());
+
+        // Intermediate keys elsewhere are found through a search directory
+        let split = TempDir::new().unwrap();
+        fs::rename(dir.path().join("auth-ignition.key"), split.path().join("auth-ignition.key")).unwrap();
+        assert!(!resolver(&keys).check(Relation::Authority, &repo, &distro).unwrap().is_valid());
+        let split_resolver = resolver(&keys).with_search_dir(split.path().to_path_buf());
+        assert!(split_resolver.check(Relation::Authority, &repo, &distro).unwrap().is_valid());
+    }
+
+    #[test]
+    fn test_forged_revoked_or_plain_keys_fail() {
+        let dir = TempDir::new().unwrap();
+        let keys = chain(dir.path());
+        let ignition = dir.path().join("auth-ignition.key");
+        let distro = dir.path().join("auth-distro.key");
+
+        // A distro key claiming the ignition key as parent without its signature
+        let other = TempDir::new().unwrap();
+        let impostor = write_key(other.path(), 9, KeyType::Distro, None);
+        let forged = fs::read_to_string(impostor.key_path().unwrap()).unwrap()
+            .replace("# created:", &format!("# parent: {}\n# created:", keys[3].fingerprint().hex()));
+        let forged_path = other.path().join("forged.key");
+        fs::write(&forged_path, forged).unwrap();
+        let check = resolver(&keys).check(Relation::Authority, &ignition, &forged_path).unwrap();
+        assert!(!check.is_valid());
+        assert!(check.explain().contains("parent signature"));
+
+        let mut revocations = RevocationList::default();
+        revocations.revoke(&keys[4], &keys[3], "lost").unwrap();
+        assert!(resolver(&keys).check(Relation::Authority, &ignition, &distro).unwrap().is_valid());
+        let revoking = resolver(&keys).with_revocations(revocations);
+        assert!(!revoking.check(Relation::Authority, &ignition, &distro).unwrap().is_valid());
+
+        let plain = dir.path().join("plain.key");
+        fs::write(&plain, "# created: 2026-01-01T00:00:00Z\nAGE-SECRET-KEY-1X\n").unwrap();
+        assert!(resolver(&keys).check(Relation::Authority, &ignition, &plain).is_err());
+    }
+}
@@ -0,0 +1,585 @@
+//! Key Maintenance - Expiration scanning and automatic rotation
+//!
+//! Scans the persisted chain (`{name}-{type}.key` files in a keys directory) against
+//! each key's effective `ExpirationPolicy` (see `expiration` for the layers). Keys inside
+//! their warning window or past expiry are reported and logged; when the policy enables
+//! auto-rotation the key is replaced with a freshly generated one and every level below
+//! it is re-issued, since their authority derives from the rotated key. Replaced key
+//! files are kept under `retired/`.
+//!
+//! Enveloped key files are judged by their `expires` and `policy` headers only after the
+//! envelope verifies (see `trust`): the chain's top key in the keys directory is the
+//! anchor, trusted at the level its file name gives, and every key below must carry a
+//! valid parent signature.
+//!
+//! Runs are non-interactive and serialized by a lock file, key files are replaced
+//! atomically, and the time the next run is due is recorded in `.padlock-maintain.json`,
+//! so the runner is safe to drive from cron or a systemd timer.
+//!
+//! Security Guardian: Edgar - Keys never outlive their policy unnoticed
+
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::encryption::age_automation::config::ClockRollbackPolicy;
+use crate::encryption::age_automation::error::{AgeError, AgeResult};
+use crate::encryption::age_automation::lifecycle::{trusted_now, HighWaterMark};
+use crate::encryption::age_automation::lifecycle::clock::{default_state_dir, write_atomic, FileLock};
+use crate::encryption::age_automation::security::AuditLogger;
+use crate::encryption::age_automation::secret::Zeroizing;
+use super::chain::{AuthorityKey, KeyFingerprint, KeyType};
+use super::envelope::KeyEnvelope;
+use super::trust::TrustAnchors;
+use super::expiration::{self, ExpirationPolicies, PolicySource};
+use super::operations::generate_native_age_keypair;
+
+/// File recording the last run and when the next one is due
+pub const STATE_FILE: &str = ".padlock-maintain.json";
+
+/// Lock file serializing concurrent runs
+const LOCK_FILE: &str = ".padlock-maintain.lock";
+
+/// Directory, inside the keys directory, holding replaced key files
+const RETIRED_DIR: &str = "retired";
+
+/// Chain levels from the top down
+const LEVELS: [KeyType; 5] = [KeyType::Skull, KeyType::Master, KeyType::Repo, KeyType::Ignition, KeyType::Distro];
+
+/// Expiration state of a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyState {
+    Healthy,
+    Warning,
+    Expired,
+}
+
+impl fmt::Display for KeyState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyState::Healthy => write!(f, "healthy"),
+            KeyState::Warning => write!(f, "warning"),
+            KeyState::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+/// One key of the persisted chain with its expiration state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyStatus {
+    pub key_type: KeyType,
+    pub path: PathBuf,
+    pub fingerprint: String,
+    pub created: DateTime<Utc>,
+    pub warning_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub state: KeyState,
+    pub auto_rotation: bool,
+    /// Layer the key's policy came from
+    pub policy_source: PolicySource,
+}
+
+/// A key replaced during a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotatedKey {
+    pub key_type: KeyType,
+    pub path: PathBuf,
+    /// Where the replaced key file was moved; None in dry-run mode
+    pub retired: Option<PathBuf>,
+    pub old_fingerprint: String,
+    /// None in dry-run mode
+    pub new_fingerprint: Option<String>,
+    /// Re-issued because a key above it was rotated, rather than for its own policy
+    pub reissued: bool,
+}
+
+/// Outcome of a maintenance run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    pub checked_at: DateTime<Utc>,
+    pub dry_run: bool,
+    /// Key states before any rotation
+    pub keys: Vec<KeyStatus>,
+    pub rotated: Vec<RotatedKey>,
+    pub next_run_due: DateTime<Utc>,
+}
+
+impl MaintenanceReport {
+    /// Keys in their warning window or expired
+    pub fn attention(&self) -> impl Iterator<Item = &KeyStatus> {
+        self.keys.iter().filter(|key| key.state != KeyState::Healthy)
+    }
+
+    /// Expired keys that were not rotated; these need manual action
+    pub fn expired_unrotated(&self) -> Vec<&KeyStatus> {
+        self.keys.iter()
+            .filter(|key| key.state == KeyState::Expired)
+            .filter(|key| !self.rotated.iter().any(|rotated| rotated.key_type == key.key_type))
+            .collect()
+    }
+
+    /// Human-readable summary
+    pub fn to_table(&self) -> String {
+        let mut out = format!("{:<10} {:<9} {:<10} {:<25} {}\n", "TYPE", "STATE", "FINGERPRINT", "EXPIRES", "PATH");
+        for key in &self.keys {
+            let expires = key.expires_at.map(|at| at.to_rfc3339()).unwrap_or_else(|| "never".to_string());
+            out.push_str(&format!("{:<10} {:<9} {:<10} {:<25} {}\n",
+                key.key_type.to_string(), key.state.to_string(), key.fingerprint, expires, key.path.display()));
+        }
+        for rotated in &self.rotated {
+            let verb = match (self.dry_run, rotated.reissued) {
+                (true, _) => "would rotate",
+                (false, true) => "re-issued",
+                (false, false) => "rotated",
+            };
+            out.push_str(&format!("{} {} {}\n", verb, rotated.key_type, rotated.path.display()));
+        }
+        out.push_str(&format!("next run due: {}\n", self.next_run_due.to_rfc3339()));
+        out
+    }
+
+    pub fn to_json(&self) -> AgeResult<String> {
+        serde_json::to_string_pretty(self).map_err(|e| AgeError::InvalidOperation {
+            operation: "maintenance_report".to_string(),
+            reason: e.to_string(),
+        })
+    }
+}
+
+/// Persisted schedule written after each run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceState {
+    pub last_run: DateTime<Utc>,
+    pub next_run_due: DateTime<Utc>,
+}
+
+impl MaintenanceState {
+    /// Read the state of a keys directory, if a run has recorded one
+    pub fn load(keys_dir: &Path) -> Option<Self> {
+        let data = fs::read(keys_dir.join(STATE_FILE)).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// True when the recorded next run time has passed
+    pub fn is_due(&self) -> bool {
+        trusted_now() >= self.next_run_due
+    }
+}
+
+/// Scans and rotates the keys of one named chain
+pub struct MaintenanceRunner {
+    keys_dir: PathBuf,
+    name: String,
+    dry_run: bool,
+    interval: Duration,
+    clock_policy: ClockRollbackPolicy,
+    clock_state_dir: PathBuf,
+    policies: ExpirationPolicies,
+    anchors: TrustAnchors,
+    audit_logger: AuditLogger,
+}
+
+impl MaintenanceRunner {
+    /// Runner for `{keys_dir}/{name}-{type}.key`
+    pub fn new(keys_dir: PathBuf, name: impl Into<String>, audit_log_path: Option<PathBuf>) -> AgeResult<Self> {
+        Ok(Self {
+            keys_dir,
+            name: name.into(),
+            dry_run: false,
+            interval: Duration::from_secs(86400),
+            clock_policy: ClockRollbackPolicy::default(),
+            clock_state_dir: default_state_dir(),
+            policies: ExpirationPolicies::default(),
+            anchors: TrustAnchors::load()?,
+            audit_logger: AuditLogger::new(audit_log_path)?,
+        })
+    }
+
+    /// Report what would be rotated without changing anything
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Longest gap between runs when no key changes state sooner (default one day)
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Whether a wall clock behind the keys directory's high-water mark aborts the run
+    pub fn with_clock_rollback_policy(mut self, policy: ClockRollbackPolicy) -> Self {
+        self.clock_policy = policy;
+        self
+    }
+
+    /// Judge keys by these policy layers instead of the built-in defaults
+    pub fn with_policies(mut self, policies: ExpirationPolicies) -> Self {
+        self.policies = policies;
+        self
+    }
+
+    /// Directory holding the clock signing key and mark mirrors
+    pub fn with_clock_state_dir(mut self, dir: PathBuf) -> Self {
+        self.clock_state_dir = dir;
+        self
+    }
+
+    /// Trust these roots in addition to the chain's own top key
+    pub fn with_trust_anchors(mut self, anchors: TrustAnchors) -> Self {
+        self.anchors = anchors;
+        self
+    }
+
+    /// Expiration state of every key in the chain, top level first
+    pub fn scan(&self) -> AgeResult<Vec<KeyStatus>> {
+        let now = trusted_now();
+        let anchors = self.chain_anchors()?;
+        LEVELS.iter()
+            .map(|&key_type| (key_type, self.key_path(key_type)))
+            .filter(|(_, path)| path.exists())
+            .map(|(key_type, path)| key_status(key_type, &path, &self.policies, &anchors, now))
+            .collect()
+    }
+
+    /// Configured roots plus the chain's top key, trusted at the level of its file name
+    fn chain_anchors(&self) -> AgeResult<TrustAnchors> {
+        let mut anchors = self.anchors.clone().with_search_dir(self.keys_dir.clone());
+        let top = LEVELS.iter().map(|&key_type| (key_type, self.key_path(key_type))).find(|(_, path)| path.exists());
+        if let Some((key_type, path)) = top {
+            if let Some(envelope) = KeyEnvelope::read(&path)? {
+                anchors = anchors.with_root(key_type, &envelope.recipient)?;
+            }
+        }
+        Ok(anchors)
+    }
+
+    /// Scan, rotate what the policies ask for, and record when to run next
+    pub fn run(&self) -> AgeResult<MaintenanceReport> {
+        let _lock = lock_run(&self.keys_dir)?;
+        let mark = HighWaterMark::new(&self.keys_dir).with_state_dir(self.clock_state_dir.clone());
+        let clock = if self.dry_run { mark.peek()? } else { mark.observe()? };
+        clock.enforce(&self.keys_dir, self.clock_policy, &self.audit_logger)?;
+        let checked_at = clock.trusted;
+        let keys = self.scan()?;
+
+        for key in keys.iter().filter(|key| key.state != KeyState::Healthy) {
+            let message = format!("KEY_{} {} {} {} (expires {})",
+                key.state.to_string().to_uppercase(), self.name, key.key_type, key.fingerprint,
+                key.expires_at.map(|at| at.to_rfc3339()).unwrap_or_default());
+            match key.state {
+                KeyState::Expired => self.audit_logger.log_error(&message)?,
+                _ => self.audit_logger.log_warning(&message)?,
+            }
+        }
+
+        // The highest key due for rotation takes every level below it along
+        let mut rotated = Vec::new();
+        if let Some(due) = keys.iter().find(|key| key.auto_rotation && key.state != KeyState::Healthy) {
+            for key in keys.iter().filter(|key| key.key_type.authority_level() <= due.key_type.authority_level()) {
+                rotated.push(self.rotate(key, key.key_type != due.key_type)?);
+            }
+        }
+
+        let after = if self.dry_run || rotated.is_empty() { keys.clone() } else { self.scan()? };
+        let next_run_due = after.iter()
+            .flat_map(|key| [key.warning_at, key.expires_at])
+            .flatten()
+            .filter(|at| *at > checked_at)
+            .chain(std::iter::once(checked_at + chrono::Duration::from_std(self.interval).unwrap_or_default()))
+            .min()
+            .unwrap_or(checked_at);
+
+        if !self.dry_run {
+            self.save_state(&MaintenanceState { last_run: checked_at, next_run_due })?;
+            self.audit_logger.log_info(&format!("MAINTENANCE_COMPLETE {} checked {} rotated {} next run {}",
+                self.name, keys.len(), rotated.len(), next_run_due.to_rfc3339()))?;
+        }
+
+        Ok(MaintenanceReport { checked_at, dry_run: self.dry_run, keys, rotated, next_run_due })
+    }
+
+    /// Replace one key file with a fresh key, keeping the old one under `retired/`
+    fn rotate(&self, key: &KeyStatus, reissued: bool) -> AgeResult<RotatedKey> {
+        let mut result = RotatedKey {
+            key_type: key.key_type,
+            path: key.path.clone(),
+            retired: None,
+            old_fingerprint: key.fingerprint.clone(),
+            new_fingerprint: None,
+            reissued,
+        };
+        if self.dry_run {
+            return Ok(result);
+        }
+
+        let (recipient, identity) = generate_native_age_keypair()?;
+
+        // A per-key policy set at creation carries over to the replacement
+        let old = Zeroizing::new(fs::read_to_string(&key.path).unwrap_or_default());
+        let content = match KeyEnvelope::parse(&old).ok().flatten() {
+            Some(previous) => self.reissue_envelope(&previous, &recipient)?.render(identity.expose_secret()),
+            None => {
+                let policy_line = old.lines().find(|line| line.starts_with(expiration::KEY_POLICY_PREFIX))
+                    .map(|line| format!("{}\n", line))
+                    .unwrap_or_default();
+                Zeroizing::new(format!("# created: {}\n{}# public key: {}\n{}\n",
+                    Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true), policy_line, recipient, identity.expose_secret()))
+            }
+        };
+
+        // Retire the old file only once the replacement is ready
+        let retired_dir = self.keys_dir.join(RETIRED_DIR);
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&retired_dir)
+            .map_err(|e| AgeError::file_error("create_dir", retired_dir.clone(), e))?;
+        let retired = retired_dir.join(format!("{}-{}-{}.key", self.name, key.key_type, key.fingerprint));
+        fs::copy(&key.path, &retired).map_err(|e| AgeError::file_error("retire", retired.clone(), e))?;
+        write_atomic(&key.path, content.as_bytes())?;
+
+        let new_fingerprint = short_fingerprint(&recipient)?;
+        self.audit_logger.log_info(&format!("KEY_{} {} {} {} -> {} (retired to {})",
+            if reissued { "REISSUED" } else { "ROTATED" },
+            self.name, key.key_type, key.fingerprint, new_fingerprint, retired.display()))?;
+
+        result.retired = Some(retired);
+        result.new_fingerprint = Some(new_fingerprint);
+        Ok(result)
+    }
+
+    /// Envelope for a rotated key: same type, policy, capabilities and lifetime, re-signed
+    /// by the parent key file. A key that had a parent is never re-issued without its
+    /// signature; only a chain anchor, which names no parent, goes unsigned.
+    fn reissue_envelope(&self, previous: &KeyEnvelope, recipient: &str) -> AgeResult<KeyEnvelope> {
+        let mut envelope = KeyEnvelope::new(previous.key_type, recipient)?;
+        let expires = previous.expires.map(|expires| envelope.created + (expires - previous.created));
+        envelope = envelope.with_policy(previous.policy.clone())
+            .with_expires(expires)
+            .with_capabilities(previous.capabilities.clone())?;
+
+        let parent = previous.key_type.parent_type()
+            .map(|parent_type| (parent_type, self.key_path(parent_type)))
+            .filter(|(_, path)| path.is_file());
+        match (parent, &previous.parent) {
+            (Some((parent_type, path)), _) => envelope.with_parent(&AuthorityKey::from_file(&path, parent_type)?),
+            (None, None) => Ok(envelope),
+            (None, Some(fingerprint)) => Err(AgeError::InvalidOperation {
+                operation: "reissue".to_string(),
+                reason: format!("{} key {} is signed by parent {}, whose key file is not in {}; it cannot be re-issued unsigned",
+                    previous.key_type, previous.fingerprint, fingerprint, self.keys_dir.display()),
+            }),
+        }
+    }
+
+    fn key_path(&self, key_type: KeyType) -> PathBuf {
+        self.keys_dir.join(format!("{}-{}.key", self.name, key_type))
+    }
+
+    fn save_state(&self, state: &MaintenanceState) -> AgeResult<()> {
+        let data = serde_json::to_vec_pretty(state).map_err(|e| AgeError::InvalidOperation {
+            operation: "save_maintenance_state".to_string(),
+            reason: e.to_string(),
+        })?;
+        let path = self.keys_dir.join(STATE_FILE);
+        let staged = self.keys_dir.join(format!("{}.tmp", STATE_FILE));
+        fs::write(&staged, data)
+            .and_then(|_| fs::rename(&staged, &path))
+            .map_err(|e| AgeError::file_error("write", path, e))
+    }
+}
+
+/// Read a key file and judge it against its effective policy
+///
+/// An enveloped file must verify against `anchors` before its headers are used.
+fn key_status(key_type: KeyType, path: &Path, policies: &ExpirationPolicies, anchors: &TrustAnchors, now: DateTime<Utc>) -> AgeResult<KeyStatus> {
+    let content = fs::read_to_string(path).map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?;
+    let public_key = content.lines()
+        .find_map(|line| line.strip_prefix("# public key: "))
+        .map(str::trim)
+        .ok_or_else(|| AgeError::InvalidOperation {
+            operation: "scan_key".to_string(),
+            reason: format!("{} has no '# public key:' line", path.display()),
+        })?;
+
+    let created = content.lines()
+        .find_map(|line| line.strip_prefix("# created: "))
+        .and_then(|value| parse_created(value.trim()))
+        .or_else(|| fs::metadata(path).and_then(|m| m.modified()).ok().map(DateTime::<Utc>::from))
+        .unwrap_or(now);
+
+    let effective = policies.for_key_file(key_type, path)?;
+    let policy = effective.policy;
+    let warning_at = policy.as_ref().map(|policy| policy.warning_at(created));
+    let expires_at = match KeyEnvelope::parse(&content)? {
+        Some(envelope) => {
+            if envelope.key_type != key_type {
+                return Err(AgeError::InvalidOperation {
+                    operation: "scan_key".to_string(),
+                    reason: format!("{} holds a {} key, not a {} key", path.display(), envelope.key_type, key_type),
+                });
+            }
+            anchors.verify(&envelope, path)?;
+            envelope.expires_at(policy.as_ref())
+        }
+        None => policy.as_ref().map(|policy| policy.expires_at(created)),
+    };
+    let state = match (warning_at, expires_at) {
+        (_, Some(expires)) if now >= expires => KeyState::Expired,
+        (Some(warning), _) if now >= warning => KeyState::Warning,
+        _ => KeyState::Healthy,
+    };
+
+    Ok(KeyStatus {
+        key_type,
+        path: path.to_path_buf(),
+        fingerprint: short_fingerprint(public_key)?,
+        created,
+        warning_at,
+        expires_at,
+        state,
+        auto_rotation: policy.is_some_and(|policy| policy.auto_rotation()),
+        policy_source: effective.source,
+    })
+}
+
+/// Creation time as written by age-keygen (RFC 3339) or the authority generator
+fn parse_created(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%z"))
+        .ok()
+        .map(|created| created.with_timezone(&Utc))
+}
+
+fn short_fingerprint(public_key: &str) -> AgeResult<String> {
+    Ok(KeyFingerprint::from_key_material(public_key.as_bytes())?.short())
+}
+
+/// Atomically replace a key file, owner-only
+/// Exclusive advisory lock held for the duration of a run
+fn lock_run(keys_dir: &Path) -> AgeResult<FileLock> {
+    let path = keys_dir.join(LOCK_FILE);
+    FileLock::try_acquire(&path)?.ok_or_else(|| AgeError::InvalidOperation {
+        operation: "maintain".to_string(),
+        reason: format!("Another maintenance run holds {}", path.display()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_key(dir: &Path, key_type: KeyType, created: DateTime<Utc>) -> String {
+        let (recipient, identity) = generate_native_age_keypair().unwrap();
+        let content = format!("# created: {}\n# public key: {}\n{}\n",
+            created.to_rfc3339(), recipient, identity.expose_secret());
+        fs::write(dir.join(format!("chain-{}.key", key_type)), content).unwrap();
+        recipient
+    }
+
+    #[test]
+    fn test_scan_reports_warning_and_expired() {
+        let dir = TempDir::new().unwrap();
+        let now = Utc::now();
+        write_key(dir.path(), KeyType::Repo, now - chrono::Duration::days(400));
+        write_key(dir.path(), KeyType::Ignition, now - chrono::Duration::days(28));
+        write_key(dir.path(), KeyType::Distro, now - chrono::Duration::days(8));
+
+        let runner = MaintenanceRunner::new(dir.path().to_path_buf(), "chain", None).unwrap();
+        let states: Vec<(KeyType, KeyState)> = runner.scan().unwrap().iter().map(|k| (k.key_type, k.state)).collect();
+        assert_eq!(states, vec![
+            (KeyType::Repo, KeyState::Healthy),
+            (KeyType::Ignition, KeyState::Warning),
+            (KeyType::Distro, KeyState::Expired),
+        ]);
+    }
+
+    #[test]
+    fn test_scan_refuses_unverified_envelopes() {
+        let dir = TempDir::new().unwrap();
+        let (repo_recipient, repo_identity) = generate_native_age_keypair().unwrap();
+        let repo_path = dir.path().join("chain-repo.key");
+        fs::write(&repo_path, KeyEnvelope::new(KeyType::Repo, &repo_recipient).unwrap().render(repo_identity.expose_secret()).as_bytes()).unwrap();
+        let repo = AuthorityKey::from_file(&repo_path, KeyType::Repo).unwrap();
+
+        let (recipient, identity) = generate_native_age_keypair().unwrap();
+        let ignition = KeyEnvelope::new(KeyType::Ignition, &recipient).unwrap()
+            .with_expires(Some(Utc::now() - chrono::Duration::days(1)))
+            .with_parent(&repo).unwrap()
+            .render(identity.expose_secret());
+        let ignition_path = dir.path().join("chain-ignition.key");
+        fs::write(&ignition_path, ignition.as_bytes()).unwrap();
+
+        let runner = MaintenanceRunner::new(dir.path().to_path_buf(), "chain", None).unwrap();
+        assert_eq!(runner.scan().unwrap()[1].state, KeyState::Expired);
+
+        // Lifting the expiry by hand breaks the parent signature instead of reviving the key
+        let edited = ignition.lines().map(|line| if line.starts_with("# expires:") { "# expires: never" } else { line })
+            .collect::<Vec<_>>().join("\n");
+        fs::write(&ignition_path, edited).unwrap();
+        assert!(runner.scan().is_err());
+    }
+
+    #[test]
+    fn test_reissue_requires_the_parent_signature() {
+        let dir = TempDir::new().unwrap();
+        let (repo_recipient, repo_identity) = generate_native_age_keypair().unwrap();
+        let repo_path = dir.path().join("chain-repo.key");
+        fs::write(&repo_path, KeyEnvelope::new(KeyType::Repo, &repo_recipient).unwrap().render(repo_identity.expose_secret()).as_bytes()).unwrap();
+        let repo = AuthorityKey::from_file(&repo_path, KeyType::Repo).unwrap();
+        let (recipient, _) = generate_native_age_keypair().unwrap();
+        let previous = KeyEnvelope::new(KeyType::Ignition, &recipient).unwrap().with_parent(&repo).unwrap();
+
+        let runner = MaintenanceRunner::new(dir.path().to_path_buf(), "chain", None).unwrap();
+        let (fresh, _) = generate_native_age_keypair().unwrap();
+        assert!(runner.reissue_envelope(&previous, &fresh).unwrap().verify_parent(&repo_recipient).is_ok());
+
+        // Without the parent key file the child is not re-issued under its old parent's name
+        fs::remove_file(&repo_path).unwrap();
+        let error = runner.reissue_envelope(&previous, &fresh).unwrap_err();
+        assert!(error.to_string().contains("cannot be re-issued unsigned"));
+
+        // An anchor names no parent and stays one
+        let anchor = KeyEnvelope::new(KeyType::Ignition, &recipient).unwrap();
+        assert!(runner.reissue_envelope(&anchor, &fresh).unwrap().parent.is_none());
+    }
+
+    #[test]
+    fn test_run_rotates_and_reissues_children() {
+        let dir = TempDir::new().unwrap();
+        let now = Utc::now();
+        let repo = write_key(dir.path(), KeyType::Repo, now - chrono::Duration::days(400));
+        let ignition = write_key(dir.path(), KeyType::Ignition, now - chrono::Duration::days(28));
+        write_key(dir.path(), KeyType::Distro, now - chrono::Duration::days(1));
+
+        let dry = MaintenanceRunner::new(dir.path().to_path_buf(), "chain", None).unwrap()
+            .with_clock_state_dir(dir.path().join("state")).with_dry_run(true);
+        assert_eq!(dry.run().unwrap().rotated.len(), 2);
+        assert!(MaintenanceState::load(dir.path()).is_none());
+        assert!(fs::read_to_string(dir.path().join("chain-ignition.key")).unwrap().contains(&ignition));
+
+        let report = MaintenanceRunner::new(dir.path().to_path_buf(), "chain", None).unwrap()
+            .with_clock_state_dir(dir.path().join("state")).run().unwrap();
+        let rotated: Vec<(KeyType, bool)> = report.rotated.iter().map(|r| (r.key_type, r.reissued)).collect();
+        assert_eq!(rotated, vec![(KeyType::Ignition, false), (KeyType::Distro, true)]);
+        assert!(!fs::read_to_string(dir.path().join("chain-ignition.key")).unwrap().contains(&ignition));
+        assert!(fs::read_to_string(dir.path().join("chain-repo.key")).unwrap().contains(&repo));
+        assert!(report.rotated[0].retired.as_ref().unwrap().exists());
+
+        // Fresh keys are healthy; the next run is due no later than the default interval
+        let state = MaintenanceState::load(dir.path()).unwrap();
+        assert!(!state.is_due());
+        assert!(state.next_run_due <= report.checked_at + chrono::Duration::days(1));
+        let again = MaintenanceRunner::new(dir.path().to_path_buf(), "chain", None).unwrap()
+            .with_clock_state_dir(dir.path().join("state")).run().unwrap();
+        assert!(again.rotated.is_empty());
+        assert_eq!(again.attention().count(), 0);
+    }
+}
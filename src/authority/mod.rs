@@ -11,11 +11,28 @@ pub mod ignition;
 pub mod validation;
 pub mod operations;
 pub mod bridge;
+pub mod maintenance;
+pub mod expiration;
+pub mod signing;
+pub mod revocation;
+pub mod certificate;
+pub mod envelope;
+pub mod lineage;
+pub mod authorization;
+pub mod capability;
+pub mod trust;
 
 // Re-export key types for convenience
 pub use chain::{KeyType, AuthorityChain, AuthorityKey, KeyFingerprint};
 pub use ignition::{IgnitionKey, PassphraseHash};
 pub use validation::{AuthorityProof, SubjectProof, AuthorityLevel};
+pub use maintenance::{MaintenanceRunner, MaintenanceReport, KeyState};
+pub use expiration::{ExpirationPolicies, EffectivePolicy, PolicySource};
+pub use certificate::{AuthorityCertificate, VerifiedCertificate};
+pub use envelope::KeyEnvelope;
+pub use authorization::{Actor, OperationAuthorizer, OperationPolicy};
+pub use capability::{KeyCapabilities, ScopedKey};
+pub use trust::TrustAnchors;
 pub use operations::{AuthorityAgeKeyGenerator, GeneratedAgeKey, AuthorityAgeEncryption, EncryptionParams, EncryptionResult};
 
 use crate::encryption::age_automation::error::{AgeError, AgeResult};
@@ -7,19 +7,22 @@
 
 use std::path::{Path, PathBuf};
 use std::fs;
-use tempfile::NamedTempFile;
-use std::io::Write;
 
 use crate::encryption::age_automation::{
     error::{AgeError, AgeResult},
     tty_automation::TtyAutomator,
     config::OutputFormat,
     security::AuditLogger,
+    secret::{SecretString, Zeroizing},
+    temp_area::{self, SecureTempFile},
+    lifecycle::load_mark,
 };
 use super::super::{
     KeyType, AuthorityChain, AuthorityKey, KeyFingerprint,
     validation::AuthorityValidationEngine,
-    ignition::IgnitionKey,
+    ignition::{IgnitionKey, UnlockLedger},
+    revocation::RevocationList,
+    authorization::{self, Actor, OperationPolicy},
 };
 
 /// Authority-based Age encryption engine
@@ -28,6 +31,8 @@ pub struct AuthorityAgeEncryption {
     validation_engine: AuthorityValidationEngine,
     tty_automator: TtyAutomator,
     audit_logger: AuditLogger,
+    temp_root: PathBuf,
+    unlock_ledger: UnlockLedger,
 }
 
 /// Encryption operation parameters
@@ -66,9 +71,36 @@ impl AuthorityAgeEncryption {
             validation_engine,
             tty_automator,
             audit_logger,
+            temp_root: temp_area::private_temp_root(),
+            unlock_ledger: UnlockLedger::default(),
         })
     }
     
+    /// Refuse to encrypt or decrypt with keys revoked in `revocations` instead of the
+    /// target repository's list; an empty list opts out of revocation checks
+    pub fn with_revocations(mut self, revocations: RevocationList) -> Self {
+        self.validation_engine = self.validation_engine.with_revocations(revocations);
+        self
+    }
+    
+    /// Authorize operations under `policy` instead of each repository's policy file
+    pub fn with_authorization_policy(mut self, policy: OperationPolicy) -> Self {
+        self.validation_engine = self.validation_engine.with_authorization_policy(policy);
+        self
+    }
+    
+    /// Place key and recipient files under `root` instead of the private temp root
+    pub fn with_temp_root(mut self, root: PathBuf) -> Self {
+        self.temp_root = root;
+        self
+    }
+    
+    /// Count ignition unlock attempts in `ledger` instead of the padlock state directory
+    pub fn with_unlock_ledger(mut self, ledger: UnlockLedger) -> Self {
+        self.unlock_ledger = ledger;
+        self
+    }
+    
     /// Encrypt file using authority key with validation
     pub fn encrypt_with_authority(
         &mut self,
@@ -77,8 +109,10 @@ impl AuthorityAgeEncryption {
         self.audit_logger.log_operation_start("encrypt_with_authority", 
             &params.input_file, &params.output_file)?;
         
-        // 1. Validate authority key exists and can perform encryption
-        let authority_key = self.get_validated_authority_key(&params.authority_key)?;
+        // 1. Validate authority key exists and the policy lets it encrypt here
+        let authority_key = self.get_validated_authority_key(&params.authority_key, &params.input_file)?;
+        self.validation_engine.authorize_operation("encrypt", &params.authority_key,
+            Some(&params.input_file), &self.audit_logger)?;
         
         // 2. Validate operation authorization if requested
         if params.verify_authority {
@@ -140,14 +174,16 @@ impl AuthorityAgeEncryption {
         self.audit_logger.log_operation_start("decrypt_with_authority", 
             input_file, output_file)?;
         
-        // 1. Get validated authority key
-        let auth_key = self.get_validated_authority_key(authority_key)?;
+        // 1. Get validated authority key the policy lets decrypt here
+        let auth_key = self.get_validated_authority_key(authority_key, input_file)?;
+        self.validation_engine.authorize_operation("decrypt", authority_key,
+            Some(input_file), &self.audit_logger)?;
         
         // 2. Extract Age secret key for decryption
         let age_secret_key = self.extract_age_secret_from_key(&auth_key)?;
         
         // 3. Create temporary key file for decryption
-        let key_file = self.create_temp_age_key_file(&age_secret_key)?;
+        let key_file = self.create_temp_age_key_file(age_secret_key.expose_secret())?;
         
         // 4. Perform Age decryption with TTY automation
         let decryption_success = self.perform_age_decryption(
@@ -169,7 +205,8 @@ impl AuthorityAgeEncryption {
             input_file: input_file.to_path_buf(),
             output_file: output_file.to_path_buf(),
             authority_used: authority_key.clone(),
-            recipients: vec![age_secret_key],
+            // The identity used, never its secret key
+            recipients: vec![String::from_utf8_lossy(auth_key.key_material().public_key()).into_owned()],
             file_size_bytes: file_size,
             success: decryption_success && output_file.exists(),
         };
@@ -190,20 +227,40 @@ impl AuthorityAgeEncryption {
         Ok(result)
     }
     
-    /// Encrypt file using ignition key with passphrase
+    /// Encrypt file using the ignition key stored at `ignition_key_path` with passphrase
+    ///
+    /// The unlock goes through the unlock ledger, so failed attempts count towards the
+    /// key's lockout across runs.
     pub fn encrypt_with_ignition_key(
         &mut self,
         input_file: &Path,
         output_file: &Path,
-        ignition_key: &mut IgnitionKey,
+        ignition_key_path: &Path,
         passphrase: &str,
         output_format: OutputFormat,
     ) -> AgeResult<EncryptionResult> {
         self.audit_logger.log_operation_start("encrypt_with_ignition_key", 
             input_file, output_file)?;
         
-        // 1. Unlock ignition key with passphrase
-        let key_material = ignition_key.unlock(passphrase)?;
+        // 1. Refuse a revoked ignition key, authorize it, then unlock it with the
+        // passphrase; the unlock judges expiry against the repository's high-water mark
+        load_mark(&authorization::repository_root(input_file))?;
+        let mut ignition_key = IgnitionKey::load(ignition_key_path)?;
+        self.unlock_ledger.apply(&mut ignition_key)?;
+        let fingerprint = ignition_key.fingerprint()?;
+        if self.validation_engine.revocations_for(Some(input_file))?.is_fingerprint_revoked(fingerprint.hex(), &self.authority_chain)? {
+            let details = format!("ignition key {} is revoked", fingerprint);
+            self.audit_logger.log_error(&format!("REVOKED_KEY_REFUSED {}", details))?;
+            return Err(AgeError::SecurityValidationFailed { validation_type: "key_revoked".to_string(), details });
+        }
+        let actor = Actor::Key {
+            key_type: ignition_key.key_type(),
+            fingerprint: fingerprint.hex().to_string(),
+            capabilities: None,
+            lineage: Vec::new(),
+        };
+        self.validation_engine.authorizer().authorize("encrypt", Some(input_file), &actor, &self.audit_logger)?;
+        let key_material = IgnitionKey::unlock_file(ignition_key_path, &self.unlock_ledger, passphrase, &self.audit_logger)?;
         
         // 2. Extract Age key from unlocked material
         let age_key = String::from_utf8(key_material.private_key().unwrap().to_vec())
@@ -236,7 +293,7 @@ impl AuthorityAgeEncryption {
         let result = EncryptionResult {
             input_file: input_file.to_path_buf(),
             output_file: output_file.to_path_buf(),
-            authority_used: ignition_key.fingerprint()?,
+            authority_used: fingerprint,
             recipients: vec![public_key],
             file_size_bytes: file_size,
             success: encryption_success && output_file.exists(),
@@ -258,13 +315,19 @@ impl AuthorityAgeEncryption {
         Ok(result)
     }
     
-    /// Get validated authority key from chain
-    fn get_validated_authority_key(&self, key_fp: &KeyFingerprint) -> AgeResult<&AuthorityKey> {
-        self.authority_chain.get_key(key_fp)
+    /// Get validated authority key from chain, refusing keys revoked in the repository
+    /// holding `path`
+    fn get_validated_authority_key(&self, key_fp: &KeyFingerprint, path: &Path) -> AgeResult<&AuthorityKey> {
+        let key = self.authority_chain.get_key(key_fp)
             .ok_or_else(|| AgeError::InvalidOperation {
                 operation: "get_authority_key".to_string(),
                 reason: format!("Authority key not found: {}", key_fp),
-            })
+            })?;
+        if let Err(e) = self.validation_engine.check_not_revoked_at(key, Some(path)) {
+            self.audit_logger.log_error(&format!("REVOKED_KEY_REFUSED {}: {}", key_fp, e))?;
+            return Err(e);
+        }
+        Ok(key)
     }
     
     /// Validate authority can perform encryption operations
@@ -311,7 +374,7 @@ impl AuthorityAgeEncryption {
     }
     
     /// Extract Age secret key from authority key
-    fn extract_age_secret_from_key(&self, authority_key: &AuthorityKey) -> AgeResult<String> {
+    fn extract_age_secret_from_key(&self, authority_key: &AuthorityKey) -> AgeResult<SecretString> {
         let key_material = authority_key.key_material();
         
         let secret_key = key_material.private_key()
@@ -320,14 +383,14 @@ impl AuthorityAgeEncryption {
                 reason: "Authority key has no private key material".to_string(),
             })?;
         
-        let secret_key_str = String::from_utf8(secret_key.to_vec())
+        let secret_key_str = String::from_utf8(secret_key.to_vec()).map(SecretString::new)
             .map_err(|_| AgeError::InvalidOperation {
                 operation: "extract_age_secret".to_string(),
                 reason: "Invalid secret key format".to_string(),
             })?;
         
         // Validate it's a proper Age secret key
-        if !secret_key_str.starts_with("AGE-SECRET-KEY-") {
+        if !secret_key_str.expose_secret().starts_with("AGE-SECRET-KEY-") {
             return Err(AgeError::InvalidOperation {
                 operation: "extract_age_secret".to_string(),
                 reason: "Not a valid Age secret key".to_string(),
@@ -338,23 +401,10 @@ impl AuthorityAgeEncryption {
     }
     
     /// Create temporary Age key file for operations
-    fn create_temp_age_key_file(&self, age_secret_key: &str) -> AgeResult<NamedTempFile> {
-        let mut temp_file = NamedTempFile::new()
-            .map_err(|e| AgeError::TemporaryResourceError {
-                resource_type: "key_file".to_string(),
-                operation: "create".to_string(),
-                reason: e.to_string(),
-            })?;
-        
+    fn create_temp_age_key_file(&self, age_secret_key: &str) -> AgeResult<SecureTempFile> {
         // Write Age key in proper format
-        writeln!(temp_file, "{}", age_secret_key)
-            .map_err(|e| AgeError::TemporaryResourceError {
-                resource_type: "key_file".to_string(),
-                operation: "write".to_string(),
-                reason: e.to_string(),
-            })?;
-        
-        Ok(temp_file)
+        let contents = Zeroizing::new(format!("{}\n", age_secret_key));
+        SecureTempFile::create_in(&self.temp_root, "identity.txt", contents.as_bytes())
     }
     
     /// Perform Age encryption with recipient list
@@ -434,24 +484,9 @@ impl AuthorityAgeEncryption {
     }
     
     /// Create temporary recipients file for Age
-    fn create_recipients_file(&self, recipients: &[String]) -> AgeResult<NamedTempFile> {
-        let mut temp_file = NamedTempFile::new()
-            .map_err(|e| AgeError::TemporaryResourceError {
-                resource_type: "recipients_file".to_string(),
-                operation: "create".to_string(),
-                reason: e.to_string(),
-            })?;
-        
-        for recipient in recipients {
-            writeln!(temp_file, "{}", recipient)
-                .map_err(|e| AgeError::TemporaryResourceError {
-                    resource_type: "recipients_file".to_string(),
-                    operation: "write".to_string(),
-                    reason: e.to_string(),
-                })?;
-        }
-        
-        Ok(temp_file)
+    fn create_recipients_file(&self, recipients: &[String]) -> AgeResult<SecureTempFile> {
+        let contents: String = recipients.iter().map(|recipient| format!("{}\n", recipient)).collect();
+        SecureTempFile::create_in(&self.temp_root, "recipients.txt", contents.as_bytes())
     }
 }
 
@@ -5,20 +5,25 @@
 //!
 //! Security Guardian: Edgar - Real Age key generation with authority validation
 
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::fs;
-use tempfile::NamedTempFile;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SubsecRound, Utc};
 
 use crate::encryption::age_automation::{
     error::{AgeError, AgeResult},
     security::AuditLogger,
+    secret::SecretString,
 };
 use super::super::{
     KeyType, AuthorityChain, AuthorityKey, KeyFingerprint,
     validation::AuthorityValidationEngine,
+    capability::KeyCapabilities,
     chain::{KeyMaterial, KeyFormat, KeyMetadata},
+    envelope::KeyEnvelope,
+    expiration::PolicyOverride,
+    ignition::ExpirationPolicy,
 };
 
 /// Age key generation with authority integration
@@ -26,6 +31,8 @@ pub struct AuthorityAgeKeyGenerator {
     authority_chain: AuthorityChain,
     validation_engine: AuthorityValidationEngine,
     audit_logger: AuditLogger,
+    key_policies: HashMap<KeyType, PolicyOverride>,
+    distro_capabilities: Option<KeyCapabilities>,
 }
 
 /// Generated Age key with authority metadata
@@ -48,9 +55,24 @@ impl AuthorityAgeKeyGenerator {
             authority_chain,
             validation_engine,
             audit_logger,
+            key_policies: HashMap::new(),
+            distro_capabilities: None,
         })
     }
     
+    /// Record an expiration policy in every key file generated for `key_type`
+    pub fn with_key_policy(mut self, key_type: KeyType, policy: PolicyOverride) -> Self {
+        self.key_policies.insert(key_type, policy);
+        self
+    }
+    
+    /// Issue every generated distro key with `capabilities`, signed into its key file
+    pub fn with_distro_capabilities(mut self, capabilities: KeyCapabilities) -> AgeResult<Self> {
+        capabilities.validate()?;
+        self.distro_capabilities = Some(capabilities);
+        Ok(self)
+    }
+    
     /// Generate Age key with authority validation
     pub fn generate_authority_age_key(
         &mut self,
@@ -78,14 +100,18 @@ impl AuthorityAgeKeyGenerator {
             KeyFormat::Age,
         );
         
-        // 4. Create authority key with metadata
+        // 4. Create authority key with metadata, expiring under its type and per-key policy
+        let creation_time = Utc::now();
+        let policy = self.key_policies.get(&key_type)
+            .map_or(Ok(ExpirationPolicy::default_for_type(key_type)), |policy| policy.apply(key_type, ExpirationPolicy::default_for_type(key_type)))?;
         let metadata = KeyMetadata {
-            creation_time: Utc::now(),
+            creation_time,
             creator: "authority_age_generator".to_string(),
             description: format!("{} authority key: {}", key_type, key_name),
-            expiration: None,
+            expiration: policy.map(|policy| policy.expires_at(creation_time)),
             last_used: None,
             usage_count: 0,
+            capabilities: self.distro_capabilities.clone().filter(|_| key_type == KeyType::Distro),
         };
         
         let authority_key = AuthorityKey::new(
@@ -97,7 +123,7 @@ impl AuthorityAgeKeyGenerator {
         
         // 5. Write key file if path specified
         let key_file_path = if let Some(path) = output_path {
-            self.write_age_key_file(path, &age_secret)?;
+            self.write_age_key_file(path, &authority_key, parent_authority, &age_public, &age_secret)?;
             Some(path.to_path_buf())
         } else {
             None
@@ -179,21 +205,32 @@ impl AuthorityAgeKeyGenerator {
     }
     
     /// Write Age key to file
-    fn write_age_key_file(&self, path: &Path, secret_key: &str) -> AgeResult<()> {
+    fn write_age_key_file(
+        &self,
+        path: &Path,
+        key: &AuthorityKey,
+        parent_authority: Option<&KeyFingerprint>,
+        public_key: &str,
+        secret_key: &str,
+    ) -> AgeResult<()> {
         // Create parent directories if needed
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)
                 .map_err(|e| AgeError::file_error("create_dir", parent.to_path_buf(), e))?;
         }
         
-        // Write Age key in standard format
-        let key_content = format!("# created: {}\n# public key: {}\n{}\n",
-            Utc::now().format("%Y-%m-%dT%H:%M:%S%z"),
-            self.extract_public_key_from_secret(secret_key)?,
-            secret_key
-        );
+        // Write Age key wrapped in a padlock envelope, signed by its parent when known
+        let mut envelope = KeyEnvelope::new(key.key_type(), public_key)?
+            .with_expires(key.metadata().expiration)
+            .with_policy(self.key_policies.get(&key.key_type()).map(PolicyOverride::to_spec))
+            .with_capabilities(key.metadata().capabilities.clone())?;
+        envelope.created = key.metadata().creation_time.trunc_subsecs(0);
+        if let Some(parent) = parent_authority.and_then(|fingerprint| self.authority_chain.get_key(fingerprint)) {
+            envelope = envelope.with_parent(parent)?;
+        }
+        let key_content = envelope.render(secret_key);
         
-        fs::write(path, key_content)
+        fs::write(path, key_content.as_bytes())
             .map_err(|e| AgeError::file_error("write", path.to_path_buf(), e))?;
         
         // Set secure permissions (user read/write only)
@@ -211,58 +248,6 @@ impl AuthorityAgeKeyGenerator {
         Ok(())
     }
     
-    /// Extract public key from Age secret key using age-keygen -y
-    fn extract_public_key_from_secret(&self, secret_key: &str) -> AgeResult<String> {
-        // Create temporary file for secret key
-        let mut temp_file = NamedTempFile::new()
-            .map_err(|e| AgeError::TemporaryResourceError {
-                resource_type: "file".to_string(),
-                operation: "create".to_string(),
-                reason: e.to_string(),
-            })?;
-        
-        // Write secret key to temp file
-        use std::io::Write;
-        temp_file.write_all(secret_key.as_bytes())
-            .map_err(|e| AgeError::TemporaryResourceError {
-                resource_type: "file".to_string(),
-                operation: "write".to_string(),
-                reason: e.to_string(),
-            })?;
-        
-        // Use age-keygen -y to extract public key
-        let output = Command::new("age-keygen")
-            .arg("-y")
-            .arg(temp_file.path())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .map_err(|e| AgeError::ProcessExecutionFailed {
-                command: "age-keygen -y".to_string(),
-                exit_code: None,
-                stderr: e.to_string(),
-            })?;
-        
-        if !output.status.success() {
-            return Err(AgeError::ProcessExecutionFailed {
-                command: "age-keygen -y".to_string(),
-                exit_code: output.status.code(),
-                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-            });
-        }
-        
-        let public_key = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        
-        if !public_key.starts_with("age1") {
-            return Err(AgeError::InvalidOperation {
-                operation: "extract_public_key".to_string(),
-                reason: "Invalid public key extracted".to_string(),
-            });
-        }
-        
-        Ok(public_key)
-    }
-    
     /// Validate parent authority for key generation
     fn validate_parent_authority(&mut self, key_type: KeyType, parent_fp: &KeyFingerprint) -> AgeResult<()> {
         let parent_key = self.authority_chain.get_key(parent_fp)
@@ -365,6 +350,25 @@ impl AuthorityAgeKeyGenerator {
     }
 }
 
+/// Generate an X25519 age keypair in-process, without `age-keygen`
+///
+/// Returns the `age1...` recipient and the `AGE-SECRET-KEY-1...` identity.
+pub fn generate_native_age_keypair() -> AgeResult<(String, SecretString)> {
+    let invalid = |e: String| AgeError::InvalidOperation {
+        operation: "generate_native_age_keypair".to_string(),
+        reason: e,
+    };
+    let secret = crate::encryption::age_automation::secret::Zeroizing::new(rand::random::<[u8; 32]>());
+    let public = x25519_dalek::PublicKey::from(&x25519_dalek::StaticSecret::from(*secret));
+    
+    let secret_hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").map_err(|e| invalid(e.to_string()))?;
+    let public_hrp = bech32::Hrp::parse("age").map_err(|e| invalid(e.to_string()))?;
+    let identity = bech32::encode_upper::<bech32::Bech32>(secret_hrp, secret.as_slice()).map_err(|e| invalid(e.to_string()))?;
+    let recipient = bech32::encode::<bech32::Bech32>(public_hrp, public.as_bytes()).map_err(|e| invalid(e.to_string()))?;
+    
+    Ok((recipient, SecretString::new(identity)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,13 +105,15 @@ mod tests {
             .expect("Should have ignition key");
         
         let ignition_passphrase = "TestIgnitionPass123!";
-        let mut ignition_key = IgnitionKey::create(
+        let ignition_key = IgnitionKey::create(
             ignition_auth_key.authority_key.key_material(),
             KeyType::Ignition,
             ignition_passphrase,
             None,
             Some("test-ignition".to_string()),
         ).expect("Should create ignition key");
+        let ignition_key_path = test_dir.path().join("test-ignition.key");
+        ignition_key.save(&ignition_key_path).expect("Should save ignition key");
         
         let ignition_input = test_dir.path().join("ignition_test.txt");
         let ignition_encrypted = test_dir.path().join("ignition_test.age");
@@ -122,7 +124,7 @@ mod tests {
         let ignition_result = encryption_engine.encrypt_with_ignition_key(
             &ignition_input,
             &ignition_encrypted,
-            &mut ignition_key,
+            &ignition_key_path,
             ignition_passphrase,
             OutputFormat::Binary,
         ).expect("Should encrypt with ignition key");
@@ -9,5 +9,5 @@ pub mod generate;
 pub mod encrypt;
 
 // Re-export main components
-pub use generate::{AuthorityAgeKeyGenerator, GeneratedAgeKey};
+pub use generate::{AuthorityAgeKeyGenerator, GeneratedAgeKey, generate_native_age_keypair};
 pub use encrypt::{AuthorityAgeEncryption, EncryptionParams, EncryptionResult};
\ No newline at end of file
@@ -0,0 +1,404 @@
+//! Key Revocation - Signed revocation list stored with the repository
+//!
+//! Revocations live in `.padlock/revocations.json`, so they travel with the repository
+//! and reach every copy of it, including places that never saw the authority chain a
+//! copied key file came from. Each entry is signed (XEdDSA, see `signing`) by an
+//! authority key at or above the revoked key's parent, and names the revoked key both
+//! by chain fingerprint and by `age1...` recipient so it can be matched either way.
+//!
+//! An entry counts only when its signature verifies against the signer's recipient, the
+//! signer's level is high enough, and the signer is known: a key of the loaded chain or
+//! of the checked key's verified lineage with the same type and recipient, or a trusted
+//! root (see `trust`) listed at the type the entry declares. Anyone can write the file,
+//! so an entry naming a key that cannot be verified fails the check instead of being
+//! skipped or believed. Revoking a key revokes every key below it, found through the
+//! chain or through the parent fingerprints of the key's envelope lineage.
+//!
+//! Security Guardian: Edgar - A revoked key stays revoked wherever the repository goes
+
+use std::fs;
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::encryption::age_automation::error::{AgeError, AgeResult};
+use crate::encryption::age_automation::lifecycle::clock::write_atomic;
+use super::chain::{AuthorityChain, AuthorityKey, KeyType};
+use super::envelope::KeyEnvelope;
+use super::signing;
+use super::trust::TrustAnchors;
+
+/// Revocation list path relative to a repository root
+pub const REVOCATION_FILE: &str = ".padlock/revocations.json";
+
+/// One signed revocation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationEntry {
+    /// Chain fingerprint (hex) of the revoked key
+    pub fingerprint: String,
+    pub key_type: KeyType,
+    pub recipient: String,
+    pub reason: String,
+    pub revoked_at: DateTime<Utc>,
+    pub signer_fingerprint: String,
+    pub signer_type: KeyType,
+    pub signer_recipient: String,
+    /// Hex XEdDSA signature over every field above
+    pub signature: String,
+}
+
+impl RevocationEntry {
+    fn signed_bytes(&self) -> Vec<u8> {
+        format!("padlock-revocation-v1\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
+            self.fingerprint, self.key_type, self.recipient, self.reason, self.revoked_at.to_rfc3339(),
+            self.signer_fingerprint, self.signer_type, self.signer_recipient).into_bytes()
+    }
+
+    /// Check the signature and that the signer is a key of `chain` or a root in `anchors`
+    /// that may revoke this key
+    pub fn verify(&self, chain: &AuthorityChain, anchors: &TrustAnchors) -> AgeResult<()> {
+        self.verify_with_lineage(chain, anchors, &[])
+    }
+
+    /// Same as `verify`, also accepting a signer from `lineage`, a key's envelopes
+    /// verified up to a trusted root
+    pub fn verify_with_lineage(&self, chain: &AuthorityChain, anchors: &TrustAnchors, lineage: &[KeyEnvelope]) -> AgeResult<()> {
+        if !signer_may_revoke(self.signer_type, self.key_type) {
+            return Err(revocation_error(format!("{} key cannot revoke a {} key", self.signer_type, self.key_type)));
+        }
+
+        let signature = hex::decode(&self.signature).map_err(|_| revocation_error("Malformed signature".to_string()))?;
+        let public = signing::recipient_public(&self.signer_recipient)?;
+        if !signing::verify(&public, &self.signed_bytes(), &signature) {
+            return Err(revocation_error(format!("Signature of revocation for {} does not verify", short(&self.fingerprint))));
+        }
+
+        if let Some(signer) = find_key(chain, &self.signer_fingerprint) {
+            if signer.key_type() != self.signer_type || signing::key_recipient(signer)? != self.signer_recipient {
+                return Err(revocation_error(format!("Signer {} does not match the authority chain", short(&self.signer_fingerprint))));
+            }
+            return Ok(());
+        }
+        if let Some(signer) = lineage.iter().find(|key| key.recipient == self.signer_recipient) {
+            if signer.key_type != self.signer_type {
+                return Err(revocation_error(format!("Signer {} is a {} key in the key's lineage, not {}", short(&self.signer_fingerprint), signer.key_type, self.signer_type)));
+            }
+            return Ok(());
+        }
+        match anchors.root_for(&self.signer_recipient) {
+            Some(root) if root.key_type == self.signer_type => Ok(()),
+            Some(root) => Err(revocation_error(format!("Signer {} is trusted as a {} root, not {}", short(&self.signer_fingerprint), root.key_type, self.signer_type))),
+            None => Err(revocation_error(format!("Signer {} is neither in the authority chain nor a trusted root", short(&self.signer_fingerprint)))),
+        }
+    }
+}
+
+/// Revocations of one repository
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RevocationList {
+    pub entries: Vec<RevocationEntry>,
+    /// Roots vouching for signers outside the chain; never stored with the list
+    #[serde(skip)]
+    anchors: TrustAnchors,
+}
+
+impl RevocationList {
+    /// Load a repository's list; empty when it has none. Signers outside the chain are
+    /// checked against the global trusted roots.
+    pub fn load(repository: &Path) -> AgeResult<Self> {
+        let anchors = TrustAnchors::load()?;
+        let path = repository.join(REVOCATION_FILE);
+        if !path.is_file() {
+            return Ok(Self::default().with_trust_anchors(anchors));
+        }
+        let data = fs::read(&path).map_err(|e| AgeError::file_error("read", path.clone(), e))?;
+        serde_json::from_slice::<Self>(&data)
+            .map(|list| list.with_trust_anchors(anchors))
+            .map_err(|e| revocation_error(format!("Invalid revocation list {}: {}", path.display(), e)))
+    }
+
+    /// Accept entries signed by roots in `anchors` as well as by chain keys
+    pub fn with_trust_anchors(mut self, anchors: TrustAnchors) -> Self {
+        self.anchors = anchors;
+        self
+    }
+
+    /// Write the list atomically, owner read/write only
+    pub fn save(&self, repository: &Path) -> AgeResult<()> {
+        let path = repository.join(REVOCATION_FILE);
+        let data = serde_json::to_vec_pretty(self).map_err(|e| revocation_error(e.to_string()))?;
+        write_atomic(&path, &data)
+    }
+
+    /// Add a revocation of `revoked` signed by `signer`
+    pub fn revoke(&mut self, revoked: &AuthorityKey, signer: &AuthorityKey, reason: &str) -> AgeResult<&RevocationEntry> {
+        if !signer_may_revoke(signer.key_type(), revoked.key_type()) {
+            return Err(revocation_error(format!(
+                "Revoking a {} key needs a {} key or higher; {} given",
+                revoked.key_type(), revoked.key_type().parent_type().unwrap_or(KeyType::Skull), signer.key_type())));
+        }
+
+        let mut entry = RevocationEntry {
+            fingerprint: revoked.fingerprint().hex().to_string(),
+            key_type: revoked.key_type(),
+            recipient: signing::key_recipient(revoked)?,
+            reason: reason.to_string(),
+            revoked_at: Utc::now(),
+            signer_fingerprint: signer.fingerprint().hex().to_string(),
+            signer_type: signer.key_type(),
+            signer_recipient: signing::key_recipient(signer)?,
+            signature: String::new(),
+        };
+        entry.signature = hex::encode(signing::sign_with_key(signer, &entry.signed_bytes())?);
+        self.entries.push(entry);
+        Ok(self.entries.last().expect("entry just pushed"))
+    }
+
+    /// Entries that verify against `chain`
+    pub fn valid_entries<'a>(&'a self, chain: &'a AuthorityChain) -> impl Iterator<Item = &'a RevocationEntry> + 'a {
+        self.entries.iter().filter(move |entry| entry.verify(chain, &self.anchors).is_ok())
+    }
+
+    /// The entry revoking `key`, directly or through a key above it in `chain`
+    ///
+    /// Fails when an entry naming the key or one of its ancestors does not verify.
+    pub fn revocation_of(&self, key: &AuthorityKey, chain: &AuthorityChain) -> AgeResult<Option<&RevocationEntry>> {
+        let recipient = signing::key_recipient(key).ok();
+        for entry in &self.entries {
+            let direct = entry.fingerprint == key.fingerprint().hex() || recipient.as_deref() == Some(entry.recipient.as_str());
+            let inherited = match find_key(chain, &entry.fingerprint) {
+                Some(revoked) => chain.find_dependent_keys(revoked.fingerprint())?.iter().any(|dependent| dependent.fingerprint() == key.fingerprint()),
+                None => false,
+            };
+            if direct || inherited {
+                return self.verified(entry, chain, &[]).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// The entry revoking the first key of `lineage` or a key above it
+    ///
+    /// `lineage` runs from a key up towards its root, as `TrustAnchors::verify` returns
+    /// it; its keys may sign entries alongside the chain and the trusted roots. Fails
+    /// when an entry naming one of these keys does not verify.
+    pub fn lineage_revocation(&self, lineage: &[KeyEnvelope], chain: &AuthorityChain) -> AgeResult<Option<&RevocationEntry>> {
+        for entry in &self.entries {
+            let inherited = chain_key(chain, &entry.fingerprint, &entry.recipient)
+                .map(|revoked| chain.find_dependent_keys(revoked.fingerprint()))
+                .transpose()?
+                .is_some_and(|dependents| dependents.iter().any(|dependent| lineage.iter().any(|key| key.fingerprint == dependent.fingerprint().hex())));
+            if inherited || lineage.iter().any(|key| key.fingerprint == entry.fingerprint || key.recipient == entry.recipient) {
+                return self.verified(entry, chain, lineage).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Fail when `key` is revoked
+    pub fn check(&self, key: &AuthorityKey, chain: &AuthorityChain) -> AgeResult<()> {
+        match self.revocation_of(key, chain)? {
+            None => Ok(()),
+            Some(entry) => {
+                let direct = entry.fingerprint == key.fingerprint().hex();
+                Err(revoked_error(&key.key_type().to_string(), key.fingerprint().hex(), entry, direct))
+            }
+        }
+    }
+
+    /// Fail when the first key of `lineage` is revoked, itself or through a key above it
+    pub fn check_lineage(&self, lineage: &[KeyEnvelope], chain: &AuthorityChain) -> AgeResult<()> {
+        let Some(key) = lineage.first() else {
+            return Ok(());
+        };
+        match self.lineage_revocation(lineage, chain)? {
+            None => Ok(()),
+            Some(entry) => {
+                let direct = entry.recipient == key.recipient || entry.fingerprint == key.fingerprint;
+                Err(revoked_error(&key.key_type.to_string(), &key.fingerprint, entry, direct))
+            }
+        }
+    }
+
+    /// Whether an `age1...` recipient is revoked, itself or through a key above it in `chain`
+    pub fn is_recipient_revoked(&self, recipient: &str, chain: &AuthorityChain) -> AgeResult<bool> {
+        if let Some(key) = chain_key(chain, "", recipient) {
+            return Ok(self.revocation_of(key, chain)?.is_some());
+        }
+        match self.entries.iter().find(|entry| entry.recipient == recipient) {
+            Some(entry) => self.verified(entry, chain, &[]).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Whether a key fingerprint (hex) is revoked, itself or through a key above it in `chain`
+    pub fn is_fingerprint_revoked(&self, fingerprint_hex: &str, chain: &AuthorityChain) -> AgeResult<bool> {
+        if let Some(key) = find_key(chain, fingerprint_hex) {
+            return Ok(self.revocation_of(key, chain)?.is_some());
+        }
+        match self.entries.iter().find(|entry| entry.fingerprint == fingerprint_hex) {
+            Some(entry) => self.verified(entry, chain, &[]).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    /// `entry` when it verifies; an entry that names a key but cannot be verified is an error
+    fn verified<'a>(&self, entry: &'a RevocationEntry, chain: &AuthorityChain, lineage: &[KeyEnvelope]) -> AgeResult<&'a RevocationEntry> {
+        entry.verify_with_lineage(chain, &self.anchors, lineage)
+            .map(|_| entry)
+            .map_err(|e| revocation_error(format!("Revocation of {} key {} cannot be verified: {}", entry.key_type, short(&entry.fingerprint), e)))
+    }
+}
+
+/// A key at or above the revoked key's parent may revoke it
+fn signer_may_revoke(signer: KeyType, revoked: KeyType) -> bool {
+    let parent_level = revoked.parent_type().map_or(revoked.authority_level(), |parent| parent.authority_level());
+    signer.authority_level() >= parent_level
+}
+
+fn find_key<'a>(chain: &'a AuthorityChain, fingerprint_hex: &str) -> Option<&'a AuthorityKey> {
+    chain_keys(chain).find(|key| key.fingerprint().hex() == fingerprint_hex)
+}
+
+/// Chain key with this fingerprint or recipient
+fn chain_key<'a>(chain: &'a AuthorityChain, fingerprint_hex: &str, recipient: &str) -> Option<&'a AuthorityKey> {
+    chain_keys(chain).find(|key| key.fingerprint().hex() == fingerprint_hex
+        || signing::key_recipient(key).is_ok_and(|key_recipient| key_recipient == recipient))
+}
+
+fn chain_keys(chain: &AuthorityChain) -> impl Iterator<Item = &AuthorityKey> {
+    [KeyType::Skull, KeyType::Master, KeyType::Repo, KeyType::Ignition, KeyType::Distro]
+        .into_iter()
+        .flat_map(|key_type| chain.get_keys_by_type(key_type))
+}
+
+fn revoked_error(key_type: &str, fingerprint_hex: &str, entry: &RevocationEntry, direct: bool) -> AgeError {
+    AgeError::SecurityValidationFailed {
+        validation_type: "key_revoked".to_string(),
+        details: if direct {
+            format!("{} key {} was revoked at {}: {}", key_type, short(fingerprint_hex), entry.revoked_at.to_rfc3339(), entry.reason)
+        } else {
+            format!("{} key {} is revoked with its ancestor {} key {} ({})", key_type, short(fingerprint_hex),
+                entry.key_type, short(&entry.fingerprint), entry.reason)
+        },
+    }
+}
+
+fn short(fingerprint_hex: &str) -> &str {
+    &fingerprint_hex[..fingerprint_hex.len().min(8)]
+}
+
+fn revocation_error(reason: String) -> AgeError {
+    AgeError::SecurityValidationFailed {
+        validation_type: "revocation".to_string(),
+        details: reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::chain::{KeyFormat, KeyMaterial};
+    use tempfile::TempDir;
+
+    fn key(seed: u8, key_type: KeyType) -> AuthorityKey {
+        let hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").unwrap();
+        let identity = bech32::encode_upper::<bech32::Bech32>(hrp, &[seed; 32]).unwrap();
+        let material = KeyMaterial::new(identity.as_bytes().to_vec(), Some(identity.into_bytes()), KeyFormat::Age);
+        AuthorityKey::new(material, key_type, None, None).unwrap()
+    }
+
+    fn chain() -> (AuthorityChain, Vec<AuthorityKey>) {
+        let keys = vec![key(1, KeyType::Repo), key(2, KeyType::Ignition), key(3, KeyType::Distro)];
+        let mut chain = AuthorityChain::new();
+        for key in &keys {
+            chain.add_key(key.clone()).unwrap();
+        }
+        chain.add_authority_relationship(keys[0].fingerprint(), keys[1].fingerprint()).unwrap();
+        chain.add_authority_relationship(keys[1].fingerprint(), keys[2].fingerprint()).unwrap();
+        (chain, keys)
+    }
+
+    #[test]
+    fn test_revocation_covers_descendants_and_survives_reload() {
+        let (chain, keys) = chain();
+        let repo = TempDir::new().unwrap();
+
+        let mut list = RevocationList::default();
+        assert!(list.revoke(&keys[1], &keys[2], "distro cannot revoke its parent").is_err());
+        list.revoke(&keys[1], &keys[0], "laptop lost").unwrap();
+        list.save(repo.path()).unwrap();
+
+        let list = RevocationList::load(repo.path()).unwrap();
+        assert!(list.check(&keys[0], &chain).is_ok());
+        assert!(list.check(&keys[1], &chain).is_err());
+        assert!(list.check(&keys[2], &chain).is_err());
+
+        // Without the chain, the copied key is still matched by its recipient when the
+        // signer is a trusted root, and refused when nothing vouches for the signer
+        let empty = AuthorityChain::new();
+        assert!(list.check(&keys[1], &empty).unwrap_err().to_string().contains("cannot be verified"));
+        let anchors = TrustAnchors::new().with_root(KeyType::Repo, &signing::key_recipient(&keys[0]).unwrap()).unwrap();
+        let list = list.with_trust_anchors(anchors);
+        assert!(list.check(&keys[1], &empty).unwrap_err().to_string().contains("was revoked"));
+        assert!(list.is_fingerprint_revoked(keys[1].fingerprint().hex(), &empty).unwrap());
+        assert!(list.is_fingerprint_revoked(keys[2].fingerprint().hex(), &chain).unwrap());
+        assert!(!list.is_recipient_revoked(&signing::key_recipient(&keys[0]).unwrap(), &chain).unwrap());
+    }
+
+    #[test]
+    fn test_forged_or_underpowered_entries_refused() {
+        let (chain, keys) = chain();
+        let mut list = RevocationList::default();
+        list.revoke(&keys[2], &keys[1], "rotated").unwrap();
+
+        let mut forged = list.clone();
+        forged.entries[0].reason = "edited".to_string();
+        assert!(forged.check(&keys[2], &chain).unwrap_err().to_string().contains("cannot be verified"));
+        assert!(forged.check(&keys[0], &chain).is_ok());
+
+        // A valid signature from a key claiming a higher type than the chain gives it
+        let mut impostor = RevocationList::default();
+        let fake_master = key(2, KeyType::Master);
+        impostor.revoke(&keys[1], &fake_master, "takeover").unwrap();
+        assert!(impostor.check(&keys[1], &chain).unwrap_err().to_string().contains("cannot be verified"));
+
+        // Outside the chain, a self-declared signer type counts for nothing
+        let outsider = key(9, KeyType::Skull);
+        let mut rogue = RevocationList::default();
+        rogue.revoke(&keys[0], &outsider, "takeover").unwrap();
+        assert!(rogue.check(&keys[0], &chain).unwrap_err().to_string().contains("cannot be verified"));
+        let anchors = TrustAnchors::new().with_root(KeyType::Master, &signing::key_recipient(&outsider).unwrap()).unwrap();
+        assert!(rogue.with_trust_anchors(anchors).check(&keys[0], &chain).unwrap_err().to_string().contains("cannot be verified"));
+
+        assert!(list.check(&keys[2], &chain).unwrap_err().to_string().contains("was revoked"));
+    }
+
+    #[test]
+    fn test_lineage_revocation_cascades_without_a_chain() {
+        let (_, keys) = chain();
+        let envelope = |key: &AuthorityKey, parent: Option<&AuthorityKey>| {
+            let envelope = KeyEnvelope::new(key.key_type(), &signing::key_recipient(key).unwrap()).unwrap();
+            match parent {
+                Some(parent) => envelope.with_parent(parent).unwrap(),
+                None => envelope,
+            }
+        };
+        let lineage = vec![envelope(&keys[2], Some(&keys[1])), envelope(&keys[1], Some(&keys[0])), envelope(&keys[0], None)];
+        let empty = AuthorityChain::new();
+
+        // The repo key in the distro key's lineage vouches for its revocation of the
+        // ignition key, which takes the distro key down with it
+        let mut list = RevocationList::default();
+        list.revoke(&keys[1], &keys[0], "laptop lost").unwrap();
+        let error = list.check_lineage(&lineage, &empty).unwrap_err().to_string();
+        assert!(error.contains("revoked with its ancestor"));
+        assert!(list.check_lineage(&lineage[1..], &empty).unwrap_err().to_string().contains("was revoked"));
+        assert!(list.check_lineage(&lineage[2..], &empty).is_ok());
+
+        // A signer outside the lineage is not believed, and not ignored either
+        let mut rogue = RevocationList::default();
+        rogue.revoke(&keys[1], &key(8, KeyType::Repo), "takeover").unwrap();
+        assert!(rogue.check_lineage(&lineage, &empty).unwrap_err().to_string().contains("cannot be verified"));
+    }
+}
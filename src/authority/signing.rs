@@ -0,0 +1,187 @@
+//! Authority Signatures - XEdDSA over age X25519 keys
+//!
+//! Authority keys are age X25519 identities, which cannot sign directly. XEdDSA (the
+//! scheme Signal uses for the same problem) turns the X25519 secret into an Ed25519-style
+//! signing key whose verifying key is derived from the `age1...` recipient alone, so
+//! anyone holding a key's public recipient can check what it signed.
+//!
+//! Security Guardian: Edgar - Signatures anyone can check against a recipient
+
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::montgomery::MontgomeryPoint;
+use curve25519_dalek::scalar::Scalar;
+use sha2::{Digest, Sha512};
+
+use crate::encryption::age_automation::error::{AgeError, AgeResult};
+use crate::encryption::age_automation::secret::Zeroizing;
+use super::chain::AuthorityKey;
+
+/// Signature length: compressed R followed by scalar s
+pub const SIGNATURE_LEN: usize = 64;
+
+/// Sign `message` with a raw X25519 secret
+pub fn sign(secret: &[u8; 32], message: &[u8]) -> [u8; SIGNATURE_LEN] {
+    let mut clamped = Zeroizing::new(*secret);
+    clamped[0] &= 248;
+    clamped[31] &= 127;
+    clamped[31] |= 64;
+    let k = Scalar::from_bytes_mod_order(*clamped);
+
+    // Use the key pair whose public point has sign bit 0, as the verifier will assume
+    let point = EdwardsPoint::mul_base(&k).compress();
+    let a = if point.as_bytes()[31] & 0x80 != 0 { -k } else { k };
+    let public = EdwardsPoint::mul_base(&a).compress();
+
+    let mut nonce = Zeroizing::new([0u8; 64]);
+    rand::fill(&mut nonce[..]);
+    let mut hash1 = Sha512::new();
+    hash1.update([0xFE]);
+    hash1.update([0xFF; 31]);
+    hash1.update(a.as_bytes());
+    hash1.update(message);
+    hash1.update(&nonce[..]);
+    let r = Scalar::from_hash(hash1);
+
+    let big_r = EdwardsPoint::mul_base(&r).compress();
+    let h = challenge(&big_r, &public, message);
+    let s = r + h * a;
+
+    let mut signature = [0u8; SIGNATURE_LEN];
+    signature[..32].copy_from_slice(big_r.as_bytes());
+    signature[32..].copy_from_slice(s.as_bytes());
+    signature
+}
+
+/// Check a signature against a raw X25519 public key
+pub fn verify(public: &[u8; 32], message: &[u8], signature: &[u8]) -> bool {
+    let Ok(signature) = <[u8; SIGNATURE_LEN]>::try_from(signature) else {
+        return false;
+    };
+    let Some(public) = MontgomeryPoint(*public).to_edwards(0) else {
+        return false;
+    };
+    let big_r = CompressedEdwardsY(signature[..32].try_into().expect("32-byte half"));
+    let Some(s) = Option::<Scalar>::from(Scalar::from_canonical_bytes(signature[32..].try_into().expect("32-byte half"))) else {
+        return false;
+    };
+
+    let h = challenge(&big_r, &public.compress(), message);
+    let expected = EdwardsPoint::vartime_double_scalar_mul_basepoint(&-h, &public, &s);
+    expected.compress() == big_r && !public.is_small_order()
+}
+
+fn challenge(big_r: &CompressedEdwardsY, public: &CompressedEdwardsY, message: &[u8]) -> Scalar {
+    let mut hash = Sha512::new();
+    hash.update(big_r.as_bytes());
+    hash.update(public.as_bytes());
+    hash.update(message);
+    Scalar::from_hash(hash)
+}
+
+/// Raw secret of an `AGE-SECRET-KEY-1...` identity
+pub fn identity_secret(identity: &str) -> AgeResult<Zeroizing<[u8; 32]>> {
+    let invalid = || key_error("Invalid AGE-SECRET-KEY identity");
+    let (hrp, data) = bech32::decode(identity.trim()).map_err(|_| invalid())?;
+    if !hrp.as_str().eq_ignore_ascii_case("age-secret-key-") {
+        return Err(invalid());
+    }
+    let data = Zeroizing::new(data);
+    let secret: [u8; 32] = data.as_slice().try_into().map_err(|_| invalid())?;
+    Ok(Zeroizing::new(secret))
+}
+
+/// Raw public key of an `age1...` recipient
+pub fn recipient_public(recipient: &str) -> AgeResult<[u8; 32]> {
+    let invalid = || key_error("Invalid age1 recipient");
+    let (hrp, data) = bech32::decode(recipient.trim()).map_err(|_| invalid())?;
+    if hrp.as_str() != "age" {
+        return Err(invalid());
+    }
+    data.try_into().map_err(|_| invalid())
+}
+
+/// `age1...` recipient of a raw X25519 secret
+pub fn recipient_for_secret(secret: &[u8; 32]) -> AgeResult<String> {
+    let public = x25519_dalek::PublicKey::from(&x25519_dalek::StaticSecret::from(*secret));
+    let hrp = bech32::Hrp::parse("age").map_err(|e| key_error(&e.to_string()))?;
+    bech32::encode::<bech32::Bech32>(hrp, public.as_bytes()).map_err(|e| key_error(&e.to_string()))
+}
+
+/// The `AGE-SECRET-KEY-1...` line of an authority key, if it holds its secret
+pub fn key_identity(key: &AuthorityKey) -> Option<Zeroizing<String>> {
+    let private = key.key_material().private_key()?;
+    let text = Zeroizing::new(String::from_utf8_lossy(private).into_owned());
+    text.lines()
+        .map(str::trim)
+        .find(|line| line.starts_with("AGE-SECRET-KEY-"))
+        .map(|line| Zeroizing::new(line.to_string()))
+}
+
+/// `age1...` recipient of an authority key, from its secret or its public material
+pub fn key_recipient(key: &AuthorityKey) -> AgeResult<String> {
+    if let Some(identity) = key_identity(key) {
+        return recipient_for_secret(&*identity_secret(&identity)?);
+    }
+    declared_recipient(key).ok_or_else(|| key_error("Authority key has no age recipient"))
+}
+
+/// `age1...` recipient named in an authority key's public material, if any
+fn declared_recipient(key: &AuthorityKey) -> Option<String> {
+    let public = String::from_utf8_lossy(key.key_material().public_key()).into_owned();
+    public.lines()
+        .map(str::trim)
+        .find_map(|line| line.strip_prefix("# public key: ").or(Some(line)).filter(|line| line.starts_with("age1")))
+        .map(str::to_string)
+}
+
+/// Sign with an authority key's identity
+pub fn sign_with_key(key: &AuthorityKey, message: &[u8]) -> AgeResult<[u8; SIGNATURE_LEN]> {
+    let identity = key_identity(key)
+        .ok_or_else(|| key_error("Signing requires the authority key's secret identity"))?;
+    Ok(sign(&*identity_secret(&identity)?, message))
+}
+
+/// Sign `challenge` and check the signature against the key's public recipient
+///
+/// Proves the caller holds the key's secret and that the secret belongs to the recipient
+/// its public material declares, so public bytes alone cannot act for the key.
+pub fn prove_possession(key: &AuthorityKey, challenge: &[u8]) -> AgeResult<[u8; SIGNATURE_LEN]> {
+    let signature = sign_with_key(key, challenge)?;
+    let recipient = declared_recipient(key).map_or_else(|| key_recipient(key), Ok)?;
+    if !verify(&recipient_public(&recipient)?, challenge, &signature) {
+        return Err(key_error("Authority key secret does not match its public recipient"));
+    }
+    Ok(signature)
+}
+
+fn key_error(reason: &str) -> AgeError {
+    AgeError::InvalidOperation {
+        operation: "authority_signature".to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_verifies_against_recipient_only() {
+        for seed in [1u8, 2, 3, 200] {
+            let secret = [seed; 32];
+            let recipient = recipient_for_secret(&secret).unwrap();
+            let public = recipient_public(&recipient).unwrap();
+
+            let signature = sign(&secret, b"revoke distro key");
+            assert!(verify(&public, b"revoke distro key", &signature));
+            assert!(!verify(&public, b"revoke master key", &signature));
+
+            let other = recipient_public(&recipient_for_secret(&[seed.wrapping_add(7); 32]).unwrap()).unwrap();
+            assert!(!verify(&other, b"revoke distro key", &signature));
+
+            let mut tampered = signature;
+            tampered[40] ^= 1;
+            assert!(!verify(&public, b"revoke distro key", &tampered));
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! Trust Anchors - Key envelopes verified up to a root the operator trusts
+//!
+//! Envelope headers (`type`, `expires`, `policy`, `capabilities`) are plain text the
+//! key's holder can edit; only the parent signature binds them. `TrustAnchors` follows a
+//! key's `parent` fingerprints through the enveloped key files beside it and in extra
+//! search directories, checking every parent signature, until it reaches a trusted
+//! root. A key whose lineage is not on disk may instead carry an authority certificate
+//! saved as `<key file>.cert` (see `certificate`), checked against the same roots.
+//!
+//! Roots live outside every repository, in `$XDG_CONFIG_HOME/padlock/trusted-roots`
+//! (or `~/.config/...`), one `<key type> <age1 recipient>` per line, `#` for comments.
+//! A root is trusted only at the type it is listed with, so retyping a root key file
+//! gains nothing.
+//!
+//! Security Guardian: Edgar - No header believed before its signature
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::encryption::age_automation::error::{AgeError, AgeResult};
+use super::certificate::AuthorityCertificate;
+use super::chain::KeyType;
+use super::envelope::KeyEnvelope;
+use super::signing;
+
+/// Trusted roots file name inside the global padlock config directory
+pub const ROOTS_FILE: &str = "trusted-roots";
+
+/// A key trusted without a parent signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustedRoot {
+    pub key_type: KeyType,
+    pub recipient: String,
+}
+
+/// Roots and key directories envelopes are verified against
+#[derive(Debug, Clone, Default)]
+pub struct TrustAnchors {
+    roots: Vec<TrustedRoot>,
+    search_dirs: Vec<PathBuf>,
+}
+
+impl TrustAnchors {
+    /// No roots: nothing verifies until one is added
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Roots from the global trusted-roots file; none when it does not exist
+    pub fn load() -> AgeResult<Self> {
+        match roots_path() {
+            Some(path) if path.is_file() => Self::from_file(&path),
+            _ => Ok(Self::new()),
+        }
+    }
+
+    /// Roots from a trusted-roots file
+    pub fn from_file(path: &Path) -> AgeResult<Self> {
+        let content = fs::read_to_string(path).map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?;
+        let mut anchors = Self::new();
+        for line in content.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let Some((key_type, recipient)) = line.split_once(char::is_whitespace) else {
+                return Err(trust_error(format!("{}: expected '<key type> <age1 recipient>', got '{}'", path.display(), line)));
+            };
+            anchors = anchors.with_root(KeyType::from_str(key_type)?, recipient.trim())?;
+        }
+        Ok(anchors)
+    }
+
+    /// Trust `recipient` as a key of `key_type`
+    pub fn with_root(mut self, key_type: KeyType, recipient: &str) -> AgeResult<Self> {
+        signing::recipient_public(recipient)?;
+        self.roots.push(TrustedRoot { key_type, recipient: recipient.to_string() });
+        Ok(self)
+    }
+
+    /// Also look for parent key files in `dir`
+    pub fn with_search_dir(mut self, dir: PathBuf) -> Self {
+        self.search_dirs.push(dir);
+        self
+    }
+
+    pub fn roots(&self) -> &[TrustedRoot] {
+        &self.roots
+    }
+
+    /// The root entry for `recipient`, if it is trusted
+    pub fn root_for(&self, recipient: &str) -> Option<&TrustedRoot> {
+        self.roots.iter().find(|root| root.recipient == recipient)
+    }
+
+    /// Check the envelope read from `path` up to a trusted root
+    ///
+    /// Returns the verified lineage, the key first and the root last (only the key when
+    /// a certificate proved it).
+    pub fn verify(&self, envelope: &KeyEnvelope, path: &Path) -> AgeResult<Vec<KeyEnvelope>> {
+        let mut dirs = vec![parent_dir(path)];
+        dirs.extend(self.search_dirs.iter().cloned());
+        let candidates = key_candidates(&dirs);
+
+        let mut lineage = vec![envelope.clone()];
+        loop {
+            let current = lineage.last().expect("lineage starts with the key");
+            if let Some(root) = self.root_for(&current.recipient) {
+                if root.key_type != current.key_type {
+                    return Err(trust_error(format!("Trusted root {} is a {} key, but its file claims {}",
+                        short(&current.fingerprint), root.key_type, current.key_type)));
+                }
+                return Ok(lineage);
+            }
+            let Some((parent, _)) = current.parent.as_ref().and_then(|parent| candidates.get(parent)) else {
+                break;
+            };
+            if current.key_type.parent_type() != Some(parent.key_type) {
+                return Err(trust_error(format!("{} key {} names a {} key as its parent",
+                    current.key_type, short(&current.fingerprint), parent.key_type)));
+            }
+            current.verify_parent(&parent.recipient)?;
+            lineage.push(parent.clone());
+        }
+
+        let certificate = certificate_path(path);
+        if certificate.is_file() {
+            self.verify_certificate(envelope, &certificate)?;
+            return Ok(vec![envelope.clone()]);
+        }
+        let last = lineage.last().expect("lineage starts with the key");
+        Err(trust_error(format!("{} key {} ({}) does not chain to a trusted root; stopped at {} key {}",
+            envelope.key_type, short(&envelope.fingerprint), path.display(), last.key_type, short(&last.fingerprint))))
+    }
+
+    /// Verified lineage of the enveloped key file with this fingerprint, looked up in
+    /// `dirs` and the search directories
+    pub fn lineage_of(&self, fingerprint_hex: &str, dirs: &[PathBuf]) -> AgeResult<Vec<KeyEnvelope>> {
+        let mut all = dirs.to_vec();
+        all.extend(self.search_dirs.iter().cloned());
+        let candidates = key_candidates(&all);
+        let (envelope, path) = candidates.get(fingerprint_hex)
+            .ok_or_else(|| trust_error(format!("No enveloped key file for {} in the key directories", short(fingerprint_hex))))?;
+        self.verify(envelope, path)
+    }
+
+    /// Check a certificate for the envelope's key against every root
+    fn verify_certificate(&self, envelope: &KeyEnvelope, path: &Path) -> AgeResult<()> {
+        let certificate = AuthorityCertificate::load(path)?;
+        let mut failure = trust_error(format!("{} does not chain to a trusted root", path.display()));
+        for root in &self.roots {
+            let verified = match certificate.verify_now(&root.recipient) {
+                Ok(verified) => verified,
+                Err(error) => {
+                    failure = error;
+                    continue;
+                }
+            };
+            if verified.root.key_type != root.key_type {
+                return Err(trust_error(format!("{} is issued by a {} key trusted only as {}", path.display(), verified.root.key_type, root.key_type)));
+            }
+            if verified.subject.recipient != envelope.recipient || verified.subject.key_type != envelope.key_type
+                || verified.capabilities != envelope.capabilities {
+                return Err(trust_error(format!("{} certifies a different key, type or capabilities than its key file", path.display())));
+            }
+            return Ok(());
+        }
+        Err(failure)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/padlock/trusted-roots`, falling back to `~/.config`
+pub fn roots_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("padlock").join(ROOTS_FILE))
+}
+
+/// Certificate saved beside a key file
+pub fn certificate_path(key_path: &Path) -> PathBuf {
+    let mut name = key_path.as_os_str().to_os_string();
+    name.push(".cert");
+    PathBuf::from(name)
+}
+
+/// Enveloped `.key` files in `dirs`, by fingerprint; the first file found wins
+pub(crate) fn key_candidates(dirs: &[PathBuf]) -> HashMap<String, (KeyEnvelope, PathBuf)> {
+    let mut candidates = HashMap::new();
+    let mut seen = Vec::new();
+    for dir in dirs {
+        if seen.contains(dir) {
+            continue;
+        }
+        seen.push(dir.clone());
+        let Ok(entries) = fs::read_dir(dir) else { continue };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().is_some_and(|ext| ext == "key") {
+                if let Ok(Some(envelope)) = KeyEnvelope::read(&path) {
+                    candidates.entry(envelope.fingerprint.clone()).or_insert((envelope, path));
+                }
+            }
+        }
+    }
+    candidates
+}
+
+/// Directory holding `path`, `.` for bare file names
+pub(crate) fn parent_dir(path: &Path) -> PathBuf {
+    path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new(".")).to_path_buf()
+}
+
+fn short(fingerprint_hex: &str) -> &str {
+    &fingerprint_hex[..fingerprint_hex.len().min(8)]
+}
+
+fn trust_error(details: String) -> AgeError {
+    AgeError::SecurityValidationFailed {
+        validation_type: "key_trust".to_string(),
+        details,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::chain::AuthorityKey;
+    use tempfile::TempDir;
+
+    fn identity(seed: u8) -> String {
+        let hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").unwrap();
+        bech32::encode_upper::<bech32::Bech32>(hrp, &[seed; 32]).unwrap()
+    }
+
+    fn recipient(seed: u8) -> String {
+        signing::recipient_for_secret(&signing::identity_secret(&identity(seed)).unwrap()).unwrap()
+    }
+
+    fn write_key(dir: &Path, seed: u8, key_type: KeyType, parent: Option<&AuthorityKey>) -> PathBuf {
+        let mut envelope = KeyEnvelope::new(key_type, &recipient(seed)).unwrap();
+        if let Some(parent) = parent {
+            envelope = envelope.with_parent(parent).unwrap();
+        }
+        let path = dir.join(format!("auth-{}.key", key_type));
+        fs::write(&path, envelope.render(&identity(seed)).as_bytes()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_lineage_verified_to_trusted_root() {
+        let dir = TempDir::new().unwrap();
+        let repo = write_key(dir.path(), 1, KeyType::Repo, None);
+        let ignition = write_key(dir.path(), 2, KeyType::Ignition, Some(&AuthorityKey::open(&repo).unwrap()));
+        let distro = write_key(dir.path(), 3, KeyType::Distro, Some(&AuthorityKey::open(&ignition).unwrap()));
+        let envelope = KeyEnvelope::read(&distro).unwrap().unwrap();
+
+        assert!(TrustAnchors::new().verify(&envelope, &distro).is_err());
+        let anchors = TrustAnchors::new().with_root(KeyType::Repo, &recipient(1)).unwrap();
+        let lineage = anchors.verify(&envelope, &distro).unwrap();
+        let types: Vec<KeyType> = lineage.iter().map(|envelope| envelope.key_type).collect();
+        assert_eq!(types, vec![KeyType::Distro, KeyType::Ignition, KeyType::Repo]);
+        assert_eq!(KeyEnvelope::read_verified(&ignition, &anchors).unwrap().key_type, KeyType::Ignition);
+
+        // A root is trusted only at its listed type
+        let misfiled = TrustAnchors::new().with_root(KeyType::Skull, &recipient(1)).unwrap();
+        assert!(misfiled.verify(&envelope, &distro).is_err());
+
+        // Parents elsewhere are found through a search directory
+        let split = TempDir::new().unwrap();
+        fs::rename(&ignition, split.path().join("auth-ignition.key")).unwrap();
+        assert!(anchors.verify(&envelope, &distro).is_err());
+        assert!(anchors.clone().with_search_dir(split.path().to_path_buf()).verify(&envelope, &distro).is_ok());
+    }
+
+    #[test]
+    fn test_edited_headers_and_roots_file() {
+        let dir = TempDir::new().unwrap();
+        let repo = write_key(dir.path(), 1, KeyType::Repo, None);
+        let ignition = write_key(dir.path(), 2, KeyType::Ignition, Some(&AuthorityKey::open(&repo).unwrap()));
+        let distro = write_key(dir.path(), 3, KeyType::Distro, Some(&AuthorityKey::open(&ignition).unwrap()));
+
+        let roots = dir.path().join(ROOTS_FILE);
+        fs::write(&roots, format!("# padlock roots\nrepo {}\n", recipient(1))).unwrap();
+        let anchors = TrustAnchors::from_file(&roots).unwrap();
+        assert_eq!(anchors.roots().len(), 1);
+        assert!(KeyEnvelope::read_verified(&distro, &anchors).is_ok());
+
+        // Pushing the expiry out breaks the parent signature
+        let content = fs::read_to_string(&distro).unwrap().replace("# expires: never", "# expires: 2099-01-01T00:00:00Z");
+        let extended = dir.path().join("extended.key");
+        fs::write(&extended, content).unwrap();
+        assert!(KeyEnvelope::read(&extended).unwrap().is_some());
+        assert!(KeyEnvelope::read_verified(&extended, &anchors).is_err());
+
+        fs::write(&roots, "repo not-a-recipient\n").unwrap();
+        assert!(TrustAnchors::from_file(&roots).is_err());
+    }
+}
@@ -5,12 +5,19 @@
 //!
 //! Security Guardian: Edgar - Cryptographic proof generation and validation
 
+use std::path::Path;
 use std::time::Duration;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::encryption::age_automation::error::{AgeError, AgeResult};
+use crate::encryption::age_automation::{
+    error::{AgeError, AgeResult},
+    security::AuditLogger,
+    lifecycle::{load_mark, trusted_now},
+};
 use super::chain::{KeyType, KeyFingerprint, AuthorityKey, AuthorityChain};
+use super::revocation::RevocationList;
+use super::authorization::{repository_root, Actor, OperationAuthorizer, OperationPolicy};
 
 /// Authority levels in the hierarchy
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -34,6 +41,17 @@ impl AuthorityLevel {
         }
     }
     
+    /// Key type that holds this authority level
+    pub fn key_type(&self) -> KeyType {
+        match self {
+            AuthorityLevel::SkullAuthority => KeyType::Skull,
+            AuthorityLevel::MasterControl => KeyType::Master,
+            AuthorityLevel::RepoControl => KeyType::Repo,
+            AuthorityLevel::IgnitionControl => KeyType::Ignition,
+            AuthorityLevel::DistroAccess => KeyType::Distro,
+        }
+    }
+
     /// Get human-readable description
     pub fn description(&self) -> &'static str {
         match self {
@@ -130,7 +148,7 @@ impl AuthorityProof {
     /// Verify authority proof is valid and current
     pub fn verify(&self, parent: &AuthorityKey, child: &AuthorityKey) -> AgeResult<bool> {
         // 1. Verify proof hasn't expired (24 hours)
-        if self.proof_timestamp + chrono::Duration::hours(24) < Utc::now() {
+        if self.proof_timestamp + chrono::Duration::hours(24) < trusted_now() {
             return Err(AgeError::InvalidOperation {
                 operation: "verify_authority_proof".to_string(),
                 reason: "Authority proof has expired".to_string(),
@@ -291,18 +309,63 @@ pub struct AuthorityValidationEngine {
     authority_chain: AuthorityChain,
     proof_cache: std::collections::HashMap<String, AuthorityProof>,
     cache_expiry: Duration,
+    /// Fixed revocations; None reads them from the repository each operation targets
+    revocations: Option<RevocationList>,
+    authorizer: OperationAuthorizer,
 }
 
 impl AuthorityValidationEngine {
     /// Create new validation engine
+    ///
+    /// Keys are checked against the `.padlock/revocations.json` of the repository an
+    /// operation targets, or of the current directory when it names no path.
     pub fn new(authority_chain: AuthorityChain) -> Self {
         Self {
             authority_chain,
             proof_cache: std::collections::HashMap::new(),
             cache_expiry: Duration::from_secs(3600), // 1 hour cache
+            revocations: None,
+            authorizer: OperationAuthorizer::new(),
+        }
+    }
+    
+    /// Refuse keys revoked in `revocations`, and every key below them, instead of
+    /// reading each repository's list; an empty list opts out of revocation checks
+    pub fn with_revocations(mut self, revocations: RevocationList) -> Self {
+        self.revocations = Some(revocations);
+        self.proof_cache.clear();
+        self
+    }
+    
+    /// Revocations that apply to operations on `path`
+    pub fn revocations_for(&self, path: Option<&Path>) -> AgeResult<RevocationList> {
+        match &self.revocations {
+            Some(revocations) => Ok(revocations.clone()),
+            None => RevocationList::load(&repository_root(path.unwrap_or(Path::new(".")))),
         }
     }
     
+    /// Authorize operations under `policy` instead of each repository's policy file
+    pub fn with_authorization_policy(mut self, policy: OperationPolicy) -> Self {
+        self.authorizer = self.authorizer.with_policy(policy);
+        self
+    }
+    
+    /// Authorizer applying the engine's operation policy
+    pub fn authorizer(&self) -> &OperationAuthorizer {
+        &self.authorizer
+    }
+    
+    /// Fail when a key, or a key above it, has been revoked
+    pub fn check_not_revoked(&self, key: &AuthorityKey) -> AgeResult<()> {
+        self.check_not_revoked_at(key, None)
+    }
+    
+    /// Same as `check_not_revoked`, against the revocations that apply to `path`
+    pub fn check_not_revoked_at(&self, key: &AuthorityKey, path: Option<&Path>) -> AgeResult<()> {
+        self.revocations_for(path)?.check(key, &self.authority_chain)
+    }
+    
     /// Test if parent has authority over child
     pub fn test_authority(&mut self, parent_fp: &KeyFingerprint, child_fp: &KeyFingerprint) -> AgeResult<bool> {
         // Get keys from chain
@@ -317,10 +380,14 @@ impl AuthorityValidationEngine {
                 reason: format!("Child key not found: {}", child_fp),
             })?;
         
+        // Revoked keys have no authority, whatever proofs were cached for them
+        self.check_not_revoked(parent)?;
+        self.check_not_revoked(child)?;
+        
         // Check cache first
         let cache_key = format!("{}:{}", parent_fp.hex(), child_fp.hex());
         if let Some(cached_proof) = self.proof_cache.get(&cache_key) {
-            if cached_proof.proof_timestamp + chrono::Duration::from_std(self.cache_expiry).unwrap_or_default() > Utc::now() {
+            if cached_proof.proof_timestamp + chrono::Duration::from_std(self.cache_expiry).unwrap_or_default() > trusted_now() {
                 return cached_proof.verify(parent, child);
             }
         }
@@ -351,6 +418,9 @@ impl AuthorityValidationEngine {
                 reason: format!("Parent key not found: {}", parent_fp),
             })?;
         
+        self.check_not_revoked(child)?;
+        self.check_not_revoked(parent)?;
+        
         // Generate and verify subject proof
         let proof = SubjectProof::generate(child, parent)?;
         proof.verify(child, parent)
@@ -386,9 +456,33 @@ impl AuthorityValidationEngine {
         Ok(true)
     }
     
+    /// Authorize `operation` on `path` for a chain key under the operation policy
+    ///
+    /// The required level comes from the policy rules; denials are written to `audit_logger`.
+    /// The target repository's high-water mark is loaded first, so expiry checks that
+    /// follow cannot be fooled by a rewound clock.
+    pub fn authorize_operation(
+        &self,
+        operation: &str,
+        key_fp: &KeyFingerprint,
+        path: Option<&Path>,
+        audit_logger: &AuditLogger,
+    ) -> AgeResult<()> {
+        let key = self.authority_chain.get_key(key_fp)
+            .ok_or_else(|| AgeError::InvalidOperation {
+                operation: "authorize_operation".to_string(),
+                reason: format!("Key not found: {}", key_fp),
+            })?;
+        if let Some(path) = path {
+            load_mark(&repository_root(path))?;
+        }
+        self.check_not_revoked_at(key, path)?;
+        self.authorizer.authorize(operation, path, &Actor::for_key(key), audit_logger)
+    }
+    
     /// Clear expired proofs from cache
     pub fn cleanup_cache(&mut self) {
-        let now = Utc::now();
+        let now = trusted_now();
         let expiry_duration = chrono::Duration::from_std(self.cache_expiry).unwrap_or_default();
         
         self.proof_cache.retain(|_, proof| {
@@ -402,7 +496,7 @@ impl AuthorityValidationEngine {
         let expired = self.proof_cache.values()
             .filter(|proof| {
                 let expiry_duration = chrono::Duration::from_std(self.cache_expiry).unwrap_or_default();
-                proof.proof_timestamp + expiry_duration <= Utc::now()
+                proof.proof_timestamp + expiry_duration <= trusted_now()
             })
             .count();
         
@@ -468,4 +562,33 @@ mod tests {
         let proof = proof.unwrap();
         assert!(proof.verify(&parent_key, &child_key).is_ok());
     }
+    
+    #[test]
+    fn test_engine_reads_repository_revocations_by_default() {
+        let key = |seed: u8, key_type: KeyType| {
+            let hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").unwrap();
+            let identity = bech32::encode_upper::<bech32::Bech32>(hrp, &[seed; 32]).unwrap();
+            let material = KeyMaterial::new(identity.as_bytes().to_vec(), Some(identity.into_bytes()), KeyFormat::Age);
+            AuthorityKey::new(material, key_type, None, None).unwrap()
+        };
+        let (repo_key, ignition) = (key(1, KeyType::Repo), key(2, KeyType::Ignition));
+        let mut chain = AuthorityChain::new();
+        chain.add_key(repo_key.clone()).unwrap();
+        chain.add_key(ignition.clone()).unwrap();
+        chain.add_authority_relationship(repo_key.fingerprint(), ignition.fingerprint()).unwrap();
+        
+        let repo = tempfile::TempDir::new().unwrap();
+        let file = repo.path().join("secrets.env");
+        let mut revocations = RevocationList::default();
+        revocations.revoke(&ignition, &repo_key, "laptop lost").unwrap();
+        revocations.save(repo.path()).unwrap();
+        
+        let engine = AuthorityValidationEngine::new(chain);
+        assert!(engine.check_not_revoked_at(&ignition, Some(&file)).is_err());
+        assert!(engine.check_not_revoked_at(&repo_key, Some(&file)).is_ok());
+        
+        // Opting out takes an explicit empty list
+        let engine = engine.with_revocations(RevocationList::default());
+        assert!(engine.check_not_revoked_at(&ignition, Some(&file)).is_ok());
+    }
 }
\ No newline at end of file
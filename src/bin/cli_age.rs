@@ -15,6 +15,8 @@ use padlock::encryption::age_automation::{
     CrudManager, LockOptions, UnlockOptions, VerificationResult,
     AgeConfig, OutputFormat, AdapterFactory
 };
+use padlock::encryption::age_automation::lifecycle::OperationPlan;
+use padlock::authority::{Actor, TrustAnchors};
 
 /// Age Automation Direct CLI Interface
 #[derive(Parser)]
@@ -31,10 +33,23 @@ struct Cli {
     #[arg(long)]
     audit_log: Option<PathBuf>,
     
+    /// Enveloped key file to act with instead of passphrase-holder authority; it must
+    /// verify up to a root in the trusted-roots file
+    #[arg(long)]
+    as_key: Option<PathBuf>,
+    
     /// Output format for encrypted files
     #[arg(long, default_value = "binary")]
     format: OutputFormatArg,
     
+    /// Print the plan for lock/unlock/rotate/allow/revoke without changing anything
+    #[arg(long)]
+    dry_run: bool,
+    
+    /// Output format for dry-run plans
+    #[arg(long, default_value = "table")]
+    plan_format: PlanFormatArg,
+    
     #[command(subcommand)]
     command: Commands,
 }
@@ -174,6 +189,7 @@ enum Commands {
 enum OutputFormatArg {
     Binary,
     Ascii,
+    Structured,
 }
 
 impl From<OutputFormatArg> for OutputFormat {
@@ -181,10 +197,18 @@ impl From<OutputFormatArg> for OutputFormat {
         match format {
             OutputFormatArg::Binary => OutputFormat::Binary,
             OutputFormatArg::Ascii => OutputFormat::AsciiArmor,
+            OutputFormatArg::Structured => OutputFormat::Structured,
         }
     }
 }
 
+/// Plan output format for CLI argument parsing
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+enum PlanFormatArg {
+    Table,
+    Json,
+}
+
 /// Main dispatcher coordinating all lifecycle operations
 struct LifecycleDispatcher {
     crud_manager: CrudManager,
@@ -193,8 +217,11 @@ struct LifecycleDispatcher {
 
 impl LifecycleDispatcher {
     /// Create new lifecycle dispatcher
-    fn new(audit_log: Option<PathBuf>, verbose: bool) -> Result<Self, Box<dyn std::error::Error>> {
-        let crud_manager = CrudManager::with_defaults()?;
+    fn new(audit_log: Option<PathBuf>, as_key: Option<PathBuf>, verbose: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut crud_manager = CrudManager::with_defaults()?;
+        if let Some(key) = as_key {
+            crud_manager = crud_manager.with_actor(Actor::holding(&key, &TrustAnchors::load()?)?);
+        }
         
         Ok(Self {
             crud_manager,
@@ -256,6 +283,50 @@ impl LifecycleDispatcher {
         }
     }
 
+    /// Print dry-run plans for a mutating command without executing it
+    fn execute_plan(&self, command: Commands, format: OutputFormat, plan_format: PlanFormatArg) -> Result<(), Box<dyn std::error::Error>> {
+        let plans: Vec<OperationPlan> = match command {
+            Commands::Lock { paths, recursive, pattern, backup, .. } => {
+                let options = LockOptions {
+                    recursive,
+                    format,
+                    pattern_filter: pattern,
+                    backup_before_lock: backup,
+                    preserve_xattrs: false,
+                };
+                paths.iter()
+                    .map(|path| self.crud_manager.plan_lock(path, &options))
+                    .collect::<Result<_, _>>()?
+            }
+            
+            Commands::Unlock { paths, selective, pattern, preserve, .. } => {
+                let options = UnlockOptions {
+                    selective,
+                    verify_before_unlock: true,
+                    pattern_filter: pattern,
+                    preserve_encrypted: preserve,
+                };
+                paths.iter()
+                    .map(|path| self.crud_manager.plan_unlock(path, &options))
+                    .collect::<Result<_, _>>()?
+            }
+            
+            Commands::Rotate { repository, .. } => vec![self.crud_manager.plan_rotate(&repository)?],
+            Commands::Allow { recipient } => vec![self.crud_manager.plan_allow(&recipient)?],
+            Commands::Revoke { recipient } => vec![self.crud_manager.plan_revoke(&recipient)?],
+            
+            _ => return Err("--dry-run is only supported for lock, unlock, rotate, allow and revoke".into()),
+        };
+        
+        for plan in &plans {
+            match plan_format {
+                PlanFormatArg::Table => print!("{}", plan.to_table()),
+                PlanFormatArg::Json => println!("{}", plan.to_json()?),
+            }
+        }
+        Ok(())
+    }
+
     /// Execute lock operation
     fn execute_lock(
         &mut self, 
@@ -273,6 +344,7 @@ impl LifecycleDispatcher {
             format,
             pattern_filter: pattern,
             backup_before_lock: backup,
+            preserve_xattrs: false,
         };
         
         for path in paths {
@@ -554,16 +626,23 @@ impl LifecycleDispatcher {
 fn main() {
     let cli = Cli::parse();
     
-    // Print banner
-    println!("🛡️ Padlock Age Automation Lifecycle Dispatcher");
-    println!("Security Guardian: Edgar - CRUD Operations Coordinator");
-    println!("Version: 0.0.1-lifecycle | Threat Status: T2.1_ELIMINATED");
-    println!();
+    // Print banner (kept out of machine-readable plan output)
+    if !(cli.dry_run && matches!(cli.plan_format, PlanFormatArg::Json)) {
+        println!("🛡️ Padlock Age Automation Lifecycle Dispatcher");
+        println!("Security Guardian: Edgar - CRUD Operations Coordinator");
+        println!("Version: 0.0.1-lifecycle | Threat Status: T2.1_ELIMINATED");
+        println!();
+    }
     
     // Create dispatcher
-    match LifecycleDispatcher::new(cli.audit_log, cli.verbose) {
+    match LifecycleDispatcher::new(cli.audit_log, cli.as_key, cli.verbose) {
         Ok(mut dispatcher) => {
-            if let Err(e) = dispatcher.execute_command(cli.command, cli.format.into()) {
+            let result = if cli.dry_run {
+                dispatcher.execute_plan(cli.command, cli.format.into(), cli.plan_format)
+            } else {
+                dispatcher.execute_command(cli.command, cli.format.into())
+            };
+            if let Err(e) = result {
                 eprintln!("❌ Operation failed: {}", e);
                 process::exit(1);
             }
@@ -592,6 +671,6 @@ mod tests {
     fn test_dispatcher_creation() {
         // Test basic dispatcher creation
         // This will fail if dependencies are missing, which is expected
-        let _result = LifecycleDispatcher::new(None, false);
+        let _result = LifecycleDispatcher::new(None, None, false);
     }
 }
\ No newline at end of file
@@ -7,6 +7,7 @@
 //!
 //! Security Guardian: Edgar - Direct authority chain interface
 
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::process;
 use clap::{Parser, Subcommand};
@@ -14,11 +15,20 @@ use std::fs;
 
 // Import our authority modules
 use padlock::authority::{
-    AuthorityChain, KeyType,
+    AuthorityChain, AuthorityKey, KeyFingerprint, KeyType,
+    bridge::AccessMatrix,
     operations::{AuthorityAgeKeyGenerator, AuthorityAgeEncryption, EncryptionParams},
-    ignition::IgnitionKey,
+    ignition::{IgnitionKey, UnlockLedger},
+    trust::TrustAnchors,
+    expiration::{ExpirationPolicies, PolicyOverride},
+    revocation::{RevocationList, REVOCATION_FILE},
+    certificate::AuthorityCertificate,
+    authorization::{keyed_operation, repository_root, Actor, OperationAuthorizer},
+    capability::KeyCapabilities,
 };
 use padlock::encryption::age_automation::config::OutputFormat;
+use padlock::encryption::age_automation::lifecycle::load_mark;
+use padlock::encryption::age_automation::security::AuditLogger;
 
 /// Authority Chain Direct CLI Interface
 #[derive(Parser)]
@@ -55,6 +65,16 @@ enum Commands {
         /// Output directory for generated keys
         #[arg(short, long)]
         output_dir: Option<PathBuf>,
+        
+        /// Expiration policy written into one level's key file, e.g.
+        /// `distro:max_age=3d,warning=12h,max_idle=1d` (repeatable)
+        #[arg(long)]
+        policy: Vec<String>,
+        
+        /// Capabilities signed into the distro key, e.g.
+        /// `paths=config/**,*.env access=read-only max_uses=100 operations=unlock,status`
+        #[arg(long)]
+        capabilities: Option<String>,
     },
     
     /// Encrypt file using authority key
@@ -129,6 +149,16 @@ enum Commands {
         output_file: Option<PathBuf>,
     },
     
+    /// Clear a locked-out ignition or distro key's failed unlock attempts
+    IgnitionReset {
+        /// Ignition key file to reset
+        key_file: PathBuf,
+        
+        /// Enveloped key file of an ignition-level or higher ancestor of the key
+        #[arg(short, long)]
+        authority: PathBuf,
+    },
+    
     /// Validate authority relationships in the chain
     Validate {
         /// Test all authority relationships
@@ -161,6 +191,10 @@ enum Commands {
         /// Chain name to look for (matches generate --name)
         #[arg(short, long, default_value = "auth")]
         name: String,
+        
+        /// Repository whose .padlock/expiration.toml overrides the global policies
+        #[arg(long)]
+        repository: Option<PathBuf>,
     },
     
     /// Test complete end-to-end workflow
@@ -178,6 +212,70 @@ enum Commands {
         benchmark: bool,
     },
     
+    /// Report which authority keys can decrypt which repository files
+    Access {
+        /// Repository to audit
+        #[arg(default_value = ".")]
+        repository: PathBuf,
+        
+        /// Chain name to load from the keys directory (matches generate --name)
+        #[arg(short, long, default_value = "auth")]
+        name: String,
+        
+        /// Fingerprint (or unique prefix) of a revoked key; repeatable
+        #[arg(long)]
+        revoked: Vec<String>,
+        
+        /// Report format
+        #[arg(long, value_enum, default_value = "table")]
+        report_format: ReportFormatArg,
+    },
+    
+    /// Revoke a key (and every key below it) in a repository's signed revocation list
+    Revoke {
+        /// Fingerprint (or unique prefix) of the key to revoke
+        fingerprint: String,
+        
+        /// Level of the chain key that signs the revocation (the revoked key's parent or higher)
+        #[arg(short, long)]
+        signer: AuthorityLevelArg,
+        
+        /// Why the key is revoked
+        #[arg(short, long)]
+        reason: String,
+        
+        /// Repository holding .padlock/revocations.json
+        #[arg(long, default_value = ".")]
+        repository: PathBuf,
+        
+        /// Chain name to load from the keys directory (matches generate --name)
+        #[arg(short, long, default_value = "auth")]
+        name: String,
+    },
+    
+    /// Export a certificate proving an ignition or distro key's lineage, checkable offline
+    Certify {
+        /// Level of the key to certify (ignition or distro)
+        #[arg(short, long)]
+        level: AuthorityLevelArg,
+        
+        /// Chain name to load from the keys directory (matches generate --name)
+        #[arg(short, long, default_value = "auth")]
+        name: String,
+        
+        /// Days the certificate stays valid
+        #[arg(long, default_value = "30")]
+        days: u32,
+        
+        /// Repository path the key is certified for (repeatable; none means unrestricted)
+        #[arg(long)]
+        scope: Vec<String>,
+        
+        /// Certificate file to write
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    
     /// Run demonstration of authority chain capabilities
     Demo {
         /// Demo scenario to run
@@ -228,6 +326,14 @@ impl From<OutputFormatArg> for OutputFormat {
     }
 }
 
+/// Report formats for the access matrix
+#[derive(Clone, Debug, clap::ValueEnum)]
+enum ReportFormatArg {
+    Table,
+    Json,
+    Csv,
+}
+
 /// Demo scenarios for authority chain testing
 #[derive(Clone, Debug, clap::ValueEnum)]
 enum DemoScenario {
@@ -258,9 +364,24 @@ impl CliAuth {
     }
     
     fn run(&mut self, command: Commands) -> Result<(), Box<dyn std::error::Error>> {
+        // Judge key and certificate expiry against the high-water mark of the repository
+        // the command touches, not only the wall clock
+        let repository = match &command {
+            Commands::Encrypt { input_file, .. }
+            | Commands::Decrypt { input_file, .. }
+            | Commands::IgnitionEncrypt { input_file, .. } => Some(repository_root(input_file)),
+            Commands::Status { repository, .. } => repository.clone(),
+            Commands::Access { repository, .. } | Commands::Revoke { repository, .. } => Some(repository.clone()),
+            _ => None,
+        };
+        load_mark(&self.keys_dir)?;
+        if let Some(repository) = &repository {
+            load_mark(repository)?;
+        }
+        
         match command {
-            Commands::Generate { name, output_dir } => {
-                self.handle_generate(name, output_dir)
+            Commands::Generate { name, output_dir, policy, capabilities } => {
+                self.handle_generate(name, output_dir, policy, capabilities)
             }
             Commands::Encrypt { input_file, authority_level, output_file, key_fingerprint, verify_authority } => {
                 self.handle_encrypt(input_file, authority_level, output_file, key_fingerprint, verify_authority)
@@ -274,22 +395,34 @@ impl CliAuth {
             Commands::IgnitionEncrypt { input_file, key_file, passphrase, output_file } => {
                 self.handle_ignition_encrypt(input_file, key_file, passphrase, output_file)
             }
+            Commands::IgnitionReset { key_file, authority } => {
+                self.handle_ignition_reset(key_file, authority)
+            }
             Commands::Validate { test_all, test_pair, detailed } => {
                 self.handle_validate(test_all, test_pair, detailed)
             }
-            Commands::Status { show_chain, show_keys, show_authorities, name } => {
-                self.handle_status(show_chain, show_keys, show_authorities, name)
+            Commands::Status { show_chain, show_keys, show_authorities, name, repository } => {
+                self.handle_status(show_chain, show_keys, show_authorities, name, repository)
             }
             Commands::Test { full_workflow, test_level, benchmark } => {
                 self.handle_test(full_workflow, test_level, benchmark)
             }
+            Commands::Access { repository, name, revoked, report_format } => {
+                self.handle_access(repository, name, revoked, report_format)
+            }
+            Commands::Revoke { fingerprint, signer, reason, repository, name } => {
+                self.handle_revoke(fingerprint, signer, reason, repository, name)
+            }
+            Commands::Certify { level, name, days, scope, output } => {
+                self.handle_certify(level, name, days, scope, output)
+            }
             Commands::Demo { scenario, cleanup } => {
                 self.handle_demo(scenario, cleanup)
             }
         }
     }
     
-    fn handle_generate(&self, name: String, output_dir: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    fn handle_generate(&self, name: String, output_dir: Option<PathBuf>, policies: Vec<String>, capabilities: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
         println!("🔑 Generating X->M->R->I->D Authority Chain");
         println!("==========================================");
         
@@ -302,6 +435,15 @@ impl CliAuth {
         // Create authority chain and key generator
         let authority_chain = AuthorityChain::new();
         let mut key_generator = AuthorityAgeKeyGenerator::new(authority_chain, None)?;
+        for policy in &policies {
+            let (level, spec) = policy.split_once(':')
+                .ok_or_else(|| format!("Invalid --policy '{}': expected <level>:<key=value,...>", policy))?;
+            key_generator = key_generator.with_key_policy(KeyType::from_str(level)?, PolicyOverride::parse_spec(spec)?);
+        }
+        if let Some(spec) = &capabilities {
+            key_generator = key_generator.with_distro_capabilities(KeyCapabilities::parse_spec(spec)?)?;
+            println!("🎯 Distro Capabilities: {}", spec);
+        }
         
         // Generate complete authority chain
         let generated_keys = key_generator.generate_complete_authority_chain(&name, &output_path)?;
@@ -377,7 +519,7 @@ impl CliAuth {
         Ok(())
     }
     
-    fn handle_ignition_encrypt(&self, input_file: PathBuf, key_file: PathBuf, _passphrase: String, output_file: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    fn handle_ignition_encrypt(&self, input_file: PathBuf, key_file: PathBuf, passphrase: String, output_file: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
         println!("🚀 Ignition Key Encryption");
         println!("==========================");
         
@@ -391,9 +533,20 @@ impl CliAuth {
         println!("🚀 Ignition Key: {}", key_file.display());
         println!("🔒 Output File: {}", output_path.display());
         
-        println!("⚠️  Ignition key encryption requires passphrase unlocking");
-        println!("💡 Implementation pending IgnitionKey workflow integration");
+        // The unlock is counted in the unlock ledger, so repeated wrong passphrases
+        // lock the key out across runs
+        let mut engine = AuthorityAgeEncryption::new(AuthorityChain::new(), None)?;
+        let result = engine.encrypt_with_ignition_key(&input_file, &output_path, &key_file, &passphrase, self.format)?;
+        
+        println!("✅ Encrypted {} bytes with ignition key {}", result.file_size_bytes, result.authority_used);
+        Ok(())
+    }
+    
+    fn handle_ignition_reset(&self, key_file: PathBuf, authority: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        let anchors = TrustAnchors::load()?.with_search_dir(self.keys_dir.clone());
+        IgnitionKey::reset_lockout_file(&key_file, &UnlockLedger::default(), &authority, &anchors, &AuditLogger::new(None)?)?;
         
+        println!("🔓 Cleared the unlock lockout of {} (reset by {})", key_file.display(), authority.display());
         Ok(())
     }
     
@@ -433,7 +586,126 @@ impl CliAuth {
         Ok(())
     }
     
-    fn handle_status(&self, show_chain: bool, show_keys: bool, show_authorities: bool, name: String) -> Result<(), Box<dyn std::error::Error>> {
+    fn handle_access(&self, repository: PathBuf, name: String, revoked: Vec<String>, report_format: ReportFormatArg) -> Result<(), Box<dyn std::error::Error>> {
+        let chain = self.load_chain(&name)?;
+        if chain.is_empty() {
+            return Err(format!("No {}-<type>.key files found in {}", name, self.keys_dir.display()).into());
+        }
+        
+        let mut revoked_fps: HashSet<KeyFingerprint> = HashSet::new();
+        for prefix in &revoked {
+            revoked_fps.insert(Self::find_by_prefix(&chain, prefix)?.fingerprint().clone());
+        }
+        
+        // Keys revoked in the repository's signed list count as revoked too
+        let revocations = RevocationList::load(&repository)?;
+        for key in Self::all_keys(&chain) {
+            if revocations.revocation_of(key, &chain)?.is_some() {
+                revoked_fps.insert(key.fingerprint().clone());
+            }
+        }
+        
+        let matrix = AccessMatrix::build(&repository, &chain, &revoked_fps)?;
+        match report_format {
+            ReportFormatArg::Table => print!("{}", matrix.to_table()),
+            ReportFormatArg::Json => println!("{}", matrix.to_json()?),
+            ReportFormatArg::Csv => print!("{}", matrix.to_csv()),
+        }
+        
+        if self.verbose {
+            eprintln!("{} of {} files flagged", matrix.flagged_files().len(), matrix.files.len());
+        }
+        Ok(())
+    }
+    
+    /// Load `{name}-{type}.key` files and link adjacent levels into a chain
+    fn load_chain(&self, name: &str) -> Result<AuthorityChain, Box<dyn std::error::Error>> {
+        let mut chain = AuthorityChain::new();
+        let mut previous: Option<KeyFingerprint> = None;
+        
+        for key_type in [KeyType::Skull, KeyType::Master, KeyType::Repo, KeyType::Ignition, KeyType::Distro] {
+            let key_file = self.keys_dir.join(format!("{}-{}.key", name, key_type));
+            if !key_file.exists() {
+                previous = None;
+                continue;
+            }
+            
+            let key = AuthorityKey::from_file(&key_file, key_type)?;
+            let fingerprint = key.fingerprint().clone();
+            chain.add_key(key)?;
+            if let Some(parent) = &previous {
+                chain.add_authority_relationship(parent, &fingerprint)?;
+            }
+            previous = Some(fingerprint);
+        }
+        
+        Ok(chain)
+    }
+    
+    fn handle_revoke(&self, fingerprint: String, signer: AuthorityLevelArg, reason: String, repository: PathBuf, name: String) -> Result<(), Box<dyn std::error::Error>> {
+        let chain = self.load_chain(&name)?;
+        let revoked = Self::find_by_prefix(&chain, &fingerprint)?;
+        let signer_type = KeyType::from(signer);
+        let signer = chain.get_keys_by_type(signer_type).into_iter().next()
+            .ok_or_else(|| format!("No {} key in chain '{}'", signer_type, name))?;
+        
+        // The repository's operation policy decides who may revoke each key type
+        OperationAuthorizer::new().authorize(&keyed_operation("revoke", revoked.key_type()), Some(&repository),
+            &Actor::for_key(signer), &AuditLogger::new(None)?)?;
+        
+        let mut revocations = RevocationList::load(&repository)?;
+        let entry = revocations.revoke(revoked, signer, &reason)?.clone();
+        revocations.save(&repository)?;
+        
+        println!("🚫 Revoked {} key {} (signed by {} key {})", entry.key_type, revoked.fingerprint(), entry.signer_type, signer.fingerprint());
+        for dependent in chain.find_dependent_keys(revoked.fingerprint())? {
+            println!("   ↳ also revoked: {} key {}", dependent.key_type(), dependent.fingerprint());
+        }
+        println!("📄 Revocation list: {}", repository.join(REVOCATION_FILE).display());
+        Ok(())
+    }
+    
+    fn handle_certify(&self, level: AuthorityLevelArg, name: String, days: u32, scope: Vec<String>, output: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        let chain = self.load_chain(&name)?;
+        let key_type = KeyType::from(level);
+        let subject = chain.get_keys_by_type(key_type).into_iter().next()
+            .ok_or_else(|| format!("No {} key in chain '{}'", key_type, name))?;
+        
+        let certificate = AuthorityCertificate::issue(&chain, subject, chrono::Duration::days(days.into()), scope)?;
+        certificate.save(&output)?;
+        
+        let top = &certificate.chain[certificate.chain.len() - 1].issuer;
+        println!("📜 Certified {} key {} ({} link(s) up to {} key {})",
+            key_type, subject.fingerprint(), certificate.chain.len(), top.key_type, &top.fingerprint[..16]);
+        println!("   Valid until {}", certificate.chain[0].not_after.format("%Y-%m-%d %H:%M UTC"));
+        if let Some(capabilities) = &certificate.chain[0].capabilities {
+            println!("   Capabilities: {}", capabilities);
+        }
+        println!("   Verify with: padlock key verify-cert {} --root {}", output.display(), top.recipient);
+        Ok(())
+    }
+    
+    /// Every key of a loaded chain, top level first
+    fn all_keys(chain: &AuthorityChain) -> impl Iterator<Item = &AuthorityKey> {
+        [KeyType::Skull, KeyType::Master, KeyType::Repo, KeyType::Ignition, KeyType::Distro]
+            .into_iter()
+            .flat_map(|key_type| chain.get_keys_by_type(key_type))
+    }
+    
+    /// The one key whose fingerprint starts with `prefix`
+    fn find_by_prefix<'a>(chain: &'a AuthorityChain, prefix: &str) -> Result<&'a AuthorityKey, Box<dyn std::error::Error>> {
+        let prefix = prefix.to_lowercase();
+        let matches: Vec<&AuthorityKey> = Self::all_keys(chain)
+            .filter(|key| key.fingerprint().hex().starts_with(prefix.as_str()))
+            .collect();
+        match matches.as_slice() {
+            [key] => Ok(key),
+            [] => Err(format!("No key with fingerprint {}", prefix).into()),
+            _ => Err(format!("Fingerprint prefix {} is ambiguous", prefix).into()),
+        }
+    }
+    
+    fn handle_status(&self, show_chain: bool, show_keys: bool, show_authorities: bool, name: String, repository: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
         println!("📊 Authority Chain Status");
         println!("=========================");
         
@@ -448,14 +720,17 @@ impl CliAuth {
         if show_keys {
             println!("\n🔑 Authority Keys:");
             println!("   🏷️  Chain Name: {}", name);
+            let policies = ExpirationPolicies::load(repository.as_deref())?;
             // Check for key files using generate pattern: {name}-{type}.key
-            let key_types = ["skull", "master", "repo", "ignition", "distro"];
-            for key_type in &key_types {
+            for key_type in [KeyType::Skull, KeyType::Master, KeyType::Repo, KeyType::Ignition, KeyType::Distro] {
+                let label = key_type.to_string().to_uppercase();
                 let key_file = self.keys_dir.join(format!("{}-{}.key", name, key_type));
                 if key_file.exists() {
-                    println!("   ✅ {} Authority Key: {}", key_type.to_uppercase(), key_file.display());
+                    let effective = policies.for_key_file(key_type, &key_file)?;
+                    println!("   ✅ {} Authority Key: {}", label, key_file.display());
+                    println!("      ⏳ Policy: {} (from {})", effective.describe(), effective.source);
                 } else {
-                    println!("   ❌ {} Authority Key: Not found", key_type.to_uppercase());
+                    println!("   ❌ {} Authority Key: Not found", label);
                 }
             }
         }
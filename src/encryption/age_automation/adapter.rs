@@ -6,9 +6,10 @@
 //!
 //! Security Guardian: Edgar - Adapter pattern for clean backend abstraction
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use super::error::{AgeError, AgeResult};
 use super::config::OutputFormat;
+use super::secret::SecretBytes;
 
 /// Core Age operations interface that all adapters must implement
 pub trait AgeAdapter {
@@ -18,6 +19,41 @@ pub trait AgeAdapter {
     /// Decrypt a file with the given passphrase
     fn decrypt(&self, input: &Path, output: &Path, passphrase: &str) -> AgeResult<()>;
     
+    /// Encrypt a file to public recipients (`age1...` or SSH public keys) instead of a passphrase
+    fn encrypt_to_recipients(&self, _input: &Path, _output: &Path, _recipients: &[String], _format: OutputFormat) -> AgeResult<()> {
+        Err(AgeError::InvalidOperation {
+            operation: "encrypt_to_recipients".to_string(),
+            reason: format!("{} does not support recipient encryption", self.adapter_name()),
+        })
+    }
+    
+    /// Decrypt a file into memory. The default decrypts into a private workspace that is
+    /// wiped before returning, and refuses when no RAM-backed root is available; adapters
+    /// that can capture age's output directly override it.
+    fn decrypt_to_bytes(&self, input: &Path, passphrase: &str) -> AgeResult<SecretBytes> {
+        let root = super::temp_area::private_temp_root();
+        if !super::temp_area::is_memory_backed(&root) {
+            return Err(AgeError::SecurityValidationFailed {
+                validation_type: "plaintext_storage".to_string(),
+                details: format!("{} is not RAM-backed; {} will not decrypt {} through it",
+                    root.display(), self.adapter_name(), input.display()),
+            });
+        }
+        let workspace = super::lifecycle::edit::EditWorkspace::create(&root, "plaintext".into())?;
+        self.decrypt(input, workspace.file(), passphrase)?;
+        std::fs::read(workspace.file())
+            .map(SecretBytes::new)
+            .map_err(|e| AgeError::file_error("read", workspace.file().to_path_buf(), e))
+    }
+    
+    /// Decrypt a file encrypted to public keys into memory, using age identity files
+    fn decrypt_to_bytes_with_identities(&self, _input: &Path, _identities: &[PathBuf]) -> AgeResult<SecretBytes> {
+        Err(AgeError::InvalidOperation {
+            operation: "decrypt_with_identities".to_string(),
+            reason: format!("{} does not support identity decryption", self.adapter_name()),
+        })
+    }
+    
     /// Validate adapter is functional and dependencies are available
     fn health_check(&self) -> AgeResult<()>;
     
@@ -87,6 +123,92 @@ impl AgeAdapter for ShellAdapter {
         result
     }
     
+    fn encrypt_to_recipients(&self, input: &Path, output: &Path, recipients: &[String], format: OutputFormat) -> AgeResult<()> {
+        self.audit_logger.log_operation_start("encrypt_to_recipients", input, output)?;
+        
+        // No passphrase prompt is involved, so age runs directly without PTY automation
+        let mut command = std::process::Command::new("age");
+        for recipient in recipients {
+            command.arg("-r").arg(recipient);
+        }
+        if let Some(flag) = format.age_flag() {
+            command.arg(flag);
+        }
+        let result = command.arg("-o").arg(output).arg(input)
+            .output()
+            .map_err(|e| AgeError::ProcessExecutionFailed {
+                command: "age".to_string(),
+                exit_code: None,
+                stderr: e.to_string(),
+            })
+            .and_then(|out| if out.status.success() {
+                Ok(())
+            } else {
+                Err(AgeError::ProcessExecutionFailed {
+                    command: "age".to_string(),
+                    exit_code: out.status.code(),
+                    stderr: String::from_utf8_lossy(&out.stderr).trim().to_string(),
+                })
+            });
+        
+        match &result {
+            Ok(_) => self.audit_logger.log_operation_success("encrypt_to_recipients", input, output)?,
+            Err(e) => self.audit_logger.log_operation_failure("encrypt_to_recipients", input, output, e)?,
+        }
+        
+        result
+    }
+    
+    fn decrypt_to_bytes(&self, input: &Path, passphrase: &str) -> AgeResult<SecretBytes> {
+        let output = Path::new("-");
+        self.audit_logger.log_operation_start("decrypt_to_bytes", input, output)?;
+
+        let result = self.pty_automator.decrypt_to_memory(input, passphrase).map(SecretBytes::new);
+
+        match &result {
+            Ok(_) => self.audit_logger.log_operation_success("decrypt_to_bytes", input, output)?,
+            Err(e) => self.audit_logger.log_operation_failure("decrypt_to_bytes", input, output, e)?,
+        }
+
+        result
+    }
+    
+    fn decrypt_to_bytes_with_identities(&self, input: &Path, identities: &[PathBuf]) -> AgeResult<SecretBytes> {
+        let output = Path::new("-");
+        self.audit_logger.log_operation_start("decrypt_with_identities", input, output)?;
+        
+        // Identity files need no prompt, so age runs directly and its output stays in memory
+        let mut command = std::process::Command::new("age");
+        command.arg("-d");
+        for identity in identities {
+            command.arg("-i").arg(identity);
+        }
+        let result = command.arg("-o").arg("-").arg(input)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .map_err(|e| AgeError::ProcessExecutionFailed {
+                command: "age".to_string(),
+                exit_code: None,
+                stderr: e.to_string(),
+            })
+            .and_then(|out| if out.status.success() {
+                Ok(SecretBytes::new(out.stdout))
+            } else {
+                Err(AgeError::DecryptionFailed {
+                    input: input.to_path_buf(),
+                    output: output.to_path_buf(),
+                    reason: String::from_utf8_lossy(&out.stderr).trim().to_string(),
+                })
+            });
+        
+        match &result {
+            Ok(_) => self.audit_logger.log_operation_success("decrypt_with_identities", input, output)?,
+            Err(e) => self.audit_logger.log_operation_failure("decrypt_with_identities", input, output, e)?,
+        }
+        
+        result
+    }
+    
     fn health_check(&self) -> AgeResult<()> {
         // Check Age binary availability
         self.pty_automator.check_age_binary()?;
@@ -186,6 +308,129 @@ impl AdapterFactory {
     }
 }
 
+/// In-process adapter for lifecycle tests that must run without the age binary.
+///
+/// Output carries a well-formed age v1 header with an scrypt stanza whose body is a
+/// passphrase digest, so decrypting with the wrong passphrase fails the way age would.
+/// ASCII armor is honored; recipient encryption writes one X25519 stanza per recipient.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::path::{Path, PathBuf};
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD_NO_PAD;
+    use sha2::{Digest, Sha256};
+    use super::AgeAdapter;
+    use super::super::config::OutputFormat;
+    use super::super::error::{AgeError, AgeResult};
+    use super::super::header;
+    use super::super::secret::SecretBytes;
+
+
+    #[derive(Clone, Default)]
+    pub(crate) struct MockAdapter;
+
+    impl MockAdapter {
+        fn header(passphrase: &str) -> Vec<u8> {
+            format!("age-encryption.org/v1\n-> scrypt {} 18\n{}\n--- {}\n",
+                STANDARD_NO_PAD.encode([0u8; 16]),
+                STANDARD_NO_PAD.encode(Sha256::digest(passphrase.as_bytes())),
+                STANDARD_NO_PAD.encode([0u8; 32])).into_bytes()
+        }
+
+        fn write(output: &Path, ciphertext: Vec<u8>, format: OutputFormat) -> AgeResult<()> {
+            let data = if format == OutputFormat::AsciiArmor { header::armor(&ciphertext) } else { ciphertext };
+            std::fs::write(output, data)
+                .map_err(|e| AgeError::file_error("write", output.to_path_buf(), e))
+        }
+
+        fn read(input: &Path) -> AgeResult<Vec<u8>> {
+            let data = std::fs::read(input)
+                .map_err(|e| AgeError::file_error("read", input.to_path_buf(), e))?;
+            if data.starts_with(header::ARMOR_BEGIN.as_bytes()) {
+                return header::dearmor(&data).map_err(|reason| AgeError::decryption_failed(input.to_path_buf(), PathBuf::new(), &reason));
+            }
+            Ok(data)
+        }
+    }
+
+    impl AgeAdapter for MockAdapter {
+        fn encrypt(&self, input: &Path, output: &Path, passphrase: &str, format: OutputFormat) -> AgeResult<()> {
+            let plaintext = std::fs::read(input)
+                .map_err(|e| AgeError::file_error("read", input.to_path_buf(), e))?;
+            let mut ciphertext = Self::header(passphrase);
+            ciphertext.extend_from_slice(&plaintext);
+            Self::write(output, ciphertext, format)
+        }
+
+        fn encrypt_to_recipients(&self, input: &Path, output: &Path, recipients: &[String], format: OutputFormat) -> AgeResult<()> {
+            let plaintext = std::fs::read(input)
+                .map_err(|e| AgeError::file_error("read", input.to_path_buf(), e))?;
+            let mut ciphertext = String::from("age-encryption.org/v1\n");
+            for recipient in recipients {
+                ciphertext.push_str(&format!("-> X25519 {}\n{}\n",
+                    STANDARD_NO_PAD.encode(Sha256::digest(recipient.as_bytes())),
+                    STANDARD_NO_PAD.encode([0u8; 32])));
+            }
+            ciphertext.push_str(&format!("--- {}\n", STANDARD_NO_PAD.encode([0u8; 32])));
+            let mut ciphertext = ciphertext.into_bytes();
+            ciphertext.extend_from_slice(&plaintext);
+            Self::write(output, ciphertext, format)
+        }
+
+        fn decrypt(&self, input: &Path, output: &Path, passphrase: &str) -> AgeResult<()> {
+            let data = Self::read(input)?;
+            let expected = Self::header(passphrase);
+            if !data.starts_with(&expected) {
+                return Err(AgeError::decryption_failed(input.to_path_buf(), output.to_path_buf(), "no identity matched"));
+            }
+            std::fs::write(output, &data[expected.len()..])
+                .map_err(|e| AgeError::file_error("write", output.to_path_buf(), e))
+        }
+
+        fn decrypt_to_bytes(&self, input: &Path, passphrase: &str) -> AgeResult<SecretBytes> {
+            let data = Self::read(input)?;
+            let expected = Self::header(passphrase);
+            if !data.starts_with(&expected) {
+                return Err(AgeError::decryption_failed(input.to_path_buf(), PathBuf::from("<memory>"), "no identity matched"));
+            }
+            Ok(SecretBytes::new(data[expected.len()..].to_vec()))
+        }
+
+        fn decrypt_to_bytes_with_identities(&self, input: &Path, identities: &[PathBuf]) -> AgeResult<SecretBytes> {
+            let data = Self::read(input)?;
+            let mut opened = false;
+            for identity in identities {
+                for known in header::KnownRecipient::load_identity_file(identity)? {
+                    let tag = format!("-> X25519 {}\n", STANDARD_NO_PAD.encode(Sha256::digest(known.recipient.as_bytes())));
+                    opened |= data.windows(tag.len()).any(|window| window == tag.as_bytes());
+                }
+            }
+            let mac = data.windows(5).position(|window| window == b"\n--- ")
+                .and_then(|start| data[start + 1..].iter().position(|&b| b == b'\n').map(|end| start + 1 + end + 1));
+            match mac {
+                Some(payload) if opened => Ok(SecretBytes::new(data[payload..].to_vec())),
+                _ => Err(AgeError::decryption_failed(input.to_path_buf(), PathBuf::from("<memory>"), "no identity matched")),
+            }
+        }
+
+        fn health_check(&self) -> AgeResult<()> {
+            Ok(())
+        }
+
+        fn adapter_name(&self) -> &'static str {
+            "MockAdapter"
+        }
+
+        fn adapter_version(&self) -> String {
+            "mock".to_string()
+        }
+
+        fn clone_box(&self) -> Box<dyn AgeAdapter> {
+            Box::new(self.clone())
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -145,7 +145,7 @@ impl AgeAutomator {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use super::adapter::AdapterFactory;
+    use super::super::adapter::AdapterFactory;
 
     #[test]
     fn test_automator_creation() {
@@ -15,6 +15,8 @@ pub enum OutputFormat {
     Binary,
     /// ASCII armor output (-a flag) - text-safe for various environments
     AsciiArmor,
+    /// Structured output for JSON/YAML/TOML/dotenv - leaf values encrypted, keys readable
+    Structured,
 }
 
 impl OutputFormat {
@@ -23,6 +25,7 @@ impl OutputFormat {
         match self {
             OutputFormat::Binary => None,
             OutputFormat::AsciiArmor => Some("-a"),
+            OutputFormat::Structured => None,
         }
     }
     
@@ -31,6 +34,7 @@ impl OutputFormat {
         match self {
             OutputFormat::Binary => "Binary format (efficient, smallest size)",
             OutputFormat::AsciiArmor => "ASCII armor (text-safe, larger size)",
+            OutputFormat::Structured => "Structured (values encrypted, keys and layout readable)",
         }
     }
     
@@ -89,6 +93,31 @@ impl Default for TtyMethod {
     }
 }
 
+/// How symlinks found while discovering repository files are treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymlinkPolicy {
+    /// Follow links whose target stays inside the repository root
+    Follow,
+    /// Leave links out of the operation
+    #[default]
+    Skip,
+    /// Fail the operation when a link is found
+    Refuse,
+}
+
+/// Response to a system clock earlier than a repository's recorded high-water mark
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClockRollbackPolicy {
+    /// Record a security event and continue, judging expiry by the high-water mark
+    #[default]
+    Warn,
+    /// Record a security event and refuse the operation
+    Block,
+}
+
+/// System locations strict validation refuses unless explicitly allowed
+pub const DEFAULT_DENIED_LOCATIONS: &[&str] = &["/etc", "/proc", "/sys", "/dev", "/boot"];
+
 /// Security validation level
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SecurityLevel {
@@ -167,6 +196,31 @@ pub struct AgeConfig {
     
     /// Temporary directory override (None for system default)
     pub temp_dir_override: Option<String>,
+    
+    /// Maximum number of pre-lock backups to keep (None for unlimited)
+    pub backup_max_count: Option<usize>,
+    
+    /// Maximum age of pre-lock backups before pruning (None for no age limit)
+    pub backup_max_age: Option<Duration>,
+    
+    /// Regexes over dotted key paths whose values stay plaintext in structured mode
+    pub structured_plaintext_keys: Vec<String>,
+    
+    /// Symlink handling during file discovery
+    pub symlink_policy: SymlinkPolicy,
+    
+    /// Absolute locations strict validation refuses
+    pub denied_locations: Vec<String>,
+    
+    /// Absolute locations allowed even inside a denied one
+    pub allowed_locations: Vec<String>,
+    
+    /// What to do when the clock is found rolled back behind a repository's high-water mark
+    pub clock_rollback_policy: ClockRollbackPolicy,
+    
+    /// Machine-local directory holding the clock signing key and key usage ledgers (None
+    /// for the XDG state dir)
+    pub clock_state_dir: Option<String>,
 }
 
 impl AgeConfig {
@@ -211,6 +265,7 @@ impl AgeConfig {
             secure_deletion: true,
             max_retries: 0, // No retries in tests for fast failure
             operation_timeout: Duration::from_secs(10),
+            clock_state_dir: Some(std::env::temp_dir().join("padlock-test-state").to_string_lossy().to_string()),
             ..Default::default()
         }
     }
@@ -251,6 +306,44 @@ impl AgeConfig {
             });
         }
         
+        if self.backup_max_count == Some(0) {
+            return Err(AgeError::ConfigurationError {
+                parameter: "backup_max_count".to_string(),
+                value: "0".to_string(),
+                reason: "Must keep at least one backup, use None for unlimited".to_string(),
+            });
+        }
+        
+        for pattern in &self.structured_plaintext_keys {
+            if let Err(e) = regex::Regex::new(pattern) {
+                return Err(AgeError::ConfigurationError {
+                    parameter: "structured_plaintext_keys".to_string(),
+                    value: pattern.clone(),
+                    reason: e.to_string(),
+                });
+            }
+        }
+        
+        for location in self.denied_locations.iter().chain(&self.allowed_locations) {
+            if !std::path::Path::new(location).is_absolute() {
+                return Err(AgeError::ConfigurationError {
+                    parameter: "path_locations".to_string(),
+                    value: location.clone(),
+                    reason: "Allowed and denied locations must be absolute paths".to_string(),
+                });
+            }
+        }
+        
+        if let Some(temp_dir) = &self.temp_dir_override {
+            if !std::path::Path::new(temp_dir).is_absolute() {
+                return Err(AgeError::ConfigurationError {
+                    parameter: "temp_dir_override".to_string(),
+                    value: temp_dir.clone(),
+                    reason: "Temporary directory must be an absolute path".to_string(),
+                });
+            }
+        }
+        
         // Validate retry settings
         if self.max_retries > 10 {
             return Err(AgeError::ConfigurationError {
@@ -304,6 +397,44 @@ impl AgeConfig {
         self.age_binary_path = Some(path.into());
         self
     }
+    
+    /// Set backup retention by count and age
+    pub fn with_backup_retention(mut self, max_count: Option<usize>, max_age: Option<Duration>) -> Self {
+        self.backup_max_count = max_count;
+        self.backup_max_age = max_age;
+        self
+    }
+    
+    /// Set regexes for keys left in plaintext by structured encryption
+    pub fn with_structured_plaintext_keys(mut self, patterns: Vec<String>) -> Self {
+        self.structured_plaintext_keys = patterns;
+        self
+    }
+    
+    /// Set symlink handling during discovery
+    pub fn with_symlink_policy(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlink_policy = policy;
+        self
+    }
+    
+    /// Keep keys and decrypted material under `path` instead of the private temp root
+    pub fn with_temp_dir<P: Into<String>>(mut self, path: P) -> Self {
+        self.temp_dir_override = Some(path.into());
+        self
+    }
+    
+    /// Set the response to a detected clock rollback
+    pub fn with_clock_rollback_policy(mut self, policy: ClockRollbackPolicy) -> Self {
+        self.clock_rollback_policy = policy;
+        self
+    }
+    
+    /// Replace the denied and allowed system locations
+    pub fn with_locations(mut self, denied: Vec<String>, allowed: Vec<String>) -> Self {
+        self.denied_locations = denied;
+        self.allowed_locations = allowed;
+        self
+    }
 }
 
 impl Default for AgeConfig {
@@ -325,6 +456,14 @@ impl Default for AgeConfig {
             retry_delay: Duration::from_secs(1),
             secure_deletion: true,
             temp_dir_override: None,
+            backup_max_count: Some(10),
+            backup_max_age: Some(Duration::from_secs(86400 * 30)),
+            structured_plaintext_keys: Vec::new(),
+            symlink_policy: SymlinkPolicy::default(),
+            denied_locations: DEFAULT_DENIED_LOCATIONS.iter().map(|s| s.to_string()).collect(),
+            allowed_locations: Vec::new(),
+            clock_rollback_policy: ClockRollbackPolicy::default(),
+            clock_state_dir: None,
         }
     }
 }
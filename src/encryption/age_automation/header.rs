@@ -0,0 +1,684 @@
+//! Age Header Parser - Recipient introspection without decryption
+//!
+//! Parses age v1 headers from binary and ASCII-armored files, reading only the header
+//! lines and never the payload. The parsed header reports stanza types (X25519, scrypt,
+//! ssh-ed25519, ssh-rsa, plugins), recipient fingerprints where the format exposes them,
+//! header MAC presence and payload size.
+//!
+//! X25519 stanzas do not name their recipient, so matching them against known keys needs
+//! the identity: the stanza's file key is trial-unwrapped from the header alone. SSH
+//! stanzas carry a 4-byte tag of the recipient key and match public keys directly.
+//!
+//! Security Guardian: Edgar - Know who can open a file without opening it
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::error::{AgeError, AgeResult};
+
+/// First line of every age v1 header
+pub const VERSION_LINE: &str = "age-encryption.org/v1";
+
+/// First line of an ASCII-armored age file
+pub const ARMOR_BEGIN: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// Last line of an ASCII-armored age file
+pub const ARMOR_END: &str = "-----END AGE ENCRYPTED FILE-----";
+
+/// Upper bound on header size; larger headers are rejected rather than read
+const MAX_HEADER_BYTES: u64 = 1024 * 1024;
+
+/// Payload STREAM chunk size and per-chunk tag overhead
+const CHUNK_SIZE: u64 = 64 * 1024;
+const TAG_SIZE: u64 = 16;
+const PAYLOAD_NONCE_SIZE: u64 = 16;
+
+const X25519_INFO: &[u8] = b"age-encryption.org/v1/X25519";
+
+/// On-disk encoding of an age file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderEncoding {
+    Binary,
+    Armored,
+}
+
+/// Recipient stanza type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StanzaKind {
+    X25519,
+    Scrypt,
+    SshEd25519,
+    SshRsa,
+    /// Any other stanza type, produced by age plugins
+    Plugin(String),
+}
+
+impl StanzaKind {
+    fn from_tag(tag: &str) -> Self {
+        match tag {
+            "X25519" => StanzaKind::X25519,
+            "scrypt" => StanzaKind::Scrypt,
+            "ssh-ed25519" => StanzaKind::SshEd25519,
+            "ssh-rsa" => StanzaKind::SshRsa,
+            other => StanzaKind::Plugin(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for StanzaKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StanzaKind::X25519 => write!(f, "X25519"),
+            StanzaKind::Scrypt => write!(f, "scrypt"),
+            StanzaKind::SshEd25519 => write!(f, "ssh-ed25519"),
+            StanzaKind::SshRsa => write!(f, "ssh-rsa"),
+            StanzaKind::Plugin(name) => write!(f, "plugin:{}", name),
+        }
+    }
+}
+
+/// One recipient stanza of a header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stanza {
+    pub kind: StanzaKind,
+    pub args: Vec<String>,
+    pub body: Vec<u8>,
+}
+
+impl Stanza {
+    /// Recipient fingerprint exposed by the stanza: the SSH key tag, as `ssh:<hex>`
+    pub fn recipient_fingerprint(&self) -> Option<String> {
+        match self.kind {
+            StanzaKind::SshEd25519 | StanzaKind::SshRsa => self.args.first()
+                .and_then(|tag| STANDARD_NO_PAD.decode(tag).ok())
+                .map(|tag| format!("ssh:{}", hex::encode(tag))),
+            _ => None,
+        }
+    }
+
+    /// scrypt work factor (log2 N) for passphrase stanzas
+    pub fn scrypt_work_factor(&self) -> Option<u8> {
+        match self.kind {
+            StanzaKind::Scrypt => self.args.get(1).and_then(|n| n.parse().ok()),
+            _ => None,
+        }
+    }
+}
+
+/// Parsed age v1 header and payload layout
+#[derive(Debug, Clone)]
+pub struct AgeHeader {
+    pub encoding: HeaderEncoding,
+    pub stanzas: Vec<Stanza>,
+    /// Header MAC (32 bytes) from the `---` line
+    pub mac: Option<Vec<u8>>,
+    /// Header length in (decoded) bytes
+    pub header_size: u64,
+    /// Encrypted payload length in (decoded) bytes, when it could be determined
+    pub payload_size: Option<u64>,
+}
+
+impl AgeHeader {
+    /// Read and parse the header of an age file, binary or armored
+    pub fn read(path: &Path) -> AgeResult<Self> {
+        let file = File::open(path).map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?;
+        let file_size = file.metadata().map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?.len();
+        let mut reader = BufReader::with_capacity(512, file);
+
+        let mut header = Self::parse(&mut reader).map_err(|reason| Self::error(path, reason))?;
+        header.payload_size = match header.encoding {
+            HeaderEncoding::Binary => file_size.checked_sub(header.header_size),
+            HeaderEncoding::Armored => armored_decoded_size(reader.get_mut(), file_size)
+                .and_then(|total| total.checked_sub(header.header_size)),
+        };
+        Ok(header)
+    }
+
+    /// Parse a header from a reader positioned at the start of an age file
+    pub fn parse<R: BufRead>(reader: &mut R) -> Result<Self, String> {
+        let mut first = Vec::new();
+        reader.read_until(b'\n', &mut first).map_err(|e| e.to_string())?;
+        let first_line = trim_line(&first);
+
+        let mut parser = HeaderParser::default();
+        if first_line == ARMOR_BEGIN.as_bytes() {
+            let mut decoded = Vec::new();
+            loop {
+                let mut line = Vec::new();
+                if reader.read_until(b'\n', &mut line).map_err(|e| e.to_string())? == 0 {
+                    return Err("Armored file ends inside the header".to_string());
+                }
+                let line = trim_line(&line);
+                if line == ARMOR_END.as_bytes() {
+                    return Err("Armored file ends inside the header".to_string());
+                }
+                decoded.extend(STANDARD.decode(line).map_err(|_| "Invalid armor line".to_string())?);
+
+                while let Some(end) = decoded.iter().position(|&b| b == b'\n') {
+                    let header_line: Vec<u8> = decoded.drain(..=end).collect();
+                    if parser.feed(&header_line)? {
+                        return Ok(parser.finish(HeaderEncoding::Armored));
+                    }
+                }
+                if parser.consumed + decoded.len() as u64 > MAX_HEADER_BYTES {
+                    return Err("Header too large".to_string());
+                }
+            }
+        }
+
+        if parser.feed(&first)? {
+            return Ok(parser.finish(HeaderEncoding::Binary));
+        }
+        loop {
+            let mut line = Vec::new();
+            if reader.read_until(b'\n', &mut line).map_err(|e| e.to_string())? == 0 {
+                return Err("File ends inside the header".to_string());
+            }
+            if parser.feed(&line)? {
+                return Ok(parser.finish(HeaderEncoding::Binary));
+            }
+            if parser.consumed > MAX_HEADER_BYTES {
+                return Err("Header too large".to_string());
+            }
+        }
+    }
+
+    /// True when the header ends with a MAC line
+    pub fn has_mac(&self) -> bool {
+        self.mac.is_some()
+    }
+
+    /// Plaintext size implied by the payload size
+    pub fn estimated_plaintext_size(&self) -> Option<u64> {
+        let sealed = self.payload_size?.checked_sub(PAYLOAD_NONCE_SIZE)?;
+        let chunks = sealed.div_ceil(CHUNK_SIZE + TAG_SIZE).max(1);
+        sealed.checked_sub(chunks * TAG_SIZE)
+    }
+
+    /// Compact description of the stanzas, e.g. `X25519 x2, scrypt`
+    pub fn summary(&self) -> String {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for stanza in &self.stanzas {
+            let kind = stanza.kind.to_string();
+            match counts.iter_mut().find(|(k, _)| *k == kind) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((kind, 1)),
+            }
+        }
+        counts.iter()
+            .map(|(kind, count)| if *count > 1 { format!("{} x{}", kind, count) } else { kind.clone() })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Known recipients able to open the file according to its header
+    pub fn openable_by<'a>(&self, known: &'a [KnownRecipient]) -> Vec<&'a KnownRecipient> {
+        known.iter()
+            .filter(|recipient| self.stanzas.iter().any(|stanza| recipient.matches(stanza)))
+            .collect()
+    }
+
+    fn error(path: &Path, reason: String) -> AgeError {
+        AgeError::InvalidOperation {
+            operation: "read_header".to_string(),
+            reason: format!("{}: {}", path.display(), reason),
+        }
+    }
+}
+
+/// Header introspection result for one file, as shown by status and verify
+#[derive(Debug, Clone)]
+pub struct FileHeaderReport {
+    pub path: PathBuf,
+    pub header: Option<AgeHeader>,
+    pub error: Option<String>,
+    /// Labels of known recipients able to open the file
+    pub openable_by: Vec<String>,
+}
+
+impl FileHeaderReport {
+    /// Read a file's header and match it against known recipients
+    pub fn inspect(path: &Path, known: &[KnownRecipient]) -> Self {
+        match AgeHeader::read(path) {
+            Ok(header) => Self {
+                path: path.to_path_buf(),
+                openable_by: header.openable_by(known).iter().map(|k| k.label.clone()).collect(),
+                header: Some(header),
+                error: None,
+            },
+            Err(e) => Self {
+                path: path.to_path_buf(),
+                header: None,
+                error: Some(e.to_string()),
+                openable_by: Vec::new(),
+            },
+        }
+    }
+
+    /// One-line description: stanzas, MAC, payload size and matching keys
+    pub fn describe(&self) -> String {
+        let header = match (&self.header, &self.error) {
+            (Some(header), _) => header,
+            (None, error) => return format!("unreadable header ({})", error.as_deref().unwrap_or("unknown error")),
+        };
+
+        let mut description = header.summary();
+        if !header.has_mac() {
+            description.push_str(", no MAC");
+        }
+        if let Some(size) = header.payload_size {
+            description.push_str(&format!(", payload {} bytes", size));
+        }
+        if !self.openable_by.is_empty() {
+            description.push_str(&format!("; opened by: {}", self.openable_by.join(", ")));
+        }
+        description
+    }
+}
+
+/// Encoding of an age file from its first bytes, or None for non-age files
+pub fn sniff(path: &Path) -> AgeResult<Option<HeaderEncoding>> {
+    let mut head = [0u8; ARMOR_BEGIN.len()];
+    let read = match File::open(path) {
+        Ok(mut file) => file.read(&mut head).map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(AgeError::file_error("read", path.to_path_buf(), e)),
+    };
+
+    let head = &head[..read];
+    if head.starts_with(VERSION_LINE.as_bytes()) {
+        Ok(Some(HeaderEncoding::Binary))
+    } else if head == ARMOR_BEGIN.as_bytes() {
+        Ok(Some(HeaderEncoding::Armored))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Wrap a binary age file in ASCII armor (64-column base64 between PEM-style lines)
+pub fn armor(binary: &[u8]) -> Vec<u8> {
+    let encoded = STANDARD.encode(binary);
+    let mut out = format!("{}\n", ARMOR_BEGIN);
+    for line in encoded.as_bytes().chunks(64) {
+        out.push_str(&String::from_utf8_lossy(line));
+        out.push('\n');
+    }
+    out.push_str(ARMOR_END);
+    out.push('\n');
+    out.into_bytes()
+}
+
+/// Decode an ASCII-armored age file back to its binary form
+pub fn dearmor(armored: &[u8]) -> Result<Vec<u8>, String> {
+    let text = std::str::from_utf8(armored).map_err(|_| "Armored file is not valid UTF-8".to_string())?;
+    let mut lines = text.trim().lines().map(|line| line.trim_end_matches('\r'));
+
+    if lines.next() != Some(ARMOR_BEGIN) {
+        return Err("Missing armor begin line".to_string());
+    }
+    let mut body = String::new();
+    let mut ended = false;
+    for line in lines {
+        if ended {
+            return Err("Data after armor end line".to_string());
+        }
+        if line == ARMOR_END {
+            ended = true;
+        } else if line.len() > 64 {
+            return Err("Armor line longer than 64 columns".to_string());
+        } else {
+            body.push_str(line);
+        }
+    }
+    if !ended {
+        return Err("Missing armor end line".to_string());
+    }
+
+    STANDARD.decode(body).map_err(|e| format!("Invalid armor base64: {}", e))
+}
+
+/// Line-by-line age header state machine
+#[derive(Default)]
+struct HeaderParser {
+    consumed: u64,
+    seen_version: bool,
+    stanzas: Vec<Stanza>,
+    open_stanza: Option<Stanza>,
+    mac: Option<Vec<u8>>,
+}
+
+impl HeaderParser {
+    /// Feed one line including its trailing newline; true once the MAC line is reached
+    fn feed(&mut self, raw: &[u8]) -> Result<bool, String> {
+        self.consumed += raw.len() as u64;
+        if !raw.ends_with(b"\n") {
+            return Err("Header line is not newline-terminated".to_string());
+        }
+        let line = std::str::from_utf8(&raw[..raw.len() - 1]).map_err(|_| "Header is not valid text".to_string())?;
+
+        if !self.seen_version {
+            if line != VERSION_LINE {
+                return Err(format!("Not an age v1 file (first line '{}')", line.chars().take(40).collect::<String>()));
+            }
+            self.seen_version = true;
+            return Ok(false);
+        }
+
+        if let Some(mut stanza) = self.open_stanza.take() {
+            let chunk = STANDARD_NO_PAD.decode(line).map_err(|_| "Invalid stanza body".to_string())?;
+            stanza.body.extend(chunk);
+            if line.len() < 64 {
+                self.stanzas.push(stanza);
+            } else {
+                self.open_stanza = Some(stanza);
+            }
+            return Ok(false);
+        }
+
+        if let Some(rest) = line.strip_prefix("-> ") {
+            let mut parts = rest.split(' ');
+            let tag = parts.next().filter(|t| !t.is_empty()).ok_or("Stanza without type")?;
+            self.open_stanza = Some(Stanza {
+                kind: StanzaKind::from_tag(tag),
+                args: parts.map(str::to_string).collect(),
+                body: Vec::new(),
+            });
+            return Ok(false);
+        }
+
+        if let Some(mac) = line.strip_prefix("--- ") {
+            if self.stanzas.is_empty() {
+                return Err("Header has no recipient stanzas".to_string());
+            }
+            self.mac = Some(STANDARD_NO_PAD.decode(mac).map_err(|_| "Invalid header MAC".to_string())?);
+            return Ok(true);
+        }
+
+        Err(format!("Unexpected header line '{}'", line.chars().take(40).collect::<String>()))
+    }
+
+    fn finish(self, encoding: HeaderEncoding) -> AgeHeader {
+        AgeHeader {
+            encoding,
+            stanzas: self.stanzas,
+            mac: self.mac,
+            header_size: self.consumed,
+            payload_size: None,
+        }
+    }
+}
+
+fn trim_line(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Decoded byte count of an armored file, computed from its size and final lines
+fn armored_decoded_size(file: &mut File, file_size: u64) -> Option<u64> {
+    let tail_len = file_size.min(256);
+    file.seek(SeekFrom::Start(file_size - tail_len)).ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+
+    let tail_text = String::from_utf8_lossy(&tail);
+    let end = tail_text.rfind(ARMOR_END)?;
+    let line_len: u64 = if tail_text.contains('\r') { 66 } else { 65 };
+    let begin_len = ARMOR_BEGIN.len() as u64 + line_len - 64;
+    let body_bytes = (file_size - tail_len + end as u64).checked_sub(begin_len)?;
+
+    let lines = body_bytes.div_ceil(line_len);
+    let chars = body_bytes - lines * (line_len - 64);
+    let padding = tail_text[..end].trim_end().chars().rev().take_while(|&c| c == '=').count() as u64;
+    (chars / 4 * 3).checked_sub(padding)
+}
+
+/// A key padlock knows about, used to match stanzas
+#[derive(Debug, Clone)]
+pub struct KnownRecipient {
+    pub label: String,
+    /// Public recipient: `age1...` or an OpenSSH public key line
+    pub recipient: String,
+    identity: Option<[u8; 32]>,
+}
+
+impl KnownRecipient {
+    /// Recipient from a public key; X25519 recipients match only with an identity
+    pub fn from_recipient(label: impl Into<String>, recipient: &str) -> AgeResult<Self> {
+        let recipient = recipient.trim();
+        if !(recipient.starts_with("age1") || recipient.starts_with("ssh-")) {
+            return Err(AgeError::InvalidOperation {
+                operation: "parse_recipient".to_string(),
+                reason: format!("Unsupported recipient '{}'", recipient.chars().take(20).collect::<String>()),
+            });
+        }
+        Ok(Self { label: label.into(), recipient: recipient.to_string(), identity: None })
+    }
+
+    /// Recipient derived from an `AGE-SECRET-KEY-1...` identity
+    pub fn from_identity(label: impl Into<String>, identity: &str) -> AgeResult<Self> {
+        let invalid = || AgeError::InvalidOperation {
+            operation: "parse_identity".to_string(),
+            reason: "Invalid AGE-SECRET-KEY identity".to_string(),
+        };
+        let (hrp, data) = bech32::decode(identity.trim()).map_err(|_| invalid())?;
+        if !hrp.as_str().eq_ignore_ascii_case("age-secret-key-") {
+            return Err(invalid());
+        }
+        let secret: [u8; 32] = data.try_into().map_err(|_| invalid())?;
+
+        let public = x25519_dalek::PublicKey::from(&x25519_dalek::StaticSecret::from(secret));
+        let hrp = bech32::Hrp::parse("age").map_err(|_| invalid())?;
+        let recipient = bech32::encode::<bech32::Bech32>(hrp, public.as_bytes()).map_err(|_| invalid())?;
+
+        Ok(Self { label: label.into(), recipient, identity: Some(secret) })
+    }
+
+    /// Load identities from an age identity file (one `AGE-SECRET-KEY-1...` per line)
+    pub fn load_identity_file(path: &Path) -> AgeResult<Vec<Self>> {
+        let content = std::fs::read_to_string(path).map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?;
+        content.lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("AGE-SECRET-KEY-"))
+            .enumerate()
+            .map(|(index, line)| Self::from_identity(format!("{}#{}", path.display(), index + 1), line))
+            .collect()
+    }
+
+    /// Load public recipients from a file (one per line, `#` comments allowed)
+    pub fn load_recipients_file(path: &Path) -> AgeResult<Vec<Self>> {
+        let content = std::fs::read_to_string(path).map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?;
+        content.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let label = line.split_whitespace().nth(2).unwrap_or(line).to_string();
+                Self::from_recipient(label, line)
+            })
+            .collect()
+    }
+
+    /// True when this key can open a stanza
+    pub fn matches(&self, stanza: &Stanza) -> bool {
+        match stanza.kind {
+            StanzaKind::X25519 => self.identity.is_some_and(|secret| unwrap_x25519(&secret, stanza)),
+            StanzaKind::SshEd25519 | StanzaKind::SshRsa => {
+                let expected = ssh_recipient_tag(&self.recipient);
+                expected.is_some() && stanza.args.first().and_then(|tag| STANDARD_NO_PAD.decode(tag).ok()) == expected
+            }
+            _ => false,
+        }
+    }
+}
+
+/// First four bytes of SHA-256 over an OpenSSH public key blob
+fn ssh_recipient_tag(recipient: &str) -> Option<Vec<u8>> {
+    let mut parts = recipient.split_whitespace();
+    let kind = parts.next()?;
+    if kind != "ssh-ed25519" && kind != "ssh-rsa" {
+        return None;
+    }
+    let blob = STANDARD.decode(parts.next()?).ok()?;
+    Some(Sha256::digest(blob)[..4].to_vec())
+}
+
+/// Trial-unwrap an X25519 stanza's file key with an identity
+fn unwrap_x25519(secret: &[u8; 32], stanza: &Stanza) -> bool {
+    let share: [u8; 32] = match stanza.args.first().and_then(|a| STANDARD_NO_PAD.decode(a).ok()).and_then(|s| s.try_into().ok()) {
+        Some(share) => share,
+        None => return false,
+    };
+    if stanza.body.len() != 32 {
+        return false;
+    }
+
+    let identity = x25519_dalek::StaticSecret::from(*secret);
+    let recipient = x25519_dalek::PublicKey::from(&identity);
+    let shared = identity.diffie_hellman(&x25519_dalek::PublicKey::from(share));
+    if shared.as_bytes().iter().all(|&b| b == 0) {
+        return false;
+    }
+
+    let mut salt = share.to_vec();
+    salt.extend_from_slice(recipient.as_bytes());
+    let mut wrap_key = [0u8; 32];
+    if Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes()).expand(X25519_INFO, &mut wrap_key).is_err() {
+        return false;
+    }
+
+    ChaCha20Poly1305::new(Key::from_slice(&wrap_key))
+        .decrypt(Nonce::from_slice(&[0u8; 12]), stanza.body.as_slice())
+        .is_ok()
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    //! Builds real age headers for tests, mirroring age's X25519 wrapping
+
+    use super::*;
+
+    /// Header bytes (without payload) for the given X25519 recipients plus optional scrypt
+    pub(crate) fn x25519_header(recipients: &[[u8; 32]], with_scrypt: bool) -> Vec<u8> {
+        let file_key: [u8; 16] = rand::random();
+        let mut header = format!("{}\n", VERSION_LINE);
+
+        for recipient in recipients {
+            let ephemeral = x25519_dalek::StaticSecret::from(rand::random::<[u8; 32]>());
+            let share = x25519_dalek::PublicKey::from(&ephemeral);
+            let shared = ephemeral.diffie_hellman(&x25519_dalek::PublicKey::from(*recipient));
+
+            let mut salt = share.as_bytes().to_vec();
+            salt.extend_from_slice(recipient);
+            let mut wrap_key = [0u8; 32];
+            Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes()).expand(X25519_INFO, &mut wrap_key).unwrap();
+            let body = ChaCha20Poly1305::new(Key::from_slice(&wrap_key))
+                .encrypt(Nonce::from_slice(&[0u8; 12]), file_key.as_slice())
+                .unwrap();
+
+            header.push_str(&format!("-> X25519 {}\n{}\n", STANDARD_NO_PAD.encode(share.as_bytes()), STANDARD_NO_PAD.encode(body)));
+        }
+        if with_scrypt {
+            header.push_str(&format!("-> scrypt {} 18\n{}\n", STANDARD_NO_PAD.encode([1u8; 16]), STANDARD_NO_PAD.encode([2u8; 32])));
+        }
+        header.push_str(&format!("--- {}\n", STANDARD_NO_PAD.encode([3u8; 32])));
+        header.into_bytes()
+    }
+
+    /// `AGE-SECRET-KEY-1...` string and public key bytes for a secret
+    pub(crate) fn identity(secret: [u8; 32]) -> (String, [u8; 32]) {
+        let hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").unwrap();
+        let encoded = bech32::encode_upper::<bech32::Bech32>(hrp, &secret).unwrap();
+        let public = x25519_dalek::PublicKey::from(&x25519_dalek::StaticSecret::from(secret));
+        (encoded, *public.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::testing::{identity, x25519_header};
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_parse_binary_header_and_match_identities() {
+        let (alice_identity, alice_public) = identity([11u8; 32]);
+        let (bob_identity, _) = identity([22u8; 32]);
+
+        let mut data = x25519_header(&[alice_public], true);
+        let header_len = data.len() as u64;
+        data.extend(vec![0u8; 16 + 100 + 16]);
+        let file = NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), &data).unwrap();
+
+        let header = AgeHeader::read(file.path()).unwrap();
+        assert_eq!(header.encoding, HeaderEncoding::Binary);
+        assert_eq!(header.summary(), "X25519, scrypt");
+        assert!(header.has_mac());
+        assert_eq!(header.header_size, header_len);
+        assert_eq!(header.payload_size, Some(132));
+        assert_eq!(header.estimated_plaintext_size(), Some(100));
+        assert_eq!(header.stanzas[1].scrypt_work_factor(), Some(18));
+
+        let known = vec![
+            KnownRecipient::from_identity("alice", &alice_identity).unwrap(),
+            KnownRecipient::from_identity("bob", &bob_identity).unwrap(),
+        ];
+        assert!(known[0].recipient.starts_with("age1"));
+        let labels: Vec<&str> = header.openable_by(&known).iter().map(|k| k.label.as_str()).collect();
+        assert_eq!(labels, vec!["alice"]);
+    }
+
+    #[test]
+    fn test_parse_armored_header() {
+        let (_, public) = identity([5u8; 32]);
+        let mut data = x25519_header(&[public, public], false);
+        let header_len = data.len() as u64;
+        data.extend(vec![7u8; 200]);
+
+        let file = NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), armor(&data)).unwrap();
+
+        assert_eq!(sniff(file.path()).unwrap(), Some(HeaderEncoding::Armored));
+        let header = AgeHeader::read(file.path()).unwrap();
+        assert_eq!(header.encoding, HeaderEncoding::Armored);
+        assert_eq!(header.summary(), "X25519 x2");
+        assert_eq!(header.header_size, header_len);
+        assert_eq!(header.payload_size, Some(200));
+    }
+
+    #[test]
+    fn test_ssh_and_plugin_stanzas() {
+        let blob = b"\x00\x00\x00\x0bssh-ed25519\x00\x00\x00\x20abcdefghijklmnopqrstuvwxyz012345";
+        let ssh_key = format!("ssh-ed25519 {} ops@example", STANDARD.encode(blob));
+        let tag = STANDARD_NO_PAD.encode(&Sha256::digest(blob)[..4]);
+
+        let header_text = format!("{}\n-> ssh-ed25519 {} AAAA\n{}\n-> piv-p256 xyz\n\n--- {}\n",
+            VERSION_LINE, tag, STANDARD_NO_PAD.encode([1u8; 32]), STANDARD_NO_PAD.encode([0u8; 32]));
+        let header = AgeHeader::parse(&mut header_text.as_bytes()).unwrap();
+        assert_eq!(header.stanzas[1].kind, StanzaKind::Plugin("piv-p256".to_string()));
+        assert!(header.stanzas[0].recipient_fingerprint().unwrap().starts_with("ssh:"));
+
+        let known = vec![KnownRecipient::from_recipient("ops", &ssh_key).unwrap()];
+        assert_eq!(header.openable_by(&known).len(), 1);
+    }
+
+    #[test]
+    fn test_rejects_non_age_and_truncated() {
+        assert!(AgeHeader::parse(&mut "hello world\n".as_bytes()).is_err());
+        assert!(AgeHeader::parse(&mut format!("{}\n-> X25519 abc\n", VERSION_LINE).as_bytes()).is_err());
+
+        let file = NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "plain text").unwrap();
+        assert_eq!(sniff(file.path()).unwrap(), None);
+        assert_eq!(sniff(Path::new("/nonexistent/file.age")).unwrap(), None);
+    }
+}
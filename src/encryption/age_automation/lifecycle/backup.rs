@@ -0,0 +1,463 @@
+//! Backup Store - Versioned, encrypted pre-lock backups
+//!
+//! Every backup lives in its own `.padlock/backups/<id>/` directory inside the repository.
+//! File contents are encrypted through the active AgeAdapter to the repository's
+//! `repository` recipient set when the rules define one, and otherwise with the passphrase
+//! of the operation that triggered the backup, so the store never holds plaintext. A small
+//! JSON manifest next to the encrypted payloads records where each file came from, allowing
+//! `list` without a passphrase and selective `restore` of individual paths.
+//!
+//! Security Guardian: Edgar - Recoverable lock and rotate operations
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::super::adapter::AgeAdapter;
+use super::super::config::OutputFormat;
+use super::super::error::{AgeError, AgeResult};
+use super::super::header::{AgeHeader, StanzaKind};
+use super::super::operations::OperationResult;
+
+/// Retention policy applied after each new backup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupRetention {
+    /// Keep at most this many backups (newest first)
+    pub max_count: Option<usize>,
+    /// Remove backups older than this
+    pub max_age: Option<Duration>,
+}
+
+impl Default for BackupRetention {
+    fn default() -> Self {
+        Self {
+            max_count: Some(10),
+            max_age: Some(Duration::from_secs(86400 * 30)),
+        }
+    }
+}
+
+/// A single file captured in a backup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupEntry {
+    /// Path of the original file relative to the repository root
+    pub relative_path: PathBuf,
+    /// Name of the encrypted payload inside the backup directory
+    pub stored_name: String,
+    /// Size of the original file in bytes
+    pub size_bytes: u64,
+}
+
+/// Manifest describing one backup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub operation: String,
+    pub entries: Vec<BackupEntry>,
+}
+
+/// Encrypted backup store rooted at `<repository>/.padlock/backups`
+pub struct BackupStore {
+    repository: PathBuf,
+    root: PathBuf,
+    recipients: Vec<String>,
+    identities: Vec<PathBuf>,
+}
+
+impl BackupStore {
+    /// Location of the backup store relative to the repository root
+    pub const DIRECTORY: &'static str = ".padlock/backups";
+
+    const MANIFEST_FILE: &'static str = "manifest.json";
+
+    /// Create a store handle for the given repository root
+    pub fn new(repository: &Path) -> Self {
+        Self {
+            repository: repository.to_path_buf(),
+            root: repository.join(Self::DIRECTORY),
+            recipients: Vec::new(),
+            identities: Vec::new(),
+        }
+    }
+
+    /// Encrypt new backups to these recipients instead of the passphrase
+    pub fn with_recipients(mut self, recipients: Vec<String>) -> Self {
+        self.recipients = recipients;
+        self
+    }
+
+    /// Identity files that open backups encrypted to recipients
+    pub fn with_identities(mut self, identities: Vec<PathBuf>) -> Self {
+        self.identities = identities;
+        self
+    }
+
+    /// Get the backup store directory
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Encrypt the given files into a new backup and return its manifest
+    pub fn create(
+        &self,
+        adapter: &dyn AgeAdapter,
+        operation: &str,
+        files: &[PathBuf],
+        passphrase: &str,
+    ) -> AgeResult<BackupManifest> {
+        let created_at = Utc::now();
+        let (id, backup_dir) = self.allocate_backup_dir(created_at)?;
+
+        let mut entries = Vec::with_capacity(files.len());
+        for (index, file) in files.iter().enumerate() {
+            let relative_path = self.relative_path(file)?;
+            let stored_name = format!("{:04}.age", index);
+            let size_bytes = fs::metadata(file)
+                .map_err(|e| AgeError::file_error("stat", file.clone(), e))?
+                .len();
+
+            let output = backup_dir.join(&stored_name);
+            let encrypted = if self.recipients.is_empty() {
+                adapter.encrypt(file, &output, passphrase, OutputFormat::Binary)
+            } else {
+                adapter.encrypt_to_recipients(file, &output, &self.recipients, OutputFormat::Binary)
+            };
+            if let Err(e) = encrypted {
+                // Never leave a half-written backup behind
+                let _ = fs::remove_dir_all(&backup_dir);
+                return Err(e);
+            }
+
+            entries.push(BackupEntry { relative_path, stored_name, size_bytes });
+        }
+
+        let manifest = BackupManifest {
+            id,
+            created_at,
+            operation: operation.to_string(),
+            entries,
+        };
+        self.write_manifest(&backup_dir, &manifest)?;
+
+        Ok(manifest)
+    }
+
+    /// List all backups, newest first
+    pub fn list(&self) -> AgeResult<Vec<BackupManifest>> {
+        let mut manifests = Vec::new();
+
+        if !self.root.exists() {
+            return Ok(manifests);
+        }
+
+        for entry in fs::read_dir(&self.root)
+            .map_err(|e| AgeError::file_error("read_dir", self.root.clone(), e))?
+        {
+            let entry = entry?;
+            let manifest_path = entry.path().join(Self::MANIFEST_FILE);
+            if manifest_path.is_file() {
+                manifests.push(Self::read_manifest(&manifest_path)?);
+            }
+        }
+
+        manifests.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.id.cmp(&a.id)));
+        Ok(manifests)
+    }
+
+    /// Get the manifest of a single backup
+    pub fn get(&self, id: &str) -> AgeResult<BackupManifest> {
+        let backup_dir = self.backup_dir(id)?;
+        let manifest_path = backup_dir.join(Self::MANIFEST_FILE);
+        if !manifest_path.is_file() {
+            return Err(AgeError::InvalidOperation {
+                operation: "backup_restore".to_string(),
+                reason: format!("Backup not found: {}", id),
+            });
+        }
+        Self::read_manifest(&manifest_path)
+    }
+
+    /// Restore files from a backup into the repository
+    ///
+    /// An empty `paths` slice restores every entry. Otherwise only entries equal to, or
+    /// located below, one of the given repository-relative paths are restored.
+    pub fn restore(
+        &self,
+        adapter: &dyn AgeAdapter,
+        id: &str,
+        paths: &[PathBuf],
+        passphrase: &str,
+        result: &mut OperationResult,
+    ) -> AgeResult<()> {
+        let manifest = self.get(id)?;
+        let backup_dir = self.backup_dir(id)?;
+
+        let selected: Vec<&BackupEntry> = manifest.entries.iter()
+            .filter(|entry| paths.is_empty() || paths.iter().any(|p| entry.relative_path.starts_with(p)))
+            .collect();
+
+        if selected.is_empty() {
+            return Err(AgeError::InvalidOperation {
+                operation: "backup_restore".to_string(),
+                reason: format!("No matching files in backup {}", id),
+            });
+        }
+
+        for entry in selected {
+            let target = self.repository.join(&entry.relative_path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| AgeError::file_error("create_dir", parent.to_path_buf(), e))?;
+            }
+
+            match self.decrypt(adapter, &backup_dir.join(&entry.stored_name), &target, passphrase) {
+                Ok(_) => result.add_success(target.display().to_string()),
+                Err(e) => {
+                    result.add_failure(target.display().to_string());
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decrypt one payload with the passphrase or, when it is encrypted to public keys,
+    /// the identity files
+    fn decrypt(&self, adapter: &dyn AgeAdapter, stored: &Path, target: &Path, passphrase: &str) -> AgeResult<()> {
+        let header = AgeHeader::read(stored)?;
+        if header.stanzas.iter().all(|stanza| stanza.kind == StanzaKind::Scrypt) {
+            return adapter.decrypt(stored, target, passphrase);
+        }
+        if self.identities.is_empty() {
+            return Err(AgeError::InvalidOperation {
+                operation: "backup_restore".to_string(),
+                reason: format!("{} is encrypted to {}; pass --identity with one of its keys",
+                    stored.display(), header.summary()),
+            });
+        }
+        let plaintext = adapter.decrypt_to_bytes_with_identities(stored, &self.identities)?;
+        fs::write(target, plaintext.expose_secret())
+            .map_err(|e| AgeError::file_error("write", target.to_path_buf(), e))
+    }
+
+    /// Prune backups according to the retention policy, returning the removed ids
+    ///
+    /// The most recent backup is always kept, whatever its age.
+    pub fn apply_retention(&self, retention: &BackupRetention) -> AgeResult<Vec<String>> {
+        let mut removed = Vec::new();
+
+        for manifest in self.retention_candidates(retention, false)? {
+            let backup_dir = self.backup_dir(&manifest.id)?;
+            fs::remove_dir_all(&backup_dir)
+                .map_err(|e| AgeError::file_error("remove_dir", backup_dir.clone(), e))?;
+            removed.push(manifest.id);
+        }
+
+        Ok(removed)
+    }
+
+    /// Backups the retention policy would remove, without removing anything
+    ///
+    /// With `pending_backup` set, the backups are evaluated as if one more backup had just
+    /// been created, which is what a dry-run of a backed-up lock needs.
+    pub fn retention_candidates(&self, retention: &BackupRetention, pending_backup: bool) -> AgeResult<Vec<BackupManifest>> {
+        let now = Utc::now();
+        let offset = usize::from(pending_backup);
+
+        Ok(self.list()?
+            .into_iter()
+            .enumerate()
+            .filter(|(index, manifest)| {
+                let position = index + offset;
+                if position == 0 {
+                    return false;
+                }
+
+                let over_count = retention.max_count.is_some_and(|max| position >= max);
+                let over_age = retention.max_age.is_some_and(|max_age| {
+                    now - manifest.created_at > chrono::Duration::from_std(max_age).unwrap_or(chrono::Duration::MAX)
+                });
+                over_count || over_age
+            })
+            .map(|(_, manifest)| manifest)
+            .collect())
+    }
+
+    /// Create a fresh, uniquely named backup directory
+    fn allocate_backup_dir(&self, created_at: DateTime<Utc>) -> AgeResult<(String, PathBuf)> {
+        fs::create_dir_all(&self.root)
+            .map_err(|e| AgeError::file_error("create_dir", self.root.clone(), e))?;
+
+        let base_id = created_at.format("%Y%m%dT%H%M%S%3fZ").to_string();
+        let mut id = base_id.clone();
+        let mut suffix = 1;
+
+        loop {
+            let dir = self.root.join(&id);
+            match fs::create_dir(&dir) {
+                Ok(_) => {
+                    #[cfg(unix)]
+                    {
+                        use std::os::unix::fs::PermissionsExt;
+                        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))
+                            .map_err(|e| AgeError::file_error("set_permissions", dir.clone(), e))?;
+                    }
+                    return Ok((id, dir));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    id = format!("{}-{}", base_id, suffix);
+                    suffix += 1;
+                }
+                Err(e) => return Err(AgeError::file_error("create_dir", dir, e)),
+            }
+        }
+    }
+
+    /// Resolve a backup id to its directory, rejecting ids that could escape the store
+    fn backup_dir(&self, id: &str) -> AgeResult<PathBuf> {
+        if id.is_empty() || id.contains('/') || id.contains('\\') || id.starts_with('.') {
+            return Err(AgeError::InvalidOperation {
+                operation: "backup_restore".to_string(),
+                reason: format!("Invalid backup id: {}", id),
+            });
+        }
+        Ok(self.root.join(id))
+    }
+
+    /// Express a file path relative to the repository root
+    fn relative_path(&self, file: &Path) -> AgeResult<PathBuf> {
+        file.strip_prefix(&self.repository)
+            .map(Path::to_path_buf)
+            .map_err(|_| AgeError::InvalidOperation {
+                operation: "backup_create".to_string(),
+                reason: format!("{} is outside repository {}", file.display(), self.repository.display()),
+            })
+    }
+
+    fn write_manifest(&self, backup_dir: &Path, manifest: &BackupManifest) -> AgeResult<()> {
+        let manifest_path = backup_dir.join(Self::MANIFEST_FILE);
+        let json = serde_json::to_vec_pretty(manifest)
+            .map_err(|e| AgeError::InvalidOperation {
+                operation: "backup_manifest".to_string(),
+                reason: e.to_string(),
+            })?;
+        fs::write(&manifest_path, json)
+            .map_err(|e| AgeError::file_error("write", manifest_path, e))
+    }
+
+    fn read_manifest(manifest_path: &Path) -> AgeResult<BackupManifest> {
+        let data = fs::read(manifest_path)
+            .map_err(|e| AgeError::file_error("read", manifest_path.to_path_buf(), e))?;
+        serde_json::from_slice(&data)
+            .map_err(|e| AgeError::InvalidOperation {
+                operation: "backup_manifest".to_string(),
+                reason: format!("Corrupt manifest {}: {}", manifest_path.display(), e),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::adapter::testing::MockAdapter;
+    use tempfile::TempDir;
+
+    fn repo_with_files(names: &[&str]) -> (TempDir, Vec<PathBuf>) {
+        let repo = TempDir::new().unwrap();
+        let files = names.iter()
+            .map(|name| {
+                let path = repo.path().join(name);
+                fs::write(&path, format!("content of {}", name)).unwrap();
+                path
+            })
+            .collect();
+        (repo, files)
+    }
+
+    #[test]
+    fn test_backup_roundtrip() {
+        let (repo, files) = repo_with_files(&["a.txt", "b.txt"]);
+        let store = BackupStore::new(repo.path());
+
+        let manifest = store.create(&MockAdapter, "lock", &files, "backup-pass").unwrap();
+        assert_eq!(manifest.entries.len(), 2);
+        assert_eq!(manifest.entries[0].relative_path, PathBuf::from("a.txt"));
+
+        // Stored payloads must not be plaintext copies
+        let stored = fs::read(store.root().join(&manifest.id).join(&manifest.entries[0].stored_name)).unwrap();
+        assert!(stored.starts_with(b"age-encryption.org/v1"));
+
+        fs::write(&files[0], "damaged").unwrap();
+        fs::write(&files[1], "damaged").unwrap();
+
+        let mut result = OperationResult::new();
+        store.restore(&MockAdapter, &manifest.id, &[PathBuf::from("a.txt")], "backup-pass", &mut result).unwrap();
+        assert_eq!(result.processed_files.len(), 1);
+        assert_eq!(fs::read_to_string(&files[0]).unwrap(), "content of a.txt");
+        assert_eq!(fs::read_to_string(&files[1]).unwrap(), "damaged");
+    }
+
+    #[test]
+    fn test_restore_wrong_passphrase_fails() {
+        let (repo, files) = repo_with_files(&["a.txt"]);
+        let store = BackupStore::new(repo.path());
+        let manifest = store.create(&MockAdapter, "lock", &files, "backup-pass").unwrap();
+
+        let mut result = OperationResult::new();
+        assert!(store.restore(&MockAdapter, &manifest.id, &[], "wrong-pass", &mut result).is_err());
+        assert_eq!(result.failed_files.len(), 1);
+    }
+
+    #[test]
+    fn test_backups_follow_the_recipient_set() {
+        use super::super::super::header::{testing, KnownRecipient};
+
+        let (repo, files) = repo_with_files(&["a.txt"]);
+        let (identity, _) = testing::identity([8u8; 32]);
+        let recipient = KnownRecipient::from_identity("ops", &identity).unwrap().recipient;
+        let identity_file = repo.path().join("ops.key");
+        fs::write(&identity_file, format!("{}\n", identity)).unwrap();
+
+        let store = BackupStore::new(repo.path()).with_recipients(vec![recipient]);
+        let manifest = store.create(&MockAdapter, "lock", &files, "backup-pass").unwrap();
+        let stored = store.root().join(&manifest.id).join(&manifest.entries[0].stored_name);
+        assert_eq!(AgeHeader::read(&stored).unwrap().summary(), "X25519");
+
+        // The operation passphrase does not open it; one of the recipients' keys does
+        fs::write(&files[0], "damaged").unwrap();
+        let mut result = OperationResult::new();
+        assert!(BackupStore::new(repo.path()).restore(&MockAdapter, &manifest.id, &[], "backup-pass", &mut result).is_err());
+        let store = BackupStore::new(repo.path()).with_identities(vec![identity_file]);
+        store.restore(&MockAdapter, &manifest.id, &[], "", &mut result).unwrap();
+        assert_eq!(fs::read_to_string(&files[0]).unwrap(), "content of a.txt");
+    }
+
+    #[test]
+    fn test_retention_by_count_keeps_newest() {
+        let (repo, files) = repo_with_files(&["a.txt"]);
+        let store = BackupStore::new(repo.path());
+
+        let ids: Vec<String> = (0..4)
+            .map(|_| store.create(&MockAdapter, "lock", &files, "backup-pass").unwrap().id)
+            .collect();
+
+        let retention = BackupRetention { max_count: Some(2), max_age: None };
+        let removed = store.apply_retention(&retention).unwrap();
+        assert_eq!(removed.len(), 2);
+
+        let remaining: Vec<String> = store.list().unwrap().into_iter().map(|m| m.id).collect();
+        assert_eq!(remaining, vec![ids[3].clone(), ids[2].clone()]);
+    }
+
+    #[test]
+    fn test_invalid_backup_id_rejected() {
+        let (repo, _) = repo_with_files(&[]);
+        let store = BackupStore::new(repo.path());
+        assert!(store.get("../outside").is_err());
+        assert!(store.get("missing").is_err());
+    }
+}
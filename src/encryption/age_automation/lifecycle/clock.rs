@@ -0,0 +1,434 @@
+//! Trusted Clock - Repository high-water mark against clock rollback
+//!
+//! Every padlock operation records the latest time it has seen in the repository's
+//! `.padlock/clock.json`. The record is HMAC-signed with a machine-local key kept in the
+//! padlock state directory (`$XDG_STATE_HOME/padlock`), and a signed copy is mirrored
+//! there too, so deleting or rewinding the repository record does not lower the mark.
+//!
+//! Expiration checks use [`trusted_now`], the later of the wall clock and every mark
+//! observed by this process, so winding the system clock back cannot revive an expired
+//! key. A wall clock behind the mark is reported as a rollback; callers log it as a
+//! security event and, under `ClockRollbackPolicy::Block`, refuse the operation.
+//!
+//! Records written on another machine cannot be verified here. Their time still counts,
+//! but only up to [`FOREIGN_MARK_MAX_LEAD_SECS`] ahead of the wall clock, and it is never
+//! signed into this machine's records, so a planted record cannot push the mark forward.
+//! Call [`load_mark`] before any expiration check that runs outside an observed operation.
+//!
+//! Security Guardian: Edgar - Time only moves forward
+
+use std::fs;
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::super::config::{AgeConfig, ClockRollbackPolicy};
+use super::super::error::{AgeError, AgeResult};
+use super::super::security::AuditLogger;
+
+/// Clock record path relative to the repository root
+pub const CLOCK_FILE: &str = ".padlock/clock.json";
+
+/// Clock differences below this are treated as ordinary skew, not rollback
+pub const ROLLBACK_TOLERANCE_SECS: i64 = 300;
+
+/// Furthest an unverifiable record may run ahead of the wall clock
+pub const FOREIGN_MARK_MAX_LEAD_SECS: i64 = ROLLBACK_TOLERANCE_SECS;
+
+/// Highest mark observed by this process, in milliseconds since the epoch
+static OBSERVED_HIGH_WATER: AtomicI64 = AtomicI64::new(i64::MIN);
+
+/// Current time that never falls behind a high-water mark this process has observed
+pub fn trusted_now() -> DateTime<Utc> {
+    let wall = Utc::now();
+    DateTime::<Utc>::from_timestamp_millis(OBSERVED_HIGH_WATER.load(Ordering::SeqCst))
+        .filter(|mark| *mark > wall)
+        .unwrap_or(wall)
+}
+
+/// Raise [`trusted_now`] to `repository`'s recorded mark without updating the record
+pub fn load_mark(repository: &Path) -> AgeResult<DateTime<Utc>> {
+    Ok(HighWaterMark::new(repository).peek()?.trusted)
+}
+
+fn raise_observed(mark: DateTime<Utc>) {
+    OBSERVED_HIGH_WATER.fetch_max(mark.timestamp_millis(), Ordering::SeqCst);
+}
+
+/// Signed "last seen time" record
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockRecord {
+    pub last_seen: DateTime<Utc>,
+    /// Incremented on every update
+    pub sequence: u64,
+    /// Id of the machine-local key that signed the record
+    pub signer: String,
+    /// Hex HMAC-SHA256 over the fields above
+    pub signature: String,
+}
+
+/// How far a stored record could be trusted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordStatus {
+    Missing,
+    /// Signed by this machine's key
+    Verified,
+    /// Signed by another machine's key; counted as a capped floor, never re-signed
+    Foreign,
+    /// Claims this machine's key but the signature does not match
+    Tampered,
+}
+
+/// Result of consulting a repository's high-water mark
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClockObservation {
+    pub wall: DateTime<Utc>,
+    /// Later of the wall clock and the recorded mark
+    pub trusted: DateTime<Utc>,
+    pub record: RecordStatus,
+    /// How far the wall clock is behind the mark, beyond the skew tolerance
+    pub rollback: Option<chrono::Duration>,
+}
+
+impl ClockObservation {
+    /// Log rollback and tampering as security events; fail under a blocking policy
+    pub fn enforce(&self, repository: &Path, policy: ClockRollbackPolicy, audit: &AuditLogger) -> AgeResult<()> {
+        if self.record == RecordStatus::Tampered {
+            audit.log_error(&format!("CLOCK_RECORD_TAMPERED {} signature mismatch; falling back to the local mirror",
+                repository.display()))?;
+        }
+
+        let Some(behind) = self.rollback else {
+            return Ok(());
+        };
+        audit.log_error(&format!("CLOCK_ROLLBACK {} wall clock {} is {}s behind high-water mark {}",
+            repository.display(), self.wall.to_rfc3339(), behind.num_seconds(), self.trusted.to_rfc3339()))?;
+
+        match policy {
+            ClockRollbackPolicy::Warn => Ok(()),
+            ClockRollbackPolicy::Block => Err(AgeError::SecurityValidationFailed {
+                validation_type: "clock_rollback".to_string(),
+                details: format!("System clock is {}s behind the last time recorded for {}; refusing to continue",
+                    behind.num_seconds(), repository.display()),
+            }),
+        }
+    }
+}
+
+/// A repository's signed high-water mark
+pub struct HighWaterMark {
+    repository: PathBuf,
+    state_dir: PathBuf,
+    /// Whether observations raise the process-wide [`trusted_now`]
+    publish: bool,
+}
+
+impl HighWaterMark {
+    /// High-water mark of `repository`, signed with the default state directory's key
+    pub fn new(repository: &Path) -> Self {
+        Self {
+            repository: repository.to_path_buf(),
+            state_dir: default_state_dir(),
+            publish: true,
+        }
+    }
+
+    /// High-water mark using the configured state directory
+    pub fn from_config(repository: &Path, config: &AgeConfig) -> Self {
+        let mark = Self::new(repository);
+        match &config.clock_state_dir {
+            Some(dir) => mark.with_state_dir(PathBuf::from(dir)),
+            None => mark,
+        }
+    }
+
+    /// Keep the signing key and mirrors under `dir`
+    pub fn with_state_dir(mut self, dir: PathBuf) -> Self {
+        self.state_dir = dir;
+        self
+    }
+
+    /// Keep observations out of [`trusted_now`], so a test's mark cannot leak into others
+    #[cfg(test)]
+    fn scoped(mut self) -> Self {
+        self.publish = false;
+        self
+    }
+
+    /// Compare the wall clock with the mark without updating it
+    pub fn peek(&self) -> AgeResult<ClockObservation> {
+        let key = self.signing_key()?;
+        Ok(self.observe_with(&key).0)
+    }
+
+    /// Compare the wall clock with the mark and advance the mark to the trusted time
+    pub fn observe(&self) -> AgeResult<ClockObservation> {
+        let key = self.signing_key()?;
+        let (observation, sequence, local) = self.observe_with(&key);
+
+        let record = sign(&key, local, sequence + 1);
+        let content = serde_json::to_vec_pretty(&record).map_err(|e| AgeError::InvalidOperation {
+            operation: "clock_record".to_string(),
+            reason: e.to_string(),
+        })?;
+        write_atomic(&self.repository.join(CLOCK_FILE), &content)?;
+        write_atomic(&self.mirror_path(), &content)?;
+        Ok(observation)
+    }
+
+    /// Observation, highest sequence number seen and the mark this machine can vouch for
+    fn observe_with(&self, key: &[u8]) -> (ClockObservation, u64, DateTime<Utc>) {
+        let wall = Utc::now();
+        let (repo_record, record) = match read_record(&self.repository.join(CLOCK_FILE)) {
+            Some(stored) => {
+                let status = verify(key, &stored);
+                (Some(stored), status)
+            }
+            None => (None, RecordStatus::Missing),
+        };
+        let mirror = read_record(&self.mirror_path()).filter(|stored| verify(key, stored) == RecordStatus::Verified);
+
+        let usable_repo = repo_record.filter(|_| matches!(record, RecordStatus::Verified | RecordStatus::Foreign));
+        let sequence = usable_repo.iter().chain(mirror.iter()).map(|stored| stored.sequence).max().unwrap_or(0);
+        let (verified_repo, foreign_repo) = match record {
+            RecordStatus::Verified => (usable_repo, None),
+            _ => (None, usable_repo),
+        };
+
+        let local = verified_repo.iter().chain(mirror.iter()).map(|stored| stored.last_seen).fold(wall, DateTime::max);
+        let foreign_cap = wall + chrono::Duration::seconds(FOREIGN_MARK_MAX_LEAD_SECS);
+        let trusted = foreign_repo.map_or(local, |stored| local.max(stored.last_seen.min(foreign_cap)));
+        if self.publish {
+            raise_observed(trusted);
+        }
+        let behind = trusted - wall;
+        let rollback = (behind.num_seconds() > ROLLBACK_TOLERANCE_SECS).then_some(behind);
+
+        (ClockObservation { wall, trusted, record, rollback }, sequence, local)
+    }
+
+    /// Mirror of this repository's record in the state directory
+    fn mirror_path(&self) -> PathBuf {
+        let canonical = self.repository.canonicalize().unwrap_or_else(|_| self.repository.clone());
+        let digest = Sha256::digest(canonical.to_string_lossy().as_bytes());
+        self.state_dir.join("clock").join(format!("{}.json", &hex::encode(digest)[..16]))
+    }
+
+    /// Machine-local signing key, created on first use
+    fn signing_key(&self) -> AgeResult<Vec<u8>> {
+        let path = self.state_dir.join("clock.key");
+        if let Ok(encoded) = fs::read_to_string(&path) {
+            if let Ok(key) = hex::decode(encoded.trim()) {
+                return Ok(key);
+            }
+        }
+
+        let key = rand::random::<[u8; 32]>().to_vec();
+        write_atomic(&path, hex::encode(&key).as_bytes())?;
+        Ok(key)
+    }
+}
+
+/// `$XDG_STATE_HOME/padlock`, falling back to `~/.local/state/padlock`
+pub fn default_state_dir() -> PathBuf {
+    std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("padlock")
+}
+
+fn key_id(key: &[u8]) -> String {
+    hex::encode(Sha256::digest(key))[..16].to_string()
+}
+
+fn mac(key: &[u8], last_seen: DateTime<Utc>, sequence: u64) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(format!("padlock-clock-v1\n{}\n{}\n", last_seen.timestamp_millis(), sequence).as_bytes());
+    mac
+}
+
+fn sign(key: &[u8], last_seen: DateTime<Utc>, sequence: u64) -> ClockRecord {
+    ClockRecord {
+        last_seen,
+        sequence,
+        signer: key_id(key),
+        signature: hex::encode(mac(key, last_seen, sequence).finalize().into_bytes()),
+    }
+}
+
+fn verify(key: &[u8], record: &ClockRecord) -> RecordStatus {
+    if record.signer != key_id(key) {
+        return RecordStatus::Foreign;
+    }
+    match hex::decode(&record.signature) {
+        Ok(signature) if mac(key, record.last_seen, record.sequence).verify_slice(&signature).is_ok() => RecordStatus::Verified,
+        _ => RecordStatus::Tampered,
+    }
+}
+
+fn read_record(path: &Path) -> Option<ClockRecord> {
+    let content = fs::read(path).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+fn create_private_parent(path: &Path) -> AgeResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(parent)
+            .map_err(|e| AgeError::file_error("create_dir", parent.to_path_buf(), e))?;
+    }
+    Ok(())
+}
+
+/// Write through a staged file and rename, owner-only, creating parent directories 0700
+pub(crate) fn write_atomic(path: &Path, content: &[u8]) -> AgeResult<()> {
+    create_private_parent(path)?;
+
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let staged = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+    let result = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&staged)
+        .and_then(|mut file| file.write_all(content).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&staged, path))
+        .map_err(|e| AgeError::file_error("write", path.to_path_buf(), e));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&staged);
+    }
+    result
+}
+
+/// Exclusive advisory lock on an owner-only lock file, released when dropped
+pub struct FileLock {
+    _file: fs::File,
+}
+
+impl FileLock {
+    /// Wait until the lock on `path` is free and take it
+    pub(crate) fn acquire(path: &Path) -> AgeResult<Self> {
+        Self::flock(path, libc::LOCK_EX)?
+            .ok_or_else(|| AgeError::file_error("lock", path.to_path_buf(), std::io::ErrorKind::WouldBlock.into()))
+    }
+
+    /// Take the lock on `path`, or `None` when another holder has it
+    pub(crate) fn try_acquire(path: &Path) -> AgeResult<Option<Self>> {
+        Self::flock(path, libc::LOCK_EX | libc::LOCK_NB)
+    }
+
+    fn flock(path: &Path, operation: libc::c_int) -> AgeResult<Option<Self>> {
+        create_private_parent(path)?;
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o600)
+            .open(path)
+            .map_err(|e| AgeError::file_error("lock", path.to_path_buf(), e))?;
+
+        // SAFETY: flock only operates on the descriptor owned by `file`
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } != 0 {
+            let error = std::io::Error::last_os_error();
+            if error.kind() == std::io::ErrorKind::WouldBlock {
+                return Ok(None);
+            }
+            return Err(AgeError::file_error("lock", path.to_path_buf(), error));
+        }
+        // The lock is released when the descriptor is closed
+        Ok(Some(Self { _file: file }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_record(repo: &Path, record: &ClockRecord) {
+        fs::write(repo.join(CLOCK_FILE), serde_json::to_vec(record).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_rollback_detected_and_mark_never_decreases() {
+        let repo = TempDir::new().unwrap();
+        let state = TempDir::new().unwrap();
+        let mark = HighWaterMark::new(repo.path()).with_state_dir(state.path().to_path_buf()).scoped();
+
+        let first = mark.observe().unwrap();
+        assert_eq!(first.record, RecordStatus::Missing);
+        assert!(first.rollback.is_none());
+
+        // Simulate a wall clock an hour behind by signing a record an hour ahead
+        let key = mark.signing_key().unwrap();
+        let future = Utc::now() + chrono::Duration::hours(1);
+        write_record(repo.path(), &sign(&key, future, 7));
+
+        let observed = mark.observe().unwrap();
+        assert_eq!(observed.record, RecordStatus::Verified);
+        assert_eq!(observed.trusted, future);
+        assert!(observed.rollback.unwrap() > chrono::Duration::minutes(55));
+        // A scoped mark leaves the process-wide trusted time to the wall clock
+        assert!(trusted_now() < future);
+
+        let audit = AuditLogger::new(None).unwrap();
+        assert!(observed.enforce(repo.path(), ClockRollbackPolicy::Warn, &audit).is_ok());
+        assert!(observed.enforce(repo.path(), ClockRollbackPolicy::Block, &audit).is_err());
+
+        // Deleting the repository record does not lower the mark: the mirror keeps it
+        fs::remove_file(repo.path().join(CLOCK_FILE)).unwrap();
+        assert_eq!(mark.peek().unwrap().trusted, future);
+    }
+
+    #[test]
+    fn test_tampered_record_is_ignored() {
+        let repo = TempDir::new().unwrap();
+        let state = TempDir::new().unwrap();
+        let mark = HighWaterMark::new(repo.path()).with_state_dir(state.path().to_path_buf());
+        let baseline = mark.observe().unwrap().trusted;
+
+        let key = mark.signing_key().unwrap();
+        let mut forged = sign(&key, baseline - chrono::Duration::days(30), 99);
+        forged.last_seen = baseline - chrono::Duration::days(60);
+        write_record(repo.path(), &forged);
+
+        let observed = mark.peek().unwrap();
+        assert_eq!(observed.record, RecordStatus::Tampered);
+        assert!(observed.trusted >= baseline);
+    }
+
+    #[test]
+    fn test_foreign_record_is_capped_and_not_mirrored() {
+        let repo = TempDir::new().unwrap();
+        let state = TempDir::new().unwrap();
+        let mark = HighWaterMark::new(repo.path()).with_state_dir(state.path().to_path_buf()).scoped();
+
+        let far_future = Utc::now() + chrono::Duration::days(3650);
+        fs::create_dir_all(repo.path().join(".padlock")).unwrap();
+        write_record(repo.path(), &sign(&[7u8; 32], far_future, 3));
+
+        let observed = mark.observe().unwrap();
+        assert_eq!(observed.record, RecordStatus::Foreign);
+        assert!(observed.rollback.is_none());
+        assert!(observed.trusted <= observed.wall + chrono::Duration::seconds(FOREIGN_MARK_MAX_LEAD_SECS));
+
+        // Only the local mark was signed: the mirror and the rewritten repository record
+        // both stay close to the wall clock
+        let key = mark.signing_key().unwrap();
+        let mirrored = read_record(&mark.mirror_path()).unwrap();
+        assert_eq!(verify(&key, &mirrored), RecordStatus::Verified);
+        assert!(mirrored.last_seen <= observed.wall + chrono::Duration::seconds(1));
+        assert_eq!(read_record(&repo.path().join(CLOCK_FILE)).unwrap(), mirrored);
+    }
+}
@@ -6,18 +6,35 @@
 //!
 //! Security Guardian: Edgar - Production CRUD coordination with authority integration
 
+use std::cell::RefCell;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 use super::super::error::{AgeError, AgeResult};
 use super::super::config::{AgeConfig, OutputFormat};
 use super::super::adapter::AgeAdapter;
 use super::super::tty_automation::TtyAutomator;
-use super::super::security::AuditLogger;
+use super::super::security::{AuditLogger, SecurityValidator};
+use super::super::header::{AgeHeader, FileHeaderReport, KnownRecipient};
 use super::super::operations::{
     RepositoryStatus, OperationResult
 };
+use super::backup::{BackupStore, BackupManifest, BackupRetention};
+use super::plan::{OperationPlan, PlannedAction};
+use super::edit::{self, SecureEditor, EditOutcome};
+use super::exec::{ExecOptions, SecretEnvironment};
+use super::vault::{self, VaultStore, VaultSecret, VaultEntry};
+use super::structured::{self, StructuredCipher, StructuredFormat, DataKeyStore};
+use super::rearmor::{self, RearmorOutcome, RepositoryManifest};
+use super::clock::HighWaterMark;
+use super::rules::{NamingScheme, PathRules};
+use super::metadata::{self, FileMetadata};
+use crate::authority::authorization::{self, Actor, OperationAuthorizer, OperationPolicy};
+use crate::authority::capability::ScopedKey;
+use crate::authority::revocation::{RevocationList, REVOCATION_FILE};
+use crate::authority::{AuthorityChain, KeyEnvelope, KeyType, TrustAnchors};
 
 /// Options for lock operations
 #[derive(Debug, Clone)]
@@ -26,6 +43,8 @@ pub struct LockOptions {
     pub format: OutputFormat,
     pub pattern_filter: Option<String>,
     pub backup_before_lock: bool,
+    /// Also record extended attributes for unlock to restore
+    pub preserve_xattrs: bool,
 }
 
 impl Default for LockOptions {
@@ -35,6 +54,7 @@ impl Default for LockOptions {
             format: OutputFormat::Binary,
             pattern_filter: None,
             backup_before_lock: false,
+            preserve_xattrs: false,
         }
     }
 }
@@ -75,6 +95,8 @@ pub struct VerificationResult {
     pub failed_files: Vec<String>,
     pub authority_status: String,
     pub overall_status: String,
+    /// Parsed age headers of verified files
+    pub file_headers: Vec<FileHeaderReport>,
 }
 
 /// Emergency operation result
@@ -92,6 +114,14 @@ pub struct CrudManager {
     audit_logger: AuditLogger,
     config: AgeConfig,
     operation_history: Vec<OperationRecord>,
+    known_recipients: Vec<KnownRecipient>,
+    actor: Actor,
+    authorizer: OperationAuthorizer,
+    scoped_keys: Vec<ScopedKey>,
+    /// Roots for revocation signers and recovery recipients (None for the global file)
+    trust_anchors: Option<TrustAnchors>,
+    /// Age identity files that open a vault encrypted to the repository recipient set
+    identity_files: Vec<PathBuf>,
 }
 
 /// Record of performed operations for audit and recovery
@@ -104,6 +134,174 @@ struct OperationRecord {
     details: HashMap<String, String>,
 }
 
+/// Path rules and manifest of the repository an operation runs in
+struct RepositoryContext {
+    root: PathBuf,
+    rules: Option<PathRules>,
+    manifest: RefCell<RepositoryManifest>,
+    revocations: RevocationList,
+    anchors: TrustAnchors,
+    preserve_xattrs: bool,
+}
+
+/// Where and how one file is locked
+struct LockTarget<'a> {
+    format: OutputFormat,
+    output: PathBuf,
+    recipients: Option<&'a [String]>,
+}
+
+impl RepositoryContext {
+    /// Load `.padlock/rules.toml` from the nearest ancestor of `path` that has one, so
+    /// files inside a mirror tree still resolve against their repository; without one the
+    /// nearest ancestor with a `.padlock` directory is the root. Revocations are checked
+    /// against `anchors`
+    fn load(path: &Path, anchors: TrustAnchors) -> AgeResult<Self> {
+        let start = authorization::repository_root(path);
+        let root = start.ancestors()
+            .find(|dir| dir.join(PathRules::FILE).is_file())
+            .map(Path::to_path_buf)
+            .unwrap_or(start);
+        let rules = PathRules::load(&root)?;
+        let manifest = RefCell::new(RepositoryManifest::load(&root)?);
+        let revocations = RevocationList::load(&root)?.with_trust_anchors(anchors.clone());
+        Ok(Self { root, rules, manifest, revocations, anchors, preserve_xattrs: false })
+    }
+
+    /// Manifest key for a locked file
+    fn metadata_key(&self, encrypted: &Path) -> String {
+        encrypted.strip_prefix(&self.root).unwrap_or(encrypted).to_string_lossy().replace('\\', "/")
+    }
+
+    /// Capture a plaintext file's metadata after it was locked to `encrypted`
+    fn record_metadata(&self, file: &Path, encrypted: &Path) -> AgeResult<()> {
+        let metadata = FileMetadata::capture(file, self.preserve_xattrs, self.is_sensitive(file))?;
+        self.manifest.borrow_mut().files.insert(self.metadata_key(encrypted), metadata);
+        Ok(())
+    }
+
+    /// Carry a locked file's recorded metadata over to its new name
+    fn move_metadata(&self, from: &Path, to: &Path) {
+        let mut manifest = self.manifest.borrow_mut();
+        if let Some(metadata) = manifest.files.remove(&self.metadata_key(from)) {
+            manifest.files.insert(self.metadata_key(to), metadata);
+        }
+    }
+
+    /// Whether a path rule marks plaintext file `file` sensitive
+    fn is_sensitive(&self, file: &Path) -> bool {
+        let relative = file.strip_prefix(&self.root).unwrap_or(file);
+        self.rules.as_ref()
+            .and_then(|rules| rules.rule_for(relative))
+            .is_some_and(|rule| rule.sensitive)
+    }
+
+    /// Metadata recorded when a locked file was created, sensitive whenever the path
+    /// rules say `plaintext` is, whatever the manifest records
+    fn metadata_for(&self, encrypted: &Path, plaintext: &Path) -> Option<FileMetadata> {
+        let mut metadata = self.manifest.borrow().files.get(&self.metadata_key(encrypted)).cloned()?;
+        metadata.sensitive |= self.is_sensitive(plaintext);
+        Some(metadata)
+    }
+
+    /// Recipients that keep files locked to scoped keys recoverable: the rules file's
+    /// `repository` set, else the trusted skull and master roots
+    fn recovery_recipients(&self) -> AgeResult<Vec<String>> {
+        let repository = self.repository_recipients();
+        if !repository.is_empty() {
+            return Ok(repository);
+        }
+        Ok(self.anchors.roots().iter()
+            .filter(|root| matches!(root.key_type, KeyType::Skull | KeyType::Master))
+            .map(|root| root.recipient.clone())
+            .collect())
+    }
+
+    /// The rules' `repository` recipient set, empty when they define none
+    fn repository_recipients(&self) -> Vec<String> {
+        self.rules.as_ref()
+            .and_then(|rules| rules.recipients.get(PathRules::REPOSITORY_SET))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// First of `recipients` the revocation list revokes; an entry naming one of them
+    /// that cannot be verified is an error
+    fn revoked_recipient<'a>(&self, recipients: &'a [String]) -> AgeResult<Option<&'a String>> {
+        let chain = AuthorityChain::new();
+        for recipient in recipients {
+            if self.revocations.is_recipient_revoked(recipient, &chain)? {
+                return Ok(Some(recipient));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Persist metadata captured during the operation
+    fn save_manifest(&self) -> AgeResult<()> {
+        let manifest = self.manifest.borrow();
+        if manifest.files.is_empty() {
+            return Ok(());
+        }
+        manifest.save(&self.root)
+    }
+
+    /// Format, output path and recipients for a file; without rules the lock options apply
+    fn lock_target(&self, file: &Path, options: &LockOptions) -> LockTarget<'_> {
+        let rules = match &self.rules {
+            Some(rules) => rules,
+            None => return LockTarget {
+                format: options.format,
+                output: CrudManager::lock_output_path(file, options),
+                recipients: None,
+            },
+        };
+
+        let relative = file.strip_prefix(&self.root).unwrap_or(file);
+        let resolved = rules.resolve(relative, options.format, NamingScheme::Replace);
+        let output = if resolved.format == OutputFormat::Structured {
+            structured::locked_path(file)
+        } else {
+            rules.output_path(&self.root, file, resolved.naming)
+        };
+        LockTarget { format: resolved.format, output, recipients: resolved.recipients }
+    }
+
+    /// Plaintext path for a locked file, mapping mirrored files back to their source
+    fn unlock_target(&self, file: &Path) -> PathBuf {
+        match &self.rules {
+            Some(rules) if rules.is_mirrored(&self.root, file) => rules.source_path(&self.root, file),
+            _ => CrudManager::unlock_output_path(file),
+        }
+    }
+
+    /// Encrypted files inside the mirror tree, if the rules define one that exists
+    fn mirrored_files(&self) -> AgeResult<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        if let Some(rules) = &self.rules {
+            let mirror = self.root.join(&rules.mirror_dir);
+            if mirror.is_dir() {
+                collect_age_files_recursive(&mirror, &mut files)?;
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+/// Recursively collect `.age` files below a directory
+fn collect_age_files_recursive(directory: &Path, files: &mut Vec<PathBuf>) -> AgeResult<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_age_files_recursive(&path, files)?;
+        } else if CrudManager::is_age_file(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
 impl CrudManager {
     /// Create new CrudManager with specified adapter and configuration
     pub fn new(
@@ -111,15 +309,60 @@ impl CrudManager {
         config: AgeConfig,
     ) -> AgeResult<Self> {
         let audit_logger = AuditLogger::new(config.audit_log_path.clone().map(PathBuf::from))?;
+        let authorizer = match &config.clock_state_dir {
+            Some(dir) => OperationAuthorizer::new().with_state_dir(PathBuf::from(dir)),
+            None => OperationAuthorizer::new(),
+        };
 
         Ok(Self {
             adapter,
             audit_logger,
             config,
             operation_history: Vec::new(),
+            known_recipients: Vec::new(),
+            actor: Actor::Passphrase,
+            authorizer,
+            scoped_keys: Vec::new(),
+            trust_anchors: None,
+            identity_files: Vec::new(),
         })
     }
 
+    /// Authority operations run with; passphrase holders by default
+    pub fn with_actor(mut self, actor: Actor) -> Self {
+        self.actor = actor;
+        self
+    }
+
+    /// Authorize operations under `policy` instead of each repository's policy file
+    pub fn with_authorization_policy(mut self, policy: OperationPolicy) -> Self {
+        self.authorizer = self.authorizer.with_policy(policy);
+        self
+    }
+
+    /// Distro keys whose recipients lock adds to the files inside their paths
+    pub fn with_scoped_keys(mut self, keys: Vec<ScopedKey>) -> Self {
+        self.scoped_keys = keys;
+        self
+    }
+
+    /// Trust `anchors` instead of the global trusted-roots file
+    pub fn with_trust_anchors(mut self, anchors: TrustAnchors) -> Self {
+        self.trust_anchors = Some(anchors);
+        self
+    }
+
+    /// Open vaults encrypted to the repository recipient set with these identity files
+    pub fn with_identity_files(mut self, identity_files: Vec<PathBuf>) -> Self {
+        self.identity_files = identity_files;
+        self
+    }
+
+    /// Keys matched against file headers in status and verify reports
+    pub fn set_known_recipients(&mut self, known: Vec<KnownRecipient>) {
+        self.known_recipients = known;
+    }
+
     /// Create CrudManager with default configuration
     pub fn with_defaults() -> AgeResult<Self> {
         let adapter = super::super::adapter::AdapterFactory::create_default()?;
@@ -135,6 +378,8 @@ impl CrudManager {
     pub fn lock(&mut self, path: &Path, passphrase: &str, options: LockOptions) -> AgeResult<OperationResult> {
         let start_time = Instant::now();
         self.audit_logger.log_operation_start_single("lock", path)?;
+        self.authorize("lock", Some(path))?;
+        self.observe_clock(path)?;
         
         let mut result = OperationResult::new();
         
@@ -147,12 +392,24 @@ impl CrudManager {
         // Validate passphrase
         self.validate_passphrase(passphrase)?;
 
+        // Per-path rules override format, naming and recipients
+        let mut context = self.context(path)?;
+        context.preserve_xattrs = options.preserve_xattrs;
+        self.check_revocations(&context)?;
+
         // Determine operation scope
         if path.is_file() {
-            self.lock_single_file(path, passphrase, &options, &mut result)?;
+            if options.backup_before_lock {
+                self.create_backup("lock", path, &[path.to_path_buf()], passphrase)?;
+            }
+            self.lock_single_file(path, passphrase, &options, &context, &mut result)?;
         } else if path.is_dir() {
             if options.recursive {
-                self.lock_repository(path, passphrase, &options, &mut result)?;
+                if options.backup_before_lock {
+                    let files = self.collect_lock_candidates(path, &options)?;
+                    self.create_backup("lock", path, &files, passphrase)?;
+                }
+                self.lock_repository(path, passphrase, &options, &context, &mut result)?;
             } else {
                 return Err(AgeError::InvalidOperation {
                     operation: "lock".to_string(),
@@ -161,6 +418,8 @@ impl CrudManager {
             }
         }
 
+        context.save_manifest()?;
+
         // Record operation
         self.record_operation("lock", path, true, &result);
         result.finalize(start_time);
@@ -172,6 +431,8 @@ impl CrudManager {
     /// READ: Status - Check encryption status and repository state
     pub fn status(&self, path: &Path) -> AgeResult<RepositoryStatus> {
         self.audit_logger.log_operation_start_single("status", path)?;
+        self.authorize("status", Some(path))?;
+        self.observe_clock(path)?;
         
         if !path.exists() {
             return Err(AgeError::file_error("read", path.to_path_buf(),
@@ -192,6 +453,8 @@ impl CrudManager {
     pub fn rotate(&mut self, repository: &Path, new_passphrase: &str) -> AgeResult<OperationResult> {
         let start_time = Instant::now();
         self.audit_logger.log_operation_start_single("rotate", repository)?;
+        self.authorize("rotate", Some(repository))?;
+        self.observe_clock(repository)?;
         
         // This is a placeholder for key rotation functionality
         // In practice, this would coordinate with authority management
@@ -227,6 +490,8 @@ impl CrudManager {
     pub fn unlock(&mut self, path: &Path, passphrase: &str, options: UnlockOptions) -> AgeResult<OperationResult> {
         let start_time = Instant::now();
         self.audit_logger.log_operation_start_single("unlock", path)?;
+        self.authorize("unlock", Some(path))?;
+        self.observe_clock(path)?;
         
         let mut result = OperationResult::new();
         
@@ -250,10 +515,12 @@ impl CrudManager {
         }
 
         // Perform unlock operation
+        let context = self.context(path)?;
+        self.check_revocations(&context)?;
         if path.is_file() {
-            self.unlock_single_file(path, passphrase, &options, &mut result)?;
+            self.unlock_single_file(path, passphrase, &options, &context, &mut result)?;
         } else if path.is_dir() {
-            self.unlock_repository(path, passphrase, &options, &mut result)?;
+            self.unlock_repository(path, passphrase, &options, &context, &mut result)?;
         }
 
         self.record_operation("unlock", path, true, &result);
@@ -270,6 +537,7 @@ impl CrudManager {
     /// ALLOW: Add recipients to authority chain
     pub fn allow(&mut self, recipient: &str) -> AgeResult<AuthorityResult> {
         self.audit_logger.log_authority_operation("allow", recipient)?;
+        self.authorize("allow", None)?;
         
         // Validate recipient format
         if recipient.is_empty() {
@@ -292,6 +560,7 @@ impl CrudManager {
     /// REVOKE: Remove recipients from authority chain  
     pub fn revoke(&mut self, recipient: &str) -> AgeResult<AuthorityResult> {
         self.audit_logger.log_authority_operation("revoke", recipient)?;
+        self.authorize("revoke", None)?;
         
         if recipient.is_empty() {
             return Err(AgeError::InvalidOperation {
@@ -312,6 +581,7 @@ impl CrudManager {
     /// RESET: Emergency repository unlock/reset
     pub fn reset(&mut self, repository: &Path, confirmation: &str) -> AgeResult<EmergencyResult> {
         self.audit_logger.log_emergency_operation("reset", repository)?;
+        self.authorize("reset", Some(repository))?;
         
         // Require explicit confirmation for destructive operation
         if confirmation != "CONFIRM_RESET" {
@@ -349,6 +619,8 @@ impl CrudManager {
     /// VERIFY: Integrity checking and validation
     pub fn verify(&self, path: &Path) -> AgeResult<VerificationResult> {
         self.audit_logger.log_operation_start_single("verify", path)?;
+        self.authorize("verify", Some(path))?;
+        self.observe_clock(path)?;
         
         if !path.exists() {
             return Err(AgeError::file_error("read", path.to_path_buf(),
@@ -369,17 +641,26 @@ impl CrudManager {
             self.verify_repository_integrity(path, &mut verified_files, &mut failed_files)?;
         }
 
+        let file_headers = verified_files.iter().chain(&failed_files)
+            .map(Path::new)
+            .filter(|file| Self::is_age_file(file))
+            .map(|file| FileHeaderReport::inspect(file, &self.known_recipients))
+            .collect();
+
         Ok(VerificationResult {
             verified_files,
             failed_files,
             authority_status: "Authority verification pending".to_string(),
             overall_status: "Verification completed".to_string(),
+            file_headers,
         })
     }
 
     /// EMERGENCY: Fail-safe recovery operations
     pub fn emergency_unlock(&mut self, repository: &Path, emergency_passphrase: &str) -> AgeResult<EmergencyResult> {
         self.audit_logger.log_emergency_operation("emergency_unlock", repository)?;
+        self.authorize("emergency", Some(repository))?;
+        self.observe_clock(repository)?;
         
         if !repository.exists() || !repository.is_dir() {
             return Err(AgeError::InvalidOperation {
@@ -408,6 +689,7 @@ impl CrudManager {
     pub fn batch_process(&mut self, directory: &Path, pattern: Option<&str>, operation: &str, passphrase: &str) -> AgeResult<OperationResult> {
         let start_time = Instant::now();
         self.audit_logger.log_operation_start_single(&format!("batch_{}", operation), directory)?;
+        self.observe_clock(directory)?;
         
         if !directory.exists() || !directory.is_dir() {
             return Err(AgeError::InvalidOperation {
@@ -416,33 +698,38 @@ impl CrudManager {
             });
         }
 
+        // Unknown operations are refused before any file is touched
+        if !matches!(operation, "lock" | "unlock") {
+            return Err(AgeError::InvalidOperation {
+                operation: "batch".to_string(),
+                reason: format!("Unsupported batch operation: {}", operation),
+            });
+        }
+        self.authorize(operation, Some(directory))?;
+        self.validate_passphrase(passphrase)?;
+
         let mut result = OperationResult::new();
         
         // Collect files matching pattern
-        let files = self.collect_files_with_pattern(directory, pattern)?;
+        let context = self.context(directory)?;
+        self.check_revocations(&context)?;
+        let files = self.files_in_scope(self.collect_files_with_pattern(directory, pattern)?, &context)?;
         
-        // Process files in batches for performance
+        // Every file is authorized on its own, since policy rules may ask more of some paths
         for file in files {
-            match operation {
-                "lock" => {
-                    if let Err(e) = self.lock_single_file(&file, passphrase, &LockOptions::default(), &mut result) {
-                        result.add_failure(format!("Failed to lock {}: {}", file.display(), e));
-                    }
-                }
-                "unlock" => {
-                    if let Err(e) = self.unlock_single_file(&file, passphrase, &UnlockOptions::default(), &mut result) {
-                        result.add_failure(format!("Failed to unlock {}: {}", file.display(), e));
-                    }
-                }
-                _ => {
-                    return Err(AgeError::InvalidOperation {
-                        operation: "batch".to_string(),
-                        reason: format!("Unsupported batch operation: {}", operation),
-                    });
-                }
+            let outcome = self.authorize(operation, Some(&file)).and_then(|_| match operation {
+                "lock" => self.lock_single_file(&file, passphrase, &LockOptions::default(), &context, &mut result),
+                _ => self.unlock_single_file(&file, passphrase, &UnlockOptions::default(), &context, &mut result),
+            });
+            if let Err(e) = outcome {
+                result.add_failure(format!("Failed to {} {}: {}", operation, file.display(), e));
             }
         }
 
+        if operation == "lock" {
+            context.save_manifest()?;
+        }
+
         self.record_operation(&format!("batch_{}", operation), directory, result.success, &result);
         result.finalize(start_time);
         
@@ -450,10 +737,612 @@ impl CrudManager {
         Ok(result)
     }
 
+    // ========================================================================================
+    // BACKUP OPERATIONS - Versioned pre-lock backups
+    // ========================================================================================
+
+    /// BACKUP LIST: Show backups stored for a repository, newest first
+    pub fn list_backups(&self, repository: &Path) -> AgeResult<Vec<BackupManifest>> {
+        self.audit_logger.log_operation_start_single("backup_list", repository)?;
+        self.authorize("backup-list", Some(repository))?;
+        BackupStore::new(&authorization::repository_root(repository)).list()
+    }
+
+    /// BACKUP RESTORE: Restore all or selected files from a backup
+    pub fn restore_backup(&mut self, repository: &Path, backup_id: &str, paths: &[PathBuf], passphrase: &str) -> AgeResult<OperationResult> {
+        let start_time = Instant::now();
+        self.audit_logger.log_operation_start_single("backup_restore", repository)?;
+        self.authorize("backup-restore", Some(repository))?;
+        self.observe_clock(repository)?;
+
+        if !repository.is_dir() {
+            return Err(AgeError::InvalidOperation {
+                operation: "backup_restore".to_string(),
+                reason: "Repository path required".to_string(),
+            });
+        }
+
+        self.validate_passphrase(passphrase)?;
+
+        let mut result = OperationResult::new();
+        self.backup_store(repository)?.restore(self.adapter.as_ref(), backup_id, paths, passphrase, &mut result)?;
+
+        self.record_operation("backup_restore", repository, true, &result);
+        result.finalize(start_time);
+
+        self.audit_logger.log_operation_complete("backup_restore", repository, &result)?;
+        Ok(result)
+    }
+
+    // ========================================================================================
+    // EDIT OPERATIONS - In-place editing of encrypted files
+    // ========================================================================================
+
+    /// EDIT: Decrypt to a private workspace, run the editor and re-encrypt if changed
+    pub fn edit(&mut self, file: &Path, passphrase: &str, editor: &SecureEditor) -> AgeResult<EditOutcome> {
+        let start_time = Instant::now();
+        self.audit_logger.log_operation_start_single("edit", file)?;
+        self.authorize("edit", Some(file))?;
+        self.observe_clock(file)?;
+
+        if !file.is_file() {
+            return Err(AgeError::file_error("read", file.to_path_buf(),
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Encrypted file not found")));
+        }
+
+        self.validate_passphrase(passphrase)?;
+
+        if editor.is_persistent_storage() {
+            self.audit_logger.log_warning(&format!("EDIT_WORKSPACE_NOT_RAM_BACKED {}", editor.root().display()))?;
+        }
+
+        // Files locked to recipients are re-encrypted to the set the rules give them
+        let context = self.context(file)?;
+        let plaintext = context.unlock_target(file);
+        let target = context.lock_target(&plaintext, &LockOptions::default());
+        let recipients = self.lock_recipients(&plaintext, target.recipients, &context)?.unwrap_or_default();
+        let editor = editor.clone().with_recipients(recipients);
+        let outcome = editor.edit(self.adapter.as_ref(), file, passphrase)?;
+
+        let mut result = OperationResult::new();
+        match outcome {
+            EditOutcome::ReEncrypted => result.add_success(file.display().to_string()),
+            EditOutcome::Unchanged => self.audit_logger.log_info(&format!("EDIT_UNCHANGED {}", file.display()))?,
+        }
+        self.record_operation("edit", file, true, &result);
+        result.finalize(start_time);
+
+        self.audit_logger.log_operation_complete("edit", file, &result)?;
+        Ok(outcome)
+    }
+
+    /// EXEC: Decrypt dotenv files in memory into an environment for a child process
+    pub fn load_secret_environment(&mut self, options: &ExecOptions, passphrase: &str) -> AgeResult<SecretEnvironment> {
+        for file in &options.env_files {
+            self.audit_logger.log_operation_start_single("exec_env", file)?;
+            self.authorize("exec", Some(file))?;
+            self.observe_clock(file)?;
+        }
+
+        self.validate_passphrase(passphrase)?;
+
+        let environment = SecretEnvironment::load(self.adapter.as_ref(), options, passphrase,
+            |name| std::env::var_os(name).is_some())?;
+
+        // Names only: values never reach the audit log
+        self.audit_logger.log_info(&format!("EXEC_ENV_LOADED {} variables: {}",
+            environment.len(), environment.names().join(",")))?;
+        Ok(environment)
+    }
+
+    // ========================================================================================
+    // VAULT OPERATIONS - Named secrets in the repository vault
+    // ========================================================================================
+
+    /// VAULT GET: Read a named secret
+    pub fn vault_get(&self, repository: &Path, name: &str, passphrase: &str) -> AgeResult<VaultSecret> {
+        self.audit_logger.log_info(&format!("VAULT_GET {} {}", name, repository.display()))?;
+        self.authorize("vault-get", Some(repository))?;
+        self.observe_clock(repository)?;
+
+        let vault = self.vault_store(repository)?.load(self.adapter.as_ref(), passphrase)?;
+        vault.get(name).cloned().ok_or_else(|| AgeError::InvalidOperation {
+            operation: "vault_get".to_string(),
+            reason: format!("No secret named '{}'", name),
+        })
+    }
+
+    /// VAULT SET: Create or update a named secret and re-encrypt the vault
+    pub fn vault_set(&mut self, repository: &Path, name: &str, value: String, tags: Option<BTreeSet<String>>, passphrase: &str) -> AgeResult<()> {
+        self.authorize("vault-set", Some(repository))?;
+        self.observe_clock(repository)?;
+        self.validate_passphrase(passphrase)?;
+
+        let store = self.vault_store(repository)?;
+        let _lock = store.lock()?;
+        let mut secrets = store.load(self.adapter.as_ref(), passphrase)?;
+        secrets.set(name, value, tags, &vault::current_actor())?;
+        store.save(self.adapter.as_ref(), &secrets, passphrase)?;
+
+        self.audit_logger.log_info(&format!("VAULT_SET {} {}", name, repository.display()))?;
+        Ok(())
+    }
+
+    /// VAULT RM: Remove a named secret and re-encrypt the vault
+    pub fn vault_remove(&mut self, repository: &Path, name: &str, passphrase: &str) -> AgeResult<()> {
+        self.authorize("vault-rm", Some(repository))?;
+        self.observe_clock(repository)?;
+        self.validate_passphrase(passphrase)?;
+
+        let store = self.vault_store(repository)?;
+        let _lock = store.lock()?;
+        let mut secrets = store.load(self.adapter.as_ref(), passphrase)?;
+        if secrets.remove(name).is_none() {
+            return Err(AgeError::InvalidOperation {
+                operation: "vault_rm".to_string(),
+                reason: format!("No secret named '{}'", name),
+            });
+        }
+        store.save(self.adapter.as_ref(), &secrets, passphrase)?;
+
+        self.audit_logger.log_info(&format!("VAULT_RM {} {}", name, repository.display()))?;
+        Ok(())
+    }
+
+    /// VAULT LIST: Secret names and metadata, never values
+    pub fn vault_list(&self, repository: &Path, tag: Option<&str>, passphrase: &str) -> AgeResult<Vec<VaultEntry>> {
+        self.audit_logger.log_info(&format!("VAULT_LIST {}", repository.display()))?;
+        self.authorize("vault-list", Some(repository))?;
+        self.observe_clock(repository)?;
+
+        let vault = self.vault_store(repository)?.load(self.adapter.as_ref(), passphrase)?;
+        Ok(vault.entries(tag))
+    }
+
+    /// Vault of `repository`, encrypted to its `repository` recipient set when the rules
+    /// define one
+    fn vault_store(&self, repository: &Path) -> AgeResult<VaultStore> {
+        Ok(VaultStore::new(repository)
+            .with_recipients(self.repository_key_recipients(repository, "vault")?)
+            .with_identities(self.identity_files.clone()))
+    }
+
+    /// The `repository` recipient set that repository-wide keys such as the vault and
+    /// the data key are encrypted to, refused when it names a revoked recipient
+    fn repository_key_recipients(&self, path: &Path, what: &str) -> AgeResult<Vec<String>> {
+        let context = self.context(path)?;
+        let recipients = context.repository_recipients();
+        if let Some(revoked) = context.revoked_recipient(&recipients)? {
+            return Err(AgeError::SecurityValidationFailed {
+                validation_type: "key_revoked".to_string(),
+                details: format!("The {} would be encrypted to revoked recipient {}; remove it from {}", what, revoked, PathRules::FILE),
+            });
+        }
+        Ok(recipients)
+    }
+
+    // ========================================================================================
+    // REARMOR OPERATIONS - Binary/ASCII armor conversion without keys
+    // ========================================================================================
+
+    /// REARMOR: Convert age files to binary or ASCII armor in place
+    pub fn rearmor(&mut self, path: &Path, format: OutputFormat) -> AgeResult<OperationResult> {
+        let start_time = Instant::now();
+        self.audit_logger.log_operation_start_single("rearmor", path)?;
+        self.authorize("rearmor", Some(path))?;
+        self.observe_clock(path)?;
+
+        let encoding = rearmor::target_encoding(format)?;
+        let files = self.collect_rearmor_candidates(path)?;
+        let context = self.context(path)?;
+        let mut result = OperationResult::new();
+
+        for file in &files {
+            match rearmor::rearmor_file(file, encoding) {
+                Ok(RearmorOutcome::Converted { from, to }) => {
+                    if from != to {
+                        context.move_metadata(&from, &to);
+                        self.audit_logger.log_info(&format!("REARMOR_RENAMED {} -> {}", from.display(), to.display()))?;
+                    }
+                    result.add_success(to.display().to_string());
+                }
+                Ok(RearmorOutcome::Unchanged(file)) => result.add_success(file.display().to_string()),
+                Err(e) => {
+                    self.audit_logger.log_warning(&format!("REARMOR_FAILED {}: {}", file.display(), e))?;
+                    result.add_failure(file.display().to_string());
+                }
+            }
+        }
+
+        context.save_manifest()?;
+
+        // Whole-repository conversions become the repository's default encoding
+        if path.is_dir() && result.failed_files.is_empty() {
+            let mut manifest = RepositoryManifest::load(path)?;
+            manifest.encoding = Some(encoding);
+            manifest.updated_at = Some(chrono::Utc::now());
+            manifest.save(path)?;
+        }
+
+        self.record_operation("rearmor", path, result.failed_files.is_empty(), &result);
+        result.finalize(start_time);
+        self.audit_logger.log_operation_complete("rearmor", path, &result)?;
+        Ok(result)
+    }
+
+    /// Default lock format recorded for a repository by a previous rearmor
+    pub fn preferred_format(&self, path: &Path) -> AgeResult<Option<OutputFormat>> {
+        Ok(RepositoryManifest::load(&Self::repository_root(path))?.preferred_format())
+    }
+
+    // ========================================================================================
+    // PLANNING OPERATIONS - Dry-run plans that never touch the filesystem or chain
+    // ========================================================================================
+
+    /// PLAN LOCK: Files that `lock` would encrypt and back up
+    pub fn plan_lock(&self, path: &Path, options: &LockOptions) -> AgeResult<OperationPlan> {
+        if !path.exists() {
+            return Err(AgeError::file_error("read", path.to_path_buf(),
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Path not found")));
+        }
+
+        let files = if path.is_file() {
+            vec![path.to_path_buf()]
+        } else if options.recursive {
+            self.collect_lock_candidates(path, options)?
+        } else {
+            return Err(AgeError::InvalidOperation {
+                operation: "lock".to_string(),
+                reason: "Directory requires --recursive flag".to_string(),
+            });
+        };
+
+        let mut plan = OperationPlan::new("lock", path.display().to_string());
+
+        if options.backup_before_lock && !files.is_empty() {
+            let store = BackupStore::new(&authorization::repository_root(path));
+            for file in &files {
+                plan.add_file(PlannedAction::Backup, file.clone(), Some(store.root().to_path_buf()));
+            }
+            let retention = BackupRetention {
+                max_count: self.config.backup_max_count,
+                max_age: self.config.backup_max_age,
+            };
+            for expired in store.retention_candidates(&retention, true)? {
+                plan.add_file(PlannedAction::Delete, store.root().join(&expired.id), None);
+            }
+        }
+
+        let context = self.context(path)?;
+        for file in self.files_in_scope(files, &context)? {
+            let target = context.lock_target(&file, options);
+            let scoped = target.recipients.map(|_| self.scoped_recipients(&file, &context)).unwrap_or_default();
+            for recipient in target.recipients.unwrap_or_default().iter().chain(&scoped) {
+                if !plan.recipients_added.contains(recipient) {
+                    plan.recipients_added.push(recipient.clone());
+                }
+            }
+            let output_path = target.output;
+            if output_path.exists() {
+                plan.warnings.push(format!("{} already exists and would be overwritten", output_path.display()));
+            }
+            plan.add_file(PlannedAction::Encrypt, file, Some(output_path));
+        }
+
+        Ok(plan)
+    }
+
+    /// PLAN UNLOCK: Files that `unlock` would decrypt
+    pub fn plan_unlock(&self, path: &Path, options: &UnlockOptions) -> AgeResult<OperationPlan> {
+        if !path.exists() {
+            return Err(AgeError::file_error("read", path.to_path_buf(),
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Path not found")));
+        }
+
+        let context = self.context(path)?;
+        let files = if path.is_file() {
+            vec![path.to_path_buf()]
+        } else {
+            let mut files = self.collect_encrypted_files_with_pattern(path, options.pattern_filter.as_deref())?;
+            files.extend(context.mirrored_files()?);
+            files
+        };
+        let files = self.files_in_scope(files, &context)?;
+
+        if options.verify_before_unlock && self.get_status_quietly(path)?.encrypted_files == 0 {
+            return Err(AgeError::InvalidOperation {
+                operation: "unlock".to_string(),
+                reason: "No encrypted files found".to_string(),
+            });
+        }
+
+        let mut plan = OperationPlan::new("unlock", path.display().to_string());
+        for file in files {
+            let output_path = context.unlock_target(&file);
+            if output_path.exists() {
+                plan.warnings.push(format!("{} already exists and would be overwritten", output_path.display()));
+            }
+            plan.add_file(PlannedAction::Decrypt, file, Some(output_path));
+        }
+
+        Ok(plan)
+    }
+
+    /// PLAN ROTATE: What `rotate` changes, which is nothing yet
+    ///
+    /// `rotate` only records the intent to rotate, so the plan lists no re-encryptions
+    /// or rotated keys and warns that the encrypted files keep their current keys.
+    pub fn plan_rotate(&self, repository: &Path) -> AgeResult<OperationPlan> {
+        if !repository.exists() || !repository.is_dir() {
+            return Err(AgeError::InvalidOperation {
+                operation: "rotate".to_string(),
+                reason: "Repository path required".to_string(),
+            });
+        }
+
+        let mut plan = OperationPlan::new("rotate", repository.display().to_string());
+        let encrypted = self.collect_encrypted_files_with_pattern(repository, None)?.len();
+        plan.warnings.push(format!(
+            "rotate only records the rotation request; {} encrypted file(s) keep their current keys and no key is replaced",
+            encrypted));
+
+        Ok(plan)
+    }
+
+    /// PLAN ALLOW: Recipient that `allow` would add
+    pub fn plan_allow(&self, recipient: &str) -> AgeResult<OperationPlan> {
+        if recipient.is_empty() {
+            return Err(AgeError::InvalidOperation {
+                operation: "allow".to_string(),
+                reason: "Recipient cannot be empty".to_string(),
+            });
+        }
+
+        let mut plan = OperationPlan::new("allow", recipient);
+        plan.recipients_added.push(recipient.to_string());
+        Ok(plan)
+    }
+
+    /// PLAN REVOKE: Recipient and key that `revoke` would remove and invalidate
+    pub fn plan_revoke(&self, recipient: &str) -> AgeResult<OperationPlan> {
+        if recipient.is_empty() {
+            return Err(AgeError::InvalidOperation {
+                operation: "revoke".to_string(),
+                reason: "Recipient cannot be empty".to_string(),
+            });
+        }
+
+        let mut plan = OperationPlan::new("revoke", recipient);
+        plan.recipients_removed.push(recipient.to_string());
+        plan.keys_invalidated.push(recipient.to_string());
+        Ok(plan)
+    }
+
+    /// PLAN REARMOR: Files `rearmor` would convert and the names they would end up with
+    pub fn plan_rearmor(&self, path: &Path, format: OutputFormat) -> AgeResult<OperationPlan> {
+        let encoding = rearmor::target_encoding(format)?;
+        let mut plan = OperationPlan::new("rearmor", path.display().to_string());
+
+        for file in self.collect_rearmor_candidates(path)? {
+            if super::super::header::sniff(&file)? == Some(encoding) && rearmor::rearmored_path(&file, encoding) == file {
+                continue;
+            }
+            let target = rearmor::rearmored_path(&file, encoding);
+            plan.add_file(PlannedAction::Rearmor, file, Some(target));
+        }
+
+        Ok(plan)
+    }
+
     // ========================================================================================
     // INTERNAL IMPLEMENTATION METHODS
     // ========================================================================================
 
+    /// Backup store of the repository holding `path`, encrypting to its `repository`
+    /// recipient set when the rules define one
+    fn backup_store(&self, path: &Path) -> AgeResult<BackupStore> {
+        Ok(BackupStore::new(&authorization::repository_root(path))
+            .with_recipients(self.repository_key_recipients(path, "backup")?)
+            .with_identities(self.identity_files.clone()))
+    }
+
+    /// Back up files into the repository's backup store and apply retention
+    fn create_backup(&self, operation: &str, path: &Path, files: &[PathBuf], passphrase: &str) -> AgeResult<()> {
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        let repository = authorization::repository_root(path);
+        let store = self.backup_store(&repository)?;
+        let manifest = store.create(self.adapter.as_ref(), operation, files, passphrase)?;
+        self.audit_logger.log_info(&format!("BACKUP_CREATED {} {} files: {}",
+            manifest.id, repository.display(), manifest.entries.len()))?;
+
+        let retention = BackupRetention {
+            max_count: self.config.backup_max_count,
+            max_age: self.config.backup_max_age,
+        };
+        for removed in store.apply_retention(&retention)? {
+            self.audit_logger.log_info(&format!("BACKUP_PRUNED {} {}", removed, repository.display()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Output path for a locked file
+    fn lock_output_path(file: &Path, options: &LockOptions) -> PathBuf {
+        if options.format == OutputFormat::Structured {
+            structured::locked_path(file)
+        } else {
+            file.with_extension("age")
+        }
+    }
+
+    /// Output path for an unlocked file, removing the .age extension
+    fn unlock_output_path(file: &Path) -> PathBuf {
+        if structured::is_locked_path(file) {
+            structured::unlocked_path(file).unwrap_or_else(|| file.with_extension(""))
+        } else {
+            file.with_extension("")
+        }
+    }
+
+    /// True for files padlock considers encrypted: `.age` files and locked structured files
+    fn is_encrypted_path(path: &Path) -> bool {
+        Self::is_age_file(path) || structured::is_locked_path(path)
+    }
+
+    /// True for `.age` files, whose headers can be inspected
+    fn is_age_file(path: &Path) -> bool {
+        path.extension().and_then(|s| s.to_str()) == Some("age")
+    }
+
+    /// Structured cipher using the repository data key, created on first structured lock
+    ///
+    /// The key belongs to the nearest ancestor with a `.padlock` directory, so files in
+    /// subdirectories share it, and is encrypted to the repository recipient set.
+    fn structured_cipher(&self, path: &Path, passphrase: &str, create: bool) -> AgeResult<StructuredCipher> {
+        let store = DataKeyStore::new(&authorization::repository_root(path))
+            .with_recipients(self.repository_key_recipients(path, "data key")?)
+            .with_identities(self.identity_files.clone());
+        let key = if create {
+            store.load_or_create(self.adapter.as_ref(), passphrase)?
+        } else {
+            store.load(self.adapter.as_ref(), passphrase)?
+        };
+        StructuredCipher::new(key, &self.config.structured_plaintext_keys)
+    }
+
+    /// Repository root for a path: the directory itself, or a file's parent directory
+    fn repository_root(path: &Path) -> PathBuf {
+        if path.is_dir() {
+            return path.to_path_buf();
+        }
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+
+    /// Advance the clock high-water mark of the repository holding `path`
+    ///
+    /// The mark belongs to the nearest ancestor with a `.padlock` directory, or the
+    /// repository root when there is none. A repository that cannot be written, such as
+    /// a read-only checkout, is still checked for rollback against its existing mark.
+    fn observe_clock(&self, path: &Path) -> AgeResult<()> {
+        if !Self::repository_root(path).is_dir() {
+            return Ok(());
+        }
+        let root = authorization::repository_root(path);
+
+        let mark = HighWaterMark::from_config(&root, &self.config);
+        let observation = match mark.observe() {
+            Ok(observation) => observation,
+            Err(e) => {
+                self.audit_logger.log_warning(&format!("CLOCK_RECORD_NOT_UPDATED {}: {}", root.display(), e))?;
+                mark.peek()?
+            }
+        };
+        observation.enforce(&root, self.config.clock_rollback_policy, &self.audit_logger)
+    }
+
+    /// Check the operation policy lets the acting authority run `operation` on `path`
+    fn authorize(&self, operation: &str, path: Option<&Path>) -> AgeResult<()> {
+        self.authorizer.authorize(operation, path, &self.actor, &self.audit_logger)
+    }
+
+    /// Drop files outside the paths of a capability-limited actor, auditing each
+    fn files_in_scope(&self, files: Vec<PathBuf>, context: &RepositoryContext) -> AgeResult<Vec<PathBuf>> {
+        let mut in_scope = Vec::with_capacity(files.len());
+        for file in files {
+            if self.actor.covers(file.strip_prefix(&context.root).unwrap_or(&file)) {
+                in_scope.push(file);
+            } else {
+                self.audit_logger.log_info(&format!("CAPABILITY_SKIPPED {}: outside the paths of {}", file.display(), self.actor))?;
+            }
+        }
+        Ok(in_scope)
+    }
+
+    /// Recipients of the scoped keys whose paths cover `file`
+    fn scoped_recipients(&self, file: &Path, context: &RepositoryContext) -> Vec<String> {
+        let relative = file.strip_prefix(&context.root).unwrap_or(file);
+        self.scoped_keys.iter()
+            .filter(|key| key.covers(relative))
+            .map(|key| key.recipient.clone())
+            .collect()
+    }
+
+    /// Recipients `file` is locked to; None for the passphrase
+    ///
+    /// Scoped keys join the rule's recipient set. A passphrase cannot be combined with
+    /// recipients, so a file a scoped key covers but no rule routes goes to the key plus
+    /// the repository's `repository` set, or failing that the trusted skull and master
+    /// roots, keeping it recoverable without the distro key.
+    fn lock_recipients(&self, file: &Path, named: Option<&[String]>, context: &RepositoryContext) -> AgeResult<Option<Vec<String>>> {
+        let scoped = self.scoped_recipients(file, context);
+        let mut recipients = match named {
+            Some(named) => named.to_vec(),
+            None if scoped.is_empty() => return Ok(None),
+            None => context.recovery_recipients()?,
+        };
+        if recipients.is_empty() {
+            return Err(AgeError::SecurityValidationFailed {
+                validation_type: "scoped_key_recipients".to_string(),
+                details: format!(
+                    "{} is covered by a scoped key but has no recipients to lock it to besides that key; add a '{}' recipient set to {} or a skull or master trusted root",
+                    file.display(), PathRules::REPOSITORY_SET, PathRules::FILE),
+            });
+        }
+        for recipient in scoped {
+            if !recipients.contains(&recipient) {
+                recipients.push(recipient);
+            }
+        }
+        if let Some(revoked) = context.revoked_recipient(&recipients)? {
+            return Err(AgeError::SecurityValidationFailed {
+                validation_type: "key_revoked".to_string(),
+                details: format!("{} would be locked to revoked recipient {}; remove it from {}", file.display(), revoked, PathRules::FILE),
+            });
+        }
+        Ok(Some(recipients))
+    }
+
+    /// Rules, manifest and revocations of the repository holding `path`
+    fn context(&self, path: &Path) -> AgeResult<RepositoryContext> {
+        let anchors = match &self.trust_anchors {
+            Some(anchors) => anchors.clone(),
+            None => TrustAnchors::load()?,
+        };
+        RepositoryContext::load(path, anchors)
+    }
+
+    /// Refuse to run when the acting key or a scoped key has been revoked in the
+    /// repository's revocation list, directly or through a key above it in its verified
+    /// lineage, logging `KEY_REVOKED`. An entry naming one of these keys that cannot be
+    /// verified also refuses.
+    fn check_revocations(&self, context: &RepositoryContext) -> AgeResult<()> {
+        let chain = AuthorityChain::new();
+        let revocations = &context.revocations;
+        let mut checks = Vec::new();
+        if let Actor::Key { fingerprint, lineage, .. } = &self.actor {
+            checks.push((self.actor.to_string(), check_key_revocation(revocations, fingerprint, None, lineage, &chain)));
+        }
+        for key in &self.scoped_keys {
+            let name = format!("scoped key {}", &key.fingerprint[..key.fingerprint.len().min(8)]);
+            checks.push((name, check_key_revocation(revocations, &key.fingerprint, Some(&key.recipient), &key.lineage, &chain)));
+        }
+
+        for (name, check) in checks {
+            if let Err(error) = check {
+                let details = format!("{} is refused by {}: {}", name, context.root.join(REVOCATION_FILE).display(), error);
+                self.audit_logger.log_error(&format!("KEY_REVOKED {}", details))?;
+                return Err(AgeError::SecurityValidationFailed { validation_type: "key_revoked".to_string(), details });
+            }
+        }
+        Ok(())
+    }
+
     /// Validate passphrase meets security requirements
     fn validate_passphrase(&self, passphrase: &str) -> AgeResult<()> {
         if passphrase.is_empty() {
@@ -474,14 +1363,42 @@ impl CrudManager {
     }
 
     /// Lock a single file
-    fn lock_single_file(&self, file: &Path, passphrase: &str, options: &LockOptions, result: &mut OperationResult) -> AgeResult<()> {
-        let output_path = if options.format == OutputFormat::AsciiArmor {
-            file.with_extension("age")
-        } else {
-            file.with_extension("age")
-        };
+    fn lock_single_file(&self, file: &Path, passphrase: &str, options: &LockOptions, context: &RepositoryContext, result: &mut OperationResult) -> AgeResult<()> {
+        let target = context.lock_target(file, options);
+        if target.format == OutputFormat::Structured {
+            let cipher = self.structured_cipher(file, passphrase, true)?;
+            return self.lock_structured_file(file, &cipher, context, result);
+        }
+        self.lock_to_target(file, &target, passphrase, context, result)
+    }
 
-        match self.adapter.encrypt(file, &output_path, passphrase, options.format) {
+    /// Encrypt a file to its resolved target, to recipients when the rules name a set
+    ///
+    /// The adapter reads the file the confined open validated, through its descriptor, and
+    /// the path must still name that file afterwards; otherwise the output is discarded.
+    fn lock_to_target(&self, file: &Path, target: &LockTarget<'_>, passphrase: &str, context: &RepositoryContext, result: &mut OperationResult) -> AgeResult<()> {
+        if let Some(parent) = target.output.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AgeError::file_error("create_dir", parent.to_path_buf(), e))?;
+        }
+
+        let outcome = self.path_validator(&Self::repository_root(file)).open_confined(file)
+            .and_then(|source| {
+                let pinned = pinned_path(&source);
+                let input = pinned.as_deref().unwrap_or(file);
+                self.lock_recipients(file, target.recipients, context)
+                    .and_then(|recipients| match recipients {
+                        Some(recipients) => self.adapter.encrypt_to_recipients(input, &target.output, &recipients, target.format)
+                            .and_then(|_| edit::record_recipients(&target.output, None, Some(&recipients))),
+                        None => self.adapter.encrypt(input, &target.output, passphrase, target.format),
+                    })
+                    .and_then(|_| ensure_unchanged(file, &source).inspect_err(|_| {
+                        let _ = std::fs::remove_file(&target.output);
+                    }))
+            })
+            .and_then(|_| context.record_metadata(file, &target.output));
+
+        match outcome {
             Ok(_) => {
                 result.add_success(file.display().to_string());
                 Ok(())
@@ -494,11 +1411,38 @@ impl CrudManager {
     }
 
     /// Lock repository (directory)
-    fn lock_repository(&self, repository: &Path, passphrase: &str, options: &LockOptions, result: &mut OperationResult) -> AgeResult<()> {
-        let files = self.collect_files_with_pattern(repository, options.pattern_filter.as_deref())?;
+    fn lock_repository(&self, repository: &Path, passphrase: &str, options: &LockOptions, context: &RepositoryContext, result: &mut OperationResult) -> AgeResult<()> {
+        let files = self.files_in_scope(self.collect_lock_candidates(repository, options)?, context)?;
+
+        if options.format == OutputFormat::Structured {
+            if files.is_empty() {
+                return Ok(());
+            }
+            let cipher = self.structured_cipher(repository, passphrase, true)?;
+            for file in files {
+                if let Err(e) = self.lock_structured_file(&file, &cipher, context, result) {
+                    eprintln!("Failed to lock {}: {}", file.display(), e);
+                }
+            }
+            return Ok(());
+        }
         
+        // Rules may route some files to structured mode; the data key is loaded once
+        let mut cipher = None;
         for file in files {
-            if let Err(e) = self.lock_single_file(&file, passphrase, options, result) {
+            let target = context.lock_target(&file, options);
+            let outcome = if target.format == OutputFormat::Structured {
+                if cipher.is_none() {
+                    cipher = Some(self.structured_cipher(repository, passphrase, true)?);
+                }
+                match &cipher {
+                    Some(cipher) => self.lock_structured_file(&file, cipher, context, result),
+                    None => Ok(()),
+                }
+            } else {
+                self.lock_to_target(&file, &target, passphrase, context, result)
+            };
+            if let Err(e) = outcome {
                 // Continue processing other files even if one fails
                 eprintln!("Failed to lock {}: {}", file.display(), e);
             }
@@ -508,11 +1452,24 @@ impl CrudManager {
     }
 
     /// Unlock a single file
-    fn unlock_single_file(&self, file: &Path, passphrase: &str, _options: &UnlockOptions, result: &mut OperationResult) -> AgeResult<()> {
-        // Determine output path by removing .age extension
-        let output_path = file.with_extension("");
+    fn unlock_single_file(&self, file: &Path, passphrase: &str, _options: &UnlockOptions, context: &RepositoryContext, result: &mut OperationResult) -> AgeResult<()> {
+        if structured::is_locked_path(file) {
+            let cipher = self.structured_cipher(file, passphrase, false)?;
+            return self.unlock_structured_file(file, &cipher, context, result);
+        }
+
+        let output_path = context.unlock_target(file);
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AgeError::file_error("create_dir", parent.to_path_buf(), e))?;
+        }
+
+        let metadata = context.metadata_for(file, &output_path);
+        let outcome = Self::prepare_unlock_output(metadata.as_ref(), context.is_sensitive(&output_path), &output_path)
+            .and_then(|_| self.adapter.decrypt(file, &output_path, passphrase))
+            .and_then(|_| self.restore_unlock_metadata(metadata.as_ref(), &output_path));
 
-        match self.adapter.decrypt(file, &output_path, passphrase) {
+        match outcome {
             Ok(_) => {
                 result.add_success(file.display().to_string());
                 Ok(())
@@ -525,26 +1482,121 @@ impl CrudManager {
     }
 
     /// Unlock repository (directory)
-    fn unlock_repository(&self, repository: &Path, passphrase: &str, options: &UnlockOptions, result: &mut OperationResult) -> AgeResult<()> {
-        let files = self.collect_encrypted_files_with_pattern(repository, options.pattern_filter.as_deref())?;
+    fn unlock_repository(&self, repository: &Path, passphrase: &str, options: &UnlockOptions, context: &RepositoryContext, result: &mut OperationResult) -> AgeResult<()> {
+        let mut files = self.collect_encrypted_files_with_pattern(repository, options.pattern_filter.as_deref())?;
+        files.extend(context.mirrored_files()?);
+        let (structured_files, age_files): (Vec<PathBuf>, Vec<PathBuf>) = self.files_in_scope(files, context)?.into_iter()
+            .partition(|file| structured::is_locked_path(file));
         
-        for file in files {
-            if let Err(e) = self.unlock_single_file(&file, passphrase, options, result) {
+        for file in age_files {
+            if let Err(e) = self.unlock_single_file(&file, passphrase, options, context, result) {
                 eprintln!("Failed to unlock {}: {}", file.display(), e);
             }
         }
 
+        if !structured_files.is_empty() {
+            let cipher = self.structured_cipher(repository, passphrase, false)?;
+            for file in structured_files {
+                if let Err(e) = self.unlock_structured_file(&file, &cipher, context, result) {
+                    eprintln!("Failed to unlock {}: {}", file.display(), e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Encrypt the values of a structured file into its locked counterpart
+    fn lock_structured_file(&self, file: &Path, cipher: &StructuredCipher, context: &RepositoryContext, result: &mut OperationResult) -> AgeResult<()> {
+        let outcome = StructuredFormat::from_path(file)
+            .ok_or_else(|| AgeError::InvalidOperation {
+                operation: "lock".to_string(),
+                reason: format!("Structured mode supports JSON, YAML, TOML and dotenv files: {}", file.display()),
+            })
+            .and_then(|format| {
+                let content = self.read_confined(file)?;
+                let encrypted = cipher.encrypt_document(&content, format)?;
+                let output_path = structured::locked_path(file);
+                std::fs::write(&output_path, encrypted)
+                    .map_err(|e| AgeError::file_error("write", output_path, e))
+            })
+            .and_then(|_| context.record_metadata(file, &structured::locked_path(file)));
+
+        match outcome {
+            Ok(_) => result.add_success(file.display().to_string()),
+            Err(_) => result.add_failure(file.display().to_string()),
+        }
+        outcome
+    }
+
+    /// Decrypt and verify a locked structured file into its plain counterpart
+    fn unlock_structured_file(&self, file: &Path, cipher: &StructuredCipher, context: &RepositoryContext, result: &mut OperationResult) -> AgeResult<()> {
+        let outcome = StructuredFormat::from_path(file)
+            .ok_or_else(|| AgeError::InvalidOperation {
+                operation: "unlock".to_string(),
+                reason: format!("Not a structured file: {}", file.display()),
+            })
+            .and_then(|format| {
+                let content = std::fs::read_to_string(file)
+                    .map_err(|e| AgeError::file_error("read", file.to_path_buf(), e))?;
+                let decrypted = cipher.decrypt_document(&content, format)?;
+                let output_path = Self::unlock_output_path(file);
+                let metadata = context.metadata_for(file, &output_path);
+                Self::prepare_unlock_output(metadata.as_ref(), context.is_sensitive(&output_path), &output_path)?;
+                std::fs::write(&output_path, decrypted)
+                    .map_err(|e| AgeError::file_error("write", output_path.clone(), e))?;
+                self.restore_unlock_metadata(metadata.as_ref(), &output_path)
+            });
+
+        match outcome {
+            Ok(_) => result.add_success(file.display().to_string()),
+            Err(_) => result.add_failure(file.display().to_string()),
+        }
+        outcome
+    }
+
+    /// Refuse or pre-create plaintext output according to the recorded metadata; files
+    /// a rule marks `sensitive` are created owner-only even without a manifest entry
+    fn prepare_unlock_output(metadata: Option<&FileMetadata>, sensitive: bool, output_path: &Path) -> AgeResult<()> {
+        match metadata {
+            Some(metadata) => metadata.prepare_output(output_path),
+            None if sensitive => metadata::create_private(output_path),
+            None => Ok(()),
+        }
+    }
+
+    /// Restore recorded metadata on unlocked output, auditing what lacked privilege
+    fn restore_unlock_metadata(&self, metadata: Option<&FileMetadata>, output_path: &Path) -> AgeResult<()> {
+        let metadata = match metadata {
+            Some(metadata) => metadata,
+            None => return Ok(()),
+        };
+        for skipped in metadata.restore(output_path)? {
+            self.audit_logger.log_warning(&format!("Could not restore {} on {}", skipped, output_path.display()))?;
+        }
         Ok(())
     }
 
+    /// Get status without audit logging, for planning
+    fn get_status_quietly(&self, path: &Path) -> AgeResult<RepositoryStatus> {
+        if path.is_file() {
+            self.get_file_status(path)
+        } else {
+            self.get_repository_status(path)
+        }
+    }
+
     /// Get status for a single file
     fn get_file_status(&self, file: &Path) -> AgeResult<RepositoryStatus> {
         let mut status = RepositoryStatus::new();
         status.total_files = 1;
 
-        // Simple heuristic: check if file has .age extension
-        if file.extension().and_then(|s| s.to_str()) == Some("age") {
+        // Simple heuristic: check if file has .age extension or a locked structured name
+        if Self::is_encrypted_path(file) {
             status.encrypted_files = 1;
+            if Self::is_age_file(file) {
+                status.file_headers.push(FileHeaderReport::inspect(file, &self.known_recipients));
+            }
         } else {
             status.unencrypted_files = 1;
         }
@@ -563,8 +1615,11 @@ impl CrudManager {
             if path.is_file() {
                 status.total_files += 1;
                 
-                if path.extension().and_then(|s| s.to_str()) == Some("age") {
+                if Self::is_encrypted_path(&path) {
                     status.encrypted_files += 1;
+                    if Self::is_age_file(&path) {
+                        status.file_headers.push(FileHeaderReport::inspect(&path, &self.known_recipients));
+                    }
                 } else {
                     status.unencrypted_files += 1;
                 }
@@ -574,10 +1629,39 @@ impl CrudManager {
         Ok(status)
     }
 
+    /// Files a rearmor of `path` would consider
+    fn collect_rearmor_candidates(&self, path: &Path) -> AgeResult<Vec<PathBuf>> {
+        if !path.exists() {
+            return Err(AgeError::file_error("read", path.to_path_buf(),
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Path not found")));
+        }
+        if path.is_file() {
+            return Ok(vec![path.to_path_buf()]);
+        }
+
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let file = entry?.path();
+            if file.is_file() && rearmor::is_rearmor_candidate(&file)? {
+                files.push(file);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
     /// Verify integrity of a single file
-    fn verify_file_integrity(&self, _file: &Path) -> AgeResult<()> {
-        // Placeholder for file integrity verification
-        // In practice, this would attempt to decrypt and verify
+    fn verify_file_integrity(&self, file: &Path) -> AgeResult<()> {
+        // Age files must carry a well-formed header; structured files are self-describing
+        if Self::is_age_file(file) {
+            let header = AgeHeader::read(file)?;
+            if header.stanzas.is_empty() {
+                return Err(AgeError::InvalidOperation {
+                    operation: "verify".to_string(),
+                    reason: format!("{} has no recipient stanzas", file.display()),
+                });
+            }
+        }
         Ok(())
     }
 
@@ -587,7 +1671,7 @@ impl CrudManager {
             let entry = entry?;
             let path = entry.path();
             
-            if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("age") {
+            if path.is_file() && Self::is_encrypted_path(&path) {
                 match self.verify_file_integrity(&path) {
                     Ok(_) => verified.push(path.display().to_string()),
                     Err(_) => failed.push(path.display().to_string()),
@@ -601,12 +1685,13 @@ impl CrudManager {
     /// Collect files matching pattern
     fn collect_files_with_pattern(&self, directory: &Path, pattern: Option<&str>) -> AgeResult<Vec<PathBuf>> {
         let mut files = Vec::new();
+        let validator = self.path_validator(directory);
 
         for entry in std::fs::read_dir(directory)? {
             let entry = entry?;
             let path = entry.path();
             
-            if path.is_file() {
+            if validator.check_discovered(&path)? && path.is_file() {
                 // Apply pattern filter if specified
                 if let Some(pattern) = pattern {
                     if let Some(filename) = path.file_name().and_then(|s| s.to_str()) {
@@ -622,15 +1707,28 @@ impl CrudManager {
         Ok(files)
     }
 
-    /// Collect encrypted files (*.age) matching pattern
+    /// Files a directory lock would encrypt; structured mode only takes unlocked structured files
+    fn collect_lock_candidates(&self, directory: &Path, options: &LockOptions) -> AgeResult<Vec<PathBuf>> {
+        let files = self.collect_files_with_pattern(directory, options.pattern_filter.as_deref())?;
+        if options.format != OutputFormat::Structured {
+            return Ok(files);
+        }
+
+        Ok(files.into_iter()
+            .filter(|file| StructuredFormat::from_path(file).is_some() && !structured::is_locked_path(file))
+            .collect())
+    }
+
+    /// Collect encrypted files (*.age and locked structured files) matching pattern
     fn collect_encrypted_files_with_pattern(&self, directory: &Path, pattern: Option<&str>) -> AgeResult<Vec<PathBuf>> {
         let mut files = Vec::new();
+        let validator = self.path_validator(directory);
 
         for entry in std::fs::read_dir(directory)? {
             let entry = entry?;
             let path = entry.path();
             
-            if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("age") {
+            if Self::is_encrypted_path(&path) && validator.check_discovered(&path)? && path.is_file() {
                 // Apply pattern filter if specified
                 if let Some(pattern) = pattern {
                     if let Some(filename) = path.file_name().and_then(|s| s.to_str()) {
@@ -646,6 +1744,21 @@ impl CrudManager {
         Ok(files)
     }
 
+    /// Path validator confined to a repository, using the configured symlink and location policy
+    fn path_validator(&self, repository: &Path) -> SecurityValidator {
+        SecurityValidator::from_config(&self.config).with_root(repository)
+    }
+
+    /// Read a plaintext file through the confined, TOCTOU-checked open
+    fn read_confined(&self, file: &Path) -> AgeResult<String> {
+        let mut content = String::new();
+        self.path_validator(&Self::repository_root(file))
+            .open_confined(file)?
+            .read_to_string(&mut content)
+            .map_err(|e| AgeError::file_error("read", file.to_path_buf(), e))?;
+        Ok(content)
+    }
+
     /// Record operation for audit and recovery purposes
     fn record_operation(&mut self, operation_type: &str, target_path: &Path, success: bool, result: &OperationResult) {
         let mut details = HashMap::new();
@@ -669,12 +1782,59 @@ impl CrudManager {
     }
 }
 
+/// Name under which other processes can open exactly the file behind `file`, without
+/// re-resolving the original path
+/// Fail when a key is revoked, itself or through a key above it in its verified lineage
+///
+/// Without a lineage only the key's own fingerprint and recipient can be matched.
+fn check_key_revocation(revocations: &RevocationList, fingerprint: &str, recipient: Option<&str>, lineage: &[KeyEnvelope], chain: &AuthorityChain) -> AgeResult<()> {
+    if !lineage.is_empty() {
+        return revocations.check_lineage(lineage, chain);
+    }
+    let revoked = revocations.is_fingerprint_revoked(fingerprint, chain)?
+        || recipient.map(|recipient| revocations.is_recipient_revoked(recipient, chain)).transpose()?.unwrap_or(false);
+    if revoked {
+        return Err(AgeError::SecurityValidationFailed {
+            validation_type: "key_revoked".to_string(),
+            details: format!("key {} is revoked", &fingerprint[..fingerprint.len().min(8)]),
+        });
+    }
+    Ok(())
+}
+
+fn pinned_path(file: &std::fs::File) -> Option<PathBuf> {
+    use std::os::fd::AsRawFd;
+    let pinned = PathBuf::from(format!("/proc/{}/fd/{}", std::process::id(), file.as_raw_fd()));
+    pinned.exists().then_some(pinned)
+}
+
+/// Fail when `path` no longer names the file opened as `opened`
+fn ensure_unchanged(path: &Path, opened: &std::fs::File) -> AgeResult<()> {
+    use std::os::unix::fs::MetadataExt;
+    let before = opened.metadata().map_err(|e| AgeError::file_error("stat", path.to_path_buf(), e))?;
+    let after = std::fs::metadata(path).map_err(|e| AgeError::file_error("stat", path.to_path_buf(), e))?;
+    if (before.dev(), before.ino()) != (after.dev(), after.ino()) {
+        return Err(AgeError::SecurityValidationFailed {
+            validation_type: "toctou".to_string(),
+            details: format!("{} was replaced while it was being encrypted", path.display()),
+        });
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use super::super::config::OutputFormat;
+    use super::super::super::config::OutputFormat;
+    use super::super::super::adapter::testing::MockAdapter;
+    use super::super::super::header::{self, HeaderEncoding};
+    use crate::authority::AuthorityLevel;
     use tempfile::TempDir;
 
+    fn mock_manager() -> CrudManager {
+        CrudManager::new(Box::new(MockAdapter), AgeConfig::testing()).unwrap()
+    }
+
     #[test]
     fn test_crud_manager_creation() {
         let result = CrudManager::with_defaults();
@@ -713,4 +1873,566 @@ mod tests {
         assert!(options.pattern_filter.is_none());
         assert!(!options.preserve_encrypted);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lock_with_backup_and_restore() {
+        let repo = TempDir::new().unwrap();
+        let secret = repo.path().join("secret.txt");
+        std::fs::write(&secret, "original").unwrap();
+
+        // Restoring needs repo authority, which passphrase holders only get from policy
+        let policy = OperationPolicy::builtin().with_passphrase_level(AuthorityLevel::RepoControl);
+        let mut crud_manager = mock_manager().with_authorization_policy(policy);
+        let options = LockOptions { recursive: true, backup_before_lock: true, ..LockOptions::default() };
+        crud_manager.lock(repo.path(), "lock-pass", options).unwrap();
+
+        let backups = crud_manager.list_backups(repo.path()).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].operation, "lock");
+
+        std::fs::remove_file(&secret).unwrap();
+        let result = crud_manager.restore_backup(repo.path(), &backups[0].id, &[], "lock-pass").unwrap();
+        assert!(result.success);
+        assert_eq!(std::fs::read_to_string(&secret).unwrap(), "original");
+    }
+
+    #[test]
+    fn test_backups_use_the_repository_root_and_recipients() {
+        let repo = TempDir::new().unwrap();
+        std::fs::create_dir_all(repo.path().join("config/prod")).unwrap();
+        std::fs::create_dir_all(repo.path().join(".padlock")).unwrap();
+        let nested = repo.path().join("config/prod/app.env");
+        std::fs::write(&nested, "TOKEN=abc").unwrap();
+
+        let (identity, _) = header::testing::identity([9u8; 32]);
+        let recipient = KnownRecipient::from_identity("ops", &identity).unwrap().recipient;
+        let identity_file = repo.path().join("ops.key");
+        std::fs::write(&identity_file, &identity).unwrap();
+        std::fs::write(repo.path().join(PathRules::FILE), format!("[recipients]\nrepository = [\"{}\"]\n", recipient)).unwrap();
+
+        // A single nested file is backed up at the repository root, to the recipient set
+        let policy = OperationPolicy::builtin().with_passphrase_level(AuthorityLevel::RepoControl);
+        let mut crud_manager = mock_manager().with_authorization_policy(policy).with_identity_files(vec![identity_file]);
+        let options = LockOptions { backup_before_lock: true, ..LockOptions::default() };
+        crud_manager.lock(&nested, "lock-pass", options).unwrap();
+        assert!(!repo.path().join("config/prod/.padlock").exists());
+
+        let backups = crud_manager.list_backups(repo.path()).unwrap();
+        assert_eq!(backups.len(), 1);
+        let stored = repo.path().join(BackupStore::DIRECTORY).join(&backups[0].id).join(&backups[0].entries[0].stored_name);
+        assert_eq!(AgeHeader::read(&stored).unwrap().summary(), "X25519");
+
+        let result = crud_manager.restore_backup(repo.path(), &backups[0].id, &[], "lock-pass").unwrap();
+        assert!(result.success);
+        assert_eq!(std::fs::read_to_string(&nested).unwrap(), "TOKEN=abc");
+    }
+
+    #[test]
+    fn test_lock_follows_path_rules() {
+        let repo = TempDir::new().unwrap();
+        std::fs::create_dir_all(repo.path().join(".padlock")).unwrap();
+        std::fs::write(repo.path().join(PathRules::FILE), r#"
+[recipients]
+ops = ["age1ops"]
+
+[[rule]]
+pattern = "*.env"
+format = "ascii"
+naming = "append"
+recipients = "ops"
+
+[[rule]]
+pattern = "blob.bin"
+naming = "mirror"
+"#).unwrap();
+        for name in ["app.env", "blob.bin", "notes.txt"] {
+            std::fs::write(repo.path().join(name), name).unwrap();
+        }
+
+        let mut crud_manager = mock_manager();
+        let options = LockOptions { recursive: true, ..LockOptions::default() };
+        crud_manager.lock(repo.path(), "lock-pass", options).unwrap();
+
+        let env = repo.path().join("app.env.age");
+        assert_eq!(header::sniff(&env).unwrap(), Some(HeaderEncoding::Armored));
+        assert!(repo.path().join("notes.age").exists());
+        let mirrored = repo.path().join("secrets/blob.bin.age");
+        assert_eq!(header::sniff(&mirrored).unwrap(), Some(HeaderEncoding::Binary));
+
+        std::fs::remove_file(repo.path().join("blob.bin")).unwrap();
+        crud_manager.unlock(&mirrored, "lock-pass", UnlockOptions { verify_before_unlock: false, ..UnlockOptions::default() }).unwrap();
+        assert_eq!(std::fs::read_to_string(repo.path().join("blob.bin")).unwrap(), "blob.bin");
+    }
+
+    #[test]
+    fn test_unlock_restores_metadata() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let repo = TempDir::new().unwrap();
+        let key = repo.path().join("deploy.key");
+        std::fs::write(&key, "key material").unwrap();
+        let mtime = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        std::fs::File::options().write(true).open(&key).unwrap().set_modified(mtime).unwrap();
+        std::fs::set_permissions(&key, std::fs::Permissions::from_mode(0o600)).unwrap();
+
+        let mut crud_manager = mock_manager();
+        crud_manager.lock(&key, "lock-pass", LockOptions::default()).unwrap();
+        let manifest = RepositoryManifest::load(repo.path()).unwrap();
+        assert!(manifest.files["deploy.age"].sensitive);
+
+        // Unlock recreates the plaintext owner-only with its original mtime
+        std::fs::remove_file(&key).unwrap();
+        let locked = repo.path().join("deploy.age");
+        let options = UnlockOptions { verify_before_unlock: false, ..UnlockOptions::default() };
+        crud_manager.unlock(&locked, "lock-pass", options.clone()).unwrap();
+        let restored = std::fs::metadata(repo.path().join("deploy")).unwrap();
+        assert_eq!(restored.mode() & 0o777, 0o600);
+        assert_eq!(restored.modified().unwrap(), mtime);
+
+        // A sensitive file whose recorded mode exposes it is refused
+        let mut manifest = RepositoryManifest::load(repo.path()).unwrap();
+        manifest.files.get_mut("deploy.age").unwrap().mode = 0o644;
+        manifest.save(repo.path()).unwrap();
+        assert!(crud_manager.unlock(&locked, "lock-pass", options.clone()).is_err());
+
+        // Clearing the manifest's flag does not override a rule marking the path sensitive
+        manifest.files.get_mut("deploy.age").unwrap().sensitive = false;
+        manifest.save(repo.path()).unwrap();
+        std::fs::write(repo.path().join(PathRules::FILE), "[[rule]]\npattern = \"deploy\"\nsensitive = true\n").unwrap();
+        assert!(crud_manager.unlock(&locked, "lock-pass", options).is_err());
+    }
+
+    #[test]
+    fn test_lock_without_backup_creates_no_store() {
+        let repo = TempDir::new().unwrap();
+        std::fs::write(repo.path().join("secret.txt"), "original").unwrap();
+
+        let mut crud_manager = mock_manager();
+        let options = LockOptions { recursive: true, ..LockOptions::default() };
+        crud_manager.lock(repo.path(), "lock-pass", options).unwrap();
+
+        assert!(!repo.path().join(BackupStore::DIRECTORY).exists());
+    }
+
+    #[test]
+    fn test_plan_lock_touches_nothing() {
+        let repo = TempDir::new().unwrap();
+        let secret = repo.path().join("secret.txt");
+        std::fs::write(&secret, "original").unwrap();
+
+        let crud_manager = mock_manager();
+        let options = LockOptions { recursive: true, backup_before_lock: true, ..LockOptions::default() };
+        let plan = crud_manager.plan_lock(repo.path(), &options).unwrap();
+
+        let encrypts = plan.files_with_action(PlannedAction::Encrypt);
+        assert_eq!(encrypts.len(), 1);
+        assert_eq!(encrypts[0].target.as_deref(), Some(repo.path().join("secret.age").as_path()));
+        assert_eq!(plan.files_with_action(PlannedAction::Backup).len(), 1);
+
+        assert!(!repo.path().join("secret.age").exists());
+        assert!(!repo.path().join(".padlock").exists());
+    }
+
+    #[test]
+    fn test_plan_unlock_and_rotate() {
+        let repo = TempDir::new().unwrap();
+        std::fs::write(repo.path().join("a.age"), "ciphertext").unwrap();
+        std::fs::write(repo.path().join("b.txt"), "plaintext").unwrap();
+
+        let crud_manager = mock_manager();
+        let unlock_plan = crud_manager.plan_unlock(repo.path(), &UnlockOptions::default()).unwrap();
+        assert_eq!(unlock_plan.files_with_action(PlannedAction::Decrypt).len(), 1);
+        assert!(!repo.path().join("a").exists());
+
+        // rotate does not re-encrypt anything yet, and its plan must not claim otherwise
+        let rotate_plan = crud_manager.plan_rotate(repo.path()).unwrap();
+        assert!(rotate_plan.files_with_action(PlannedAction::ReEncrypt).is_empty());
+        assert!(rotate_plan.keys_rotated.is_empty());
+        assert!(rotate_plan.warnings[0].contains("1 encrypted file(s) keep their current keys"));
+    }
+
+    #[test]
+    fn test_plan_allow_revoke() {
+        let crud_manager = mock_manager();
+        assert_eq!(crud_manager.plan_allow("age1recipient").unwrap().recipients_added, vec!["age1recipient"]);
+
+        let revoke = crud_manager.plan_revoke("age1recipient").unwrap();
+        assert_eq!(revoke.recipients_removed, vec!["age1recipient"]);
+        assert_eq!(revoke.keys_invalidated, vec!["age1recipient"]);
+
+        assert!(crud_manager.plan_revoke("").is_err());
+    }
+
+    #[test]
+    fn test_vault_operations() {
+        let repo = TempDir::new().unwrap();
+        let mut crud_manager = mock_manager();
+
+        crud_manager.vault_set(repo.path(), "api_token", "s3cret".to_string(), None, "vault-pass").unwrap();
+        crud_manager.vault_set(repo.path(), "db_url", "postgres://".to_string(), None, "vault-pass").unwrap();
+        assert!(repo.path().join(".padlock/vault.age").is_file());
+
+        assert_eq!(crud_manager.vault_get(repo.path(), "api_token", "vault-pass").unwrap().value.expose_secret(), "s3cret");
+        assert_eq!(crud_manager.vault_list(repo.path(), None, "vault-pass").unwrap().len(), 2);
+
+        crud_manager.vault_remove(repo.path(), "api_token", "vault-pass").unwrap();
+        assert!(crud_manager.vault_get(repo.path(), "api_token", "vault-pass").is_err());
+        assert!(crud_manager.vault_remove(repo.path(), "api_token", "vault-pass").is_err());
+        assert!(crud_manager.vault_remove(repo.path(), "db_url", "").is_err());
+
+        // With a repository recipient set the vault is encrypted to it on the next write
+        let (identity, _) = header::testing::identity([4u8; 32]);
+        let recipient = KnownRecipient::from_identity("ops", &identity).unwrap().recipient;
+        let identity_file = repo.path().join("ops.key");
+        std::fs::write(&identity_file, &identity).unwrap();
+        std::fs::write(repo.path().join(PathRules::FILE), format!("[recipients]\nrepository = [\"{}\"]\n", recipient)).unwrap();
+        let mut crud_manager = mock_manager().with_identity_files(vec![identity_file]);
+        crud_manager.vault_set(repo.path(), "api_token", "rotated".to_string(), None, "vault-pass").unwrap();
+        assert_eq!(AgeHeader::read(&repo.path().join(".padlock/vault.age")).unwrap().summary(), "X25519");
+        assert_eq!(crud_manager.vault_get(repo.path(), "api_token", "vault-pass").unwrap().value.expose_secret(), "rotated");
+        assert!(mock_manager().vault_get(repo.path(), "api_token", "vault-pass").is_err());
+    }
+
+    #[test]
+    fn test_rearmor_rename_moves_manifest_entry() {
+        let repo = TempDir::new().unwrap();
+        let secret = repo.path().join("notes");
+        std::fs::write(&secret, "notes").unwrap();
+        let mut crud_manager = mock_manager();
+        crud_manager.lock(&secret, "lock-pass", LockOptions { format: OutputFormat::AsciiArmor, ..LockOptions::default() }).unwrap();
+
+        // An armored file under an armor-only name is renamed to .age when made binary
+        std::fs::rename(repo.path().join("notes.age"), repo.path().join("notes.asc")).unwrap();
+        let mut manifest = RepositoryManifest::load(repo.path()).unwrap();
+        let metadata = manifest.files.remove("notes.age").unwrap();
+        manifest.files.insert("notes.asc".to_string(), metadata.clone());
+        manifest.save(repo.path()).unwrap();
+
+        crud_manager.rearmor(&repo.path().join("notes.asc"), OutputFormat::Binary).unwrap();
+        let manifest = RepositoryManifest::load(repo.path()).unwrap();
+        assert!(!manifest.files.contains_key("notes.asc"));
+        assert_eq!(manifest.files.get("notes.age"), Some(&metadata));
+    }
+
+    #[test]
+    fn test_structured_lock_and_unlock() {
+        let repo = TempDir::new().unwrap();
+        std::fs::write(repo.path().join("app.yaml"), "db:\n  password: hunter2\n  port: 5432\n").unwrap();
+        std::fs::write(repo.path().join("notes.txt"), "not structured").unwrap();
+
+        let config = AgeConfig::testing().with_structured_plaintext_keys(vec!["port$".to_string()]);
+        let mut crud_manager = CrudManager::new(Box::new(MockAdapter), config).unwrap();
+        let options = LockOptions { recursive: true, format: OutputFormat::Structured, ..LockOptions::default() };
+        let result = crud_manager.lock(repo.path(), "structured-pass", options).unwrap();
+        assert_eq!(result.processed_files.len(), 1);
+
+        let locked = std::fs::read_to_string(repo.path().join("app.enc.yaml")).unwrap();
+        assert!(locked.contains("password: ENC["));
+        assert!(locked.contains("port: 5432"));
+        assert!(repo.path().join(".padlock/data.key.age").is_file());
+        assert!(!repo.path().join("notes.enc.txt").exists());
+
+        std::fs::remove_file(repo.path().join("app.yaml")).unwrap();
+        let status = crud_manager.status(repo.path()).unwrap();
+        assert_eq!(status.encrypted_files, 1);
+
+        crud_manager.unlock(repo.path(), "structured-pass", UnlockOptions::default()).unwrap();
+        let unlocked = std::fs::read_to_string(repo.path().join("app.yaml")).unwrap();
+        assert!(unlocked.contains("password: hunter2"));
+    }
+
+    #[test]
+    fn test_structured_data_key_follows_the_repository() {
+        let repo = TempDir::new().unwrap();
+        std::fs::create_dir_all(repo.path().join("config/prod")).unwrap();
+        let nested = repo.path().join("config/prod/app.json");
+        std::fs::write(&nested, "{\"password\": \"hunter2\"}").unwrap();
+        std::fs::create_dir_all(repo.path().join(".padlock")).unwrap();
+
+        // A single nested file uses the repository's data key, not one beside the file
+        let options = LockOptions { format: OutputFormat::Structured, ..LockOptions::default() };
+        mock_manager().lock(&nested, "structured-pass", options.clone()).unwrap();
+        let store = repo.path().join(DataKeyStore::FILE);
+        assert!(store.is_file());
+        assert!(!repo.path().join("config/prod/.padlock").exists());
+        assert_eq!(AgeHeader::read(&store).unwrap().summary(), "scrypt");
+
+        // With a repository recipient set the data key is moved to it
+        let (identity, _) = header::testing::identity([6u8; 32]);
+        let recipient = KnownRecipient::from_identity("ops", &identity).unwrap().recipient;
+        let identity_file = repo.path().join("ops.key");
+        std::fs::write(&identity_file, &identity).unwrap();
+        std::fs::write(repo.path().join(PathRules::FILE), format!("[recipients]\nrepository = [\"{}\"]\n", recipient)).unwrap();
+        let other = repo.path().join("config/other.json");
+        std::fs::write(&other, "{\"token\": \"abc\"}").unwrap();
+        let mut crud_manager = mock_manager().with_identity_files(vec![identity_file]);
+        crud_manager.lock(&other, "structured-pass", options).unwrap();
+        assert_eq!(AgeHeader::read(&store).unwrap().summary(), "X25519");
+
+        crud_manager.unlock(&repo.path().join("config/prod/app.enc.json"), "structured-pass", UnlockOptions::default()).unwrap();
+        assert!(std::fs::read_to_string(&nested).unwrap().contains("hunter2"));
+    }
+
+    #[test]
+    fn test_status_and_verify_report_headers() {
+        use super::super::super::header::testing;
+
+        let repo = TempDir::new().unwrap();
+        let (identity, public) = testing::identity([7u8; 32]);
+        let (_, other) = testing::identity([9u8; 32]);
+        let mut mine = testing::x25519_header(&[public], false);
+        mine.extend_from_slice(&[0u8; 48]);
+        std::fs::write(repo.path().join("mine.age"), &mine).unwrap();
+        std::fs::write(repo.path().join("theirs.age"), testing::x25519_header(&[other], true)).unwrap();
+        std::fs::write(repo.path().join("broken.age"), "not an age file").unwrap();
+
+        let mut crud_manager = mock_manager();
+        crud_manager.set_known_recipients(vec![KnownRecipient::from_identity("ops", &identity).unwrap()]);
+
+        let status = crud_manager.status(repo.path()).unwrap();
+        assert_eq!(status.file_headers.len(), 3);
+        let report = |name: &str| status.file_headers.iter().find(|r| r.path.ends_with(name)).unwrap();
+        assert_eq!(report("mine.age").openable_by, vec!["ops".to_string()]);
+        assert_eq!(report("mine.age").header.as_ref().unwrap().payload_size, Some(48));
+        assert!(report("theirs.age").openable_by.is_empty());
+        assert!(report("broken.age").error.is_some());
+
+        let verification = crud_manager.verify(repo.path()).unwrap();
+        assert_eq!(verification.verified_files.len(), 2);
+        assert_eq!(verification.failed_files.len(), 1);
+        assert!(verification.failed_files[0].ends_with("broken.age"));
+    }
+
+    #[test]
+    fn test_operations_enforce_authorization_policy() {
+        use crate::authority::authorization::PolicyRule;
+
+        let repo = TempDir::new().unwrap();
+        std::fs::write(repo.path().join("notes.txt"), "notes").unwrap();
+
+        // Passphrase holders may lock but not run emergency procedures
+        let mut crud_manager = mock_manager();
+        assert!(crud_manager.emergency_unlock(repo.path(), "pass").is_err());
+        assert!(crud_manager.reset(repo.path(), "CONFIRM_RESET").is_err());
+        let options = LockOptions { recursive: true, ..LockOptions::default() };
+        crud_manager.lock(repo.path(), "pass", options.clone()).unwrap();
+
+        let skull = Actor::Key { key_type: KeyType::Skull, fingerprint: "0123456789abcdef".to_string(), capabilities: None, lineage: Vec::new() };
+        let mut crud_manager = mock_manager().with_actor(skull);
+        assert!(crud_manager.emergency_unlock(repo.path(), "pass").is_ok());
+
+        let policy = OperationPolicy::builtin()
+            .with_rule(PolicyRule::new("lock", AuthorityLevel::MasterControl));
+        let mut crud_manager = mock_manager().with_authorization_policy(policy);
+        let error = crud_manager.lock(repo.path(), "pass", options).unwrap_err();
+        assert!(error.to_string().contains("lock requires master"));
+
+        // Batches go through the same policy and refuse operations they cannot dispatch
+        let error = crud_manager.batch_process(repo.path(), None, "lock", "pass").unwrap_err();
+        assert!(error.to_string().contains("lock requires master"));
+        let error = crud_manager.batch_process(repo.path(), None, "shred", "pass").unwrap_err();
+        assert!(error.to_string().contains("Unsupported batch operation"));
+    }
+
+    #[test]
+    fn test_scoped_distro_keys_limit_lock_and_unlock() {
+        use crate::authority::capability::{self, KeyCapabilities};
+
+        let write_files = |repo: &Path| {
+            std::fs::create_dir_all(repo.join(".padlock")).unwrap();
+            std::fs::write(repo.join("app.env"), "app").unwrap();
+            std::fs::write(repo.join("prod.env"), "prod").unwrap();
+        };
+        let capabilities = KeyCapabilities::parse_spec("paths=app.*").unwrap();
+        let options = LockOptions { recursive: true, ..LockOptions::default() };
+
+        // Lock encrypts only in-scope files to the scoped key's recipient
+        let stanzas = |path: PathBuf| String::from_utf8_lossy(&std::fs::read(path).unwrap()).matches("-> X25519").count();
+        let scoped = ScopedKey { fingerprint: "3f9a".to_string(), recipient: "age1agent".to_string(), capabilities: Some(capabilities.clone()), lineage: Vec::new() };
+        let repo = TempDir::new().unwrap();
+        write_files(repo.path());
+        std::fs::write(repo.path().join(PathRules::FILE), "[recipients]\nops = [\"age1ops\"]\n\n[[rule]]\npattern = \"*.env\"\nrecipients = \"ops\"\n").unwrap();
+        let mut crud_manager = mock_manager().with_scoped_keys(vec![scoped.clone()]);
+        crud_manager.lock(repo.path(), "pass", options.clone()).unwrap();
+        assert_eq!(stanzas(repo.path().join("app.age")), 2);
+        assert_eq!(stanzas(repo.path().join("prod.age")), 1);
+
+        // Without a rule's set, in-scope files go to the key and the repository set, not the passphrase
+        let repo = TempDir::new().unwrap();
+        write_files(repo.path());
+        std::fs::write(repo.path().join(PathRules::FILE), "[recipients]\nrepository = [\"age1repo\"]\n").unwrap();
+        let mut crud_manager = mock_manager().with_scoped_keys(vec![scoped]);
+        crud_manager.lock(repo.path(), "pass", options.clone()).unwrap();
+        assert_eq!(stanzas(repo.path().join("app.age")), 2);
+        assert_eq!(stanzas(repo.path().join("prod.age")), 0);
+
+        // A key limited to app.* only touches those files
+        let repo = TempDir::new().unwrap();
+        write_files(repo.path());
+        let agent = |capabilities: KeyCapabilities| Actor::Key { key_type: KeyType::Distro, fingerprint: "3f9a".to_string(), capabilities: Some(capabilities), lineage: Vec::new() };
+        let mut crud_manager = mock_manager().with_actor(agent(capabilities.clone()));
+        let locked = crud_manager.lock(repo.path(), "pass", options.clone()).unwrap();
+        assert_eq!(locked.processed_files.len(), 1);
+        assert!(repo.path().join("app.age").exists() && !repo.path().join("prod.age").exists());
+
+        crud_manager.lock(&repo.path().join("prod.env"), "pass", LockOptions::default()).unwrap_err();
+        mock_manager().lock(&repo.path().join("prod.env"), "pass", LockOptions::default()).unwrap();
+        let unlock = UnlockOptions { verify_before_unlock: false, ..UnlockOptions::default() };
+        let plan = crud_manager.plan_unlock(repo.path(), &unlock).unwrap();
+        assert_eq!(plan.files.len(), 1);
+        let unlocked = crud_manager.unlock(repo.path(), "pass", unlock.clone()).unwrap();
+        assert_eq!(unlocked.processed_files.len(), 1);
+
+        // Read-only keys may not lock at all
+        let read_only = KeyCapabilities { access: capability::Access::ReadOnly, ..capabilities };
+        let mut crud_manager = mock_manager().with_actor(agent(read_only));
+        let error = crud_manager.lock(repo.path(), "pass", options).unwrap_err();
+        assert!(error.to_string().contains("read-only"));
+        crud_manager.unlock(&repo.path().join("app.age"), "pass", unlock).unwrap();
+    }
+
+    #[test]
+    fn test_revoked_keys_cannot_lock_or_unlock() {
+        use crate::authority::chain::{AuthorityKey, KeyFormat, KeyMaterial};
+        use crate::authority::signing;
+
+        let key = |seed: u8, key_type: KeyType| {
+            let hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").unwrap();
+            let identity = bech32::encode_upper::<bech32::Bech32>(hrp, &[seed; 32]).unwrap();
+            let material = KeyMaterial::new(identity.as_bytes().to_vec(), Some(identity.into_bytes()), KeyFormat::Age);
+            AuthorityKey::new(material, key_type, None, None).unwrap()
+        };
+        let (ignition, distro) = (key(1, KeyType::Ignition), key(2, KeyType::Distro));
+        let anchors = TrustAnchors::new().with_root(KeyType::Ignition, &signing::key_recipient(&ignition).unwrap()).unwrap();
+
+        let repo = TempDir::new().unwrap();
+        std::fs::create_dir_all(repo.path().join(".padlock")).unwrap();
+        std::fs::write(repo.path().join("app.env"), "app").unwrap();
+        let options = LockOptions { recursive: true, ..LockOptions::default() };
+        let actor = Actor::for_key(&distro);
+        mock_manager().with_actor(actor.clone()).with_trust_anchors(anchors.clone())
+            .lock(repo.path(), "pass", options.clone()).unwrap();
+
+        let mut revocations = RevocationList::default();
+        revocations.revoke(&distro, &ignition, "left the team").unwrap();
+        revocations.save(repo.path()).unwrap();
+
+        let log = repo.path().join("audit.log");
+        let config = AgeConfig { audit_log_path: Some(log.to_string_lossy().to_string()), ..AgeConfig::testing() };
+        let mut crud_manager = CrudManager::new(Box::new(MockAdapter), config).unwrap()
+            .with_actor(actor).with_trust_anchors(anchors.clone());
+        let unlock = UnlockOptions { verify_before_unlock: false, ..UnlockOptions::default() };
+        assert!(crud_manager.unlock(repo.path(), "pass", unlock).unwrap_err().to_string().contains("revoked"));
+        assert!(crud_manager.lock(repo.path(), "pass", options.clone()).unwrap_err().to_string().contains("revoked"));
+        assert!(std::fs::read_to_string(&log).unwrap().contains("KEY_REVOKED"));
+
+        // A revoked scoped key is not added to newly locked files
+        let scoped = ScopedKey { fingerprint: distro.fingerprint().hex().to_string(), recipient: signing::key_recipient(&distro).unwrap(), capabilities: None, lineage: Vec::new() };
+        let mut crud_manager = mock_manager().with_scoped_keys(vec![scoped]).with_trust_anchors(anchors);
+        assert!(crud_manager.lock(repo.path(), "pass", options).unwrap_err().to_string().contains("revoked"));
+    }
+
+    #[test]
+    fn test_revocation_cascades_through_the_actor_lineage() {
+        use crate::authority::chain::AuthorityKey;
+        use crate::authority::signing;
+
+        // master (trusted root) -> repo -> ignition -> distro, all enveloped key files
+        let keys = TempDir::new().unwrap();
+        let issue = |seed: u8, key_type: KeyType, parent: Option<&AuthorityKey>| {
+            let hrp = bech32::Hrp::parse("AGE-SECRET-KEY-").unwrap();
+            let identity = bech32::encode_upper::<bech32::Bech32>(hrp, &[seed; 32]).unwrap();
+            let recipient = signing::recipient_for_secret(&signing::identity_secret(&identity).unwrap()).unwrap();
+            let mut envelope = KeyEnvelope::new(key_type, &recipient).unwrap();
+            if let Some(parent) = parent {
+                envelope = envelope.with_parent(parent).unwrap();
+            }
+            let path = keys.path().join(format!("{}.key", key_type));
+            std::fs::write(&path, envelope.render(&identity).as_bytes()).unwrap();
+            (AuthorityKey::from_file(&path, key_type).unwrap(), path)
+        };
+        let (master, _) = issue(1, KeyType::Master, None);
+        let (repo_key, _) = issue(2, KeyType::Repo, Some(&master));
+        let (ignition, _) = issue(3, KeyType::Ignition, Some(&repo_key));
+        let (_, distro_path) = issue(4, KeyType::Distro, Some(&ignition));
+        let anchors = TrustAnchors::new().with_root(KeyType::Master, &signing::key_recipient(&master).unwrap()).unwrap();
+        let actor = Actor::holding(&distro_path, &anchors).unwrap();
+
+        let repo = TempDir::new().unwrap();
+        std::fs::create_dir_all(repo.path().join(".padlock")).unwrap();
+        std::fs::write(repo.path().join("app.env"), "app").unwrap();
+        let options = LockOptions { recursive: true, ..LockOptions::default() };
+        let manager = || mock_manager().with_actor(actor.clone()).with_trust_anchors(anchors.clone());
+        manager().lock(repo.path(), "pass", options.clone()).unwrap();
+
+        // The repo key is neither a trusted root nor in a loaded chain, but it is in the
+        // distro key's verified lineage, so its revocation of the ignition key counts
+        let mut revocations = RevocationList::default();
+        revocations.revoke(&ignition, &repo_key, "laptop lost").unwrap();
+        revocations.save(repo.path()).unwrap();
+        let unlock = UnlockOptions { verify_before_unlock: false, ..UnlockOptions::default() };
+        let error = manager().unlock(repo.path(), "pass", unlock).unwrap_err().to_string();
+        assert!(error.contains("revoked with its ancestor"));
+
+        // An entry about the lineage that nothing vouches for refuses instead of passing
+        let mut forged = RevocationList::default();
+        forged.revoke(&ignition, &repo_key, "laptop lost").unwrap();
+        forged.entries[0].reason = "edited".to_string();
+        forged.save(repo.path()).unwrap();
+        let error = manager().lock(repo.path(), "pass", options).unwrap_err().to_string();
+        assert!(error.contains("cannot be verified"));
+    }
+
+    #[test]
+    fn test_lock_encrypts_the_validated_file() {
+        use std::sync::{Arc, Mutex};
+
+        /// Swaps the plaintext for a decoy just before reading it, like a racing writer
+        #[derive(Clone)]
+        struct SwappingAdapter {
+            target: PathBuf,
+            decoy: PathBuf,
+            read: Arc<Mutex<Vec<u8>>>,
+        }
+
+        impl AgeAdapter for SwappingAdapter {
+            fn encrypt(&self, input: &Path, output: &Path, passphrase: &str, format: OutputFormat) -> AgeResult<()> {
+                std::fs::rename(&self.decoy, &self.target).unwrap();
+                *self.read.lock().unwrap() = std::fs::read(input).unwrap();
+                MockAdapter.encrypt(input, output, passphrase, format)
+            }
+            fn decrypt(&self, input: &Path, output: &Path, passphrase: &str) -> AgeResult<()> {
+                MockAdapter.decrypt(input, output, passphrase)
+            }
+            fn health_check(&self) -> AgeResult<()> {
+                Ok(())
+            }
+            fn adapter_name(&self) -> &'static str {
+                "SwappingAdapter"
+            }
+            fn adapter_version(&self) -> String {
+                String::new()
+            }
+            fn clone_box(&self) -> Box<dyn AgeAdapter> {
+                Box::new(self.clone())
+            }
+        }
+
+        let repo = TempDir::new().unwrap();
+        let secret = repo.path().join("secret.txt");
+        let decoy = repo.path().join("decoy.txt");
+        std::fs::write(&secret, "validated").unwrap();
+        std::fs::write(&decoy, "swapped in").unwrap();
+
+        let adapter = SwappingAdapter { target: secret.clone(), decoy, read: Arc::default() };
+        let read = adapter.read.clone();
+        let mut crud_manager = CrudManager::new(Box::new(adapter), AgeConfig::testing()).unwrap();
+        let error = crud_manager.lock(&secret, "pass", LockOptions::default()).unwrap_err();
+        assert!(error.to_string().contains("replaced"));
+        assert_eq!(*read.lock().unwrap(), b"validated");
+        let outputs = std::fs::read_dir(repo.path()).unwrap()
+            .filter(|entry| entry.as_ref().unwrap().path().extension().is_some_and(|ext| ext == "age"))
+            .count();
+        assert_eq!(outputs, 0);
+    }
+}
@@ -0,0 +1,532 @@
+//! Secure Edit - Edit encrypted files without leaving plaintext on persistent storage
+//!
+//! `padlock edit` decrypts into a private 0700 workspace on RAM-backed storage
+//! (`/dev/shm`, then `$XDG_RUNTIME_DIR`), runs `$EDITOR`, and re-encrypts only when the
+//! content changed. The workspace is a [`SecureTempArea`], wiped on every exit path:
+//! normal return, errors and panics, SIGTERM/SIGHUP while the editor runs (changes are
+//! discarded), and crashes or SIGKILL (stale workspaces of dead processes are swept by
+//! the next run).
+//!
+//! X25519 stanzas do not name their recipient, so every file padlock encrypts to public
+//! keys has its recipient set recorded in `.padlock/recipients.json`, keyed by a digest
+//! of the header it was written with. Re-encryption compares the expected set against
+//! that record and refuses files it has no record for.
+//!
+//! Security Guardian: Edgar - No plaintext at rest while editing
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicI32, Ordering};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::super::adapter::AgeAdapter;
+use super::super::config::OutputFormat;
+use super::super::error::{AgeError, AgeResult};
+use super::super::header::{self, AgeHeader, HeaderEncoding, KnownRecipient, StanzaKind};
+use super::super::temp_area::{self, SecureTempArea};
+use super::clock::write_atomic;
+use crate::authority::authorization::repository_root;
+
+pub use super::super::temp_area::private_temp_root;
+
+/// Name prefix of edit workspaces; the owning process id follows it
+pub const WORKSPACE_PREFIX: &str = "padlock-edit-";
+
+/// Result of an edit session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditOutcome {
+    /// Editor exited without changing the content; the encrypted file is untouched
+    Unchanged,
+    /// Content changed and the encrypted file was replaced
+    ReEncrypted,
+}
+
+/// Editor command from `$VISUAL`, then `$EDITOR`, falling back to `vi`
+pub fn editor_command() -> String {
+    ["VISUAL", "EDITOR"].iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Private directory holding one decrypted file, wiped on drop
+pub struct EditWorkspace {
+    area: SecureTempArea,
+    file: PathBuf,
+}
+
+impl EditWorkspace {
+    /// Create a 0700 workspace under `root` for a file called `file_name`
+    pub fn create(root: &Path, file_name: OsString) -> AgeResult<Self> {
+        let area = SecureTempArea::create_with_prefix(root, WORKSPACE_PREFIX)?;
+        let file = area.path().join(file_name);
+        Ok(Self { area, file })
+    }
+
+    /// Path of the plaintext file inside the workspace
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// Workspace directory
+    pub fn dir(&self) -> &Path {
+        self.area.path()
+    }
+
+    /// Overwrite and remove everything in the workspace
+    pub fn wipe(&self) -> AgeResult<()> {
+        self.area.wipe()
+    }
+}
+
+/// Remove workspaces and temp areas left behind by processes that no longer exist
+pub fn sweep_stale_workspaces(root: &Path) -> Vec<PathBuf> {
+    temp_area::sweep_stale(root)
+}
+
+/// Encrypted output format of an existing age file
+pub fn detect_format(encrypted: &Path) -> AgeResult<OutputFormat> {
+    match header::sniff(encrypted)? {
+        Some(HeaderEncoding::Armored) => Ok(OutputFormat::AsciiArmor),
+        _ => Ok(OutputFormat::Binary),
+    }
+}
+
+/// Decrypt-edit-re-encrypt session for a single encrypted file
+#[derive(Debug, Clone)]
+pub struct SecureEditor {
+    root: PathBuf,
+    editor: String,
+    recipients: Vec<String>,
+}
+
+impl Default for SecureEditor {
+    fn default() -> Self {
+        Self {
+            root: private_temp_root(),
+            editor: editor_command(),
+            recipients: Vec::new(),
+        }
+    }
+}
+
+impl SecureEditor {
+    /// Editor using the private temp root and `$VISUAL`/`$EDITOR`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Override the workspace root directory
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = root;
+        self
+    }
+
+    /// Override the editor command (run through `sh -c`, so it may carry arguments)
+    pub fn with_editor(mut self, editor: impl Into<String>) -> Self {
+        self.editor = editor.into();
+        self
+    }
+
+    /// Recipients files encrypted to public keys are expected to have; such files are
+    /// re-encrypted to them only when they match the file's existing stanzas
+    pub fn with_recipients(mut self, recipients: Vec<String>) -> Self {
+        self.recipients = recipients;
+        self
+    }
+
+    /// Workspace root directory
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// True when workspaces live on storage that is not RAM-backed
+    pub fn is_persistent_storage(&self) -> bool {
+        let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+        !(self.root.starts_with("/dev/shm") || runtime_dir.is_some_and(|dir| self.root.starts_with(dir)))
+    }
+
+    /// Decrypt `file`, run the editor on the plaintext and re-encrypt if it changed
+    pub fn edit(&self, adapter: &dyn AgeAdapter, file: &Path, passphrase: &str) -> AgeResult<EditOutcome> {
+        sweep_stale_workspaces(&self.root);
+
+        // Refuse before decrypting when the result could not be re-encrypted faithfully
+        reencryption_recipients(file, &self.recipients)?;
+        let format = detect_format(file)?;
+        let plaintext_name = file.file_stem()
+            .map(|stem| stem.to_os_string())
+            .unwrap_or_else(|| OsString::from("plaintext"));
+
+        let workspace = EditWorkspace::create(&self.root, plaintext_name)?;
+        adapter.decrypt(file, workspace.file(), passphrase)?;
+        let before = digest_file(workspace.file())?;
+
+        let guard = SignalGuard::install();
+        let status = self.run_editor(workspace.file());
+        let interrupted = guard.interrupted();
+        drop(guard);
+
+        if let Some(signal) = interrupted {
+            workspace.wipe()?;
+            return Err(AgeError::InvalidOperation {
+                operation: "edit".to_string(),
+                reason: format!("Interrupted by signal {}; changes discarded", signal),
+            });
+        }
+        status?;
+
+        if digest_file(workspace.file())? == before {
+            return Ok(EditOutcome::Unchanged);
+        }
+
+        replace_encrypted(adapter, workspace.file(), file, passphrase, &self.recipients, format)?;
+        Ok(EditOutcome::ReEncrypted)
+    }
+
+    /// Run the editor on a file and wait for it to exit
+    fn run_editor(&self, file: &Path) -> AgeResult<()> {
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$1\"", self.editor))
+            .arg("padlock-edit")
+            .arg(file)
+            .status()
+            .map_err(|e| AgeError::InvalidOperation {
+                operation: "edit".to_string(),
+                reason: format!("Failed to launch editor '{}': {}", self.editor, e),
+            })?;
+
+        if !status.success() {
+            return Err(AgeError::InvalidOperation {
+                operation: "edit".to_string(),
+                reason: format!("Editor '{}' exited with {}; changes discarded", self.editor, status),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Encrypt `plaintext` next to `target` and atomically replace it, keeping its permissions
+/// and its recipient set (see [`reencryption_recipients`])
+pub(crate) fn replace_encrypted(adapter: &dyn AgeAdapter, plaintext: &Path, target: &Path, passphrase: &str, recipients: &[String], format: OutputFormat) -> AgeResult<()> {
+    let recipients = reencryption_recipients(target, recipients)?;
+    write_encrypted(adapter, plaintext, target, passphrase, recipients, format)
+}
+
+/// Encrypt `plaintext` to `recipients`, or the passphrase when `None`, next to `target`
+/// and atomically replace it, keeping its permissions
+pub(crate) fn write_encrypted(adapter: &dyn AgeAdapter, plaintext: &Path, target: &Path, passphrase: &str, recipients: Option<&[String]>, format: OutputFormat) -> AgeResult<()> {
+    let file_name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let staged = target.with_file_name(format!(".{}.padlock-edit", file_name));
+
+    let result = match recipients {
+        Some(recipients) => adapter.encrypt_to_recipients(plaintext, &staged, recipients, format),
+        None => adapter.encrypt(plaintext, &staged, passphrase, format),
+    }
+        .and_then(|_| {
+            if let Ok(metadata) = fs::metadata(target) {
+                fs::set_permissions(&staged, metadata.permissions())
+                    .map_err(|e| AgeError::file_error("set_permissions", staged.clone(), e))?;
+            }
+            let replaced = target.exists().then(|| AgeHeader::read(target)).transpose()?;
+            fs::rename(&staged, target)
+                .map_err(|e| AgeError::file_error("rename", target.to_path_buf(), e))?;
+            record_recipients(target, replaced.as_ref(), recipients)
+        });
+
+    if result.is_err() {
+        let _ = fs::remove_file(&staged);
+    }
+    result
+}
+
+/// Recipient sets of files encrypted to public keys, stored in `.padlock/recipients.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RecipientRecords {
+    /// Recipients keyed by the digest of the header they were encrypted with
+    headers: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl RecipientRecords {
+    const FILE: &'static str = ".padlock/recipients.json";
+
+    fn load(root: &Path) -> AgeResult<Self> {
+        let path = root.join(Self::FILE);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let content = fs::read(&path).map_err(|e| AgeError::file_error("read", path.clone(), e))?;
+        serde_json::from_slice(&content).map_err(|e| AgeError::InvalidOperation {
+            operation: "recipients_load".to_string(),
+            reason: format!("Corrupt recipient record {}: {}", path.display(), e),
+        })
+    }
+
+    fn save(&self, root: &Path) -> AgeResult<()> {
+        let content = serde_json::to_vec_pretty(self).map_err(|e| AgeError::InvalidOperation {
+            operation: "recipients_save".to_string(),
+            reason: e.to_string(),
+        })?;
+        write_atomic(&root.join(Self::FILE), &content)
+    }
+}
+
+/// Digest identifying one encryption: every stanza and the header MAC
+fn header_digest(header: &AgeHeader) -> String {
+    let mut hasher = Sha256::new();
+    for stanza in &header.stanzas {
+        hasher.update(format!("{:?}\n{}\n", stanza.kind, stanza.args.join(" ")));
+        hasher.update(&stanza.body);
+    }
+    hasher.update(header.mac.as_deref().unwrap_or_default());
+    hex::encode(hasher.finalize())
+}
+
+/// Record the recipients `target` was just encrypted to, dropping the record of the
+/// header it replaced; passphrase files (`None`) are not recorded
+pub(crate) fn record_recipients(target: &Path, replaced: Option<&AgeHeader>, recipients: Option<&[String]>) -> AgeResult<()> {
+    let root = repository_root(target);
+    let mut records = RecipientRecords::load(&root)?;
+    let mut changed = replaced.is_some_and(|header| records.headers.remove(&header_digest(header)).is_some());
+    if let Some(recipients) = recipients {
+        let header = AgeHeader::read(target)?;
+        records.headers.insert(header_digest(&header), recipients.iter().cloned().collect());
+        changed = true;
+    }
+    if changed {
+        records.save(&root)?;
+    }
+    Ok(())
+}
+
+/// Recipients to re-encrypt `target` to, or `None` for the passphrase.
+///
+/// A passphrase (scrypt) file stays a passphrase file. A file encrypted to public keys is
+/// re-encrypted to `recipients` only when they are exactly the set it was locked to: a
+/// file with X25519 stanzas needs a recorded set (see [`record_recipients`]) equal to
+/// `recipients`, and a file of SSH stanzas needs a matching key for each of them.
+/// Anything else, plugin stanzas included, is refused rather than silently re-keyed. A
+/// missing target is encrypted to `recipients` when given, else to the passphrase.
+pub(crate) fn reencryption_recipients<'a>(target: &Path, recipients: &'a [String]) -> AgeResult<Option<&'a [String]>> {
+    if !target.exists() {
+        return Ok((!recipients.is_empty()).then_some(recipients));
+    }
+    let existing = AgeHeader::read(target)?;
+    if existing.stanzas.iter().all(|stanza| stanza.kind == StanzaKind::Scrypt) {
+        return Ok(None);
+    }
+
+    let refuse = |reason: String| Err(AgeError::SecurityValidationFailed {
+        validation_type: "recipient_set".to_string(),
+        details: format!("{} is encrypted to {}; {}", target.display(), existing.summary(), reason),
+    });
+    if recipients.is_empty() {
+        return refuse("no recipient set is known for it, so it cannot be re-encrypted".to_string());
+    }
+    if recipients.len() != existing.stanzas.len() {
+        return refuse(format!("the expected set has {} recipients", recipients.len()));
+    }
+    if existing.stanzas.iter().any(|stanza| stanza.kind == StanzaKind::X25519) {
+        let expected: BTreeSet<String> = recipients.iter().cloned().collect();
+        return match RecipientRecords::load(&repository_root(target))?.headers.get(&header_digest(&existing)) {
+            Some(recorded) if *recorded == expected => Ok(Some(recipients)),
+            Some(_) => refuse("it was locked to a different recipient set".to_string()),
+            None => refuse("its X25519 recipients were not recorded when it was encrypted, so they cannot be compared".to_string()),
+        };
+    }
+    let known = recipients.iter()
+        .map(|recipient| KnownRecipient::from_recipient(recipient.clone(), recipient))
+        .collect::<AgeResult<Vec<_>>>()?;
+    let ssh_matched = existing.stanzas.iter()
+        .all(|stanza| matches!(stanza.kind, StanzaKind::SshEd25519 | StanzaKind::SshRsa)
+            && known.iter().any(|known| known.matches(stanza)));
+    if !ssh_matched {
+        return refuse("its stanzas do not match the expected recipient set".to_string());
+    }
+    Ok(Some(recipients))
+}
+
+/// SHA256 of a file's content
+fn digest_file(path: &Path) -> AgeResult<Vec<u8>> {
+    let data = fs::read(path).map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?;
+    Ok(Sha256::digest(&data).to_vec())
+}
+
+/// Last terminating signal received while the editor was running
+static RECEIVED_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+extern "C" fn record_signal(signal: libc::c_int) {
+    if signal == libc::SIGTERM || signal == libc::SIGHUP {
+        RECEIVED_SIGNAL.store(signal, Ordering::SeqCst);
+    }
+}
+
+/// Catches signals while the editor runs so the workspace can be wiped.
+///
+/// SIGINT and SIGQUIT are caught and ignored because the terminal delivers them to the
+/// editor too; handlers (unlike SIG_IGN) are reset on exec, so the editor still sees them.
+struct SignalGuard {
+    previous: Vec<(libc::c_int, libc::sighandler_t)>,
+}
+
+impl SignalGuard {
+    const SIGNALS: [libc::c_int; 4] = [libc::SIGINT, libc::SIGQUIT, libc::SIGTERM, libc::SIGHUP];
+
+    fn install() -> Self {
+        RECEIVED_SIGNAL.store(0, Ordering::SeqCst);
+        let handler = record_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        let previous = Self::SIGNALS.iter()
+            // SAFETY: record_signal only touches an atomic, which is async-signal-safe
+            .map(|&signal| (signal, unsafe { libc::signal(signal, handler) }))
+            .collect();
+        Self { previous }
+    }
+
+    fn interrupted(&self) -> Option<libc::c_int> {
+        match RECEIVED_SIGNAL.load(Ordering::SeqCst) {
+            0 => None,
+            signal => Some(signal),
+        }
+    }
+}
+
+impl Drop for SignalGuard {
+    fn drop(&mut self) {
+        for &(signal, handler) in &self.previous {
+            // SAFETY: restores the disposition returned by the matching libc::signal call
+            unsafe { libc::signal(signal, handler) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::adapter::testing::MockAdapter;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn encrypted_fixture(dir: &Path, content: &str) -> PathBuf {
+        let plain = dir.join("secrets.env");
+        let encrypted = dir.join("secrets.env.age");
+        fs::write(&plain, content).unwrap();
+        MockAdapter.encrypt(&plain, &encrypted, "edit-pass", OutputFormat::Binary).unwrap();
+        fs::remove_file(&plain).unwrap();
+        encrypted
+    }
+
+    #[test]
+    fn test_edit_reencrypts_changed_content() {
+        let repo = TempDir::new().unwrap();
+        let scratch = TempDir::new().unwrap();
+        let encrypted = encrypted_fixture(repo.path(), "A=1\n");
+
+        let editor = SecureEditor::new()
+            .with_root(scratch.path().to_path_buf())
+            .with_editor("echo B=2 >>");
+        let outcome = editor.edit(&MockAdapter, &encrypted, "edit-pass").unwrap();
+        assert_eq!(outcome, EditOutcome::ReEncrypted);
+
+        let decrypted = repo.path().join("check.env");
+        MockAdapter.decrypt(&encrypted, &decrypted, "edit-pass").unwrap();
+        assert_eq!(fs::read_to_string(&decrypted).unwrap(), "A=1\nB=2\n");
+
+        // Workspace wiped, no staged file left beside the original
+        assert_eq!(fs::read_dir(temp_area::user_root(scratch.path()).unwrap()).unwrap().count(), 0);
+        assert!(!repo.path().join(".secrets.env.age.padlock-edit").exists());
+    }
+
+    #[test]
+    fn test_edit_unchanged_leaves_file_alone() {
+        let repo = TempDir::new().unwrap();
+        let scratch = TempDir::new().unwrap();
+        let encrypted = encrypted_fixture(repo.path(), "A=1\n");
+        let original = fs::read(&encrypted).unwrap();
+
+        let editor = SecureEditor::new()
+            .with_root(scratch.path().to_path_buf())
+            .with_editor("true");
+        assert_eq!(editor.edit(&MockAdapter, &encrypted, "edit-pass").unwrap(), EditOutcome::Unchanged);
+        assert_eq!(fs::read(&encrypted).unwrap(), original);
+        assert_eq!(fs::read_dir(temp_area::user_root(scratch.path()).unwrap()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_editor_failure_discards_changes() {
+        let repo = TempDir::new().unwrap();
+        let scratch = TempDir::new().unwrap();
+        let encrypted = encrypted_fixture(repo.path(), "A=1\n");
+        let original = fs::read(&encrypted).unwrap();
+
+        let editor = SecureEditor::new()
+            .with_root(scratch.path().to_path_buf())
+            .with_editor("sh -c 'echo X=9 >> \"$0\"; exit 3'");
+        assert!(editor.edit(&MockAdapter, &encrypted, "edit-pass").is_err());
+        assert_eq!(fs::read(&encrypted).unwrap(), original);
+        assert_eq!(fs::read_dir(temp_area::user_root(scratch.path()).unwrap()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_reencryption_keeps_the_recipient_set() {
+        let repo = TempDir::new().unwrap();
+        let recipient = |seed: u8| {
+            let hrp = bech32::Hrp::parse("age").unwrap();
+            bech32::encode::<bech32::Bech32>(hrp, &[seed; 32]).unwrap()
+        };
+        let team = vec![recipient(1), recipient(2)];
+        let plain = repo.path().join("team.env");
+        let encrypted = repo.path().join("team.env.age");
+        fs::write(&plain, "A=1\n").unwrap();
+        MockAdapter.encrypt_to_recipients(&plain, &encrypted, &team, OutputFormat::Binary).unwrap();
+        let original = fs::read(&encrypted).unwrap();
+
+        // X25519 stanzas hide their recipients, so a file with no recorded set is refused
+        let error = replace_encrypted(&MockAdapter, &plain, &encrypted, "pass", &team, OutputFormat::Binary).unwrap_err();
+        assert!(error.to_string().contains("not recorded"));
+        record_recipients(&encrypted, None, Some(&team)).unwrap();
+
+        // Without the set, or with a different one of the same size, the file is not re-keyed
+        let error = replace_encrypted(&MockAdapter, &plain, &encrypted, "pass", &[], OutputFormat::Binary).unwrap_err();
+        assert!(error.to_string().contains("X25519 x2"));
+        assert!(replace_encrypted(&MockAdapter, &plain, &encrypted, "pass", &team[..1], OutputFormat::Binary).is_err());
+        let swapped = vec![recipient(1), recipient(3)];
+        let error = replace_encrypted(&MockAdapter, &plain, &encrypted, "pass", &swapped, OutputFormat::Binary).unwrap_err();
+        assert!(error.to_string().contains("different recipient set"));
+        assert_eq!(fs::read(&encrypted).unwrap(), original);
+
+        fs::write(&plain, "A=2\n").unwrap();
+        replace_encrypted(&MockAdapter, &plain, &encrypted, "pass", &team, OutputFormat::Binary).unwrap();
+        let header = AgeHeader::read(&encrypted).unwrap();
+        assert_eq!(header.stanzas.len(), 2);
+        assert!(header.stanzas.iter().all(|stanza| stanza.kind == StanzaKind::X25519));
+        // The re-encrypted file carries its record forward
+        replace_encrypted(&MockAdapter, &plain, &encrypted, "pass", &team, OutputFormat::Binary).unwrap();
+
+        // Passphrase files stay passphrase files
+        let locked = encrypted_fixture(repo.path(), "B=1\n");
+        replace_encrypted(&MockAdapter, &plain, &locked, "edit-pass", &team, OutputFormat::Binary).unwrap();
+        assert_eq!(AgeHeader::read(&locked).unwrap().stanzas[0].kind, StanzaKind::Scrypt);
+    }
+
+    #[test]
+    fn test_workspace_permissions_and_sweep() {
+        let scratch = TempDir::new().unwrap();
+        let workspace = EditWorkspace::create(scratch.path(), OsString::from("plain")).unwrap();
+        let mode = fs::metadata(workspace.dir()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        // A live workspace survives the sweep; one owned by a dead process does not
+        let stale = temp_area::user_root(scratch.path()).unwrap().join(format!("{}{}-0", WORKSPACE_PREFIX, i32::MAX));
+        fs::create_dir(&stale).unwrap();
+        fs::write(stale.join("plain"), "secret").unwrap();
+
+        let swept = sweep_stale_workspaces(scratch.path());
+        assert_eq!(swept, vec![stale.clone()]);
+        assert!(workspace.dir().exists());
+        assert!(!stale.exists());
+    }
+}
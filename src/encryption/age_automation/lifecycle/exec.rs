@@ -0,0 +1,279 @@
+//! Secret Exec - Run commands with decrypted secrets injected as environment variables
+//!
+//! `padlock exec --env secrets.env.age -- ./deploy.sh` decrypts dotenv-style files in
+//! memory and hands the variables to the child process. Plaintext never touches the
+//! repository or persistent temp storage, which makes this the way CI jobs and
+//! distributed (D-key) agents consume secrets.
+//!
+//! Precedence: files are applied in the order given, so later files override earlier
+//! ones. Variables already present in the parent environment are never replaced unless
+//! overwriting is explicitly enabled.
+//!
+//! Security Guardian: Edgar - Secrets delivered to processes, never to disk
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::super::adapter::AgeAdapter;
+use super::super::error::{AgeError, AgeResult};
+use super::super::secret::SecretString;
+
+/// Options controlling how decrypted variables are injected
+#[derive(Debug, Clone, Default)]
+pub struct ExecOptions {
+    /// Encrypted dotenv files, lowest precedence first
+    pub env_files: Vec<PathBuf>,
+    /// Prefix prepended to every injected variable name
+    pub prefix: Option<String>,
+    /// Replace variables already set in the parent environment
+    pub overwrite: bool,
+}
+
+/// Decrypted variables ready to be passed to a child process
+///
+/// Values are held as `SecretString`s, so they are wiped on drop and `Debug` shows only
+/// the variable names.
+#[derive(Debug, Clone, Default)]
+pub struct SecretEnvironment {
+    vars: BTreeMap<String, SecretString>,
+    origins: BTreeMap<String, PathBuf>,
+}
+
+impl SecretEnvironment {
+    /// Decrypt and merge the configured files.
+    ///
+    /// `is_set` reports whether a variable already exists in the target environment.
+    pub fn load(
+        adapter: &dyn AgeAdapter,
+        options: &ExecOptions,
+        passphrase: &str,
+        is_set: impl Fn(&str) -> bool,
+    ) -> AgeResult<Self> {
+        if options.env_files.is_empty() {
+            return Err(AgeError::InvalidOperation {
+                operation: "exec".to_string(),
+                reason: "At least one --env file is required".to_string(),
+            });
+        }
+
+        let mut environment = Self::default();
+        for file in &options.env_files {
+            let plaintext = adapter.decrypt_to_bytes(file, passphrase)?;
+            let content = std::str::from_utf8(plaintext.expose_secret()).map_err(|_| AgeError::InvalidOperation {
+                operation: "exec".to_string(),
+                reason: format!("{} is not valid UTF-8 dotenv content", file.display()),
+            })?;
+
+            for (key, value) in parse_dotenv(content)
+                .map_err(|reason| AgeError::InvalidOperation {
+                    operation: "exec".to_string(),
+                    reason: format!("{}: {}", file.display(), reason),
+                })?
+            {
+                let name = format!("{}{}", options.prefix.as_deref().unwrap_or(""), key);
+                environment.vars.insert(name.clone(), SecretString::new(value));
+                environment.origins.insert(name, file.clone());
+            }
+        }
+
+        if !options.overwrite {
+            let conflicts: Vec<&str> = environment.vars.keys()
+                .map(String::as_str)
+                .filter(|name| is_set(name))
+                .collect();
+            if !conflicts.is_empty() {
+                return Err(AgeError::InvalidOperation {
+                    operation: "exec".to_string(),
+                    reason: format!("Refusing to overwrite existing variables: {} (use --overwrite)", conflicts.join(", ")),
+                });
+            }
+        }
+
+        Ok(environment)
+    }
+
+    /// Variable names in sorted order
+    pub fn names(&self) -> Vec<&str> {
+        self.vars.keys().map(String::as_str).collect()
+    }
+
+    /// Value of a variable
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(SecretString::expose_secret)
+    }
+
+    /// File a variable was taken from
+    pub fn origin(&self, name: &str) -> Option<&Path> {
+        self.origins.get(name).map(PathBuf::as_path)
+    }
+
+    /// Number of variables
+    pub fn len(&self) -> usize {
+        self.vars.len()
+    }
+
+    /// True when no variables were loaded
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_empty()
+    }
+
+    /// Build a command for `program` with the variables applied on top of the inherited environment
+    pub fn command(&self, program: &str, args: &[String]) -> Command {
+        let mut command = Command::new(program);
+        command.args(args).envs(self.vars.iter().map(|(name, value)| (name, value.expose_secret())));
+        command
+    }
+}
+
+/// Parse dotenv content into ordered key/value pairs.
+///
+/// Supports `KEY=value`, an optional `export ` prefix, `#` comments, single-quoted
+/// literals and double-quoted values with `\n`, `\t`, `\"` and `\\` escapes.
+pub fn parse_dotenv(content: &str) -> Result<Vec<(String, String)>, String> {
+    let mut pairs = Vec::new();
+
+    for (index, raw_line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").map(str::trim_start).unwrap_or(line);
+        let (key, value) = line.split_once('=')
+            .ok_or_else(|| format!("line {}: expected KEY=VALUE", line_number))?;
+        let key = key.trim();
+
+        if !is_valid_name(key) {
+            return Err(format!("line {}: invalid variable name '{}'", line_number, key));
+        }
+
+        let value = parse_value(value.trim())
+            .map_err(|reason| format!("line {}: {}", line_number, reason))?;
+        pairs.push((key.to_string(), value));
+    }
+
+    Ok(pairs)
+}
+
+/// True for POSIX-style environment variable names
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parse the right-hand side of a dotenv assignment
+fn parse_value(value: &str) -> Result<String, String> {
+    if let Some(rest) = value.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or("unterminated single quote")?;
+        return Ok(rest[..end].to_string());
+    }
+
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut parsed = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok(parsed),
+                '\\' => match chars.next() {
+                    Some('n') => parsed.push('\n'),
+                    Some('t') => parsed.push('\t'),
+                    Some('r') => parsed.push('\r'),
+                    Some(other) => parsed.push(other),
+                    None => break,
+                },
+                other => parsed.push(other),
+            }
+        }
+        return Err("unterminated double quote".to_string());
+    }
+
+    // Unquoted: inline comments start at " #"
+    let value = match value.find(" #") {
+        Some(position) => &value[..position],
+        None => value,
+    };
+    Ok(value.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::adapter::testing::MockAdapter;
+    use super::super::super::config::OutputFormat;
+    use tempfile::TempDir;
+
+    fn encrypted_env(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let plain = dir.join(name);
+        let encrypted = dir.join(format!("{}.age", name));
+        std::fs::write(&plain, content).unwrap();
+        MockAdapter.encrypt(&plain, &encrypted, "exec-pass", OutputFormat::Binary).unwrap();
+        std::fs::remove_file(&plain).unwrap();
+        encrypted
+    }
+
+    #[test]
+    fn test_parse_dotenv() {
+        let content = "# comment\nexport A=1\nB = 'raw $value'\nC=\"line\\nnext\"\nD=plain # trailing\n\n";
+        let pairs = parse_dotenv(content).unwrap();
+        assert_eq!(pairs, vec![
+            ("A".to_string(), "1".to_string()),
+            ("B".to_string(), "raw $value".to_string()),
+            ("C".to_string(), "line\nnext".to_string()),
+            ("D".to_string(), "plain".to_string()),
+        ]);
+
+        assert!(parse_dotenv("1BAD=x").is_err());
+        assert!(parse_dotenv("NOEQUALS").is_err());
+        assert!(parse_dotenv("Q=\"open").is_err());
+    }
+
+    #[test]
+    fn test_precedence_and_prefix() {
+        let dir = TempDir::new().unwrap();
+        let base = encrypted_env(dir.path(), "base.env", "TOKEN=base\nREGION=eu\n");
+        let local = encrypted_env(dir.path(), "local.env", "TOKEN=local\n");
+
+        let options = ExecOptions {
+            env_files: vec![base.clone(), local.clone()],
+            prefix: Some("APP_".to_string()),
+            overwrite: false,
+        };
+        let env = SecretEnvironment::load(&MockAdapter, &options, "exec-pass", |_| false).unwrap();
+
+        assert_eq!(env.names(), vec!["APP_REGION", "APP_TOKEN"]);
+        assert_eq!(env.get("APP_TOKEN"), Some("local"));
+        assert_eq!(env.origin("APP_TOKEN"), Some(local.as_path()));
+        assert_eq!(env.origin("APP_REGION"), Some(base.as_path()));
+    }
+
+    #[test]
+    fn test_existing_variables_protected() {
+        let dir = TempDir::new().unwrap();
+        let file = encrypted_env(dir.path(), "secrets.env", "HOME=/tmp/evil\n");
+
+        let mut options = ExecOptions { env_files: vec![file], ..ExecOptions::default() };
+        let err = SecretEnvironment::load(&MockAdapter, &options, "exec-pass", |name| name == "HOME").unwrap_err();
+        assert!(err.to_string().contains("HOME"));
+
+        options.overwrite = true;
+        let env = SecretEnvironment::load(&MockAdapter, &options, "exec-pass", |name| name == "HOME").unwrap();
+        assert_eq!(env.get("HOME"), Some("/tmp/evil"));
+    }
+
+    #[test]
+    fn test_command_receives_variables() {
+        let dir = TempDir::new().unwrap();
+        let file = encrypted_env(dir.path(), "secrets.env", "PADLOCK_EXEC_TEST=visible\n");
+        let options = ExecOptions { env_files: vec![file], ..ExecOptions::default() };
+        let env = SecretEnvironment::load(&MockAdapter, &options, "exec-pass", |_| false).unwrap();
+
+        let output = env.command("sh", &["-c".to_string(), "printf %s \"$PADLOCK_EXEC_TEST\"".to_string()])
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "visible");
+        assert!(std::fs::read_dir(dir.path()).unwrap().all(|e| e.unwrap().path().extension().is_some_and(|x| x == "age")));
+    }
+}
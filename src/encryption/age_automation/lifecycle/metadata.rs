@@ -0,0 +1,333 @@
+//! File Metadata - Preserve permissions, ownership and timestamps across lock/unlock
+//!
+//! Lock captures each file's mode, owner, group, modification time and optionally its
+//! extended attributes into the repository manifest, keyed by the locked file's path
+//! relative to the repository root. Unlock restores them on the decrypted file, so a `0600` key
+//! file comes back `0600` and make-style tooling sees the original mtime.
+//!
+//! Files are sensitive when they had no group or other access at lock time, or when a
+//! path rule marks them so. Their plaintext is created owner-only before any byte is
+//! written, and unlock refuses to produce a group- or world-readable copy of them.
+//!
+//! The manifest is an ordinary repository file, so restore treats it as untrusted: only
+//! the permission bits are applied and setuid, setgid or sticky modes are refused, the
+//! owner is only changed when running as that owner, and `security.*`, `trusted.*` and
+//! `system.*` attributes are never written back. Unlock also re-derives `sensitive` from
+//! the path rules, so editing the manifest cannot clear it.
+//!
+//! Extended attribute values are stored base64-encoded in the manifest, which is not
+//! encrypted; only enable them for attributes that are safe to commit.
+//!
+//! Security Guardian: Edgar - Plaintext comes back exactly as locked, never looser
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+use std::time::SystemTime;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::super::error::{AgeError, AgeResult};
+
+/// Permission bits granting any access to group or other
+const GROUP_OTHER_BITS: u32 = 0o077;
+
+/// setuid, setgid and sticky bits
+const SPECIAL_BITS: u32 = 0o7000;
+
+/// Attribute namespaces carrying security labels, privileged data or ACLs
+const PROTECTED_XATTR_PREFIXES: &[&str] = &["security.", "trusted.", "system."];
+
+/// Metadata recorded for one locked file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileMetadata {
+    /// Permission bits as captured, including setuid/setgid/sticky
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub modified: DateTime<Utc>,
+    /// Extended attributes, base64-encoded values
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub xattrs: BTreeMap<String, String>,
+    #[serde(default)]
+    pub sensitive: bool,
+}
+
+impl FileMetadata {
+    /// Capture a file's metadata; `sensitive` marks it regardless of its mode
+    pub fn capture(path: &Path, with_xattrs: bool, sensitive: bool) -> AgeResult<Self> {
+        let metadata = fs::metadata(path).map_err(|e| AgeError::file_error("stat", path.to_path_buf(), e))?;
+        let modified = metadata.modified().map_err(|e| AgeError::file_error("stat", path.to_path_buf(), e))?;
+        let mode = metadata.mode() & 0o7777;
+
+        let xattrs = if with_xattrs {
+            xattr::list(path)
+                .map_err(|e| AgeError::file_error("read_xattrs", path.to_path_buf(), e))?
+                .into_iter()
+                .filter(|(name, _)| !is_protected_xattr(name))
+                .map(|(name, value)| (name, STANDARD.encode(value)))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            mode,
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            modified: DateTime::<Utc>::from(modified),
+            xattrs,
+            sensitive: sensitive || mode & GROUP_OTHER_BITS == 0,
+        })
+    }
+
+    /// True when the recorded mode grants group or other any access
+    pub fn exposes_to_others(&self) -> bool {
+        self.mode & GROUP_OTHER_BITS != 0
+    }
+
+    /// Prepare the plaintext output before decryption writes to it
+    ///
+    /// Sensitive files are created (or reset) owner-only so plaintext never exists with
+    /// looser permissions, and are refused outright if the recorded mode would expose them.
+    pub fn prepare_output(&self, path: &Path) -> AgeResult<()> {
+        if !self.sensitive {
+            return Ok(());
+        }
+        if self.exposes_to_others() {
+            return Err(AgeError::SecurityValidationFailed {
+                validation_type: "sensitive_file_permissions".to_string(),
+                details: format!("Refusing to unlock sensitive file {} with group/world access (mode {:o})",
+                    path.display(), self.mode),
+            });
+        }
+
+        create_private(path)
+    }
+
+    /// Restore the recorded metadata on an unlocked file
+    ///
+    /// Returns what was not restored: protected attributes, an owner other than the
+    /// running user, or changes refused for lack of privilege. Special mode bits fail
+    /// the restore, as does anything else going wrong.
+    pub fn restore(&self, path: &Path) -> AgeResult<Vec<String>> {
+        if self.mode & SPECIAL_BITS != 0 {
+            return Err(AgeError::SecurityValidationFailed {
+                validation_type: "metadata_restore".to_string(),
+                details: format!("Refusing to restore setuid, setgid or sticky mode {:o} on {}", self.mode, path.display()),
+            });
+        }
+        let mut skipped = Vec::new();
+
+        // The mtime goes first: setting it needs write access the final mode may remove
+        let modified = SystemTime::from(self.modified);
+        fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(modified))
+            .map_err(|e| AgeError::file_error("set_modified", path.to_path_buf(), e))?;
+
+        for (name, value) in &self.xattrs {
+            if is_protected_xattr(name) {
+                skipped.push(format!("xattr {}: protected namespace", name));
+                continue;
+            }
+            let value = STANDARD.decode(value).map_err(|e| AgeError::InvalidOperation {
+                operation: "restore_metadata".to_string(),
+                reason: format!("Corrupt xattr {} for {}: {}", name, path.display(), e),
+            })?;
+            if let Err(e) = xattr::set(path, name, &value) {
+                skipped.push(format!("xattr {}: {}", name, e));
+            }
+        }
+
+        let current = fs::metadata(path).map_err(|e| AgeError::file_error("stat", path.to_path_buf(), e))?;
+        if current.uid() != self.uid || current.gid() != self.gid {
+            // SAFETY: geteuid has no preconditions and cannot fail
+            let euid = unsafe { libc::geteuid() };
+            if euid != self.uid {
+                skipped.push(format!("owner {}:{}: running as uid {}", self.uid, self.gid, euid));
+            } else {
+                match std::os::unix::fs::chown(path, Some(self.uid), Some(self.gid)) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                        skipped.push(format!("owner {}:{}: {}", self.uid, self.gid, e));
+                    }
+                    Err(e) => return Err(AgeError::file_error("chown", path.to_path_buf(), e)),
+                }
+            }
+        }
+
+        fs::set_permissions(path, fs::Permissions::from_mode(self.mode & 0o777))
+            .map_err(|e| AgeError::file_error("set_permissions", path.to_path_buf(), e))?;
+        Ok(skipped)
+    }
+}
+
+/// Create (or reset) `path` empty and owner-only before plaintext is written to it
+pub fn create_private(path: &Path) -> AgeResult<()> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .and_then(|_| fs::set_permissions(path, fs::Permissions::from_mode(0o600)))
+        .map_err(|e| AgeError::file_error("create", path.to_path_buf(), e))
+}
+
+/// Attributes that are never captured or restored
+fn is_protected_xattr(name: &str) -> bool {
+    PROTECTED_XATTR_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+#[cfg(target_os = "linux")]
+mod xattr {
+    use std::collections::BTreeMap;
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    fn c_path(path: &Path) -> io::Result<CString> {
+        CString::new(path.as_os_str().as_bytes()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+
+    /// Read all extended attributes of a file; filesystems without xattrs have none
+    pub fn list(path: &Path) -> io::Result<BTreeMap<String, Vec<u8>>> {
+        let path = c_path(path)?;
+        let mut attributes = BTreeMap::new();
+
+        // SAFETY: a null buffer with size 0 asks for the required length only
+        let size = unsafe { libc::listxattr(path.as_ptr(), std::ptr::null_mut(), 0) };
+        if size < 0 {
+            let error = io::Error::last_os_error();
+            return match error.raw_os_error() {
+                Some(libc::ENOTSUP) => Ok(attributes),
+                _ => Err(error),
+            };
+        }
+
+        let mut names = vec![0u8; size as usize];
+        // SAFETY: the buffer is valid for names.len() bytes
+        let size = unsafe { libc::listxattr(path.as_ptr(), names.as_mut_ptr().cast(), names.len()) };
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        names.truncate(size as usize);
+
+        for name in names.split(|byte| *byte == 0).filter(|name| !name.is_empty()) {
+            let c_name = CString::new(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            // SAFETY: as above, first query the length, then read into a buffer of that size
+            let length = unsafe { libc::getxattr(path.as_ptr(), c_name.as_ptr(), std::ptr::null_mut(), 0) };
+            if length < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut value = vec![0u8; length as usize];
+            let length = unsafe { libc::getxattr(path.as_ptr(), c_name.as_ptr(), value.as_mut_ptr().cast(), value.len()) };
+            if length < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            value.truncate(length as usize);
+            attributes.insert(String::from_utf8_lossy(name).into_owned(), value);
+        }
+        Ok(attributes)
+    }
+
+    /// Set one extended attribute
+    pub fn set(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+        let path = c_path(path)?;
+        let name = CString::new(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        // SAFETY: all pointers are valid for the given lengths
+        let status = unsafe { libc::setxattr(path.as_ptr(), name.as_ptr(), value.as_ptr().cast(), value.len(), 0) };
+        if status < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod xattr {
+    use std::collections::BTreeMap;
+    use std::io;
+    use std::path::Path;
+
+    pub fn list(_path: &Path) -> io::Result<BTreeMap<String, Vec<u8>>> {
+        Ok(BTreeMap::new())
+    }
+
+    pub fn set(_path: &Path, _name: &str, _value: &[u8]) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "extended attributes are only supported on Linux"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_capture_and_restore_mode_and_mtime() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("id_key");
+        fs::write(&path, "secret").unwrap();
+        let mtime = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000);
+        fs::File::options().write(true).open(&path).unwrap().set_modified(mtime).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o400)).unwrap();
+
+        let captured = FileMetadata::capture(&path, false, false).unwrap();
+        assert_eq!(captured.mode, 0o400);
+        assert!(captured.sensitive);
+
+        fs::remove_file(&path).unwrap();
+        captured.prepare_output(&path).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().mode() & 0o777, 0o600);
+        fs::write(&path, "secret").unwrap();
+
+        assert!(captured.restore(&path).unwrap().is_empty());
+        let restored = fs::metadata(&path).unwrap();
+        assert_eq!(restored.mode() & 0o7777, 0o400);
+        assert_eq!(restored.modified().unwrap(), mtime);
+    }
+
+    #[test]
+    fn test_sensitive_file_refuses_exposed_mode() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("shared.txt");
+        fs::write(&path, "data").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let plain = FileMetadata::capture(&path, false, false).unwrap();
+        assert!(!plain.sensitive);
+        plain.prepare_output(&dir.path().join("out.txt")).unwrap();
+
+        let marked = FileMetadata::capture(&path, false, true).unwrap();
+        assert!(marked.prepare_output(&dir.path().join("out.txt")).is_err());
+    }
+
+    #[test]
+    fn test_restore_distrusts_manifest_entries() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tool");
+        fs::write(&path, "#!/bin/sh").unwrap();
+        let captured = FileMetadata::capture(&path, false, false).unwrap();
+
+        let setuid = FileMetadata { mode: 0o4755, ..captured.clone() };
+        assert!(setuid.restore(&path).is_err());
+
+        // SAFETY: geteuid has no preconditions
+        let foreign_uid = unsafe { libc::geteuid() }.wrapping_add(1);
+        let mut forged = FileMetadata { uid: foreign_uid, mode: 0o750, ..captured };
+        forged.xattrs.insert("security.capability".to_string(), STANDARD.encode([1u8, 2, 3]));
+        let skipped = forged.restore(&path).unwrap();
+        assert!(skipped.iter().any(|entry| entry.starts_with("owner")));
+        assert!(skipped.iter().any(|entry| entry.starts_with("xattr security.capability")));
+        let restored = fs::metadata(&path).unwrap();
+        assert_ne!(restored.uid(), foreign_uid);
+        assert_eq!(restored.mode() & 0o7777, 0o750);
+    }
+}
@@ -7,6 +7,26 @@
 //! Security Guardian: Edgar - Production lifecycle management framework
 
 pub mod crud_manager;
+pub mod backup;
+pub mod plan;
+pub mod edit;
+pub mod exec;
+pub mod vault;
+pub mod structured;
+pub mod rearmor;
+pub mod rules;
+pub mod metadata;
+pub mod clock;
 
 // Re-export core lifecycle types
-pub use crud_manager::{CrudManager, LockOptions, UnlockOptions, VerificationResult};
\ No newline at end of file
+pub use crud_manager::{CrudManager, LockOptions, UnlockOptions, VerificationResult};
+pub use backup::{BackupStore, BackupManifest, BackupEntry, BackupRetention};
+pub use plan::{OperationPlan, PlannedAction, PlannedFileChange};
+pub use edit::{SecureEditor, EditOutcome};
+pub use exec::{ExecOptions, SecretEnvironment};
+pub use vault::{Vault, VaultStore, VaultSecret, VaultEntry};
+pub use structured::{StructuredCipher, StructuredFormat, DataKeyStore};
+pub use rearmor::{RearmorOutcome, RepositoryManifest};
+pub use rules::{PathRules, PathRule, NamingScheme};
+pub use metadata::FileMetadata;
+pub use clock::{HighWaterMark, ClockObservation, RecordStatus, trusted_now, load_mark};
\ No newline at end of file
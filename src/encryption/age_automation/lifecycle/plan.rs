@@ -0,0 +1,176 @@
+//! Operation Plans - Dry-run descriptions of mutating lifecycle operations
+//!
+//! An OperationPlan is what a CrudManager operation would do, computed without touching
+//! the filesystem or the authority chain. Plans list every file change, key change and
+//! recipient change so large rotations and revocations can be reviewed before they run.
+//!
+//! Security Guardian: Edgar - Reviewable operations before execution
+
+use std::fmt;
+use std::path::PathBuf;
+use serde::Serialize;
+
+use super::super::error::{AgeError, AgeResult};
+
+/// Kind of change a plan makes to a single file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlannedAction {
+    /// Plaintext file encrypted to a new ciphertext file
+    Encrypt,
+    /// Ciphertext file decrypted to a plaintext file
+    Decrypt,
+    /// Ciphertext file re-encrypted in place under new key material
+    ReEncrypt,
+    /// File or directory removed
+    Delete,
+    /// Copy captured in the backup store
+    Backup,
+    /// Ciphertext converted between binary and ASCII armor, possibly renamed
+    Rearmor,
+}
+
+impl fmt::Display for PlannedAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlannedAction::Encrypt => write!(f, "encrypt"),
+            PlannedAction::Decrypt => write!(f, "decrypt"),
+            PlannedAction::ReEncrypt => write!(f, "re-encrypt"),
+            PlannedAction::Delete => write!(f, "delete"),
+            PlannedAction::Backup => write!(f, "backup"),
+            PlannedAction::Rearmor => write!(f, "rearmor"),
+        }
+    }
+}
+
+/// A single planned file change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedFileChange {
+    pub action: PlannedAction,
+    pub source: PathBuf,
+    pub target: Option<PathBuf>,
+}
+
+/// Structured dry-run result of a mutating operation
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OperationPlan {
+    pub operation: String,
+    pub target: String,
+    pub files: Vec<PlannedFileChange>,
+    pub keys_rotated: Vec<String>,
+    pub keys_invalidated: Vec<String>,
+    pub recipients_added: Vec<String>,
+    pub recipients_removed: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl OperationPlan {
+    /// Create an empty plan for an operation on a target
+    pub fn new(operation: &str, target: impl Into<String>) -> Self {
+        Self {
+            operation: operation.to_string(),
+            target: target.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a planned file change
+    pub fn add_file(&mut self, action: PlannedAction, source: PathBuf, target: Option<PathBuf>) {
+        self.files.push(PlannedFileChange { action, source, target });
+    }
+
+    /// Files affected by a given action
+    pub fn files_with_action(&self, action: PlannedAction) -> Vec<&PlannedFileChange> {
+        self.files.iter().filter(|change| change.action == action).collect()
+    }
+
+    /// True when executing the plan would change nothing
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+            && self.keys_rotated.is_empty()
+            && self.keys_invalidated.is_empty()
+            && self.recipients_added.is_empty()
+            && self.recipients_removed.is_empty()
+    }
+
+    /// Render the plan as pretty-printed JSON
+    pub fn to_json(&self) -> AgeResult<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| AgeError::InvalidOperation {
+                operation: "plan_to_json".to_string(),
+                reason: e.to_string(),
+            })
+    }
+
+    /// Render the plan as a human-readable table
+    pub fn to_table(&self) -> String {
+        let mut rows: Vec<(String, String, String)> = self.files.iter()
+            .map(|change| (
+                change.action.to_string(),
+                change.source.display().to_string(),
+                change.target.as_ref().map(|t| t.display().to_string()).unwrap_or_default(),
+            ))
+            .collect();
+        for key in &self.keys_rotated {
+            rows.push(("rotate-key".to_string(), key.clone(), String::new()));
+        }
+        for key in &self.keys_invalidated {
+            rows.push(("invalidate-key".to_string(), key.clone(), String::new()));
+        }
+        for recipient in &self.recipients_added {
+            rows.push(("add-recipient".to_string(), recipient.clone(), String::new()));
+        }
+        for recipient in &self.recipients_removed {
+            rows.push(("remove-recipient".to_string(), recipient.clone(), String::new()));
+        }
+
+        let headers = ("ACTION", "SUBJECT", "TARGET");
+        let action_width = rows.iter().map(|r| r.0.len()).chain([headers.0.len()]).max().unwrap_or(0);
+        let subject_width = rows.iter().map(|r| r.1.len()).chain([headers.1.len()]).max().unwrap_or(0);
+
+        let mut out = format!("Plan: {} {} (dry run, nothing changed)\n", self.operation, self.target);
+        out.push_str(&format!("{:<aw$}  {:<sw$}  {}\n", headers.0, headers.1, headers.2,
+            aw = action_width, sw = subject_width));
+        for (action, subject, target) in &rows {
+            out.push_str(format!("{:<aw$}  {:<sw$}  {}\n", action, subject, target,
+                aw = action_width, sw = subject_width).trim_end());
+            out.push('\n');
+        }
+        if rows.is_empty() {
+            out.push_str("(no changes)\n");
+        }
+        for warning in &self.warnings {
+            out.push_str(&format!("warning: {}\n", warning));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_rendering() {
+        let mut plan = OperationPlan::new("lock", "/repo");
+        plan.add_file(PlannedAction::Encrypt, PathBuf::from("/repo/a.txt"), Some(PathBuf::from("/repo/a.age")));
+        plan.recipients_added.push("age1example".to_string());
+
+        let table = plan.to_table();
+        assert!(table.contains("encrypt"));
+        assert!(table.contains("/repo/a.age"));
+        assert!(table.contains("add-recipient"));
+
+        let json = plan.to_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["files"][0]["action"], "encrypt");
+        assert_eq!(value["recipients_added"][0], "age1example");
+    }
+
+    #[test]
+    fn test_empty_plan() {
+        let plan = OperationPlan::new("unlock", "/repo");
+        assert!(plan.is_empty());
+        assert!(plan.to_table().contains("(no changes)"));
+    }
+}
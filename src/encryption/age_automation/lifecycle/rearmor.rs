@@ -0,0 +1,257 @@
+//! Rearmor - Convert age files between binary and ASCII armor without keys
+//!
+//! ASCII armor is PEM-style base64 around the exact bytes of a binary age file, so the
+//! two encodings convert losslessly without decrypting anything. Converted files are
+//! written to a staged sibling and renamed into place with their permissions preserved.
+//!
+//! padlock names encrypted files `.age` in both encodings. Files using an armor-only
+//! extension (`.asc`, `.armor`, `.txt`) are renamed to `.age` when converted to binary.
+//! The repository manifest at `.padlock/manifest.json` records the encoding a repository
+//! was converted to, so later locks produce the same encoding, along with the file
+//! metadata lock captures for unlock to restore.
+//!
+//! Security Guardian: Edgar - Change the wrapping, never the contents
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::super::config::OutputFormat;
+use super::super::error::{AgeError, AgeResult};
+use super::super::header::{self, AgeHeader, HeaderEncoding};
+use super::metadata::FileMetadata;
+
+/// Current repository manifest format version
+pub const MANIFEST_FORMAT_VERSION: u32 = 1;
+
+/// Result of converting one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RearmorOutcome {
+    /// File rewritten in the requested encoding, possibly under a new name
+    Converted { from: PathBuf, to: PathBuf },
+    /// File already used the requested encoding
+    Unchanged(PathBuf),
+}
+
+/// Target encoding for an output format; only binary and armor apply
+pub fn target_encoding(format: OutputFormat) -> AgeResult<HeaderEncoding> {
+    match format {
+        OutputFormat::Binary => Ok(HeaderEncoding::Binary),
+        OutputFormat::AsciiArmor => Ok(HeaderEncoding::Armored),
+        OutputFormat::Structured => Err(AgeError::InvalidOperation {
+            operation: "rearmor".to_string(),
+            reason: "Structured files have no armor encoding".to_string(),
+        }),
+    }
+}
+
+/// Path a file gets after conversion: armor-only extensions become `.age` for binary
+pub fn rearmored_path(path: &Path, to: HeaderEncoding) -> PathBuf {
+    let armor_extension = OutputFormat::detect_from_path(path) == OutputFormat::AsciiArmor;
+    if to == HeaderEncoding::Binary && armor_extension {
+        path.with_extension("age")
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// True for files a directory rearmor converts: `.age` files and armor-named age files
+pub fn is_rearmor_candidate(path: &Path) -> AgeResult<bool> {
+    let age_named = path.extension().and_then(|s| s.to_str()) == Some("age");
+    let armor_named = OutputFormat::detect_from_path(path) == OutputFormat::AsciiArmor;
+    Ok((age_named || armor_named) && header::sniff(path)?.is_some())
+}
+
+/// Convert a single age file in place
+pub fn rearmor_file(path: &Path, to: HeaderEncoding) -> AgeResult<RearmorOutcome> {
+    let from = header::sniff(path)?.ok_or_else(|| AgeError::InvalidOperation {
+        operation: "rearmor".to_string(),
+        reason: format!("{} is not an age file", path.display()),
+    })?;
+    let target = rearmored_path(path, to);
+    if from == to && target == path {
+        return Ok(RearmorOutcome::Unchanged(path.to_path_buf()));
+    }
+    if target != path && target.exists() {
+        return Err(AgeError::InvalidOperation {
+            operation: "rearmor".to_string(),
+            reason: format!("{} already exists", target.display()),
+        });
+    }
+
+    let data = fs::read(path).map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?;
+    let converted = match (from, to) {
+        (HeaderEncoding::Binary, HeaderEncoding::Armored) => header::armor(&data),
+        (HeaderEncoding::Armored, HeaderEncoding::Binary) => header::dearmor(&data).map_err(|reason| rearmor_error(path, reason))?,
+        _ => data,
+    };
+
+    // The converted bytes must still carry a well-formed header
+    AgeHeader::parse(&mut converted.as_slice()).map_err(|reason| rearmor_error(path, reason))?;
+
+    write_replacement(path, &target, &converted)?;
+    if target != path {
+        fs::remove_file(path).map_err(|e| AgeError::file_error("remove", path.to_path_buf(), e))?;
+    }
+    Ok(RearmorOutcome::Converted { from: path.to_path_buf(), to: target })
+}
+
+/// Write `data` next to `target` and rename it over `target`, keeping `source` permissions
+fn write_replacement(source: &Path, target: &Path, data: &[u8]) -> AgeResult<()> {
+    let file_name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let staged = target.with_file_name(format!(".{}.padlock-rearmor", file_name));
+
+    let result = fs::File::create(&staged)
+        .and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        })
+        .map_err(|e| AgeError::file_error("write", staged.clone(), e))
+        .and_then(|_| {
+            let metadata = fs::metadata(source).map_err(|e| AgeError::file_error("read", source.to_path_buf(), e))?;
+            fs::set_permissions(&staged, metadata.permissions())
+                .map_err(|e| AgeError::file_error("set_permissions", staged.clone(), e))?;
+            fs::rename(&staged, target)
+                .map_err(|e| AgeError::file_error("rename", target.to_path_buf(), e))
+        });
+
+    if result.is_err() {
+        let _ = fs::remove_file(&staged);
+    }
+    result
+}
+
+fn rearmor_error(path: &Path, reason: String) -> AgeError {
+    AgeError::InvalidOperation {
+        operation: "rearmor".to_string(),
+        reason: format!("{}: {}", path.display(), reason),
+    }
+}
+
+/// Repository-level settings recorded under `.padlock/manifest.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepositoryManifest {
+    pub version: u32,
+    /// Encoding the repository was last converted to
+    pub encoding: Option<HeaderEncoding>,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Metadata of the plaintext behind each locked file, keyed by repository-relative locked path
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub files: BTreeMap<String, FileMetadata>,
+}
+
+impl Default for RepositoryManifest {
+    fn default() -> Self {
+        Self {
+            version: MANIFEST_FORMAT_VERSION,
+            encoding: None,
+            updated_at: None,
+            files: BTreeMap::new(),
+        }
+    }
+}
+
+impl RepositoryManifest {
+    /// Manifest path relative to the repository root
+    pub const FILE: &'static str = ".padlock/manifest.json";
+
+    /// Load a repository's manifest; a missing manifest is the default
+    pub fn load(repository: &Path) -> AgeResult<Self> {
+        let path = repository.join(Self::FILE);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read(&path).map_err(|e| AgeError::file_error("read", path.clone(), e))?;
+        serde_json::from_slice(&content).map_err(|e| AgeError::InvalidOperation {
+            operation: "manifest_load".to_string(),
+            reason: format!("Corrupt repository manifest {}: {}", path.display(), e),
+        })
+    }
+
+    /// Write the manifest into the repository
+    pub fn save(&self, repository: &Path) -> AgeResult<()> {
+        let path = repository.join(Self::FILE);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| AgeError::file_error("create_dir", parent.to_path_buf(), e))?;
+        }
+
+        let content = serde_json::to_vec_pretty(self).map_err(|e| AgeError::InvalidOperation {
+            operation: "manifest_save".to_string(),
+            reason: e.to_string(),
+        })?;
+        fs::write(&path, content).map_err(|e| AgeError::file_error("write", path, e))
+    }
+
+    /// Output format new locks should use when none was requested
+    pub fn preferred_format(&self) -> Option<OutputFormat> {
+        self.encoding.map(|encoding| match encoding {
+            HeaderEncoding::Binary => OutputFormat::Binary,
+            HeaderEncoding::Armored => OutputFormat::AsciiArmor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::header::testing::{identity, x25519_header};
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rearmor_roundtrip_preserves_bytes() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("secret.age");
+        let mut binary = x25519_header(&[identity([4u8; 32]).1], true);
+        binary.extend((0..300u32).map(|i| i as u8));
+        fs::write(&path, &binary).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        let outcome = rearmor_file(&path, HeaderEncoding::Armored).unwrap();
+        assert_eq!(outcome, RearmorOutcome::Converted { from: path.clone(), to: path.clone() });
+        assert_eq!(header::sniff(&path).unwrap(), Some(HeaderEncoding::Armored));
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o640);
+        assert_eq!(rearmor_file(&path, HeaderEncoding::Armored).unwrap(), RearmorOutcome::Unchanged(path.clone()));
+
+        rearmor_file(&path, HeaderEncoding::Binary).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), binary);
+    }
+
+    #[test]
+    fn test_armor_extension_renamed_for_binary() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notes.asc");
+        let binary = x25519_header(&[identity([6u8; 32]).1], false);
+        fs::write(&path, header::armor(&binary)).unwrap();
+        assert!(is_rearmor_candidate(&path).unwrap());
+
+        let outcome = rearmor_file(&path, HeaderEncoding::Binary).unwrap();
+        let renamed = dir.path().join("notes.age");
+        assert_eq!(outcome, RearmorOutcome::Converted { from: path.clone(), to: renamed.clone() });
+        assert!(!path.exists());
+        assert_eq!(fs::read(&renamed).unwrap(), binary);
+
+        fs::write(dir.path().join("plain.txt"), "not age").unwrap();
+        assert!(!is_rearmor_candidate(&dir.path().join("plain.txt")).unwrap());
+        assert!(rearmor_file(&dir.path().join("plain.txt"), HeaderEncoding::Armored).is_err());
+    }
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let repo = TempDir::new().unwrap();
+        assert_eq!(RepositoryManifest::load(repo.path()).unwrap().preferred_format(), None);
+
+        let manifest = RepositoryManifest {
+            encoding: Some(HeaderEncoding::Armored),
+            updated_at: Some(Utc::now()),
+            ..RepositoryManifest::default()
+        };
+        manifest.save(repo.path()).unwrap();
+        let loaded = RepositoryManifest::load(repo.path()).unwrap();
+        assert_eq!(loaded.preferred_format(), Some(OutputFormat::AsciiArmor));
+    }
+}
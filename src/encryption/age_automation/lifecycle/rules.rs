@@ -0,0 +1,366 @@
+//! Path Rules - Per-path output format, naming and recipients
+//!
+//! A repository can route files to different encodings and audiences with a rules file
+//! at `.padlock/rules.toml`. Each rule maps a glob pattern to an output format, a naming
+//! scheme and a named recipient set; the first matching rule wins and unset fields fall
+//! back to the lock options. Without a rules file every file is locked as before.
+//!
+//! ```toml
+//! mirror_dir = "secrets"
+//!
+//! [recipients]
+//! ops = ["age1...", "ssh-ed25519 AAAA... ops@example"]
+//!
+//! [[rule]]
+//! pattern = "**/*.env"
+//! format = "ascii"
+//! naming = "append"
+//! recipients = "ops"
+//!
+//! [[rule]]
+//! pattern = "blobs/**"
+//! format = "binary"
+//! naming = "mirror"
+//!
+//! [[rule]]
+//! pattern = "keys/*"
+//! sensitive = true
+//! ```
+//!
+//! The recipient set named `repository`, when present, is also what lock encrypts files
+//! to when a scoped distro key covers them but no rule names a set: a passphrase cannot
+//! be combined with recipients, so those files go to the key and this set instead.
+//!
+//! Patterns are matched against repository-relative paths with `/` separators: `*` and
+//! `?` stay within one path segment, `**` spans segments, and patterns without a `/`
+//! match the file name at any depth.
+//!
+//! Security Guardian: Edgar - The right audience for every path
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use regex::Regex;
+use serde::Deserialize;
+
+use super::super::config::OutputFormat;
+use super::super::error::{AgeError, AgeResult};
+
+/// Default directory for the mirrored naming scheme
+pub const DEFAULT_MIRROR_DIR: &str = "secrets";
+
+/// How a locked file is named
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NamingScheme {
+    /// `config.yaml` -> `config.yaml.age`
+    Append,
+    /// `config.yaml` -> `config.age`
+    Replace,
+    /// `config/app.yaml` -> `<mirror_dir>/config/app.yaml.age`
+    Mirror,
+}
+
+/// Output format named in a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleFormat {
+    Binary,
+    Ascii,
+    Structured,
+}
+
+impl From<RuleFormat> for OutputFormat {
+    fn from(format: RuleFormat) -> Self {
+        match format {
+            RuleFormat::Binary => OutputFormat::Binary,
+            RuleFormat::Ascii => OutputFormat::AsciiArmor,
+            RuleFormat::Structured => OutputFormat::Structured,
+        }
+    }
+}
+
+/// One pattern rule
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PathRule {
+    pub pattern: String,
+    pub format: Option<RuleFormat>,
+    pub naming: Option<NamingScheme>,
+    /// Name of a set in `[recipients]`; files are then encrypted to it instead of a passphrase
+    pub recipients: Option<String>,
+    /// Treat matching files as sensitive: owner-only plaintext on unlock
+    #[serde(default)]
+    pub sensitive: bool,
+    #[serde(skip)]
+    matcher: Option<Regex>,
+}
+
+impl PathRule {
+    /// True when the rule applies to a repository-relative path
+    pub fn matches(&self, relative: &Path) -> bool {
+        let path = relative.to_string_lossy().replace('\\', "/");
+        self.matcher.as_ref().is_some_and(|matcher| matcher.is_match(&path))
+    }
+}
+
+/// Effective settings for one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedRule<'a> {
+    pub format: OutputFormat,
+    pub naming: NamingScheme,
+    pub recipients: Option<&'a [String]>,
+    /// Pattern of the matching rule, if any
+    pub pattern: Option<&'a str>,
+}
+
+/// Rules file contents
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PathRules {
+    #[serde(default = "default_mirror_dir")]
+    pub mirror_dir: String,
+    #[serde(default)]
+    pub recipients: BTreeMap<String, Vec<String>>,
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<PathRule>,
+}
+
+fn default_mirror_dir() -> String {
+    DEFAULT_MIRROR_DIR.to_string()
+}
+
+impl PathRules {
+    /// Rules file path relative to the repository root
+    pub const FILE: &'static str = ".padlock/rules.toml";
+
+    /// Recipient set that files locked to scoped keys without a rule's set also go to
+    pub const REPOSITORY_SET: &'static str = "repository";
+
+    /// Load a repository's rules; None when it has no rules file
+    pub fn load(repository: &Path) -> AgeResult<Option<Self>> {
+        let path = repository.join(Self::FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| AgeError::file_error("read", path.clone(), e))?;
+        Self::parse(&content).map(Some)
+    }
+
+    /// Parse and validate rules from TOML
+    pub fn parse(content: &str) -> AgeResult<Self> {
+        let mut rules: PathRules = toml::from_str(content)
+            .map_err(|e| rules_error(format!("Invalid rules file: {}", e)))?;
+
+        let mirror = Path::new(&rules.mirror_dir);
+        if rules.mirror_dir.is_empty() || mirror.is_absolute() || mirror.components().any(|c| c.as_os_str() == "..") {
+            return Err(rules_error(format!("mirror_dir must be a relative path inside the repository: '{}'", rules.mirror_dir)));
+        }
+
+        for (name, set) in &rules.recipients {
+            if set.is_empty() {
+                return Err(rules_error(format!("Recipient set '{}' is empty", name)));
+            }
+            if let Some(bad) = set.iter().find(|r| !(r.starts_with("age1") || r.starts_with("ssh-"))) {
+                return Err(rules_error(format!("Recipient set '{}' has an unsupported recipient '{}'", name, bad)));
+            }
+        }
+
+        for rule in &mut rules.rules {
+            if let Some(set) = &rule.recipients {
+                if !rules.recipients.contains_key(set) {
+                    return Err(rules_error(format!("Rule '{}' names unknown recipient set '{}'", rule.pattern, set)));
+                }
+            }
+            if rule.format == Some(RuleFormat::Structured) && (rule.recipients.is_some() || rule.naming.is_some()) {
+                return Err(rules_error(format!(
+                    "Rule '{}': structured files use the repository data key and their own naming", rule.pattern)));
+            }
+            rule.matcher = Some(glob_to_regex(&rule.pattern)?);
+        }
+
+        Ok(rules)
+    }
+
+    /// First rule matching a repository-relative path
+    pub fn rule_for(&self, relative: &Path) -> Option<&PathRule> {
+        self.rules.iter().find(|rule| rule.matches(relative))
+    }
+
+    /// Effective settings for a path; fields a rule leaves unset use the caller's defaults
+    pub fn resolve(&self, relative: &Path, default_format: OutputFormat, default_naming: NamingScheme) -> ResolvedRule<'_> {
+        match self.rule_for(relative) {
+            Some(rule) => ResolvedRule {
+                format: rule.format.map(OutputFormat::from).unwrap_or(default_format),
+                naming: rule.naming.unwrap_or(default_naming),
+                recipients: rule.recipients.as_ref().and_then(|set| self.recipients.get(set)).map(Vec::as_slice),
+                pattern: Some(&rule.pattern),
+            },
+            None => ResolvedRule {
+                format: default_format,
+                naming: default_naming,
+                recipients: None,
+                pattern: None,
+            },
+        }
+    }
+
+    /// Locked path of `file` (inside `repository`) under a naming scheme
+    pub fn output_path(&self, repository: &Path, file: &Path, naming: NamingScheme) -> PathBuf {
+        match naming {
+            NamingScheme::Replace => file.with_extension("age"),
+            NamingScheme::Append => append_age(file),
+            NamingScheme::Mirror => {
+                let relative = file.strip_prefix(repository).unwrap_or(file);
+                append_age(&repository.join(&self.mirror_dir).join(relative))
+            }
+        }
+    }
+
+    /// Plaintext path for a locked file; mirrored files map back out of the mirror tree
+    pub fn source_path(&self, repository: &Path, encrypted: &Path) -> PathBuf {
+        let mirror_root = repository.join(&self.mirror_dir);
+        match encrypted.strip_prefix(&mirror_root) {
+            Ok(relative) => repository.join(relative).with_extension(""),
+            Err(_) => encrypted.with_extension(""),
+        }
+    }
+
+    /// True when a path lies inside the mirror tree
+    pub fn is_mirrored(&self, repository: &Path, path: &Path) -> bool {
+        path.starts_with(repository.join(&self.mirror_dir))
+    }
+}
+
+/// `name.ext` -> `name.ext.age`
+fn append_age(file: &Path) -> PathBuf {
+    let mut name = file.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".age");
+    file.with_file_name(name)
+}
+
+/// Translate a glob pattern into an anchored regex over `/`-separated relative paths
+pub fn glob_to_regex(pattern: &str) -> AgeResult<Regex> {
+    let trimmed = pattern.trim_start_matches("./");
+    if trimmed.is_empty() {
+        return Err(rules_error("Empty rule pattern".to_string()));
+    }
+
+    let mut regex = String::from("^");
+    if !trimmed.contains('/') {
+        regex.push_str("(?:.*/)?");
+    }
+
+    let chars: Vec<char> = trimmed.trim_start_matches('/').chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    regex.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    regex.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                let end = chars[i + 1..].iter().position(|&c| c == ']')
+                    .ok_or_else(|| rules_error(format!("Unclosed '[' in pattern '{}'", pattern)))?;
+                let class: String = chars[i + 1..i + 1 + end].iter().collect();
+                let class = class.strip_prefix('!').map(|rest| format!("^{}", rest)).unwrap_or(class);
+                regex.push_str(&format!("[{}]", class.replace('\\', "\\\\")));
+                i += end + 2;
+                continue;
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    regex.push('$');
+
+    Regex::new(&regex).map_err(|e| rules_error(format!("Invalid pattern '{}': {}", pattern, e)))
+}
+
+fn rules_error(reason: String) -> AgeError {
+    AgeError::ConfigurationError {
+        parameter: "rules".to_string(),
+        value: PathRules::FILE.to_string(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+[recipients]
+ops = ["age1qyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqs3290gq"]
+
+[[rule]]
+pattern = "**/*.env"
+format = "ascii"
+naming = "append"
+recipients = "ops"
+
+[[rule]]
+pattern = "blobs/**"
+naming = "mirror"
+
+[[rule]]
+pattern = "*.yaml"
+format = "structured"
+"#;
+
+    #[test]
+    fn test_glob_matching() {
+        let matches = |pattern: &str, path: &str| glob_to_regex(pattern).unwrap().is_match(path);
+        assert!(matches("*.env", "prod.env"));
+        assert!(matches("*.env", "deploy/prod.env"));
+        assert!(matches("**/*.env", "prod.env"));
+        assert!(matches("**/*.env", "a/b/prod.env"));
+        assert!(!matches("config/*.env", "config/sub/prod.env"));
+        assert!(matches("blobs/**", "blobs/x/y.bin"));
+        assert!(matches("key?.pem", "key1.pem"));
+        assert!(matches("[!a]*.txt", "b.txt"));
+        assert!(!matches("[!a]*.txt", "a.txt"));
+        assert!(!matches("*.env", "prod.envx"));
+    }
+
+    #[test]
+    fn test_rules_resolution_and_naming() {
+        let rules = PathRules::parse(RULES).unwrap();
+        let repo = Path::new("/repo");
+
+        let env = rules.resolve(Path::new("deploy/prod.env"), OutputFormat::Binary, NamingScheme::Replace);
+        assert_eq!(env.format, OutputFormat::AsciiArmor);
+        assert_eq!(env.naming, NamingScheme::Append);
+        assert_eq!(env.recipients.unwrap().len(), 1);
+        assert_eq!(rules.output_path(repo, Path::new("/repo/deploy/prod.env"), env.naming), Path::new("/repo/deploy/prod.env.age"));
+
+        let blob = rules.resolve(Path::new("blobs/a.bin"), OutputFormat::Binary, NamingScheme::Replace);
+        assert_eq!(blob.format, OutputFormat::Binary);
+        let mirrored = rules.output_path(repo, Path::new("/repo/blobs/a.bin"), blob.naming);
+        assert_eq!(mirrored, Path::new("/repo/secrets/blobs/a.bin.age"));
+        assert_eq!(rules.source_path(repo, &mirrored), Path::new("/repo/blobs/a.bin"));
+
+        let other = rules.resolve(Path::new("notes.txt"), OutputFormat::Binary, NamingScheme::Replace);
+        assert_eq!(other.pattern, None);
+        assert_eq!(rules.output_path(repo, Path::new("/repo/notes.txt"), other.naming), Path::new("/repo/notes.age"));
+        assert_eq!(rules.resolve(Path::new("app.yaml"), OutputFormat::Binary, NamingScheme::Replace).format, OutputFormat::Structured);
+    }
+
+    #[test]
+    fn test_invalid_rules_rejected() {
+        assert!(PathRules::parse("[[rule]]\npattern = \"*\"\nrecipients = \"missing\"\n").is_err());
+        assert!(PathRules::parse("mirror_dir = \"../out\"\n").is_err());
+        assert!(PathRules::parse("[recipients]\nops = [\"not-a-key\"]\n").is_err());
+        assert!(PathRules::parse("[[rule]]\npattern = \"*.json\"\nformat = \"structured\"\nnaming = \"append\"\n").is_err());
+        assert!(PathRules::parse("[[rule]]\npattern = \"[abc\"\n").is_err());
+        assert!(PathRules::parse("[[rule]]\npattern = \"*\"\nformt = \"ascii\"\n").is_err());
+    }
+}
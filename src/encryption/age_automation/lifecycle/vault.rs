@@ -0,0 +1,347 @@
+//! Secrets Vault - Named key/value secrets in a single encrypted file
+//!
+//! The vault lives at `<repo>/.padlock/vault.age` and holds every named secret with its
+//! metadata, so teams do not need a separate encrypted file per value. The plaintext is
+//! JSON that only ever exists in memory or in a wiped RAM-backed workspace; updates are
+//! encrypted next to the vault and renamed over it, so readers never see a partial file.
+//!
+//! Writers hold an exclusive lock on `.padlock/vault.lock` from load to save, so
+//! concurrent updates cannot drop each other's changes. When the repository rules define
+//! the `repository` recipient set, every save encrypts the vault to that set (opened with
+//! age identity files); otherwise the vault stays a passphrase file.
+//!
+//! Security Guardian: Edgar - One encrypted home for small secrets
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::super::adapter::AgeAdapter;
+use super::super::config::OutputFormat;
+use super::super::error::{AgeError, AgeResult};
+use super::super::header::{AgeHeader, StanzaKind};
+use super::super::secret::{serde_exposed_string, SecretString, Zeroizing};
+use super::clock::FileLock;
+use super::edit::{self, EditWorkspace};
+
+/// Current vault document format version
+pub const VAULT_FORMAT_VERSION: u32 = 1;
+
+/// A named secret and its metadata
+///
+/// The value is a `SecretString`: `Debug` redacts it and it is only serialized into the
+/// vault document, which is written encrypted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultSecret {
+    #[serde(with = "serde_exposed_string")]
+    pub value: SecretString,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: String,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+}
+
+/// Secret metadata without the value, for listings
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VaultEntry {
+    pub name: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: String,
+    pub updated_at: DateTime<Utc>,
+    pub tags: BTreeSet<String>,
+}
+
+/// Decrypted vault document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vault {
+    pub version: u32,
+    pub secrets: BTreeMap<String, VaultSecret>,
+}
+
+impl Default for Vault {
+    fn default() -> Self {
+        Self {
+            version: VAULT_FORMAT_VERSION,
+            secrets: BTreeMap::new(),
+        }
+    }
+}
+
+impl Vault {
+    /// Look up a secret
+    pub fn get(&self, name: &str) -> Option<&VaultSecret> {
+        self.secrets.get(name)
+    }
+
+    /// Create or update a secret. Existing tags are kept when `tags` is None.
+    pub fn set(&mut self, name: &str, value: String, tags: Option<BTreeSet<String>>, actor: &str) -> AgeResult<()> {
+        validate_secret_name(name)?;
+        let now = Utc::now();
+
+        match self.secrets.get_mut(name) {
+            Some(secret) => {
+                secret.value = SecretString::new(value);
+                secret.updated_by = actor.to_string();
+                secret.updated_at = now;
+                if let Some(tags) = tags {
+                    secret.tags = tags;
+                }
+            }
+            None => {
+                self.secrets.insert(name.to_string(), VaultSecret {
+                    value: SecretString::new(value),
+                    created_by: actor.to_string(),
+                    created_at: now,
+                    updated_by: actor.to_string(),
+                    updated_at: now,
+                    tags: tags.unwrap_or_default(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a secret, returning it if it existed
+    pub fn remove(&mut self, name: &str) -> Option<VaultSecret> {
+        self.secrets.remove(name)
+    }
+
+    /// Metadata of all secrets, optionally restricted to those carrying a tag
+    pub fn entries(&self, tag: Option<&str>) -> Vec<VaultEntry> {
+        self.secrets.iter()
+            .filter(|(_, secret)| tag.is_none_or(|tag| secret.tags.contains(tag)))
+            .map(|(name, secret)| VaultEntry {
+                name: name.clone(),
+                created_by: secret.created_by.clone(),
+                created_at: secret.created_at,
+                updated_by: secret.updated_by.clone(),
+                updated_at: secret.updated_at,
+                tags: secret.tags.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Location and persistence of a repository's vault
+pub struct VaultStore {
+    path: PathBuf,
+    lock_path: PathBuf,
+    recipients: Vec<String>,
+    identities: Vec<PathBuf>,
+}
+
+/// Exclusive hold on a vault for a load-modify-save cycle, released on drop
+pub type VaultLock = FileLock;
+
+impl VaultStore {
+    /// Vault path relative to the repository root
+    pub const FILE: &'static str = ".padlock/vault.age";
+
+    /// Writer lock path relative to the repository root
+    pub const LOCK_FILE: &'static str = ".padlock/vault.lock";
+
+    /// Vault store for a repository
+    pub fn new(repository: &Path) -> Self {
+        Self {
+            path: repository.join(Self::FILE),
+            lock_path: repository.join(Self::LOCK_FILE),
+            recipients: Vec::new(),
+            identities: Vec::new(),
+        }
+    }
+
+    /// Encrypt the vault to these recipients on save instead of the passphrase
+    pub fn with_recipients(mut self, recipients: Vec<String>) -> Self {
+        self.recipients = recipients;
+        self
+    }
+
+    /// Identity files that open a vault encrypted to recipients
+    pub fn with_identities(mut self, identities: Vec<PathBuf>) -> Self {
+        self.identities = identities;
+        self
+    }
+
+    /// Wait for and take the writer lock; hold it across load and save
+    pub fn lock(&self) -> AgeResult<VaultLock> {
+        FileLock::acquire(&self.lock_path)
+    }
+
+    /// Path of the encrypted vault file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// True when the vault file exists
+    pub fn exists(&self) -> bool {
+        self.path.is_file()
+    }
+
+    /// Decrypt the vault; a missing vault is empty
+    pub fn load(&self, adapter: &dyn AgeAdapter, passphrase: &str) -> AgeResult<Vault> {
+        if !self.exists() {
+            return Ok(Vault::default());
+        }
+
+        let header = AgeHeader::read(&self.path)?;
+        let plaintext = if header.stanzas.iter().all(|stanza| stanza.kind == StanzaKind::Scrypt) {
+            adapter.decrypt_to_bytes(&self.path, passphrase)?
+        } else if self.identities.is_empty() {
+            return Err(AgeError::InvalidOperation {
+                operation: "vault_load".to_string(),
+                reason: format!("{} is encrypted to {}; pass --identity with one of its keys",
+                    self.path.display(), header.summary()),
+            });
+        } else {
+            adapter.decrypt_to_bytes_with_identities(&self.path, &self.identities)?
+        };
+        let vault: Vault = serde_json::from_slice(plaintext.expose_secret())
+            .map_err(|e| AgeError::InvalidOperation {
+                operation: "vault_load".to_string(),
+                reason: format!("Corrupt vault document: {}", e),
+            })?;
+
+        if vault.version > VAULT_FORMAT_VERSION {
+            return Err(AgeError::InvalidOperation {
+                operation: "vault_load".to_string(),
+                reason: format!("Vault format version {} is newer than supported version {}",
+                    vault.version, VAULT_FORMAT_VERSION),
+            });
+        }
+        Ok(vault)
+    }
+
+    /// Encrypt the vault and atomically replace the existing file.
+    ///
+    /// With recipients configured the vault is encrypted to exactly that set, moving a
+    /// passphrase vault over; without them a vault already encrypted to keys is refused.
+    pub fn save(&self, adapter: &dyn AgeAdapter, vault: &Vault, passphrase: &str) -> AgeResult<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AgeError::file_error("create_dir", parent.to_path_buf(), e))?;
+        }
+
+        let document = serde_json::to_vec_pretty(vault)
+            .map(Zeroizing::new)
+            .map_err(|e| AgeError::InvalidOperation {
+                operation: "vault_save".to_string(),
+                reason: e.to_string(),
+            })?;
+
+        let format = if self.exists() { edit::detect_format(&self.path)? } else { OutputFormat::Binary };
+        let workspace = EditWorkspace::create(&edit::private_temp_root(), "vault.json".into())?;
+        std::fs::write(workspace.file(), document.as_slice())
+            .map_err(|e| AgeError::file_error("write", workspace.file().to_path_buf(), e))?;
+
+        let recipients = match self.recipients.as_slice() {
+            [] => edit::reencryption_recipients(&self.path, &[])?,
+            recipients => Some(recipients),
+        };
+        edit::write_encrypted(adapter, workspace.file(), &self.path, passphrase, recipients, format)
+    }
+}
+
+/// Name recorded as the author of vault changes
+pub fn current_actor() -> String {
+    ["PADLOCK_ACTOR", "USER", "USERNAME"].iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Secret names: letters, digits and `_ - . /`, not starting with `/` or `.`
+fn validate_secret_name(name: &str) -> AgeResult<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with(['/', '.'])
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/'));
+
+    if !valid {
+        return Err(AgeError::InvalidOperation {
+            operation: "vault_set".to_string(),
+            reason: format!("Invalid secret name '{}'", name),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::adapter::testing::MockAdapter;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_vault_roundtrip() {
+        let repo = TempDir::new().unwrap();
+        let store = VaultStore::new(repo.path());
+        assert!(store.load(&MockAdapter, "vault-pass").unwrap().secrets.is_empty());
+
+        let mut vault = Vault::default();
+        let tags: BTreeSet<String> = ["prod".to_string()].into();
+        vault.set("db/password", "hunter2".to_string(), Some(tags), "alice").unwrap();
+        store.save(&MockAdapter, &vault, "vault-pass").unwrap();
+
+        let raw = std::fs::read_to_string(store.path()).unwrap();
+        assert!(raw.starts_with("age-encryption.org/v1"));
+
+        let mut loaded = store.load(&MockAdapter, "vault-pass").unwrap();
+        assert_eq!(loaded.get("db/password").unwrap().value.expose_secret(), "hunter2");
+        assert!(!format!("{:?}", loaded).contains("hunter2"));
+        assert!(store.load(&MockAdapter, "wrong-pass").is_err());
+
+        // Updates keep creation metadata and existing tags
+        loaded.set("db/password", "hunter3".to_string(), None, "bob").unwrap();
+        let secret = loaded.get("db/password").unwrap();
+        assert_eq!(secret.created_by, "alice");
+        assert_eq!(secret.updated_by, "bob");
+        assert!(secret.tags.contains("prod"));
+
+        assert_eq!(loaded.entries(Some("prod")).len(), 1);
+        assert!(loaded.entries(Some("dev")).is_empty());
+        assert!(loaded.remove("db/password").is_some());
+    }
+
+    #[test]
+    fn test_vault_follows_recipient_set_under_writer_lock() {
+        use super::super::super::header::{testing, KnownRecipient};
+
+        let repo = TempDir::new().unwrap();
+        let (identity, _) = testing::identity([5u8; 32]);
+        let recipient = KnownRecipient::from_identity("ops", &identity).unwrap().recipient;
+        let identity_file = repo.path().join("ops.key");
+        std::fs::write(&identity_file, format!("{}\n", identity)).unwrap();
+
+        // A passphrase vault moves to the recipient set on its next save
+        let mut vault = Vault::default();
+        vault.set("token", "abc".to_string(), None, "alice").unwrap();
+        VaultStore::new(repo.path()).save(&MockAdapter, &vault, "vault-pass").unwrap();
+        let store = VaultStore::new(repo.path()).with_recipients(vec![recipient]);
+        store.save(&MockAdapter, &vault, "vault-pass").unwrap();
+        let header = AgeHeader::read(store.path()).unwrap();
+        assert_eq!(header.stanzas.len(), 1);
+        assert_eq!(header.stanzas[0].kind, StanzaKind::X25519);
+
+        assert!(store.load(&MockAdapter, "vault-pass").unwrap_err().to_string().contains("--identity"));
+        let store = store.with_identities(vec![identity_file]);
+        assert_eq!(store.load(&MockAdapter, "vault-pass").unwrap().get("token").unwrap().value.expose_secret(), "abc");
+
+        // Without the set, a vault encrypted to keys is not re-keyed to the passphrase
+        assert!(VaultStore::new(repo.path()).save(&MockAdapter, &vault, "vault-pass").is_err());
+
+        // A second writer cannot take the lock while the first holds it
+        let _held = store.lock().unwrap();
+        assert!(FileLock::try_acquire(&repo.path().join(VaultStore::LOCK_FILE)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_invalid_secret_names() {
+        let mut vault = Vault::default();
+        for name in ["", "/abs", ".hidden", "has space", "semi;colon"] {
+            assert!(vault.set(name, "x".to_string(), None, "alice").is_err(), "{}", name);
+        }
+    }
+}
@@ -44,17 +44,23 @@ pub mod lifecycle;
 pub mod security;
 pub mod error;
 pub mod config;
+pub mod header;
+pub mod secret;
+pub mod temp_area;
 
 // Re-export core types for convenience
 pub use adapter::{AgeAdapter, AdapterFactory};
 pub use age_engine::AgeAutomator;
-pub use config::{AgeConfig, OutputFormat, TtyMethod};
+pub use config::{AgeConfig, ClockRollbackPolicy, OutputFormat, SymlinkPolicy, TtyMethod};
 pub use error::{AgeError, AgeResult};
 pub use operations::{
     Operation, FileEncryption, RepositoryOperations, RepositoryStatus, OperationResult
 };
 pub use lifecycle::{CrudManager, LockOptions, UnlockOptions, VerificationResult};
 pub use security::{AuditLogger, SecurityValidator};
+pub use secret::{SecretBytes, SecretString};
+pub use temp_area::{SecureTempArea, SecureTempFile};
+pub use header::{AgeHeader, FileHeaderReport, HeaderEncoding, KnownRecipient, Stanza, StanzaKind};
 
 /// Module version aligned with padlock versioning
 pub const VERSION: &str = "0.0.1-age-automation";
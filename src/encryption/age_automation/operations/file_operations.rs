@@ -11,6 +11,7 @@ use std::time::Instant;
 use super::super::adapter::AgeAdapter;
 use super::super::config::OutputFormat;
 use super::super::error::{AgeError, AgeResult};
+use super::super::secret::SecretString;
 use super::super::security::{AuditLogger, SecurityValidator};
 use super::{Operation, FileEncryption, OperationResult};
 
@@ -19,7 +20,7 @@ pub struct FileEncryptOperation {
     adapter: Box<dyn AgeAdapter>,
     input_path: PathBuf,
     output_path: PathBuf,
-    passphrase: String,
+    passphrase: SecretString,
     format: OutputFormat,
     audit_logger: AuditLogger,
     validator: SecurityValidator,
@@ -41,7 +42,7 @@ impl FileEncryptOperation {
             adapter,
             input_path: input.to_path_buf(),
             output_path: output.to_path_buf(),
-            passphrase: passphrase.to_string(),
+            passphrase: SecretString::from(passphrase),
             format,
             audit_logger,
             validator,
@@ -64,7 +65,7 @@ impl FileEncryptOperation {
             adapter,
             input_path: input.to_path_buf(),
             output_path: output.to_path_buf(),
-            passphrase: passphrase.to_string(),
+            passphrase: SecretString::from(passphrase),
             format,
             audit_logger,
             validator,
@@ -93,7 +94,7 @@ impl Operation for FileEncryptOperation {
         self.validator.validate_file_path(&self.output_path)?;
         
         // Validate passphrase security
-        self.validator.validate_passphrase_security(&self.passphrase)?;
+        self.validator.validate_passphrase_security(self.passphrase.expose_secret())?;
         
         // Check if output file already exists (warn but allow)
         if self.output_path.exists() {
@@ -110,7 +111,7 @@ impl Operation for FileEncryptOperation {
     fn execute(&self) -> AgeResult<()> {
         self.audit_logger.log_operation_start("encrypt", &self.input_path, &self.output_path)?;
         
-        let result = self.adapter.encrypt(&self.input_path, &self.output_path, &self.passphrase, self.format);
+        let result = self.adapter.encrypt(&self.input_path, &self.output_path, self.passphrase.expose_secret(), self.format);
         
         match &result {
             Ok(_) => {
@@ -156,7 +157,7 @@ pub struct FileDecryptOperation {
     adapter: Box<dyn AgeAdapter>,
     input_path: PathBuf,
     output_path: PathBuf,
-    passphrase: String,
+    passphrase: SecretString,
     audit_logger: AuditLogger,
     validator: SecurityValidator,
 }
@@ -176,7 +177,7 @@ impl FileDecryptOperation {
             adapter,
             input_path: input.to_path_buf(),
             output_path: output.to_path_buf(),
-            passphrase: passphrase.to_string(),
+            passphrase: SecretString::from(passphrase),
             audit_logger,
             validator,
         })
@@ -197,7 +198,7 @@ impl FileDecryptOperation {
             adapter,
             input_path: input.to_path_buf(),
             output_path: output.to_path_buf(),
-            passphrase: passphrase.to_string(),
+            passphrase: SecretString::from(passphrase),
             audit_logger,
             validator,
         })
@@ -229,7 +230,7 @@ impl Operation for FileDecryptOperation {
         self.validator.validate_file_path(&self.output_path)?;
         
         // Validate passphrase security
-        self.validator.validate_passphrase_security(&self.passphrase)?;
+        self.validator.validate_passphrase_security(self.passphrase.expose_secret())?;
         
         // Validate adapter health
         self.adapter.health_check()?;
@@ -241,7 +242,7 @@ impl Operation for FileDecryptOperation {
     fn execute(&self) -> AgeResult<()> {
         self.audit_logger.log_operation_start("decrypt", &self.input_path, &self.output_path)?;
         
-        let result = self.adapter.decrypt(&self.input_path, &self.output_path, &self.passphrase);
+        let result = self.adapter.decrypt(&self.input_path, &self.output_path, self.passphrase.expose_secret());
         
         match &result {
             Ok(_) => {
@@ -376,29 +377,20 @@ impl FileEncryption for FileOperationsManager {
     }
     
     fn is_encrypted_file(&self, path: &Path) -> AgeResult<bool> {
-        if !path.exists() {
-            return Ok(false);
-        }
-        
-        let content = fs::read(path)
-            .map_err(|e| AgeError::file_error("read", path.to_path_buf(), e))?;
-        
-        // Check for Age headers
-        Ok(content.starts_with(b"age-encryption.org/v1") || 
-           content.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----"))
+        // Only the first line is read, never the whole file
+        Ok(super::super::header::sniff(path)?.is_some())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::super::*;
-    use tempfile::{NamedTempFile, TempDir};
-    use super::super::adapter::ShellAdapter;
+    use super::*;
+    use tempfile::NamedTempFile;
+    use crate::encryption::age_automation::adapter::testing::MockAdapter;
     
     #[test]
     fn test_file_operations_manager_creation() {
-        let temp_dir = TempDir::new().unwrap();
-        let adapter = Box::new(ShellAdapter::new(temp_dir.path()).unwrap());
+        let adapter = Box::new(MockAdapter);
         let manager = FileOperationsManager::new(adapter);
         assert!(manager.is_ok());
     }
@@ -427,8 +419,7 @@ mod tests {
     }
     
     fn create_test_manager() -> FileOperationsManager {
-        let temp_dir = TempDir::new().unwrap();
-        let adapter = Box::new(ShellAdapter::new(temp_dir.path()).unwrap());
+        let adapter = Box::new(MockAdapter);
         FileOperationsManager::new(adapter).unwrap()
     }
 }
\ No newline at end of file
@@ -11,6 +11,7 @@ pub mod repository_operations;
 use std::path::Path;
 use super::error::AgeResult;
 use super::config::OutputFormat;
+use super::header::FileHeaderReport;
 
 /// Core operation trait defining common operation behavior
 pub trait Operation {
@@ -66,6 +67,8 @@ pub struct RepositoryStatus {
     pub encrypted_files: usize,
     pub unencrypted_files: usize,
     pub failed_files: Vec<String>,
+    /// Parsed age headers of encrypted files
+    pub file_headers: Vec<FileHeaderReport>,
 }
 
 impl RepositoryStatus {
@@ -75,6 +78,7 @@ impl RepositoryStatus {
             encrypted_files: 0,
             unencrypted_files: 0,
             failed_files: Vec::new(),
+            file_headers: Vec::new(),
         }
     }
     
@@ -12,6 +12,9 @@ use std::time::Instant;
 use super::super::adapter::AgeAdapter;
 use super::super::config::OutputFormat;
 use super::super::error::{AgeError, AgeResult};
+use super::super::lifecycle::edit;
+use super::super::lifecycle::rules::{NamingScheme, PathRules};
+use super::super::secret::SecretString;
 use super::super::security::{AuditLogger, SecurityValidator};
 use super::{Operation, RepositoryOperations, RepositoryStatus, OperationResult, FileEncryption};
 use super::file_operations::FileOperationsManager;
@@ -20,8 +23,9 @@ use super::file_operations::FileOperationsManager;
 pub struct RepositoryEncryptOperation {
     adapter: Box<dyn AgeAdapter>,
     repo_path: PathBuf,
-    passphrase: String,
+    passphrase: SecretString,
     format: OutputFormat,
+    rules: Option<PathRules>,
     audit_logger: AuditLogger,
     validator: SecurityValidator,
     file_manager: FileOperationsManager,
@@ -36,25 +40,46 @@ impl RepositoryEncryptOperation {
         format: OutputFormat,
     ) -> AgeResult<Self> {
         let audit_logger = AuditLogger::new(None)?;
-        let validator = SecurityValidator::new(true);
+        let validator = SecurityValidator::new(true).with_root(repo_path);
         let file_manager = FileOperationsManager::new(adapter.clone_box())?;
+        let rules = PathRules::load(repo_path)?;
         
         Ok(Self {
             adapter,
             repo_path: repo_path.to_path_buf(),
-            passphrase: passphrase.to_string(),
+            passphrase: SecretString::from(passphrase),
             format,
+            rules,
             audit_logger,
             validator,
             file_manager,
         })
     }
     
+    /// Output format, output path and recipients for a file, honouring the repository's path rules
+    fn output_for(&self, file_path: &Path) -> (OutputFormat, PathBuf, Option<&[String]>) {
+        let appended = file_path.with_extension(
+            format!("{}.age", file_path.extension().unwrap_or_default().to_string_lossy())
+        );
+        let rules = match &self.rules {
+            Some(rules) => rules,
+            None => return (self.format, appended, None),
+        };
+
+        let relative = file_path.strip_prefix(&self.repo_path).unwrap_or(file_path);
+        let resolved = rules.resolve(relative, self.format, NamingScheme::Append);
+        let output_path = match resolved.naming {
+            NamingScheme::Append => appended,
+            naming => rules.output_path(&self.repo_path, file_path, naming),
+        };
+        (resolved.format, output_path, resolved.recipients)
+    }
+
     /// Get all files in repository that can be encrypted
     fn discover_files(&self) -> AgeResult<Vec<PathBuf>> {
         let mut files = Vec::new();
         
-        fn visit_dir(dir: &Path, files: &mut Vec<PathBuf>) -> AgeResult<()> {
+        fn visit_dir(dir: &Path, validator: &SecurityValidator, files: &mut Vec<PathBuf>) -> AgeResult<()> {
             let entries = fs::read_dir(dir)
                 .map_err(|e| AgeError::file_error("read_dir", dir.to_path_buf(), e))?;
             
@@ -62,6 +87,9 @@ impl RepositoryEncryptOperation {
                 let entry = entry
                     .map_err(|e| AgeError::file_error("read_entry", dir.to_path_buf(), e))?;
                 let path = entry.path();
+                if !validator.check_discovered(&path)? {
+                    continue;
+                }
                 
                 if path.is_file() {
                     // Skip already encrypted files
@@ -82,13 +110,13 @@ impl RepositoryEncryptOperation {
                             continue;
                         }
                     }
-                    visit_dir(&path, files)?;
+                    visit_dir(&path, validator, files)?;
                 }
             }
             Ok(())
         }
         
-        visit_dir(&self.repo_path, &mut files)?;
+        visit_dir(&self.repo_path, &self.validator, &mut files)?;
         Ok(files)
     }
 }
@@ -114,7 +142,7 @@ impl Operation for RepositoryEncryptOperation {
         self.validator.validate_file_path(&self.repo_path)?;
         
         // Validate passphrase security
-        self.validator.validate_passphrase_security(&self.passphrase)?;
+        self.validator.validate_passphrase_security(self.passphrase.expose_secret())?;
         
         // Validate adapter health
         self.adapter.health_check()?;
@@ -135,11 +163,18 @@ impl Operation for RepositoryEncryptOperation {
         let mut failed = 0;
         
         for file_path in files {
-            let output_path = file_path.with_extension(
-                format!("{}.age", file_path.extension().unwrap_or_default().to_string_lossy())
-            );
+            let (format, output_path, recipients) = self.output_for(&file_path);
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| AgeError::file_error("create_dir", parent.to_path_buf(), e))?;
+            }
             
-            match self.file_manager.encrypt_file(&file_path, &output_path, &self.passphrase, self.format) {
+            let outcome = match recipients {
+                Some(recipients) => self.adapter.encrypt_to_recipients(&file_path, &output_path, recipients, format)
+                    .and_then(|_| edit::record_recipients(&output_path, None, Some(recipients))),
+                None => self.file_manager.encrypt_file(&file_path, &output_path, self.passphrase.expose_secret(), format),
+            };
+            match outcome {
                 Ok(_) => {
                     processed += 1;
                     self.audit_logger.log_info(&format!("Encrypted: {} -> {}", 
@@ -176,9 +211,7 @@ impl Operation for RepositoryEncryptOperation {
         let mut encrypted_count = 0;
         
         for file_path in files {
-            let output_path = file_path.with_extension(
-                format!("{}.age", file_path.extension().unwrap_or_default().to_string_lossy())
-            );
+            let (_, output_path, _) = self.output_for(&file_path);
             
             if output_path.exists() {
                 encrypted_count += 1;
@@ -194,7 +227,7 @@ impl Operation for RepositoryEncryptOperation {
 pub struct RepositoryDecryptOperation {
     adapter: Box<dyn AgeAdapter>,
     repo_path: PathBuf,
-    passphrase: String,
+    passphrase: SecretString,
     audit_logger: AuditLogger,
     validator: SecurityValidator,
     file_manager: FileOperationsManager,
@@ -208,13 +241,13 @@ impl RepositoryDecryptOperation {
         passphrase: &str,
     ) -> AgeResult<Self> {
         let audit_logger = AuditLogger::new(None)?;
-        let validator = SecurityValidator::new(true);
+        let validator = SecurityValidator::new(true).with_root(repo_path);
         let file_manager = FileOperationsManager::new(adapter.clone_box())?;
         
         Ok(Self {
             adapter,
             repo_path: repo_path.to_path_buf(),
-            passphrase: passphrase.to_string(),
+            passphrase: SecretString::from(passphrase),
             audit_logger,
             validator,
             file_manager,
@@ -225,7 +258,7 @@ impl RepositoryDecryptOperation {
     fn discover_encrypted_files(&self) -> AgeResult<Vec<PathBuf>> {
         let mut files = Vec::new();
         
-        fn visit_dir(dir: &Path, files: &mut Vec<PathBuf>) -> AgeResult<()> {
+        fn visit_dir(dir: &Path, validator: &SecurityValidator, files: &mut Vec<PathBuf>) -> AgeResult<()> {
             let entries = fs::read_dir(dir)
                 .map_err(|e| AgeError::file_error("read_dir", dir.to_path_buf(), e))?;
             
@@ -233,6 +266,9 @@ impl RepositoryDecryptOperation {
                 let entry = entry
                     .map_err(|e| AgeError::file_error("read_entry", dir.to_path_buf(), e))?;
                 let path = entry.path();
+                if !validator.check_discovered(&path)? {
+                    continue;
+                }
                 
                 if path.is_file() {
                     // Check if file is encrypted
@@ -246,7 +282,7 @@ impl RepositoryDecryptOperation {
                     if let Some(name) = path.file_name() {
                         let name_str = name.to_string_lossy();
                         if !name_str.starts_with('.') {
-                            visit_dir(&path, files)?;
+                            visit_dir(&path, validator, files)?;
                         }
                     }
                 }
@@ -254,7 +290,7 @@ impl RepositoryDecryptOperation {
             Ok(())
         }
         
-        visit_dir(&self.repo_path, &mut files)?;
+        visit_dir(&self.repo_path, &self.validator, &mut files)?;
         Ok(files)
     }
 }
@@ -280,7 +316,7 @@ impl Operation for RepositoryDecryptOperation {
         self.validator.validate_file_path(&self.repo_path)?;
         
         // Validate passphrase security
-        self.validator.validate_passphrase_security(&self.passphrase)?;
+        self.validator.validate_passphrase_security(self.passphrase.expose_secret())?;
         
         // Validate adapter health
         self.adapter.health_check()?;
@@ -304,7 +340,7 @@ impl Operation for RepositoryDecryptOperation {
             // Remove .age extension for output
             let output_path = file_path.with_extension("");
             
-            match self.file_manager.decrypt_file(&file_path, &output_path, &self.passphrase) {
+            match self.file_manager.decrypt_file(&file_path, &output_path, self.passphrase.expose_secret()) {
                 Ok(_) => {
                     processed += 1;
                     self.audit_logger.log_info(&format!("Decrypted: {} -> {}", 
@@ -485,14 +521,12 @@ impl RepositoryOperations for RepositoryOperationsManager {
 
 #[cfg(test)]
 mod tests {
-    use super::super::*;
-    use tempfile::TempDir;
-    use super::super::adapter::ShellAdapter;
+    use super::*;
+    use crate::encryption::age_automation::adapter::testing::MockAdapter;
     
     #[test]
     fn test_repository_operations_manager_creation() {
-        let temp_dir = TempDir::new().unwrap();
-        let adapter = Box::new(ShellAdapter::new(temp_dir.path()).unwrap());
+        let adapter = Box::new(MockAdapter);
         let manager = RepositoryOperationsManager::new(adapter);
         assert!(manager.is_ok());
     }
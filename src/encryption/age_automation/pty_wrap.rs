@@ -10,6 +10,7 @@ use std::io::{Write, Read};
 use portable_pty::*;
 use tempfile::TempDir;
 use super::error::{AgeError, AgeResult};
+use super::secret::SecretString;
 use super::config::OutputFormat;
 
 /// PTY-based Age automator - reliable and robust
@@ -98,7 +99,7 @@ impl PtyAgeAutomator {
             })?;
 
         // Handle age interaction with timeout and proper process monitoring
-        let passphrase_clone = passphrase.to_string();
+        let passphrase_clone = SecretString::from(passphrase);
         let timeout_duration = self.timeout;
         let automation_thread = thread::spawn(move || -> AgeResult<()> {
             let mut buffer = [0u8; 1024];
@@ -133,7 +134,7 @@ impl PtyAgeAutomator {
                         if output_buffer.contains("Enter passphrase") ||
                            output_buffer.contains("passphrase:") {
                             // Send passphrase
-                            writer.write_all(passphrase_clone.as_bytes())
+                            writer.write_all(passphrase_clone.expose_secret().as_bytes())
                                 .map_err(|e| AgeError::ProcessExecutionFailed {
                                     command: "pty_write_passphrase".to_string(),
                                     exit_code: None,
@@ -153,7 +154,7 @@ impl PtyAgeAutomator {
                         if output_buffer.contains("Confirm passphrase") ||
                            output_buffer.contains("confirm:") {
                             // Send confirmation
-                            writer.write_all(passphrase_clone.as_bytes())
+                            writer.write_all(passphrase_clone.expose_secret().as_bytes())
                                 .map_err(|e| AgeError::ProcessExecutionFailed {
                                     command: "pty_write_confirm".to_string(),
                                     exit_code: None,
@@ -285,7 +286,7 @@ impl PtyAgeAutomator {
             })?;
 
         // Handle decryption interaction with timeout
-        let passphrase_clone = passphrase.to_string();
+        let passphrase_clone = SecretString::from(passphrase);
         let timeout_duration = self.timeout;
         let automation_thread = thread::spawn(move || -> AgeResult<()> {
             let mut buffer = [0u8; 1024];
@@ -311,7 +312,7 @@ impl PtyAgeAutomator {
                         // Look for passphrase prompt
                         if output_buffer.contains("Enter passphrase") ||
                            output_buffer.contains("passphrase:") {
-                            writer.write_all(passphrase_clone.as_bytes())
+                            writer.write_all(passphrase_clone.expose_secret().as_bytes())
                                 .map_err(|e| AgeError::ProcessExecutionFailed {
                                     command: "pty_write_passphrase".to_string(),
                                     exit_code: None,
@@ -379,6 +380,135 @@ impl PtyAgeAutomator {
         }
     }
 
+    /// Decrypt file into memory: age writes the plaintext (`-o -`) to a pipe read by this
+    /// process, while the passphrase prompt runs on a separate PTY as age's controlling
+    /// terminal, so the plaintext never passes through a file or the terminal
+    pub fn decrypt_to_memory(&self, input: &Path, passphrase: &str) -> AgeResult<Vec<u8>> {
+        use std::os::fd::{FromRawFd, OwnedFd};
+        use std::os::unix::process::CommandExt;
+        use std::process::{Command, Stdio};
+
+        if !input.exists() {
+            return Err(AgeError::file_error("read", input.to_path_buf(),
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Input file not found")));
+        }
+
+        let pty_error = |stage: &str, e: std::io::Error| AgeError::ProcessExecutionFailed {
+            command: stage.to_string(),
+            exit_code: None,
+            stderr: format!("Failed to create PTY: {}", e),
+        };
+        let (mut master_fd, mut slave_fd) = (-1, -1);
+        // SAFETY: openpty writes two fresh descriptors, which are owned below
+        let opened = unsafe {
+            libc::openpty(&mut master_fd, &mut slave_fd, std::ptr::null_mut(), std::ptr::null(), std::ptr::null())
+        };
+        if opened != 0 {
+            return Err(pty_error("create_pty", std::io::Error::last_os_error()));
+        }
+        // SAFETY: both descriptors were just opened and are not owned elsewhere
+        let (master, slave) = unsafe { (OwnedFd::from_raw_fd(master_fd), OwnedFd::from_raw_fd(slave_fd)) };
+        let slave_stderr = slave.try_clone().map_err(|e| pty_error("create_pty", e))?;
+
+        let mut cmd = Command::new("age");
+        cmd.arg("-d").arg("-o").arg("-").arg(input)
+            .stdin(Stdio::from(slave))
+            .stderr(Stdio::from(slave_stderr))
+            .stdout(Stdio::piped());
+        // SAFETY: only async-signal-safe calls; make the PTY (now stdin) the controlling
+        // terminal so age prompts on it through /dev/tty
+        unsafe {
+            cmd.pre_exec(|| {
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let mut child = cmd.spawn()
+            .map_err(|e| AgeError::ProcessExecutionFailed {
+                command: "age".to_string(),
+                exit_code: None,
+                stderr: format!("Failed to spawn age: {}", e),
+            })?;
+        // Drop the parent's copies of the slave so the PTY reports EOF once age exits
+        drop(cmd);
+
+        let mut terminal = std::fs::File::from(master);
+        let passphrase_clone = SecretString::from(passphrase);
+        let prompt_thread = thread::spawn(move || -> AgeResult<()> {
+            let mut buffer = [0u8; 1024];
+            let mut output_buffer = String::new();
+            loop {
+                match terminal.read(&mut buffer) {
+                    Ok(0) => return Ok(()),
+                    Ok(n) => {
+                        output_buffer.push_str(&String::from_utf8_lossy(&buffer[..n]));
+                        if output_buffer.contains("Enter passphrase") || output_buffer.contains("passphrase:") {
+                            terminal.write_all(passphrase_clone.expose_secret().as_bytes())
+                                .and_then(|_| terminal.write_all(b"\n"))
+                                .map_err(|e| AgeError::ProcessExecutionFailed {
+                                    command: "pty_write_passphrase".to_string(),
+                                    exit_code: None,
+                                    stderr: format!("Failed to write passphrase: {}", e),
+                                })?;
+                            output_buffer.clear();
+                        }
+                    }
+                    // EIO once the last slave descriptor closes
+                    Err(_) => return Ok(()),
+                }
+            }
+        });
+
+        let timeout_duration = self.timeout;
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, receiver) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            let mut plaintext = Vec::new();
+            let _ = sender.send(stdout.read_to_end(&mut plaintext).map(|_| plaintext));
+        });
+        let plaintext = match receiver.recv_timeout(timeout_duration) {
+            Ok(read) => read.map_err(|e| AgeError::ProcessExecutionFailed {
+                command: "age_stdout".to_string(),
+                exit_code: None,
+                stderr: format!("Failed to read decrypted output: {}", e),
+            }),
+            Err(_) => {
+                let _ = child.kill();
+                Err(AgeError::ProcessExecutionFailed {
+                    command: "pty_automation_timeout".to_string(),
+                    exit_code: None,
+                    stderr: format!("PTY automation timed out after {:?}", timeout_duration),
+                })
+            }
+        };
+
+        let exit_status = child.wait()
+            .map_err(|e| AgeError::ProcessExecutionFailed {
+                command: "age_wait".to_string(),
+                exit_code: None,
+                stderr: format!("Failed to wait for age process: {}", e),
+            })?;
+        prompt_thread.join()
+            .map_err(|_| AgeError::ProcessExecutionFailed {
+                command: "automation_thread".to_string(),
+                exit_code: None,
+                stderr: "Automation thread panicked".to_string(),
+            })??;
+        let plaintext = plaintext?;
+
+        if exit_status.success() {
+            Ok(plaintext)
+        } else {
+            Err(AgeError::DecryptionFailed {
+                input: input.to_path_buf(),
+                output: std::path::PathBuf::from("-"),
+                reason: format!("Age decryption failed with exit status: {:?}", exit_status),
+            })
+        }
+    }
+
     /// Check if Age binary is available
     pub fn check_age_binary(&self) -> AgeResult<()> {
         let pty_system = native_pty_system();
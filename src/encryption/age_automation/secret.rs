@@ -0,0 +1,319 @@
+//! Secret Types - Passphrases and private keys that wipe themselves
+//!
+//! `SecretString` and `SecretBytes` hold passphrases and private key material. Their
+//! buffers are zeroed on drop, `Debug` prints a redaction marker, and they do not
+//! implement `Serialize`: a field holding one must opt in explicitly through
+//! [`serde_exposed`] or [`serde_exposed_string`]. Buffers are `mlock`ed when they are
+//! created so they never reach swap; where the OS refuses, the secret still works.
+//!
+//! While any secret is alive the process has core dumps disabled; the previous limit
+//! and dumpable flag are restored once the last secret is dropped.
+//!
+//! Security Guardian: Edgar - Secrets leave no residue
+
+use std::fmt;
+use std::sync::Mutex;
+use zeroize::Zeroize;
+
+pub use zeroize::Zeroizing;
+
+/// Process-wide core dump suppression shared by all live secrets
+struct CoreDumpState {
+    live_secrets: usize,
+    saved: Option<SavedCoreDumps>,
+}
+
+/// Core dump settings in force before the first live secret
+struct SavedCoreDumps {
+    limit: Option<libc::rlimit>,
+    dumpable: Option<libc::c_int>,
+}
+
+static CORE_DUMPS: Mutex<CoreDumpState> = Mutex::new(CoreDumpState { live_secrets: 0, saved: None });
+
+/// Register a live secret, disabling core dumps for the first one
+fn acquire_core_dump_guard() {
+    let mut state = CORE_DUMPS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if state.live_secrets == 0 {
+        state.saved = Some(disable_core_dumps());
+    }
+    state.live_secrets += 1;
+}
+
+/// Release a live secret, restoring core dumps after the last one
+fn release_core_dump_guard() {
+    let mut state = CORE_DUMPS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    state.live_secrets = state.live_secrets.saturating_sub(1);
+    if state.live_secrets == 0 {
+        if let Some(saved) = state.saved.take() {
+            restore_core_dumps(&saved);
+        }
+    }
+}
+
+/// Set the core size limit to zero and mark the process non-dumpable
+///
+/// Returns the previous limit and dumpable flag so they can be restored.
+fn disable_core_dumps() -> SavedCoreDumps {
+    let mut previous = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    // SAFETY: getrlimit/setrlimit only read and write the rlimit structs passed in
+    let limit = unsafe {
+        libc::getrlimit(libc::RLIMIT_CORE, &mut previous) == 0 && {
+            let disabled = libc::rlimit { rlim_cur: 0, rlim_max: previous.rlim_max };
+            libc::setrlimit(libc::RLIMIT_CORE, &disabled) == 0
+        }
+    };
+
+    // Piped core handlers ignore RLIMIT_CORE, so also drop the dumpable flag
+    #[cfg(target_os = "linux")]
+    // SAFETY: PR_GET_DUMPABLE and PR_SET_DUMPABLE take plain integer arguments
+    let dumpable = unsafe {
+        let dumpable = libc::prctl(libc::PR_GET_DUMPABLE, 0, 0, 0, 0);
+        (dumpable >= 0 && libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) == 0).then_some(dumpable)
+    };
+    #[cfg(not(target_os = "linux"))]
+    let dumpable = None;
+
+    SavedCoreDumps { limit: limit.then_some(previous), dumpable }
+}
+
+/// Put back the settings `disable_core_dumps` replaced, and only those
+fn restore_core_dumps(saved: &SavedCoreDumps) {
+    // SAFETY: see disable_core_dumps
+    unsafe {
+        if let Some(limit) = &saved.limit {
+            libc::setrlimit(libc::RLIMIT_CORE, limit);
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(dumpable) = saved.dumpable {
+            libc::prctl(libc::PR_SET_DUMPABLE, dumpable, 0, 0, 0);
+        }
+    }
+}
+
+/// True while core dumps are suppressed because secrets are alive
+pub fn core_dumps_disabled() -> bool {
+    CORE_DUMPS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).live_secrets > 0
+}
+
+/// Owned secret buffer shared by the string and byte variants
+struct SecretBuffer {
+    bytes: Vec<u8>,
+    locked: bool,
+}
+
+impl SecretBuffer {
+    /// Take ownership of `bytes` and lock them into memory where the OS allows it
+    fn new(bytes: Vec<u8>) -> Self {
+        acquire_core_dump_guard();
+        let mut buffer = Self { bytes, locked: false };
+        buffer.mlock();
+        buffer
+    }
+
+    fn mlock(&mut self) -> bool {
+        if self.locked || self.bytes.capacity() == 0 {
+            return self.locked;
+        }
+        // SAFETY: the range covers the vector's own allocation, which never moves
+        // because the buffer is not resized after construction
+        self.locked = unsafe { libc::mlock(self.bytes.as_ptr().cast(), self.bytes.capacity()) } == 0;
+        self.locked
+    }
+}
+
+impl Clone for SecretBuffer {
+    fn clone(&self) -> Self {
+        Self::new(self.bytes.clone())
+    }
+}
+
+impl Drop for SecretBuffer {
+    fn drop(&mut self) {
+        // Zero the whole allocation, not just the initialized length
+        self.bytes.zeroize();
+        if self.locked {
+            // SAFETY: unlocks exactly the range locked in mlock
+            unsafe {
+                libc::munlock(self.bytes.as_ptr().cast(), self.bytes.capacity());
+            }
+        }
+        release_core_dump_guard();
+    }
+}
+
+/// A passphrase or other textual secret
+#[derive(Clone)]
+pub struct SecretString(SecretBuffer);
+
+impl SecretString {
+    pub fn new(secret: String) -> Self {
+        Self(SecretBuffer::new(secret.into_bytes()))
+    }
+
+    /// Borrow the secret; keep the borrow short and never copy it into an owned value
+    pub fn expose_secret(&self) -> &str {
+        // Only ever constructed from a String, so the bytes are valid UTF-8
+        std::str::from_utf8(&self.0.bytes).unwrap_or_default()
+    }
+
+    /// Lock the buffer into memory so it is never swapped; false if the OS refused
+    ///
+    /// Secrets are locked when created, so this only retries a refused lock.
+    pub fn mlock(&mut self) -> bool {
+        self.0.mlock()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.bytes.is_empty()
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self::new(secret.to_string())
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString([REDACTED])")
+    }
+}
+
+/// Private key bytes or other binary secrets
+#[derive(Clone)]
+pub struct SecretBytes(SecretBuffer);
+
+impl SecretBytes {
+    pub fn new(secret: Vec<u8>) -> Self {
+        Self(SecretBuffer::new(secret))
+    }
+
+    /// Borrow the secret; keep the borrow short and never copy it into an owned value
+    pub fn expose_secret(&self) -> &[u8] {
+        &self.0.bytes
+    }
+
+    /// Lock the buffer into memory so it is never swapped; false if the OS refused
+    ///
+    /// Secrets are locked when created, so this only retries a refused lock.
+    pub fn mlock(&mut self) -> bool {
+        self.0.mlock()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.bytes.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(secret: Vec<u8>) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretBytes([REDACTED; {}])", self.len())
+    }
+}
+
+/// Explicit serde opt-in for `Option<SecretBytes>` fields that must be persisted
+///
+/// Use with `#[serde(with = "serde_exposed")]` on fields whose serialized form is
+/// itself protected, such as key material that is encrypted before it is written.
+pub mod serde_exposed {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use super::SecretBytes;
+
+    pub fn serialize<S: Serializer>(secret: &Option<SecretBytes>, serializer: S) -> Result<S::Ok, S::Error> {
+        secret.as_ref().map(SecretBytes::expose_secret).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<SecretBytes>, D::Error> {
+        Ok(Option::<Vec<u8>>::deserialize(deserializer)?.map(SecretBytes::new))
+    }
+}
+
+/// Explicit serde opt-in for `SecretString` fields that must be persisted
+///
+/// Use with `#[serde(with = "serde_exposed_string")]` on fields of documents that are
+/// only ever written encrypted, such as the secrets vault.
+pub mod serde_exposed_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use super::SecretString;
+
+    pub fn serialize<S: Serializer>(secret: &SecretString, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(secret.expose_secret())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SecretString, D::Error> {
+        Ok(SecretString::new(String::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_is_redacted() {
+        let passphrase = SecretString::from("hunter2-correct-horse");
+        let key = SecretBytes::new(b"AGE-SECRET-KEY-1".to_vec());
+        assert_eq!(format!("{:?}", passphrase), "SecretString([REDACTED])");
+        assert!(!format!("{:?}", key).contains("AGE"));
+        assert_eq!(passphrase.expose_secret(), "hunter2-correct-horse");
+    }
+
+    #[test]
+    fn test_core_dumps_disabled_while_secrets_live() {
+        let mut secret = SecretBytes::new(vec![7u8; 64]);
+        assert!(core_dumps_disabled());
+        // mlock may be refused under a tight RLIMIT_MEMLOCK; a created secret is already
+        // locked when it was allowed, and retrying must not fail otherwise
+        let locked = secret.0.locked;
+        assert_eq!(locked, secret.mlock());
+        assert_eq!(secret.clone().expose_secret(), &[7u8; 64][..]);
+
+        let mut limit = libc::rlimit { rlim_cur: 1, rlim_max: 1 };
+        unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut limit) };
+        assert_eq!(limit.rlim_cur, 0);
+        #[cfg(target_os = "linux")]
+        assert_eq!(unsafe { libc::prctl(libc::PR_GET_DUMPABLE, 0, 0, 0, 0) }, 0);
+    }
+
+    #[test]
+    fn test_restore_puts_back_the_saved_dumpable_flag() {
+        // A process that was already non-dumpable must stay that way after the last
+        // secret is gone, rather than being forced dumpable
+        let saved = SavedCoreDumps { limit: None, dumpable: Some(0) };
+        let _secret = SecretBytes::new(vec![1u8; 8]);
+        restore_core_dumps(&saved);
+        #[cfg(target_os = "linux")]
+        assert_eq!(unsafe { libc::prctl(libc::PR_GET_DUMPABLE, 0, 0, 0, 0) }, 0);
+    }
+
+    #[test]
+    fn test_serialization_requires_opt_in() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Wrapped {
+            #[serde(with = "serde_exposed")]
+            key: Option<SecretBytes>,
+        }
+
+        let json = serde_json::to_string(&Wrapped { key: Some(SecretBytes::new(vec![1, 2])) }).unwrap();
+        let back: Wrapped = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.key.unwrap().expose_secret(), &[1, 2]);
+    }
+}
@@ -7,8 +7,10 @@
 
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::path::{Component, Path, PathBuf};
 use chrono::{DateTime, Utc};
+use super::config::{AgeConfig, SecurityLevel, SymlinkPolicy, DEFAULT_DENIED_LOCATIONS};
 use super::error::{AgeError, AgeResult};
 use super::operations::{OperationResult, RepositoryStatus};
 
@@ -157,42 +159,149 @@ impl AuditLogger {
 }
 
 /// Security validator for operations and inputs
+///
+/// Paths are judged by where they really point: they are canonicalized, symlinks
+/// included, then confined to the declared repository root and checked against the
+/// denied and allowed system locations. Relative paths without a declared root are
+/// confined to the working directory.
 pub struct SecurityValidator {
     strict_mode: bool,
+    root: Option<PathBuf>,
+    symlink_policy: SymlinkPolicy,
+    denied_locations: Vec<PathBuf>,
+    allowed_locations: Vec<PathBuf>,
 }
 
 impl SecurityValidator {
     /// Create new security validator
     pub fn new(strict_mode: bool) -> Self {
-        Self { strict_mode }
+        Self {
+            strict_mode,
+            root: None,
+            symlink_policy: SymlinkPolicy::default(),
+            denied_locations: DEFAULT_DENIED_LOCATIONS.iter().map(PathBuf::from).collect(),
+            allowed_locations: Vec::new(),
+        }
+    }
+
+    /// Create a validator with the configured symlink policy and system locations
+    pub fn from_config(config: &AgeConfig) -> Self {
+        Self::new(config.security_validation && config.security_level != SecurityLevel::Basic)
+            .with_symlink_policy(config.symlink_policy)
+            .with_locations(
+                config.denied_locations.iter().map(PathBuf::from).collect(),
+                config.allowed_locations.iter().map(PathBuf::from).collect(),
+            )
+    }
+
+    /// Confine validated paths to a repository root
+    pub fn with_root(mut self, root: &Path) -> Self {
+        self.root = Some(root.to_path_buf());
+        self
+    }
+
+    /// Set symlink handling during discovery
+    pub fn with_symlink_policy(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlink_policy = policy;
+        self
+    }
+
+    /// Replace the denied and allowed system locations
+    pub fn with_locations(mut self, denied: Vec<PathBuf>, allowed: Vec<PathBuf>) -> Self {
+        self.denied_locations = denied;
+        self.allowed_locations = allowed;
+        self
     }
 
     /// Validate file path for security issues
     pub fn validate_file_path(&self, path: &Path) -> AgeResult<()> {
-        let path_str = path.to_string_lossy();
+        self.resolve(path).map(|_| ())
+    }
+
+    /// Canonical form of a path after confinement and location checks
+    pub fn resolve(&self, path: &Path) -> AgeResult<PathBuf> {
+        let resolved = canonicalize_lenient(path)
+            .map_err(|e| AgeError::file_error("canonicalize", path.to_path_buf(), e))?;
 
-        // Check for path traversal attempts
-        if path_str.contains("..") {
+        let root = match &self.root {
+            Some(root) => Some(root.clone()),
+            None if path.is_relative() => Some(std::env::current_dir()
+                .map_err(|e| AgeError::file_error("current_dir", path.to_path_buf(), e))?),
+            None => None,
+        };
+        if let Some(root) = root {
+            let root = canonicalize_lenient(&root)
+                .map_err(|e| AgeError::file_error("canonicalize", root.clone(), e))?;
+            if !resolved.starts_with(&root) {
+                return Err(AgeError::SecurityValidationFailed {
+                    validation_type: "path_confinement".to_string(),
+                    details: format!("{} resolves to {}, outside {}", path.display(), resolved.display(), root.display()),
+                });
+            }
+        }
+
+        if self.strict_mode && self.is_denied(&resolved) {
             return Err(AgeError::SecurityValidationFailed {
-                validation_type: "path_traversal".to_string(),
-                details: format!("Path contains '..' traversal: {}", path_str),
+                validation_type: "sensitive_path".to_string(),
+                details: format!("Access to sensitive path: {}", resolved.display()),
             });
         }
 
-        // Check for absolute paths to sensitive locations
-        if self.strict_mode {
-            let sensitive_paths = ["/etc", "/proc", "/sys", "/dev"];
-            for sensitive in &sensitive_paths {
-                if path_str.starts_with(sensitive) {
-                    return Err(AgeError::SecurityValidationFailed {
-                        validation_type: "sensitive_path".to_string(),
-                        details: format!("Access to sensitive path: {}", path_str),
-                    });
-                }
+        Ok(resolved)
+    }
+
+    /// Whether discovery should include a directory entry, applying the symlink policy
+    ///
+    /// Followed links must still resolve inside the root; escaping links are an error.
+    pub fn check_discovered(&self, path: &Path) -> AgeResult<bool> {
+        let metadata = std::fs::symlink_metadata(path)
+            .map_err(|e| AgeError::file_error("stat", path.to_path_buf(), e))?;
+        if metadata.file_type().is_symlink() {
+            match self.symlink_policy {
+                SymlinkPolicy::Skip => return Ok(false),
+                SymlinkPolicy::Refuse => return Err(symlink_refused(path)),
+                SymlinkPolicy::Follow => {}
             }
         }
+        self.resolve(path).map(|_| true)
+    }
 
-        Ok(())
+    /// Open a validated file for reading, failing if it changed after validation
+    ///
+    /// The canonical path is opened without following a final symlink, and the opened
+    /// file must be the same inode that was validated and still resolve to the same path.
+    pub fn open_confined(&self, path: &Path) -> AgeResult<std::fs::File> {
+        let is_link = std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink());
+        if is_link && self.symlink_policy != SymlinkPolicy::Follow {
+            return Err(symlink_refused(path));
+        }
+
+        let resolved = self.resolve(path)?;
+        let validated = std::fs::metadata(&resolved)
+            .map_err(|e| AgeError::file_error("stat", resolved.clone(), e))?;
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(&resolved)
+            .map_err(|e| AgeError::file_error("open", resolved.clone(), e))?;
+        let opened = file.metadata().map_err(|e| AgeError::file_error("stat", resolved.clone(), e))?;
+
+        if (opened.dev(), opened.ino()) != (validated.dev(), validated.ino()) || self.resolve(path)? != resolved {
+            return Err(AgeError::SecurityValidationFailed {
+                validation_type: "toctou".to_string(),
+                details: format!("{} changed between validation and open", path.display()),
+            });
+        }
+        Ok(file)
+    }
+
+    /// True for paths inside a denied location and not inside an allowed one
+    fn is_denied(&self, resolved: &Path) -> bool {
+        let within = |locations: &[PathBuf]| locations.iter().any(|location| {
+            resolved.starts_with(location)
+                || canonicalize_lenient(location).is_ok_and(|canonical| resolved.starts_with(canonical))
+        });
+        within(&self.denied_locations) && !within(&self.allowed_locations)
     }
 
     /// Validate passphrase for security requirements
@@ -214,10 +323,47 @@ impl SecurityValidator {
     }
 }
 
+/// Canonicalize a path that may not exist yet
+///
+/// Existing prefixes are canonicalized, resolving symlinks, and `..` is applied to the
+/// resolved path the way the kernel would. Missing trailing components are kept as given.
+fn canonicalize_lenient(path: &Path) -> std::io::Result<PathBuf> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+
+    let mut resolved = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => resolved.push(component.as_os_str()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(name) => {
+                resolved.push(name);
+                if std::fs::symlink_metadata(&resolved).is_ok() {
+                    resolved = resolved.canonicalize()?;
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+fn symlink_refused(path: &Path) -> AgeError {
+    AgeError::SecurityValidationFailed {
+        validation_type: "symlink_refused".to_string(),
+        details: format!("Symlink not allowed by policy: {}", path.display()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::NamedTempFile;
+    use tempfile::{NamedTempFile, TempDir};
 
     #[test]
     fn test_audit_logger_creation() {
@@ -246,4 +392,54 @@ mod tests {
         assert!(validator.validate_passphrase_security("password$(rm -rf /)").is_err());
         assert!(validator.validate_passphrase_security("validpassword").is_ok());
     }
+
+    #[test]
+    fn test_confinement_uses_canonical_paths() {
+        let repo = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        std::fs::write(repo.path().join("a..b"), "ok").unwrap();
+        std::fs::create_dir(repo.path().join("sub")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), repo.path().join("escape")).unwrap();
+        let validator = SecurityValidator::new(true).with_root(repo.path());
+
+        assert!(validator.validate_file_path(&repo.path().join("a..b")).is_ok());
+        assert!(validator.validate_file_path(&repo.path().join("sub/../a..b")).is_ok());
+        assert!(validator.validate_file_path(&repo.path().join("sub/new.txt")).is_ok());
+        assert!(validator.validate_file_path(&repo.path().join("../elsewhere")).is_err());
+        assert!(validator.validate_file_path(&repo.path().join("escape/file")).is_err());
+    }
+
+    #[test]
+    fn test_symlink_policy_during_discovery() {
+        let repo = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        std::fs::write(repo.path().join("target.txt"), "data").unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "data").unwrap();
+        std::os::unix::fs::symlink(repo.path().join("target.txt"), repo.path().join("inner")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("secret.txt"), repo.path().join("outer")).unwrap();
+        let validator = |policy| SecurityValidator::new(true).with_root(repo.path()).with_symlink_policy(policy);
+
+        assert!(validator(SymlinkPolicy::Skip).check_discovered(&repo.path().join("target.txt")).unwrap());
+        assert!(!validator(SymlinkPolicy::Skip).check_discovered(&repo.path().join("inner")).unwrap());
+        assert!(validator(SymlinkPolicy::Refuse).check_discovered(&repo.path().join("inner")).is_err());
+        assert!(validator(SymlinkPolicy::Follow).check_discovered(&repo.path().join("inner")).unwrap());
+        assert!(validator(SymlinkPolicy::Follow).check_discovered(&repo.path().join("outer")).is_err());
+
+        assert!(validator(SymlinkPolicy::Skip).open_confined(&repo.path().join("target.txt")).is_ok());
+        assert!(validator(SymlinkPolicy::Skip).open_confined(&repo.path().join("inner")).is_err());
+    }
+
+    #[test]
+    fn test_location_allow_and_deny_lists() {
+        let base = TempDir::new().unwrap();
+        std::fs::create_dir(base.path().join("shared")).unwrap();
+        let validator = SecurityValidator::new(true)
+            .with_locations(vec![base.path().to_path_buf()], vec![base.path().join("shared")]);
+
+        assert!(validator.validate_file_path(&base.path().join("private.key")).is_err());
+        assert!(validator.validate_file_path(&base.path().join("shared/notes.txt")).is_ok());
+        assert!(SecurityValidator::new(true).validate_file_path(Path::new("/etc/passwd")).is_err());
+        assert!(SecurityValidator::new(true).validate_file_path(Path::new("/etcetera/file")).is_ok());
+        assert!(SecurityValidator::new(false).validate_file_path(Path::new("/etc/passwd")).is_ok());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,547 @@
+//! Secure Temp Area - Private scratch space for keys and decrypted material
+//!
+//! Every temporary file padlock writes that holds a secret (age identity files,
+//! recipient lists, decrypted plaintext) lives in a per-use 0700 directory, preferably on
+//! RAM-backed storage (`/dev/shm`, then `$XDG_RUNTIME_DIR`), honouring
+//! `AgeConfig.temp_dir_override`. Files are created 0600 and never reopened by name.
+//! Areas sit inside a per-user `padlock-<uid>` directory that must be a real 0700
+//! directory owned by the caller, so shared roots like `/dev/shm` cannot be used to
+//! plant entries for another user's sweep.
+//!
+//! Areas are wiped (overwritten, then removed) on drop, by a panic hook for the
+//! panicking thread's areas, and on SIGINT/SIGTERM before the signal is re-raised.
+//! Areas left behind by crashed or SIGKILLed processes are swept the first time a
+//! process creates an area under the same root. Sweeping and wiping never follow
+//! symlinks and only touch entries owned by the current uid.
+//!
+//! Security Guardian: Edgar - Secrets never touch shared temp space
+
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Mutex, Once};
+use std::thread::{self, ThreadId};
+
+use super::config::AgeConfig;
+use super::error::{AgeError, AgeResult};
+
+/// Name prefix of temp areas; the owning process id follows it
+pub const TEMP_AREA_PREFIX: &str = "padlock-tmp-";
+
+/// Name prefix every padlock area shares, whatever kind of area it is
+const AREA_NAME_PREFIX: &str = "padlock-";
+
+/// Live areas with the thread that created them
+static LIVE_AREAS: Mutex<Vec<(PathBuf, ThreadId)>> = Mutex::new(Vec::new());
+
+/// Roots already swept by this process
+static SWEPT_ROOTS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+static CLEANUP_HOOKS: Once = Once::new();
+
+/// Write end of the pipe the signal handler uses to wake the cleanup thread
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// Preferred location for secret material: RAM-backed when available
+pub fn private_temp_root() -> PathBuf {
+    let shm = Path::new("/dev/shm");
+    if shm.is_dir() && !fs::metadata(shm).map(|m| m.permissions().readonly()).unwrap_or(true) {
+        return shm.to_path_buf();
+    }
+
+    if let Some(runtime_dir) = std::env::var_os("XDG_RUNTIME_DIR") {
+        let runtime_dir = PathBuf::from(runtime_dir);
+        if runtime_dir.is_dir() {
+            return runtime_dir;
+        }
+    }
+
+    std::env::temp_dir()
+}
+
+/// Whether `path` lives on tmpfs or ramfs, so files under it never reach a disk
+pub fn is_memory_backed(path: &Path) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+            return false;
+        };
+        // SAFETY: statfs only writes into the zeroed struct it is given
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+            return false;
+        }
+        // RAMFS_MAGIC from linux/magic.h; libc does not export it
+        const RAMFS_MAGIC: i64 = 0x858458f6;
+        // f_type and the magic constants differ in width between targets
+        #[allow(clippy::unnecessary_cast)]
+        let (kind, tmpfs) = (stat.f_type as i64, libc::TMPFS_MAGIC as i64);
+        kind == tmpfs || kind == RAMFS_MAGIC
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// Temp root from the configured override, falling back to the private root
+pub fn temp_root(config: &AgeConfig) -> PathBuf {
+    config.temp_dir_override.as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(private_temp_root)
+}
+
+/// Private 0700 directory for secret files, wiped on drop
+#[derive(Debug)]
+pub struct SecureTempArea {
+    dir: PathBuf,
+}
+
+impl SecureTempArea {
+    /// Create an area under the private temp root
+    pub fn new() -> AgeResult<Self> {
+        Self::create_in(&private_temp_root())
+    }
+
+    /// Create an area under the configured temp root
+    pub fn from_config(config: &AgeConfig) -> AgeResult<Self> {
+        Self::create_in(&temp_root(config))
+    }
+
+    /// Create an area under `root`
+    pub fn create_in(root: &Path) -> AgeResult<Self> {
+        Self::create_with_prefix(root, TEMP_AREA_PREFIX)
+    }
+
+    /// Create an area named `<prefix><pid>-<random>` in the per-user directory under `root`
+    pub(crate) fn create_with_prefix(root: &Path, prefix: &str) -> AgeResult<Self> {
+        CLEANUP_HOOKS.call_once(|| {
+            install_panic_cleanup();
+            install_signal_cleanup();
+        });
+        let user_dir = user_root(root)?;
+        sweep_once(root);
+
+        let dir = user_dir.join(format!("{}{}-{:016x}", prefix, std::process::id(), rand::random::<u64>()));
+        fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&dir)
+            .map_err(|e| AgeError::TemporaryResourceError {
+                resource_type: "temp_area".to_string(),
+                operation: "create".to_string(),
+                reason: format!("{}: {}", dir.display(), e),
+            })?;
+
+        lock_registry().push((dir.clone(), thread::current().id()));
+        Ok(Self { dir })
+    }
+
+    /// Area directory
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Create a new 0600 file in the area holding `contents`
+    pub fn write_file(&self, name: &str, contents: &[u8]) -> AgeResult<PathBuf> {
+        let path = self.dir.join(name);
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .map_err(|e| AgeError::TemporaryResourceError {
+                resource_type: "temp_file".to_string(),
+                operation: "create".to_string(),
+                reason: format!("{}: {}", path.display(), e),
+            })?;
+
+        file.write_all(contents)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| AgeError::TemporaryResourceError {
+                resource_type: "temp_file".to_string(),
+                operation: "write".to_string(),
+                reason: format!("{}: {}", path.display(), e),
+            })?;
+        Ok(path)
+    }
+
+    /// Overwrite and remove everything in the area
+    pub fn wipe(&self) -> AgeResult<()> {
+        lock_registry().retain(|(dir, _)| dir != &self.dir);
+        wipe_directory(&self.dir)
+    }
+}
+
+impl Drop for SecureTempArea {
+    fn drop(&mut self) {
+        let _ = self.wipe();
+    }
+}
+
+/// A single 0600 secret file in its own temp area
+#[derive(Debug)]
+pub struct SecureTempFile {
+    path: PathBuf,
+    _area: SecureTempArea,
+}
+
+impl SecureTempFile {
+    /// Write `contents` to a new file called `name` in a fresh area under `root`
+    pub fn create_in(root: &Path, name: &str, contents: &[u8]) -> AgeResult<Self> {
+        let area = SecureTempArea::create_in(root)?;
+        let path = area.write_file(name, contents)?;
+        Ok(Self { path, _area: area })
+    }
+
+    /// Same as `create_in`, under the private temp root
+    pub fn new(name: &str, contents: &[u8]) -> AgeResult<Self> {
+        Self::create_in(&private_temp_root(), name, contents)
+    }
+
+    /// Path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// This user's directory for areas under `root`, created 0700 on first use
+///
+/// An existing entry is only accepted when it is a directory (not a symlink) owned by
+/// the current uid and closed to group and others; anything else was not made by us.
+pub(crate) fn user_root(root: &Path) -> AgeResult<PathBuf> {
+    let dir = root.join(format!("{}{}", AREA_NAME_PREFIX, current_uid()));
+    match fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+        Err(e) => {
+            return Err(AgeError::TemporaryResourceError {
+                resource_type: "temp_root".to_string(),
+                operation: "create".to_string(),
+                reason: format!("{}: {}", dir.display(), e),
+            });
+        }
+    }
+
+    if !is_private_dir(&dir) {
+        return Err(AgeError::SecurityValidationFailed {
+            validation_type: "temp_root_owner".to_string(),
+            details: format!("{} is not a 0700 directory owned by uid {}", dir.display(), current_uid()),
+        });
+    }
+    Ok(dir)
+}
+
+/// Remove areas and edit workspaces left behind by processes that no longer exist
+///
+/// Only this user's directory under `root` is scanned, and only directories owned by
+/// the current uid are wiped; symlinks are skipped.
+pub fn sweep_stale(root: &Path) -> Vec<PathBuf> {
+    let mut swept = Vec::new();
+    let user_dir = root.join(format!("{}{}", AREA_NAME_PREFIX, current_uid()));
+    if !is_private_dir(&user_dir) {
+        return swept;
+    }
+    let entries = match fs::read_dir(&user_dir) {
+        Ok(entries) => entries,
+        Err(_) => return swept,
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(pid) = area_pid(&name) else {
+            continue;
+        };
+        // DirEntry::metadata does not follow symlinks
+        let owned_dir = entry.metadata()
+            .map(|meta| meta.file_type().is_dir() && meta.uid() == current_uid())
+            .unwrap_or(false);
+
+        if owned_dir && !process_alive(pid) && wipe_directory(&entry.path()).is_ok() {
+            swept.push(entry.path());
+        }
+    }
+
+    swept
+}
+
+/// Owning process id of an area named `padlock-<kind>-<pid>-<random>`
+fn area_pid(name: &str) -> Option<i32> {
+    let rest = name.strip_prefix(AREA_NAME_PREFIX)?;
+    let mut parts = rest.rsplitn(3, '-');
+    let _random = parts.next()?;
+    let pid = parts.next()?;
+    let _kind = parts.next()?;
+    pid.parse().ok()
+}
+
+/// A real directory owned by the current uid with no group or other access
+fn is_private_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .map(|meta| meta.file_type().is_dir() && meta.uid() == current_uid() && meta.mode() & 0o077 == 0)
+        .unwrap_or(false)
+}
+
+fn current_uid() -> u32 {
+    // SAFETY: getuid has no preconditions and cannot fail
+    unsafe { libc::getuid() }
+}
+
+/// Sweep `root` the first time this process uses it
+fn sweep_once(root: &Path) {
+    let mut swept = SWEPT_ROOTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if !swept.iter().any(|done| done == root) {
+        sweep_stale(root);
+        swept.push(root.to_path_buf());
+    }
+}
+
+fn lock_registry() -> std::sync::MutexGuard<'static, Vec<(PathBuf, ThreadId)>> {
+    LIVE_AREAS.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Wipe registered areas, optionally only those created by one thread
+fn wipe_registered(owner: Option<ThreadId>) {
+    // try_lock: a panic while the registry is held must not deadlock the hook
+    let mut areas = match LIVE_AREAS.try_lock() {
+        Ok(areas) => areas,
+        Err(std::sync::TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(std::sync::TryLockError::WouldBlock) => return,
+    };
+    areas.retain(|(dir, thread)| {
+        if owner.is_some_and(|owner| owner != *thread) {
+            return true;
+        }
+        let _ = wipe_directory(dir);
+        false
+    });
+}
+
+/// Chain a panic hook that wipes the panicking thread's areas
+///
+/// Unwinding would run the destructors anyway; the hook also covers `panic = "abort"`
+/// builds and areas held by values that are leaked.
+fn install_panic_cleanup() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        wipe_registered(Some(thread::current().id()));
+        previous(info);
+    }));
+}
+
+extern "C" fn forward_signal(signal: libc::c_int) {
+    let fd = SIGNAL_PIPE.load(Ordering::SeqCst);
+    if fd >= 0 {
+        let byte = signal as u8;
+        // SAFETY: write(2) is async-signal-safe; the buffer is one valid byte
+        unsafe { libc::write(fd, (&byte as *const u8).cast(), 1) };
+    }
+}
+
+/// Wipe all areas on SIGINT/SIGTERM, then terminate with the same signal
+///
+/// The handler only writes to a pipe; a dedicated thread does the wiping outside signal
+/// context. Signals that are already ignored or handled by someone else are left alone.
+fn install_signal_cleanup() {
+    let mut fds = [0 as libc::c_int; 2];
+    // SAFETY: pipe writes two descriptors into the array
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return;
+    }
+    for fd in fds {
+        // SAFETY: plain descriptor flag change on a descriptor we own
+        unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+    }
+    SIGNAL_PIPE.store(fds[1], Ordering::SeqCst);
+
+    let handler = forward_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: forward_signal only loads an atomic and calls write(2)
+        let previous = unsafe { libc::signal(signal, handler) };
+        if previous != libc::SIG_DFL {
+            // SAFETY: restores the disposition returned just above
+            unsafe { libc::signal(signal, previous) };
+        }
+    }
+
+    let reader = fds[0];
+    let _ = thread::Builder::new()
+        .name("padlock-temp-cleanup".to_string())
+        .spawn(move || {
+            let mut byte = 0u8;
+            loop {
+                // SAFETY: reads at most one byte into a valid buffer
+                let read = unsafe { libc::read(reader, (&mut byte as *mut u8).cast(), 1) };
+                if read == 1 {
+                    break;
+                }
+                if read < 0 && std::io::Error::last_os_error().raw_os_error() == Some(libc::EINTR) {
+                    continue;
+                }
+                return;
+            }
+
+            wipe_registered(None);
+            let signal = libc::c_int::from(byte);
+            // SAFETY: restore the default action and deliver the signal to ourselves
+            unsafe {
+                libc::signal(signal, libc::SIG_DFL);
+                libc::raise(signal);
+            }
+        });
+}
+
+/// Overwrite every file in a directory with zeros, then remove the directory
+///
+/// The directory must be a real directory owned by the current uid. Only regular files
+/// owned by the current uid are overwritten, opened with `O_NOFOLLOW`; symlinks are
+/// removed without touching what they point to.
+pub(crate) fn wipe_directory(dir: &Path) -> AgeResult<()> {
+    let meta = match fs::symlink_metadata(dir) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(AgeError::file_error("wipe", dir.to_path_buf(), e)),
+    };
+    if !meta.file_type().is_dir() || meta.uid() != current_uid() {
+        return Err(AgeError::SecurityValidationFailed {
+            validation_type: "temp_area_owner".to_string(),
+            details: format!("{} is not a directory owned by uid {}", dir.display(), current_uid()),
+        });
+    }
+
+    let entries = fs::read_dir(dir).map_err(|e| AgeError::file_error("wipe", dir.to_path_buf(), e))?;
+    for entry in entries.flatten() {
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if !meta.file_type().is_file() || meta.uid() != current_uid() {
+            continue;
+        }
+        let opened = fs::OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(entry.path());
+        if let Ok(mut file) = opened {
+            let _ = file.write_all(&vec![0u8; meta.len() as usize]);
+            let _ = file.sync_all();
+        }
+    }
+
+    fs::remove_dir_all(dir).map_err(|e| AgeError::file_error("wipe", dir.to_path_buf(), e))
+}
+
+/// True when a process with this id exists
+fn process_alive(pid: i32) -> bool {
+    // SAFETY: signal 0 performs permission and existence checks only
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn mode(path: &Path) -> u32 {
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn test_area_and_file_permissions_and_drop_cleanup() {
+        let scratch = TempDir::new().unwrap();
+        let area = SecureTempArea::create_in(scratch.path()).unwrap();
+        assert_eq!(mode(area.path()), 0o700);
+
+        let key = area.write_file("identity", b"AGE-SECRET-KEY-1TEST\n").unwrap();
+        assert_eq!(mode(&key), 0o600);
+        assert!(area.write_file("identity", b"again").is_err());
+
+        let dir = area.path().to_path_buf();
+        drop(area);
+        assert!(!dir.exists());
+        assert!(!lock_registry().iter().any(|(live, _)| live == &dir));
+    }
+
+    #[test]
+    fn test_panic_wipes_thread_areas() {
+        let scratch = TempDir::new().unwrap();
+        let root = scratch.path().to_path_buf();
+        let leaked = thread::spawn(move || {
+            let area = SecureTempArea::create_in(&root).unwrap();
+            let dir = area.path().to_path_buf();
+            std::mem::forget(area);
+            if dir.exists() {
+                panic!("temp area test panic");
+            }
+        }).join();
+        assert!(leaked.is_err());
+        assert_eq!(fs::read_dir(user_root(scratch.path()).unwrap()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_sweep_removes_dead_process_areas() {
+        let scratch = TempDir::new().unwrap();
+        let file = SecureTempFile::create_in(scratch.path(), "recipients", b"age1xyz\n").unwrap();
+
+        let stale = user_root(scratch.path()).unwrap().join(format!("{}{}-0", TEMP_AREA_PREFIX, i32::MAX));
+        fs::create_dir(&stale).unwrap();
+        fs::write(stale.join("identity"), "secret").unwrap();
+
+        assert_eq!(sweep_stale(scratch.path()), vec![stale.clone()]);
+        assert!(!stale.exists());
+        assert!(file.path().exists());
+    }
+
+    #[test]
+    fn test_sweep_never_follows_planted_entries() {
+        let scratch = TempDir::new().unwrap();
+        let victim = scratch.path().join("victim.key");
+        fs::write(&victim, "AGE-SECRET-KEY-1VICTIM").unwrap();
+
+        // Entries planted directly in the shared root are outside the user directory
+        let planted = scratch.path().join(format!("{}{}-0", TEMP_AREA_PREFIX, i32::MAX));
+        fs::create_dir(&planted).unwrap();
+        std::os::unix::fs::symlink(&victim, planted.join("identity")).unwrap();
+        assert!(sweep_stale(scratch.path()).is_empty());
+
+        // Inside the user directory, a symlinked area and symlinked files are not followed
+        let user_dir = user_root(scratch.path()).unwrap();
+        std::os::unix::fs::symlink(&planted, user_dir.join(format!("{}{}-1", TEMP_AREA_PREFIX, i32::MAX))).unwrap();
+        let stale = user_dir.join(format!("{}{}-2", TEMP_AREA_PREFIX, i32::MAX));
+        fs::create_dir(&stale).unwrap();
+        std::os::unix::fs::symlink(&victim, stale.join("identity")).unwrap();
+
+        assert_eq!(sweep_stale(scratch.path()), vec![stale]);
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "AGE-SECRET-KEY-1VICTIM");
+        assert!(wipe_directory(&user_dir.join(format!("{}{}-1", TEMP_AREA_PREFIX, i32::MAX))).is_err());
+    }
+
+    #[test]
+    fn test_user_root_rejects_foreign_or_open_directories() {
+        let scratch = TempDir::new().unwrap();
+        let user_dir = user_root(scratch.path()).unwrap();
+        assert_eq!(mode(&user_dir), 0o700);
+
+        fs::set_permissions(&user_dir, fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(user_root(scratch.path()).is_err());
+        assert!(sweep_stale(scratch.path()).is_empty());
+
+        fs::remove_dir(&user_dir).unwrap();
+        std::os::unix::fs::symlink(scratch.path(), &user_dir).unwrap();
+        assert!(user_root(scratch.path()).is_err());
+    }
+
+    #[test]
+    fn test_memory_backed_detection() {
+        assert!(!is_memory_backed(Path::new("/nonexistent/padlock")));
+        if cfg!(target_os = "linux") && Path::new("/dev/shm").is_dir() {
+            assert!(is_memory_backed(Path::new("/dev/shm")));
+        }
+    }
+}
@@ -5,13 +5,27 @@
 //!
 //! Security Guardian: Edgar - Production padlock system interface
 
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+use padlock::authority::{
+    Actor, AuthorityChain, AuthorityKey, KeyEnvelope, KeyType, ScopedKey, TrustAnchors,
+    authorization::{sign_repository_policy, REPOSITORY_FILE},
+    certificate::{root_recipient, AuthorityCertificate},
+    expiration::ExpirationPolicies,
+    lineage::{LineageResolver, Relation},
+    maintenance::MaintenanceRunner,
+    revocation::RevocationList,
+};
 use padlock::encryption::age_automation::{
     lifecycle::crud_manager::{CrudManager, LockOptions, UnlockOptions},
-    config::{AgeConfig, OutputFormat},
+    lifecycle::plan::OperationPlan,
+    lifecycle::edit::{SecureEditor, EditOutcome},
+    lifecycle::exec::ExecOptions,
+    temp_area,
+    config::{AgeConfig, ClockRollbackPolicy, OutputFormat},
+    header::KnownRecipient,
     adapter::AdapterFactory,
-    error::AgeResult,
+    error::{AgeError, AgeResult},
 };
 
 #[derive(Parser)]
@@ -27,6 +41,45 @@ struct PadlockCli {
     
     #[arg(long, global = true, help = "Audit log file path")]
     audit_log: Option<PathBuf>,
+    
+    #[arg(long, global = true, help = "Private directory for keys and decrypted material (default: /dev/shm or $XDG_RUNTIME_DIR)")]
+    temp_dir: Option<PathBuf>,
+    
+    #[arg(long, global = true, help = "Refuse to run when the system clock is behind the repository's last recorded time")]
+    block_clock_rollback: bool,
+    
+    #[arg(long, global = true, help = "Show what lock/unlock/rotate would change without changing anything")]
+    dry_run: bool,
+    
+    #[arg(long, global = true, value_enum, default_value = "table", help = "Dry-run plan output format")]
+    plan_format: PlanFormat,
+    
+    #[arg(long = "identity", global = true, help = "Identity file whose keys status/test match against file headers and that opens a vault encrypted to the repository recipient set (repeatable)")]
+    identities: Vec<PathBuf>,
+    
+    #[arg(long = "recipients", global = true, help = "Recipients file whose keys status/test match against file headers (repeatable)")]
+    recipients: Vec<PathBuf>,
+    
+    #[arg(long, global = true, help = "Enveloped key file to act with, verified against the trusted-roots file; operations are authorized at its level instead of the passphrase level (distro unless a trusted policy raises it)")]
+    as_key: Option<PathBuf>,
+    
+    #[arg(long = "distro-key", global = true, help = "Issued distro key whose recipient lock adds to the files inside its paths; verified against the trusted-roots file (repeatable)")]
+    distro_keys: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PlanFormat {
+    Table,
+    Json,
+}
+
+/// Print a dry-run plan in the requested format
+fn print_plan(plan: &OperationPlan, format: PlanFormat) -> AgeResult<()> {
+    match format {
+        PlanFormat::Table => print!("{}", plan.to_table()),
+        PlanFormat::Json => println!("{}", plan.to_json()?),
+    }
+    Ok(())
 }
 
 #[derive(Subcommand)]
@@ -39,8 +92,14 @@ enum Commands {
         passphrase: String,
         #[arg(long, help = "Use ASCII armor format")]
         armor: bool,
+        #[arg(long, conflicts_with = "armor", help = "Encrypt JSON/YAML/TOML/dotenv values, keep keys readable")]
+        structured: bool,
+        #[arg(long = "plaintext-key", requires = "structured", help = "Regex over dotted key paths to leave unencrypted (repeatable)")]
+        plaintext_keys: Vec<String>,
         #[arg(long, help = "Remove source files after encryption")]
         remove_source: bool,
+        #[arg(long, help = "Also preserve extended attributes (stored unencrypted in the manifest)")]
+        xattrs: bool,
     },
     
     /// Unlock (decrypt) files in repository
@@ -53,13 +112,37 @@ enum Commands {
         remove_encrypted: bool,
     },
     
+    /// Edit an encrypted file in place without leaving plaintext on disk
+    Edit {
+        #[arg(help = "Encrypted file to edit")]
+        file: PathBuf,
+        #[arg(short, long, help = "Passphrase for the encrypted file")]
+        passphrase: String,
+        #[arg(long, help = "Editor command (default: $VISUAL, $EDITOR, vi)")]
+        editor: Option<String>,
+    },
+    
+    /// Run a command with decrypted dotenv secrets in its environment
+    Exec {
+        #[arg(long = "env", required = true, help = "Encrypted dotenv file (repeatable; later files take precedence)")]
+        env_files: Vec<PathBuf>,
+        #[arg(short, long, help = "Passphrase for the encrypted files")]
+        passphrase: String,
+        #[arg(long, help = "Prefix added to every injected variable name")]
+        prefix: Option<String>,
+        #[arg(long, help = "Replace variables already set in the environment")]
+        overwrite: bool,
+        #[arg(last = true, required = true, help = "Command and arguments to run")]
+        command: Vec<String>,
+    },
+    
     /// Show repository encryption status
     Status {
         #[arg(help = "Repository path to check")]
         path: PathBuf,
     },
     
-    /// Rotate encryption keys (re-encrypt with new passphrase)
+    /// Rotate encryption keys (records the rotation request; files are not re-encrypted yet)
     Rotate {
         #[arg(help = "Repository path")]
         path: PathBuf,
@@ -69,6 +152,14 @@ enum Commands {
         new_passphrase: String,
     },
     
+    /// Convert encrypted files between binary and ASCII armor without keys
+    Rearmor {
+        #[arg(help = "Encrypted file or repository path")]
+        path: PathBuf,
+        #[arg(long, value_enum, help = "Target encoding")]
+        to: ArmorTarget,
+    },
+    
     /// Perform system health check
     Test {
         #[arg(help = "Optional test repository path")]
@@ -84,18 +175,416 @@ enum Commands {
         #[arg(long, help = "Force operation despite warnings")]
         force: bool,
     },
+    
+    /// Manage named secrets in the repository vault
+    Vault {
+        #[command(subcommand)]
+        action: VaultCommands,
+    },
+    
+    /// Manage versioned pre-lock backups
+    Backup {
+        #[command(subcommand)]
+        action: BackupCommands,
+    },
+    
+    /// Report expiring authority keys and auto-rotate them (safe for cron/systemd timers)
+    Maintain {
+        #[arg(long, default_value = "./keys", help = "Keys directory holding {name}-{type}.key files")]
+        keys_dir: PathBuf,
+        #[arg(short, long, default_value = "auth", help = "Authority chain name")]
+        name: String,
+        #[arg(long, help = "Print the report as JSON")]
+        json: bool,
+    },
+    
+    /// Inspect and verify authority keys and certificates
+    Key {
+        #[command(subcommand)]
+        action: KeyCommands,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ArmorTarget {
+    Ascii,
+    Binary,
+}
+
+impl From<ArmorTarget> for OutputFormat {
+    fn from(target: ArmorTarget) -> Self {
+        match target {
+            ArmorTarget::Ascii => OutputFormat::AsciiArmor,
+            ArmorTarget::Binary => OutputFormat::Binary,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum VaultCommands {
+    /// Print a secret's value
+    Get {
+        #[arg(help = "Secret name")]
+        name: String,
+        #[arg(long, default_value = ".", help = "Repository path")]
+        repo: PathBuf,
+        #[arg(short, long, help = "Vault passphrase")]
+        passphrase: String,
+    },
+    
+    /// Create or update a secret
+    Set {
+        #[arg(help = "Secret name")]
+        name: String,
+        #[arg(help = "Secret value (read from stdin when omitted)")]
+        value: Option<String>,
+        #[arg(long = "tag", help = "Tag for the secret (repeatable; replaces existing tags)")]
+        tags: Vec<String>,
+        #[arg(long, default_value = ".", help = "Repository path")]
+        repo: PathBuf,
+        #[arg(short, long, help = "Vault passphrase")]
+        passphrase: String,
+    },
+    
+    /// Remove a secret
+    Rm {
+        #[arg(help = "Secret name")]
+        name: String,
+        #[arg(long, default_value = ".", help = "Repository path")]
+        repo: PathBuf,
+        #[arg(short, long, help = "Vault passphrase")]
+        passphrase: String,
+    },
+    
+    /// List secret names and metadata
+    List {
+        #[arg(long, help = "Only list secrets with this tag")]
+        tag: Option<String>,
+        #[arg(long, default_value = ".", help = "Repository path")]
+        repo: PathBuf,
+        #[arg(short, long, help = "Vault passphrase")]
+        passphrase: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum BackupCommands {
+    /// List backups stored for a repository
+    List {
+        #[arg(long, default_value = ".", help = "Repository path")]
+        repo: PathBuf,
+    },
+    
+    /// Restore files from a backup
+    Restore {
+        #[arg(help = "Backup id (see 'backup list')")]
+        id: String,
+        #[arg(help = "Repository-relative paths to restore (default: all)")]
+        paths: Vec<PathBuf>,
+        #[arg(long, default_value = ".", help = "Repository path")]
+        repo: PathBuf,
+        #[arg(short, long, help = "Passphrase used when the backup was taken")]
+        passphrase: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum KeyCommands {
+    /// Print a key file's type: skull, master, repo, ignition, distro or unknown
+    Type {
+        #[arg(long, help = "Key file")]
+        key: PathBuf,
+        #[arg(long, help = "Also verify the envelope's parent signature against this age1... recipient or key file")]
+        parent: Option<String>,
+    },
+    
+    /// Does key1 have authority over key2? Prints AUTHORITY_VALID (exit 0), AUTHORITY_INVALID (1) or ERROR (2)
+    Authority {
+        #[command(flatten)]
+        pair: KeyPairArgs,
+    },
+    
+    /// Is key1 subject to key2? Prints AUTHORITY_VALID (exit 0), AUTHORITY_INVALID (1) or ERROR (2)
+    Subject {
+        #[command(flatten)]
+        pair: KeyPairArgs,
+    },
+    
+    /// Answer yes (exit 0) or no (exit 1) about a key file
+    Is {
+        #[arg(value_enum)]
+        what: KeyQuestion,
+        #[arg(long, help = "Key file")]
+        key: PathBuf,
+        #[arg(long, help = "Repository whose expiration policy applies (for 'expired')")]
+        repo: Option<PathBuf>,
+    },
+    
+    /// Verify an authority certificate using only the trusted root's public key
+    VerifyCert {
+        #[arg(help = "Certificate file (see 'cli_auth certify')")]
+        cert: PathBuf,
+        #[arg(long, help = "Trusted root: age1... recipient or a key file holding it")]
+        root: String,
+        #[arg(long, help = "Also check that this identity file holds the certified key")]
+        key: Option<PathBuf>,
+        #[arg(long, help = "Also refuse keys revoked in this repository's revocation list")]
+        repo: Option<PathBuf>,
+        #[arg(long, help = "Print the verified certificate as JSON")]
+        json: bool,
+    },
+    
+    /// Sign a repository's .padlock/authorization.toml so operations accept it
+    SignPolicy {
+        #[arg(long, help = "Repo-level or higher key file listed in the trusted-roots file")]
+        key: PathBuf,
+        #[arg(long, default_value = ".", help = "Repository path")]
+        repo: PathBuf,
+    },
+}
+
+#[derive(clap::Args)]
+struct KeyPairArgs {
+    #[arg(long, help = "First key file")]
+    key1: PathBuf,
+    #[arg(long, help = "Second key file")]
+    key2: PathBuf,
+    #[arg(long = "keys-dir", help = "Extra directory to search for intermediate key files (repeatable)")]
+    keys_dirs: Vec<PathBuf>,
+    #[arg(long, help = "Also refuse keys revoked in this repository's revocation list")]
+    repo: Option<PathBuf>,
+    #[arg(long, help = "Show the full lineage path between the two keys")]
+    explain: bool,
+}
+
+/// Print the lineage verdict token and exit with its code (0 valid, 1 invalid, 2 error)
+fn run_lineage_check(relation: Relation, pair: &KeyPairArgs) -> ! {
+    let check = pair.repo.as_deref().map_or(Ok(RevocationList::default()), RevocationList::load)
+        .and_then(|revocations| Ok((revocations, TrustAnchors::load()?)))
+        .and_then(|(revocations, anchors)| {
+            let resolver = pair.keys_dirs.iter().cloned()
+                .fold(LineageResolver::new().with_revocations(revocations).with_trust_anchors(anchors), LineageResolver::with_search_dir);
+            resolver.check(relation, &pair.key1, &pair.key2)
+        });
+    match check {
+        Ok(check) => {
+            println!("{}", check.token());
+            if pair.explain {
+                print!("{}", check.explain());
+            }
+            std::process::exit(if check.is_valid() { 0 } else { 1 });
+        }
+        Err(error) => {
+            println!("ERROR");
+            if pair.explain {
+                eprintln!("  {}", error);
+            }
+            std::process::exit(2);
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum KeyQuestion {
+    Skull,
+    Master,
+    Repo,
+    Ignition,
+    Distro,
+    Expired,
+}
+
+impl KeyQuestion {
+    /// Key type asked about; None for 'expired'
+    fn key_type(self) -> Option<KeyType> {
+        match self {
+            KeyQuestion::Skull => Some(KeyType::Skull),
+            KeyQuestion::Master => Some(KeyType::Master),
+            KeyQuestion::Repo => Some(KeyType::Repo),
+            KeyQuestion::Ignition => Some(KeyType::Ignition),
+            KeyQuestion::Distro => Some(KeyType::Distro),
+            KeyQuestion::Expired => None,
+        }
+    }
+}
+
+/// Run a key subcommand; none of them needs the age binary
+fn run_key_command(action: &KeyCommands) -> AgeResult<()> {
+    match action {
+        KeyCommands::Type { key, parent } => {
+            let envelope = KeyEnvelope::read(key)?;
+            if let (Some(envelope), Some(parent)) = (&envelope, parent) {
+                envelope.verify_parent(&root_recipient(parent)?)?;
+            }
+            match envelope {
+                Some(envelope) => println!("{}", envelope.key_type),
+                None => println!("unknown"),
+            }
+        }
+        
+        KeyCommands::Authority { pair } => run_lineage_check(Relation::Authority, pair),
+        KeyCommands::Subject { pair } => run_lineage_check(Relation::Subject, pair),
+        
+        KeyCommands::Is { what, key, repo } => {
+            let envelope = KeyEnvelope::read(key)?;
+            let answer = match (what.key_type(), &envelope) {
+                (Some(key_type), envelope) => envelope.as_ref().is_some_and(|envelope| envelope.key_type == key_type),
+                (None, None) => return Err(AgeError::InvalidOperation {
+                    operation: "key_is_expired".to_string(),
+                    reason: format!("{} is a plain age key; its type and lifetime are unknown", key.display()),
+                }),
+                (None, Some(envelope)) => {
+                    let effective = ExpirationPolicies::load(repo.as_deref())?.for_key_file(envelope.key_type, key)?;
+                    envelope.is_expired(effective.policy.as_ref())
+                }
+            };
+            println!("{}", if answer { "yes" } else { "no" });
+            if !answer {
+                std::process::exit(1);
+            }
+        }
+        
+        KeyCommands::VerifyCert { cert, root, key, repo, json } => {
+            let certificate = AuthorityCertificate::load(cert)?;
+            let verified = certificate.verify_now(&root_recipient(root)?)?;
+            
+            if let Some(repo) = repo {
+                let revocations = RevocationList::load(repo)?;
+                let chain = AuthorityChain::new();
+                let mut revoked = None;
+                for link in &certificate.chain {
+                    if revocations.is_recipient_revoked(&link.subject.recipient, &chain)? {
+                        revoked = Some(link);
+                        break;
+                    }
+                }
+                if let Some(link) = revoked {
+                    return Err(AgeError::SecurityValidationFailed {
+                        validation_type: "key_revoked".to_string(),
+                        details: format!("{} key {} in the certificate is revoked", link.subject.key_type, link.subject.fingerprint),
+                    });
+                }
+            }
+            if let Some(key) = key {
+                if !certificate.is_held_by(key)? {
+                    return Err(AgeError::SecurityValidationFailed {
+                        validation_type: "authority_certificate".to_string(),
+                        details: format!("{} does not hold the certified key", key.display()),
+                    });
+                }
+            }
+            
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&verified)
+                    .map_err(|e| AgeError::InvalidOperation { operation: "verify_cert".to_string(), reason: e.to_string() })?);
+            } else {
+                println!("✅ Valid certificate for {} key {}", verified.subject.key_type, verified.subject.fingerprint);
+                println!("   Recipient: {}", verified.subject.recipient);
+                println!("   Chained to {} key {} in {} link(s)", verified.root.key_type, verified.root.fingerprint, verified.depth);
+                println!("   Valid {} to {}", verified.not_before.format("%Y-%m-%d %H:%M UTC"), verified.not_after.format("%Y-%m-%d %H:%M UTC"));
+                if verified.scope.is_empty() {
+                    println!("   Scope: unrestricted");
+                } else {
+                    println!("   Scope: {}", verified.scope.join(", "));
+                }
+                if let Some(capabilities) = &verified.capabilities {
+                    println!("   Capabilities: {}", capabilities);
+                }
+                if key.is_some() {
+                    println!("   Key file holds the certified key");
+                }
+            }
+        }
+        
+        KeyCommands::SignPolicy { key, repo } => {
+            let signature = sign_repository_policy(repo, &AuthorityKey::open(key)?)?;
+            println!("✅ Signed {} ({})", repo.join(REPOSITORY_FILE).display(), signature.display());
+        }
+    }
+    Ok(())
+}
+
+/// Run key maintenance; fails when expired keys are left that policy could not rotate
+fn run_maintenance(keys_dir: &Path, name: &str, json: bool, cli: &PadlockCli) -> AgeResult<()> {
+    let runner = MaintenanceRunner::new(keys_dir.to_path_buf(), name, cli.audit_log.clone())?
+        .with_dry_run(cli.dry_run)
+        .with_clock_rollback_policy(clock_rollback_policy(cli))
+        .with_policies(ExpirationPolicies::load(None)?);
+    let report = runner.run()?;
+    
+    if json {
+        println!("{}", report.to_json()?);
+    } else {
+        print!("{}", report.to_table());
+    }
+    
+    let expired = report.expired_unrotated();
+    if !expired.is_empty() {
+        return Err(AgeError::SecurityValidationFailed {
+            validation_type: "key_expiration".to_string(),
+            details: format!("{} expired key(s) need manual rotation: {}", expired.len(),
+                expired.iter().map(|key| key.key_type.to_string()).collect::<Vec<_>>().join(", ")),
+        });
+    }
+    Ok(())
+}
+
+/// Rollback response selected on the command line
+fn clock_rollback_policy(cli: &PadlockCli) -> ClockRollbackPolicy {
+    if cli.block_clock_rollback {
+        ClockRollbackPolicy::Block
+    } else {
+        ClockRollbackPolicy::Warn
+    }
 }
 
 fn main() -> AgeResult<()> {
     let cli = PadlockCli::parse();
     
+    // Key maintenance needs no age binary, so it runs before the adapter is created
+    if let Commands::Maintain { ref keys_dir, ref name, json } = cli.command {
+        return run_maintenance(keys_dir, name, json, &cli);
+    }
+    if let Commands::Key { ref action } = cli.command {
+        return run_key_command(action);
+    }
+    
     // Initialize the CRUD manager
     let adapter = AdapterFactory::create_default()?;
     let mut config = AgeConfig::production();
     if let Some(ref audit_path) = cli.audit_log {
         config = config.with_audit_log_path(audit_path.to_string_lossy().to_string());
     }
-    let mut crud_manager = CrudManager::new(adapter, config)?;
+    if let Commands::Lock { ref plaintext_keys, .. } = cli.command {
+        config = config.with_structured_plaintext_keys(plaintext_keys.clone());
+    }
+    if let Some(ref temp_dir) = cli.temp_dir {
+        config = config.with_temp_dir(temp_dir.to_string_lossy().to_string());
+    }
+    config = config.with_clock_rollback_policy(clock_rollback_policy(&cli));
+    
+    // Clear keys and plaintext left behind by crashed runs
+    let temp_root = temp_area::temp_root(&config);
+    temp_area::sweep_stale(&temp_root);
+    let mut crud_manager = CrudManager::new(adapter, config)?.with_identity_files(cli.identities.clone());
+    let anchors = TrustAnchors::load()?;
+    if let Some(ref key) = cli.as_key {
+        crud_manager = crud_manager.with_actor(Actor::holding(key, &anchors)?);
+    }
+    if !cli.distro_keys.is_empty() {
+        let scoped_keys = cli.distro_keys.iter().map(|key| ScopedKey::load(key, &anchors)).collect::<AgeResult<Vec<_>>>()?;
+        crud_manager = crud_manager.with_scoped_keys(scoped_keys);
+    }
+    
+    let mut known_recipients = Vec::new();
+    for identity_file in &cli.identities {
+        known_recipients.extend(KnownRecipient::load_identity_file(identity_file)?);
+    }
+    for recipients_file in &cli.recipients {
+        known_recipients.extend(KnownRecipient::load_recipients_file(recipients_file)?);
+    }
+    let matching_keys = !known_recipients.is_empty();
+    crud_manager.set_known_recipients(known_recipients);
     
     if cli.verbose {
         eprintln!("Padlock v0.0.1 - Cryptographic Repository Management");
@@ -105,14 +594,27 @@ fn main() -> AgeResult<()> {
     }
     
     match cli.command {
-        Commands::Lock { path, passphrase, armor, remove_source } => {
+        Commands::Lock { path, passphrase, armor, structured, plaintext_keys: _, remove_source, xattrs } => {
+            let format = if structured {
+                OutputFormat::Structured
+            } else if armor {
+                OutputFormat::AsciiArmor
+            } else {
+                // Repositories converted with rearmor keep their encoding
+                crud_manager.preferred_format(&path)?.unwrap_or(OutputFormat::Binary)
+            };
             let options = LockOptions {
                 recursive: true,
-                format: if armor { OutputFormat::AsciiArmor } else { OutputFormat::Binary },
+                format,
                 pattern_filter: None,
                 backup_before_lock: !remove_source,  // If not removing source, create backup
+                preserve_xattrs: xattrs,
             };
             
+            if cli.dry_run {
+                return print_plan(&crud_manager.plan_lock(&path, &options)?, cli.plan_format);
+            }
+            
             if cli.verbose {
                 eprintln!("Locking repository: {}", path.display());
             }
@@ -137,6 +639,10 @@ fn main() -> AgeResult<()> {
                 preserve_encrypted: !remove_encrypted,
             };
             
+            if cli.dry_run {
+                return print_plan(&crud_manager.plan_unlock(&path, &options)?, cli.plan_format);
+            }
+            
             if cli.verbose {
                 eprintln!("Unlocking repository: {}", path.display());
             }
@@ -153,6 +659,38 @@ fn main() -> AgeResult<()> {
             }
         }
         
+        Commands::Edit { file, passphrase, editor } => {
+            let mut secure_editor = SecureEditor::new().with_root(temp_root.clone());
+            if let Some(editor) = editor {
+                secure_editor = secure_editor.with_editor(editor);
+            }
+            
+            if secure_editor.is_persistent_storage() {
+                eprintln!("Warning: no RAM-backed temp directory found, using {}", secure_editor.root().display());
+            }
+            
+            match crud_manager.edit(&file, &passphrase, &secure_editor)? {
+                EditOutcome::ReEncrypted => println!("Re-encrypted: {}", file.display()),
+                EditOutcome::Unchanged => println!("No changes: {}", file.display()),
+            }
+        }
+        
+        Commands::Exec { env_files, passphrase, prefix, overwrite, command } => {
+            use std::os::unix::process::CommandExt;
+            
+            let options = ExecOptions { env_files, prefix, overwrite };
+            let environment = crud_manager.load_secret_environment(&options, &passphrase)?;
+            
+            if cli.verbose {
+                eprintln!("Injecting {} variables into: {}", environment.len(), command[0]);
+            }
+            
+            // exec replaces this process, so it only returns on failure
+            let error = environment.command(&command[0], &command[1..]).exec();
+            eprintln!("Failed to execute {}: {}", command[0], error);
+            std::process::exit(127);
+        }
+        
         Commands::Status { path } => {
             if cli.verbose {
                 eprintln!("Checking status of: {}", path.display());
@@ -174,6 +712,16 @@ fn main() -> AgeResult<()> {
                 println!("  Status: PARTIALLY ENCRYPTED");
             }
             
+            if !status.file_headers.is_empty() {
+                println!("  Age headers:");
+                for report in &status.file_headers {
+                    println!("    {}: {}", report.path.display(), report.describe());
+                    if matching_keys && report.header.is_some() && report.openable_by.is_empty() {
+                        println!("      no known key can open this file");
+                    }
+                }
+            }
+            
             if !status.failed_files.is_empty() {
                 println!("  Failed files: {}", status.failed_files.len());
                 for failed_file in &status.failed_files {
@@ -183,6 +731,10 @@ fn main() -> AgeResult<()> {
         }
         
         Commands::Rotate { path, old_passphrase, new_passphrase } => {
+            if cli.dry_run {
+                return print_plan(&crud_manager.plan_rotate(&path)?, cli.plan_format);
+            }
+            
             if cli.verbose {
                 eprintln!("Rotating encryption for: {}", path.display());
             }
@@ -210,7 +762,15 @@ fn main() -> AgeResult<()> {
                 Ok(result) => {
                     println!("System health check: PASSED");
                     println!("Verified files: {}", result.verified_files.len());
+                    if !result.failed_files.is_empty() {
+                        println!("Failed files: {}", result.failed_files.len());
+                    }
                     println!("Overall status: {}", result.overall_status);
+                    if cli.verbose || matching_keys {
+                        for report in &result.file_headers {
+                            println!("  {}: {}", report.path.display(), report.describe());
+                        }
+                    }
                 }
                 Err(e) => {
                     eprintln!("System health check: FAILED");
@@ -251,6 +811,114 @@ fn main() -> AgeResult<()> {
                 }
             }
         }
+        
+        Commands::Vault { action } => match action {
+            VaultCommands::Get { name, repo, passphrase } => {
+                let secret = crud_manager.vault_get(&repo, &name, &passphrase)?;
+                println!("{}", secret.value.expose_secret());
+            }
+            
+            VaultCommands::Set { name, value, tags, repo, passphrase } => {
+                let value = match value {
+                    Some(value) => value,
+                    None => {
+                        let mut value = String::new();
+                        std::io::Read::read_to_string(&mut std::io::stdin(), &mut value)
+                            .map_err(|e| padlock::encryption::age_automation::AgeError::file_error("read", PathBuf::from("<stdin>"), e))?;
+                        value.trim_end_matches('\n').to_string()
+                    }
+                };
+                let tags = if tags.is_empty() { None } else { Some(tags.into_iter().collect()) };
+                
+                crud_manager.vault_set(&repo, &name, value, tags, &passphrase)?;
+                println!("Stored secret: {}", name);
+            }
+            
+            VaultCommands::Rm { name, repo, passphrase } => {
+                crud_manager.vault_remove(&repo, &name, &passphrase)?;
+                println!("Removed secret: {}", name);
+            }
+            
+            VaultCommands::List { tag, repo, passphrase } => {
+                let entries = crud_manager.vault_list(&repo, tag.as_deref(), &passphrase)?;
+                
+                if entries.is_empty() {
+                    println!("No secrets in vault: {}", repo.display());
+                }
+                
+                for entry in &entries {
+                    let tags: Vec<&str> = entry.tags.iter().map(String::as_str).collect();
+                    println!("{}  updated {} by {}  [{}]",
+                        entry.name,
+                        entry.updated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                        entry.updated_by,
+                        tags.join(","));
+                }
+            }
+        },
+        
+        Commands::Rearmor { path, to } => {
+            if cli.dry_run {
+                return print_plan(&crud_manager.plan_rearmor(&path, to.into())?, cli.plan_format);
+            }
+            
+            let result = crud_manager.rearmor(&path, to.into())?;
+            println!("Rearmor operation completed");
+            println!("Files processed: {}", result.processed_files.len());
+            if cli.verbose {
+                for file in &result.processed_files {
+                    println!("  {}", file);
+                }
+            }
+            
+            if !result.failed_files.is_empty() {
+                eprintln!("Warning: {} files failed processing", result.failed_files.len());
+                for failed_file in &result.failed_files {
+                    eprintln!("  Failed: {}", failed_file);
+                }
+                std::process::exit(1);
+            }
+        }
+        
+        Commands::Backup { action } => match action {
+            BackupCommands::List { repo } => {
+                let backups = crud_manager.list_backups(&repo)?;
+                
+                if backups.is_empty() {
+                    println!("No backups found in: {}", repo.display());
+                }
+                
+                for backup in &backups {
+                    println!("{}  {}  {}  {} files",
+                        backup.id,
+                        backup.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                        backup.operation,
+                        backup.entries.len());
+                    
+                    if cli.verbose {
+                        for entry in &backup.entries {
+                            println!("    {} ({} bytes)", entry.relative_path.display(), entry.size_bytes);
+                        }
+                    }
+                }
+            }
+            
+            BackupCommands::Restore { id, paths, repo, passphrase } => {
+                if cli.verbose {
+                    eprintln!("Restoring backup {} into: {}", id, repo.display());
+                }
+                
+                let result = crud_manager.restore_backup(&repo, &id, &paths, &passphrase)?;
+                println!("Restore operation completed successfully");
+                println!("Files restored: {}", result.processed_files.len());
+                
+                for restored in &result.processed_files {
+                    println!("  Restored: {}", restored);
+                }
+            }
+        },
+        
+        Commands::Maintain { .. } | Commands::Key { .. } => unreachable!("handled before the adapter is created"),
     }
     
     Ok(())
@@ -1,8 +1,10 @@
 use padlock::authority::chain::{KeyType, AuthorityKey, AuthorityChain, KeyMaterial, KeyFormat, KeyMetadata};
-use padlock::authority::ignition::{IgnitionKey, validate_passphrase_strength};
+use padlock::authority::ignition::{IgnitionKey, UnlockPolicy, validate_passphrase_strength};
 use padlock::authority::validation::{AuthorityValidationEngine, validate_authority_hierarchy, AuthorityLevel};
 use padlock::encryption::age_automation::{AgeConfig, OutputFormat, TtyMethod, AgeResult};
+use padlock::encryption::age_automation::security::AuditLogger;
 use std::path::Path;
+use std::time::Duration;
 use chrono::Utc;
 
 /// Test Suite: Authority Chain Data Structures API
@@ -67,6 +69,7 @@ mod authority_chain_tests {
             expiration: None,
             last_used: None,
             usage_count: 0,
+            capabilities: None,
         };
 
         let authority_key = AuthorityKey::new(
@@ -247,6 +250,7 @@ mod ignition_key_tests {
 
     #[test]
     fn test_ignition_key_unlock_api() {
+        let audit = AuditLogger::new(None).unwrap();
         let key_material = KeyMaterial::new(
             b"unlock_test_public".to_vec(),
             Some(b"unlock_test_private".to_vec()),
@@ -263,7 +267,7 @@ mod ignition_key_tests {
         ).expect("Ignition key creation should succeed");
 
         // Test successful unlock
-        let unlocked_material = ignition_key.unlock(passphrase);
+        let unlocked_material = ignition_key.unlock(passphrase, &audit);
         assert!(unlocked_material.is_ok(), "Unlock with correct passphrase should succeed");
 
         // Verify metadata updated
@@ -271,13 +275,14 @@ mod ignition_key_tests {
         assert!(ignition_key.metadata().last_unlock.is_some());
 
         // Test failed unlock
-        let failed_unlock = ignition_key.unlock("WrongPassphrase123!");
+        let failed_unlock = ignition_key.unlock("WrongPassphrase123!", &audit);
         assert!(failed_unlock.is_err(), "Unlock with wrong passphrase should fail");
         assert_eq!(ignition_key.metadata().failed_unlock_attempts, 1);
     }
 
     #[test]
     fn test_ignition_key_passphrase_change_api() {
+        let audit = AuditLogger::new(None).unwrap();
         let key_material = KeyMaterial::new(
             b"change_test_public".to_vec(),
             Some(b"change_test_private".to_vec()),
@@ -293,18 +298,20 @@ mod ignition_key_tests {
             old_passphrase,
             None,
             Some("passphrase-change-test".to_string()),
-        ).expect("Ignition key creation should succeed");
+        ).expect("Ignition key creation should succeed")
+        // No backoff, so the next attempt is not throttled after a failure
+        .with_unlock_policy(UnlockPolicy::new(5, Duration::ZERO, Duration::ZERO));
 
         // Test successful passphrase change
-        let change_result = ignition_key.change_passphrase(old_passphrase, new_passphrase);
+        let change_result = ignition_key.change_passphrase(old_passphrase, new_passphrase, &audit);
         assert!(change_result.is_ok(), "Passphrase change should succeed");
 
         // Test old passphrase no longer works
-        let old_unlock = ignition_key.unlock(old_passphrase);
+        let old_unlock = ignition_key.unlock(old_passphrase, &audit);
         assert!(old_unlock.is_err(), "Old passphrase should no longer work");
 
         // Test new passphrase works
-        let new_unlock = ignition_key.unlock(new_passphrase);
+        let new_unlock = ignition_key.unlock(new_passphrase, &audit);
         assert!(new_unlock.is_ok(), "New passphrase should work");
     }
 
@@ -807,6 +814,7 @@ mod security_api_tests {
 
     #[test]
     fn test_ignition_key_security_properties_api() {
+        let audit = AuditLogger::new(None).unwrap();
         let key_material = KeyMaterial::new(
             b"security_test_key".to_vec(),
             Some(b"security_test_private".to_vec()),
@@ -820,12 +828,14 @@ mod security_api_tests {
             passphrase,
             None,
             Some("security-test".to_string()),
-        ).unwrap();
+        ).unwrap()
+        // No backoff, so the next attempt is not throttled after a failure
+        .with_unlock_policy(UnlockPolicy::new(5, Duration::ZERO, Duration::ZERO));
 
         // Test that failed unlock attempts are tracked
         let initial_failed_attempts = ignition_key.metadata().failed_unlock_attempts;
         
-        let _failed_unlock = ignition_key.unlock("WrongPassword123!");
+        let _failed_unlock = ignition_key.unlock("WrongPassword123!", &audit);
         assert_eq!(
             ignition_key.metadata().failed_unlock_attempts,
             initial_failed_attempts + 1,
@@ -833,7 +843,7 @@ mod security_api_tests {
         );
 
         // Test that successful unlock resets failure tracking mindset
-        let successful_unlock = ignition_key.unlock(passphrase);
+        let successful_unlock = ignition_key.unlock(passphrase, &audit);
         assert!(successful_unlock.is_ok(), "Correct passphrase should unlock key");
         assert_eq!(ignition_key.metadata().unlock_count, 1, "Successful unlocks should be counted");
         assert!(ignition_key.metadata().last_unlock.is_some(), "Last unlock time should be recorded");